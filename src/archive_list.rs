@@ -0,0 +1,123 @@
+use std::{fs, path::PathBuf};
+
+use tracing::info;
+
+use crate::{error::Error, installed_mods::LocalModInfo};
+
+/// A list of archive file names kept by Everest in the mods directory, e.g. `blacklist.txt`
+///
+/// Lines are kept verbatim so that comments and ordering written by the user or by
+/// Olympus survive modifications.
+#[derive(Debug)]
+pub struct ArchiveList {
+    path: PathBuf,
+    lines: Vec<String>,
+}
+
+impl ArchiveList {
+    /// Loads a list file, an absent file is an empty list
+    pub fn load(path: PathBuf) -> Result<Self, Error> {
+        let lines = if path.exists() {
+            fs::read_to_string(&path)?
+                .lines()
+                .map(String::from)
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Ok(Self { path, lines })
+    }
+
+    /// Names of the listed entries, comments and blank lines excluded
+    pub fn entries(&self) -> impl Iterator<Item = &str> {
+        self.lines
+            .iter()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+    }
+
+    /// Checks whether an archive file name (e.g. `SpeedrunTool.zip`) is listed
+    pub fn contains(&self, file_name: &str) -> bool {
+        self.entries().any(|entry| entry == file_name)
+    }
+
+    /// Adds an archive file name, returns `false` if it was already listed
+    pub fn add(&mut self, file_name: &str) -> bool {
+        if self.contains(file_name) {
+            return false;
+        }
+        self.lines.push(file_name.to_string());
+        true
+    }
+
+    /// Removes every line listing an archive file name, returns `false` if it was not listed
+    pub fn remove(&mut self, file_name: &str) -> bool {
+        let count = self.lines.len();
+        self.lines.retain(|line| line.trim() != file_name);
+        self.lines.len() != count
+    }
+
+    /// Replaces every line listing an archive file name with another name, keeping its position
+    ///
+    /// Returns `false` if the archive was not listed.
+    pub fn rename(&mut self, file_name: &str, new_file_name: &str) -> bool {
+        let mut renamed = false;
+        for line in self
+            .lines
+            .iter_mut()
+            .filter(|line| line.trim() == file_name)
+        {
+            *line = new_file_name.to_string();
+            renamed = true;
+        }
+        renamed
+    }
+
+    /// Writes the list back to the mods directory
+    pub fn save(&self) -> Result<(), Error> {
+        info!("Writing {}", self.path.display());
+        let mut content = self.lines.join("\n");
+        content.push('\n');
+        fs::write(&self.path, content)?;
+        Ok(())
+    }
+}
+
+/// Adds (or removes) the archives of installed mods to a list, reporting each change
+///
+/// `names` are mod names or archive file names. Returns whether the list was modified.
+pub fn apply_changes(
+    list: &mut ArchiveList,
+    installed_mods: &[LocalModInfo],
+    names: &[String],
+    add: bool,
+    list_name: &str,
+) -> bool {
+    let mut changed = false;
+    for name in names {
+        let file_names: Vec<String> = installed_mods
+            .iter()
+            .filter(|m| &m.manifest.name == name || &m.archive_file_name() == name)
+            .map(|m| m.archive_file_name())
+            .collect();
+        if file_names.is_empty() {
+            println!("The mod '{}' is not currently installed.", name);
+            continue;
+        }
+        for file_name in file_names {
+            let modified = if add {
+                list.add(&file_name)
+            } else {
+                list.remove(&file_name)
+            };
+            changed |= modified;
+            match (add, modified) {
+                (true, true) => println!("Added {} to {}", file_name, list_name),
+                (true, false) => println!("{} is already in {}", file_name, list_name),
+                (false, true) => println!("Removed {} from {}", file_name, list_name),
+                (false, false) => println!("{} is not in {}", file_name, list_name),
+            }
+        }
+    }
+    changed
+}
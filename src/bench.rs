@@ -0,0 +1,120 @@
+use std::{
+    fmt, fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+use indicatif::{HumanBytes, HumanCount};
+
+use crate::{
+    cache::RegistryCache,
+    checksum::{Hasher, hash_file_with},
+    error::Error,
+    installed_mods::list_installed_mods,
+    mod_registry::ModRegistry,
+};
+
+/// Time spent in one stage of the benchmark
+#[derive(Debug)]
+pub struct StageTiming {
+    pub stage: &'static str,
+    /// What was processed, e.g. `120 mods`
+    pub detail: String,
+    pub elapsed: Duration,
+    /// Bytes processed, to report a throughput
+    pub bytes: Option<u64>,
+}
+
+impl fmt::Display for StageTiming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<18} {:>10.1} ms  {}",
+            self.stage,
+            self.elapsed.as_secs_f64() * 1000.0,
+            self.detail
+        )?;
+        if let Some(bytes) = self.bytes {
+            let seconds = self.elapsed.as_secs_f64();
+            if seconds > 0.0 {
+                write!(f, " ({}/s)", HumanBytes((bytes as f64 / seconds) as u64))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Times the scanning of mod manifests
+pub fn bench_scan(mods_dir: &Path) -> Result<StageTiming, Error> {
+    let started = Instant::now();
+    let installed_mods = list_installed_mods(mods_dir)?;
+    Ok(StageTiming {
+        stage: "Manifest scanning",
+        detail: format!("{} mods", HumanCount(installed_mods.len() as u64)),
+        elapsed: started.elapsed(),
+        bytes: None,
+    })
+}
+
+/// Times the xxHash64 hashing of every archive of the mods directory, as done by update checks
+pub fn bench_hashing(mods_dir: &Path) -> Result<StageTiming, Error> {
+    let archives: Vec<_> = list_installed_mods(mods_dir)?
+        .into_iter()
+        .map(|mod_info| mod_info.archive_path)
+        .collect();
+    let mut bytes = 0;
+    let started = Instant::now();
+    for archive in &archives {
+        bytes += fs::metadata(archive)?.len();
+        hash_file_with(archive, Hasher::for_checksums(&[]))?;
+    }
+    Ok(StageTiming {
+        stage: "Hashing",
+        detail: format!("{} archives, {}", archives.len(), HumanBytes(bytes)),
+        elapsed: started.elapsed(),
+        bytes: Some(bytes),
+    })
+}
+
+/// Times the parsing of the cached registry snapshot, `None` if no registry was cached yet
+pub async fn bench_registry_parsing(cache_dir: &Path) -> Result<Option<StageTiming>, Error> {
+    let path = RegistryCache::load(cache_dir).current_path();
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let data = Bytes::from(fs::read(&path)?);
+    let bytes = data.len() as u64;
+    let started = Instant::now();
+    let mod_registry = ModRegistry::from(data).await?;
+    Ok(Some(StageTiming {
+        stage: "Registry parsing",
+        detail: format!(
+            "{} mods, {}",
+            HumanCount(mod_registry.entries.len() as u64),
+            HumanBytes(bytes)
+        ),
+        elapsed: started.elapsed(),
+        bytes: Some(bytes),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_timing_display() {
+        let timing = StageTiming {
+            stage: "Hashing",
+            detail: String::from("2 archives, 8.00 MiB"),
+            elapsed: Duration::from_millis(250),
+            bytes: Some(8 * 1024 * 1024),
+        };
+
+        assert_eq!(
+            timing.to_string(),
+            "Hashing                 250.0 ms  2 archives, 8.00 MiB (32.00 MiB/s)"
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    ops::{Deref, DerefMut},
+    path::Path,
+};
+
+use crate::{archive_list::ArchiveList, constant::BLACKLIST_FILE, error::Error};
+
+/// Everest's `Mods/blacklist.txt`, listing the archives (or folders) which are not loaded
+#[derive(Debug)]
+pub struct Blacklist(ArchiveList);
+
+impl Blacklist {
+    /// Loads the blacklist of a mods directory, an absent file is an empty blacklist
+    pub fn load(mods_dir: &Path) -> Result<Self, Error> {
+        ArchiveList::load(mods_dir.join(BLACKLIST_FILE)).map(Self)
+    }
+}
+
+impl Deref for Blacklist {
+    type Target = ArchiveList;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Blacklist {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    const CONTENT: &str = "# This is the blacklist. Lines starting with # are ignored.\n\
+        \n\
+        ExtendedVariantMode.zip\n  \
+        # SpeedrunTool.zip\n";
+
+    fn blacklist_with_content(content: &str) -> (TempDir, Blacklist) {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(BLACKLIST_FILE), content).unwrap();
+        let blacklist = Blacklist::load(dir.path()).unwrap();
+        (dir, blacklist)
+    }
+
+    #[test]
+    fn test_comments_are_not_entries() {
+        let (_dir, blacklist) = blacklist_with_content(CONTENT);
+
+        assert!(blacklist.contains("ExtendedVariantMode.zip"));
+        assert!(!blacklist.contains("SpeedrunTool.zip"));
+        assert_eq!(blacklist.entries().count(), 1);
+    }
+
+    #[test]
+    fn test_add_preserves_existing_lines() {
+        let (dir, mut blacklist) = blacklist_with_content(CONTENT);
+
+        assert!(blacklist.add("CelesteNet.Client.zip"));
+        assert!(!blacklist.add("CelesteNet.Client.zip"));
+        blacklist.save().unwrap();
+
+        let saved = fs::read_to_string(dir.path().join(BLACKLIST_FILE)).unwrap();
+        assert_eq!(saved, format!("{}CelesteNet.Client.zip\n", CONTENT));
+    }
+
+    #[test]
+    fn test_missing_file_is_empty() {
+        let dir = TempDir::new().unwrap();
+
+        let blacklist = Blacklist::load(dir.path()).unwrap();
+
+        assert_eq!(blacklist.entries().count(), 0);
+    }
+}
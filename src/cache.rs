@@ -0,0 +1,330 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use bytes::Bytes;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use xxhash_rust::xxh64::xxh64;
+
+use crate::{
+    constant::{
+        EXTRA_REGISTRY_CACHE_DIRECTORY, REGISTRY_CACHE_FILE, REGISTRY_CACHE_STATE_FILE,
+        REGISTRY_PREVIOUS_CACHE_FILE,
+    },
+    download::{ModDownloader, RegistryValidators},
+    error::Error,
+    timeutil,
+};
+
+/// On-disk snapshots of the remote mod registry
+///
+/// The latest fetched registry is kept together with the snapshot it replaced, so that
+/// changes in the ecosystem can be reviewed between two runs. Where it was fetched from, its
+/// validators and when it was last checked are stored next to it.
+#[derive(Debug, Clone)]
+pub struct RegistryCache {
+    cache_dir: PathBuf,
+    state: Option<RegistryState>,
+}
+
+/// What is known of the latest snapshot, stored as `everest_update.state.yaml`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RegistryState {
+    #[serde(rename = "URL")]
+    url: String,
+    #[serde(flatten)]
+    validators: RegistryValidators,
+    /// Seconds since the Unix epoch at which the snapshot was fetched or confirmed unchanged
+    #[serde(rename = "CheckedAt")]
+    checked_at: u64,
+}
+
+impl RegistryCache {
+    /// Loads what is known of the cached snapshots, an unreadable state is no state
+    pub fn load(cache_dir: &Path) -> Self {
+        let state_path = cache_dir.join(REGISTRY_CACHE_STATE_FILE);
+        let state = fs::read(&state_path).ok().and_then(|content| {
+            serde_yaml_ng::from_slice(&content)
+                .inspect_err(|e| warn!("Ignoring the invalid {}: {}", state_path.display(), e))
+                .ok()
+        });
+        Self {
+            cache_dir: cache_dir.to_path_buf(),
+            state,
+        }
+    }
+
+    /// Path to the snapshot of the latest fetched registry
+    pub fn current_path(&self) -> PathBuf {
+        self.cache_dir.join(REGISTRY_CACHE_FILE)
+    }
+
+    /// Path to the snapshot preceding the latest change
+    pub fn previous_path(&self) -> PathBuf {
+        self.cache_dir.join(REGISTRY_PREVIOUS_CACHE_FILE)
+    }
+
+    /// Fetches the registry of the downloader, unless the snapshot is still current
+    ///
+    /// The server is asked whether the registry changed since the snapshot when it sent
+    /// validators for it. The snapshot is stored even without them, to be used offline.
+    pub async fn fetch(&mut self, downloader: &ModDownloader) -> Result<Bytes, Error> {
+        let url = downloader.registry_url();
+        let current_path = self.current_path();
+        let previous = self
+            .state
+            .as_ref()
+            .filter(|state| state.url == url && current_path.exists())
+            .map(|state| state.validators.clone());
+
+        match downloader
+            .fetch_mod_registry_if_changed(previous.as_ref())
+            .await?
+        {
+            None => {
+                info!("Registry is unchanged since the last run");
+                let data = Bytes::from(fs::read(&current_path)?);
+                if let Some(state) = &mut self.state {
+                    state.checked_at = timeutil::now();
+                }
+                self.save_state();
+                Ok(data)
+            }
+            Some((data, validators)) => {
+                self.state = match self.store(url, &data) {
+                    Ok(()) => Some(RegistryState {
+                        url: url.to_string(),
+                        validators,
+                        checked_at: timeutil::now(),
+                    }),
+                    Err(e) => {
+                        warn!("Failed to cache the mod registry: {}", e);
+                        None
+                    }
+                };
+                self.save_state();
+                Ok(data)
+            }
+        }
+    }
+
+    /// Snapshot of the registry at `url` fetched by a previous run, if any, to use offline
+    pub fn cached(&self, url: &str) -> Option<Bytes> {
+        self.state.as_ref().filter(|state| state.url == url)?;
+        fs::read(self.current_path()).ok().map(Bytes::from)
+    }
+
+    /// Time since the snapshot of the registry at `url` was fetched or confirmed unchanged, if any
+    pub fn age(&self, url: &str) -> Option<Duration> {
+        let state = self.state.as_ref().filter(|state| state.url == url)?;
+        Some(Duration::from_secs(
+            timeutil::now().saturating_sub(state.checked_at),
+        ))
+    }
+
+    fn save_state(&self) {
+        let state_path = self.cache_dir.join(REGISTRY_CACHE_STATE_FILE);
+        let result = match &self.state {
+            Some(state) => serde_yaml_ng::to_string(state)
+                .map_err(Error::from)
+                .and_then(|content| write(&state_path, content.as_bytes())),
+            None => match fs::remove_file(&state_path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+                _ => Ok(()),
+            },
+        };
+        if let Err(e) = result {
+            warn!("Failed to save {}: {}", state_path.display(), e);
+        }
+    }
+
+    /// Stores freshly fetched registry data from `url`
+    ///
+    /// The current snapshot is rotated to the previous one only if the content has changed,
+    /// so repeated fetches of an unchanged registry do not erase the history. Snapshots of
+    /// another registry are dropped instead, they would show up as bogus changes.
+    fn store(&self, url: &str, data: &[u8]) -> Result<(), Error> {
+        let current_path = self.current_path();
+        if current_path.exists() {
+            if fs::read(&current_path)? == data {
+                info!("Registry is unchanged since the last fetch");
+                return Ok(());
+            }
+            // Snapshots without a state were stored by versions which only kept the main registry
+            if self.state.as_ref().is_none_or(|state| state.url == url) {
+                fs::rename(&current_path, self.previous_path())?;
+            } else {
+                fs::remove_file(&current_path)?;
+                if let Err(e) = fs::remove_file(self.previous_path())
+                    && e.kind() != io::ErrorKind::NotFound
+                {
+                    return Err(e.into());
+                }
+            }
+        }
+
+        fs::create_dir_all(&self.cache_dir)?;
+        info!("Caching registry snapshot at {}", current_path.display());
+        fs::write(&current_path, data)?;
+        Ok(())
+    }
+
+    /// Loads the snapshot preceding the latest change, if any
+    pub fn load_previous(&self) -> Result<Option<Bytes>, Error> {
+        read_if_exists(&self.previous_path())
+    }
+}
+
+fn read_if_exists(path: &Path) -> Result<Option<Bytes>, Error> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(Bytes::from(fs::read(path)?)))
+}
+
+/// Reads a cached file if it was written less than `max_age` ago
+pub fn read_fresh(path: &Path, max_age: Duration) -> Result<Option<Bytes>, Error> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let age = SystemTime::now()
+        .duration_since(fs::metadata(path)?.modified()?)
+        .unwrap_or_default();
+    if age > max_age {
+        info!(
+            "Cache {} is outdated ({}s old)",
+            path.display(),
+            age.as_secs()
+        );
+        return Ok(None);
+    }
+    read_if_exists(path)
+}
+
+/// Reads a cached file, calling `fetch` to refresh it when it is older than `max_age`
+///
+/// If the fetch fails, an outdated cache is used rather than failing the command.
+pub async fn fetch_cached<F, Fut>(path: &Path, max_age: Duration, fetch: F) -> Result<Bytes, Error>
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<Bytes, Error>>,
+{
+    if let Some(data) = read_fresh(path, max_age)? {
+        info!("Using cached {}", path.display());
+        return Ok(data);
+    }
+
+    match fetch().await {
+        Ok(data) => {
+            if let Err(e) = write(path, &data) {
+                warn!("Failed to cache {}: {}", path.display(), e);
+            }
+            Ok(data)
+        }
+        Err(e) if path.exists() => {
+            warn!(
+                "Failed to refresh {}, using stale cache: {}",
+                path.display(),
+                e
+            );
+            Ok(Bytes::from(fs::read(path)?))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Path of the cached copy of an extra registry
+pub fn extra_registry_path(cache_dir: &Path, url: &str) -> PathBuf {
+    // URLs may contain characters which are not allowed in file names
+    cache_dir
+        .join(EXTRA_REGISTRY_CACHE_DIRECTORY)
+        .join(format!("{:016x}.yaml", xxh64(url.as_bytes(), 0)))
+}
+
+/// Fetches an extra registry and caches it, reading the cached copy instead without a downloader
+///
+/// When the fetch fails, the cached copy is used rather than losing the mods of the registry.
+pub async fn extra_registry(
+    cache_dir: &Path,
+    url: &str,
+    downloader: Option<&ModDownloader>,
+) -> Result<Option<Bytes>, Error> {
+    let path = extra_registry_path(cache_dir, url);
+    let Some(downloader) = downloader else {
+        return read_if_exists(&path);
+    };
+    match downloader.fetch_extra_registry(url).await {
+        Ok(data) => {
+            if let Err(e) = write(&path, &data) {
+                warn!("Failed to cache {}: {}", path.display(), e);
+            }
+            Ok(Some(data))
+        }
+        Err(e) if path.exists() => {
+            warn!("Failed to refresh {}, using the cached copy: {}", url, e);
+            read_if_exists(&path)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Writes data to a cache file, creating the cache directory if needed
+pub fn write(path: &Path, data: &[u8]) -> Result<(), Error> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, data)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_store_keeps_the_previous_snapshot_of_the_same_registry_only() {
+        let dir = TempDir::new().unwrap();
+        let mut registry_cache = RegistryCache::load(dir.path());
+        registry_cache.store("https://a", b"first").unwrap();
+        registry_cache.state = Some(RegistryState {
+            url: String::from("https://a"),
+            validators: RegistryValidators::default(),
+            checked_at: timeutil::now(),
+        });
+        registry_cache.save_state();
+
+        let registry_cache = RegistryCache::load(dir.path());
+        registry_cache.store("https://a", b"second").unwrap();
+        assert_eq!(registry_cache.cached("https://a").unwrap(), "second");
+        assert_eq!(registry_cache.load_previous().unwrap().unwrap(), "first");
+        assert!(registry_cache.age("https://a").unwrap() < Duration::from_secs(60));
+        assert!(registry_cache.cached("https://b").is_none());
+
+        registry_cache.store("https://b", b"other").unwrap();
+        assert!(registry_cache.load_previous().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_extra_registries_are_read_from_their_cached_copy_offline() {
+        let dir = TempDir::new().unwrap();
+        let url = "https://example.com/overlay.yaml?token=a/b";
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let read_offline = || {
+            runtime
+                .block_on(extra_registry(dir.path(), url, None))
+                .unwrap()
+        };
+        assert_eq!(read_offline(), None);
+
+        write(&extra_registry_path(dir.path(), url), b"Overlay: {}").unwrap();
+        assert_eq!(read_offline().as_deref(), Some(&b"Overlay: {}"[..]));
+        assert_ne!(
+            extra_registry_path(dir.path(), url),
+            extra_registry_path(dir.path(), "https://example.com/other.yaml")
+        );
+    }
+}
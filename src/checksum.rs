@@ -0,0 +1,259 @@
+use std::{
+    fs::File,
+    io::Read,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use md5::{Digest, Md5};
+use sha2::Sha256;
+use tracing::info_span;
+use xxhash_rust::xxh64::Xxh64;
+
+use crate::error::{Context, Error};
+
+/// Hash algorithms used by registry checksums
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumKind {
+    XxHash64,
+    Md5,
+}
+
+impl ChecksumKind {
+    /// Detects the algorithm of a hexadecimal checksum from its length
+    pub fn detect(checksum: &str) -> Option<Self> {
+        if !checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        match checksum.len() {
+            16 => Some(Self::XxHash64),
+            32 => Some(Self::Md5),
+            _ => None,
+        }
+    }
+}
+
+/// Computes the digests needed to verify a file against a list of expected checksums
+///
+/// xxHash64 is always computed, MD5 only when one of the expected checksums is an MD5,
+/// and SHA-256 only when requested with `with_sha256`.
+pub struct Hasher {
+    xxh64: Xxh64,
+    md5: Option<Md5>,
+    sha256: Option<Sha256>,
+}
+
+impl Hasher {
+    pub fn for_checksums(expected: &[String]) -> Self {
+        let needs_md5 = expected
+            .iter()
+            .any(|checksum| ChecksumKind::detect(checksum) == Some(ChecksumKind::Md5));
+        Self {
+            xxh64: Xxh64::new(0),
+            md5: needs_md5.then(Md5::new),
+            sha256: None,
+        }
+    }
+
+    /// Also computes the SHA-256 digest
+    pub fn with_sha256(mut self) -> Self {
+        self.sha256 = Some(Sha256::new());
+        self
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.xxh64.update(data);
+        if let Some(md5) = &mut self.md5 {
+            md5.update(data);
+        }
+        if let Some(sha256) = &mut self.sha256 {
+            sha256.update(data);
+        }
+    }
+
+    pub fn finish(self) -> Digests {
+        Digests {
+            xxh64: format!("{:016x}", self.xxh64.digest()),
+            md5: self.md5.map(|md5| format!("{:032x}", md5.finalize())),
+            sha256: self
+                .sha256
+                .map(|sha256| format!("{:064x}", sha256.finalize())),
+        }
+    }
+}
+
+/// Hexadecimal digests of a file
+#[derive(Debug, Clone)]
+pub struct Digests {
+    pub xxh64: String,
+    pub md5: Option<String>,
+    pub sha256: Option<String>,
+}
+
+impl Digests {
+    /// Checks whether any of the expected checksums matches the digest of its algorithm
+    pub fn matches(&self, expected: &[String]) -> bool {
+        expected
+            .iter()
+            .any(|checksum| match ChecksumKind::detect(checksum) {
+                Some(ChecksumKind::XxHash64) => checksum.eq_ignore_ascii_case(&self.xxh64),
+                Some(ChecksumKind::Md5) => self
+                    .md5
+                    .as_deref()
+                    .is_some_and(|md5| checksum.eq_ignore_ascii_case(md5)),
+                None => false,
+            })
+    }
+
+    /// Checks the SHA-256 digest, `false` if it was not computed
+    pub fn matches_sha256(&self, expected: &str) -> bool {
+        self.sha256
+            .as_deref()
+            .is_some_and(|sha256| expected.eq_ignore_ascii_case(sha256))
+    }
+
+    /// The digest to show when verification fails
+    pub fn display_for(&self, expected: &[String]) -> String {
+        match &self.md5 {
+            Some(md5) if !expected.iter().any(|checksum| checksum.len() == 16) => md5.clone(),
+            _ => self.xxh64.clone(),
+        }
+    }
+}
+
+/// Computes the digests of a file needed to verify it against `expected`
+pub fn hash_file(file_path: &Path, expected: &[String]) -> Result<Digests, Error> {
+    hash_file_with(file_path, Hasher::for_checksums(expected))
+}
+
+/// Hashes files on up to `jobs` threads, see `hash_file`
+///
+/// The results are in the order of `files`.
+pub fn hash_files(files: &[(&Path, &[String])], jobs: u32) -> Vec<Result<Digests, Error>> {
+    let next = AtomicUsize::new(0);
+    let workers = (jobs.max(1) as usize).min(files.len());
+    let mut results: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut hashed = Vec::new();
+                    // Each thread takes the next file to hash until none is left
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some((path, expected)) = files.get(index) else {
+                            return hashed;
+                        };
+                        hashed.push((index, hash_file(path, expected)));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("hashing thread panicked"))
+            .collect()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, digests)| digests).collect()
+}
+
+/// Feeds a file to a hasher and returns the resulting digests
+pub fn hash_file_with(file_path: &Path, mut hasher: Hasher) -> Result<Digests, Error> {
+    let _span = info_span!("hash").entered();
+    let mut file = File::open(file_path).in_file(file_path)?;
+    let mut buffer = [0u8; 8192];
+    loop {
+        let bytes_read = file.read(&mut buffer).in_file(file_path)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+    }
+    Ok(hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digests_of(data: &[u8], expected: &[String]) -> Digests {
+        let mut hasher = Hasher::for_checksums(expected);
+        hasher.update(data);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_detect_checksum_kind() {
+        assert_eq!(
+            ChecksumKind::detect("e7b5cefe7d93e0ef"),
+            Some(ChecksumKind::XxHash64)
+        );
+        assert_eq!(
+            ChecksumKind::detect("d41d8cd98f00b204e9800998ecf8427e"),
+            Some(ChecksumKind::Md5)
+        );
+        assert_eq!(ChecksumKind::detect("not a checksum!!"), None);
+    }
+
+    #[test]
+    fn test_matches_md5_checksums() {
+        let expected = vec![String::from("900150983CD24FB0D6963F7D28E17F72")];
+
+        let digests = digests_of(b"abc", &expected);
+
+        assert!(digests.matches(&expected));
+        assert!(!digests.matches(&[String::from("0000000000000000")]));
+    }
+
+    #[test]
+    fn test_md5_is_only_computed_when_expected() {
+        let digests = digests_of(b"abc", &[String::from("0000000000000000")]);
+
+        assert!(digests.md5.is_none());
+        assert!(digests.sha256.is_none());
+        assert!(digests.matches(std::slice::from_ref(&digests.xxh64)));
+    }
+
+    #[test]
+    fn test_sha256_digest() {
+        let mut hasher = Hasher::for_checksums(&[]).with_sha256();
+        hasher.update(b"abc");
+
+        let digests = hasher.finish();
+
+        assert!(
+            digests
+                .matches_sha256("BA7816BF8F01CFEA414140DE5DAE2223B00361A396177A9CB410FF61F20015AD")
+        );
+    }
+
+    #[test]
+    fn test_hash_files_keeps_the_order_of_the_files() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let contents: Vec<_> = (0..5).map(|i| format!("archive {}", i)).collect();
+        let paths: Vec<_> = contents
+            .iter()
+            .enumerate()
+            .map(|(i, content)| {
+                let path = dir.path().join(format!("{}.zip", i));
+                std::fs::write(&path, content).unwrap();
+                path
+            })
+            .collect();
+        let files: Vec<(&Path, &[String])> =
+            paths.iter().map(|path| (path.as_path(), &[][..])).collect();
+
+        let results = hash_files(&files, 2);
+
+        let expected: Vec<_> = contents
+            .iter()
+            .map(|content| digests_of(content.as_bytes(), &[]).xxh64)
+            .collect();
+        let hashes: Vec<_> = results
+            .into_iter()
+            .map(|digests| digests.unwrap().xxh64)
+            .collect();
+        assert_eq!(hashes, expected);
+    }
+}
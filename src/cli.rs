@@ -1,6 +1,7 @@
-use std::path::PathBuf;
+use std::{net::IpAddr, path::PathBuf};
 
-use clap::{Args, Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use tracing::level_filters::LevelFilter;
 
 #[derive(Debug, Parser)]
 #[command(version, about = "Mod management tool for Celeste", long_about = None)]
@@ -9,6 +10,74 @@ pub struct Cli {
     #[arg(short = 'd', long = "mods-dir", value_name = "DIR")]
     pub mods_dir: Option<PathBuf>,
 
+    /// Another mods directory to list and update together with the main one, e.g. a shared
+    /// network folder. Can be repeated.
+    #[arg(long = "extra-mods-dir", value_name = "DIR")]
+    pub extra_mods_dirs: Vec<PathBuf>,
+
+    /// Proxy server URL for all requests (e.g. http://host:port or socks5://host:port).
+    /// The HTTP_PROXY, HTTPS_PROXY and ALL_PROXY environment variables are used otherwise.
+    #[arg(long = "proxy", value_name = "URL")]
+    pub proxy: Option<String>,
+
+    /// Seconds to wait for a connection to be established [default: 30, 0 to disable]
+    #[arg(long = "connect-timeout", value_name = "SECS")]
+    pub connect_timeout: Option<u64>,
+
+    /// Seconds to wait for data on an established connection before aborting [default: 60, 0 to disable]
+    #[arg(long = "read-timeout", value_name = "SECS")]
+    pub read_timeout: Option<u64>,
+
+    /// Times a download failing checksum verification is attempted again [default: 2]
+    #[arg(long = "retries", value_name = "N")]
+    pub retries: Option<u32>,
+
+    /// Number of concurrent downloads, and of threads hashing installed archives [default: 4]
+    #[arg(short = 'j', long = "jobs", value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub jobs: Option<u32>,
+
+    /// Keep downloads failing checksum verification as <name>.zip.corrupt instead of deleting them
+    #[arg(long = "keep-corrupt", action)]
+    pub keep_corrupt: bool,
+
+    /// Skip checksum verification of downloads, e.g. when the registry lags behind a hotfix.
+    /// Installed archives are marked as unverified.
+    #[arg(long = "no-verify", action)]
+    pub no_verify: bool,
+
+    /// PEM file with additional root certificates to trust (e.g. for TLS-intercepting proxies)
+    #[arg(long = "ca-cert", value_name = "FILE")]
+    pub ca_cert: Option<PathBuf>,
+
+    /// Disable TLS certificate verification. This is dangerous, use only as a last resort.
+    #[arg(long, action)]
+    pub insecure: bool,
+
+    /// Change the mods directory (install, update, doctor, normalize, undo) even while Celeste is running
+    #[arg(long, action)]
+    pub force: bool,
+
+    /// URL of the mod registry (everest_update.yaml) to use instead of the official one,
+    /// e.g. a local mirror started with the serve command
+    #[arg(long = "registry-url", value_name = "URL")]
+    pub registry_url: Option<String>,
+
+    /// Hide progress bars, e.g. of downloads, of the registry parsing and of the scanning of installed mods
+    #[arg(short = 'q', long, action)]
+    pub quiet: bool,
+
+    /// Print how long each phase (registry fetch, scan, hashing, downloads...) took at the end
+    #[arg(long, action)]
+    pub timings: bool,
+
+    /// Format of the log messages. JSON logs are written to stderr, one object per line.
+    #[arg(long = "log-format", value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Most verbose level of the log messages: off, error, warn, info, debug or trace
+    #[arg(long = "log-level", value_name = "LEVEL", default_value_t = LevelFilter::ERROR)]
+    pub log_level: LevelFilter,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -19,32 +88,508 @@ pub enum Commands {
     Search(SearchArgs),
     /// Show mod information from the remote mod registry
     Info(InfoArgs),
-    /// Install a mod
+    /// Browse the featured, newest or most liked submissions of the Celeste section on GameBanana
+    Browse(BrowseArgs),
+    /// Install a mod along with its missing dependencies
     Install(InstallArgs),
     /// List installed mods
-    List,
+    List(ListArgs),
     /// Show detailed information about an installed mod
     Show(ShowArgs),
-    /// Check for updates
+    /// Search text in the dialog files and maps of installed mods
+    Grep(GrepArgs),
+    /// Report the dialog languages supported by installed maps
+    Languages(LanguagesArgs),
+    /// Add mods to Everest's favorites (favorites.txt)
+    Favorite(ModNamesArgs),
+    /// Remove mods from Everest's favorites (favorites.txt)
+    Unfavorite(ModNamesArgs),
+    /// Exclude mods from updates by adding them to updaterblacklist.txt
+    Pin(ModNamesArgs),
+    /// Allow updates of pinned mods again
+    Unpin(ModNamesArgs),
+    /// Summarize the mods directory, game version, installed mods, pending updates and problems
+    Status,
+    /// Diagnose problems with installed mods, such as duplicates
+    Doctor(DoctorArgs),
+    /// Rename installed archives after their mod names, e.g. `1298450.zip` to `SpeedrunTool.zip`
+    Normalize(NormalizeArgs),
+    /// Report asset files shipped by more than one installed mod
+    Conflicts,
+    /// Show the history of installs, updates and other changes to the mods directory
+    History(HistoryArgs),
+    /// Revert the most recent install, update or blacklist change
+    Undo(UndoArgs),
+    /// Save the installed archives, blacklist, favorites, pins and Everest version as a snapshot
+    Freeze(FreezeArgs),
+    /// Restore the mods directory to a snapshot made by `freeze`
+    Thaw(ThawArgs),
+    /// Delete a snapshot and the stored archives which no other snapshot contains
+    DeleteSnapshot(DeleteSnapshotArgs),
+    /// Compare two exported setups, or one with the enabled installed mods
+    Diff(DiffArgs),
+    /// Choose mod by mod which mods of someone else's setup to install
+    Merge(MergeArgs),
+    /// Watch the mods directory, reporting added and removed archives and their missing dependencies
+    Watch,
+    /// Verify the integrity (CRC) of every entry of every installed mod archive
+    CheckArchives,
+    /// Create the everest.yaml and folder layout of a new mod in the current directory
+    Init(InitArgs),
+    /// Validate the everest.yaml of a mod folder, a mod zip or a manifest file
+    Lint(LintArgs),
+    /// Check a mod zip for packaging mistakes before uploading it
+    Preflight(PreflightArgs),
+    /// Unpack a mod archive into a directory, e.g. to inspect its files or turn it into a mod folder
+    Extract(ExtractArgs),
+    /// Compute the xxHash64 checksum of files, as listed in the mod registry
+    Hash(HashArgs),
+    /// List the available updates of installed mods without installing them
+    Outdated(OutdatedArgs),
+    /// Install the available updates of installed mods
     Update(UpdateArgs),
+    /// Update the installed mods after confirmation, then start Celeste
+    Launch(LaunchArgs),
+    /// Follow registry entries without installing them, e.g. an upcoming collab, `update` reports their changes
+    Subscribe(SubscribeArgs),
+    /// Stop following registry entries
+    Unsubscribe(ModNamesArgs),
+    /// Identify archives from their hash, whatever their file name, and adopt them
+    Identify(IdentifyArgs),
+    /// Show the dependency tree of a mod, or of all installed mods
+    Deps(DepsArgs),
+    /// Show which installed mods require a mod
+    Why(WhyArgs),
+    /// List installed mods grouped by GameBanana author, or the other mods of one author
+    Authors(AuthorsArgs),
+    /// Check that the dependencies of all installed mods are installed
+    Depcheck,
+    /// Download the latest archive of every installed mod into a directory for offline use
+    Vendor(VendorArgs),
+    /// Host a vendor directory as a mod registry over HTTP for offline installs
+    Serve(ServeArgs),
+    /// Inspect the remote mod registry, or add mods to the local one
+    #[command(subcommand)]
+    Registry(RegistryCommands),
+    /// Install, verify or create mod packs (curated lists of mods)
+    #[command(subcommand)]
+    Pack(PackCommands),
+    /// Save and load sets of enabled mods, switching them through blacklist.txt
+    #[command(subcommand)]
+    Profile(ProfileCommands),
+    /// Time manifest scanning, hashing and registry parsing on the mods directory
+    #[command(hide = true)]
+    Bench,
+}
+
+#[derive(Debug, Args)]
+pub struct ModNamesArgs {
+    /// Mod names or archive file names
+    #[arg(required = true)]
+    pub names: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct SubscribeArgs {
+    /// Registry entry names, list the subscriptions if none is given
+    pub names: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct HistoryArgs {
+    /// Only show operations on this mod
+    pub name: Option<String>,
+    /// Only show the last N operations
+    #[arg(short = 'n', long, value_name = "N")]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Args)]
+pub struct NormalizeArgs {
+    /// Only show what would be renamed
+    #[arg(long, action)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct UndoArgs {
+    /// Only show what would be reverted
+    #[arg(long, action)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct FreezeArgs {
+    /// Name of the snapshot, replacing a snapshot of the same name [default: current date and time]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct ThawArgs {
+    /// Name of the snapshot, list the snapshots if none is given
+    pub name: Option<String>,
+    /// Only show what would be changed
+    #[arg(long, action)]
+    pub dry_run: bool,
+    /// Remove or replace mods with maps in the save files (strawberries, a session) without asking
+    #[arg(long, action)]
+    pub ignore_saves: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct DeleteSnapshotArgs {
+    /// Name of the snapshot
+    pub name: String,
+}
+
+#[derive(Debug, Args)]
+pub struct DoctorArgs {
+    /// Delete copies with maps in the save files (strawberries, a session) without asking
+    #[arg(long, action)]
+    pub ignore_saves: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct DiffArgs {
+    /// Pack (`pack create`) or CSV export (`list --csv`) of a setup
+    pub first: PathBuf,
+    /// Setup to compare it with [default: the enabled installed mods]
+    pub second: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct MergeArgs {
+    /// Pack (`pack create`) or CSV export (`list --csv`) of the setup
+    pub source: PathBuf,
+    /// Also offer the mods answered with "always skip" before
+    #[arg(long, action)]
+    pub include_skipped: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct InitArgs {
+    /// Mod name [default: name of the current directory]
+    pub name: Option<String>,
+    /// Initial version of the mod
+    #[arg(long = "mod-version", value_name = "VERSION", default_value = "1.0.0")]
+    pub mod_version: String,
+    /// Set up the mod for code (DLL field and Source folder)
+    #[arg(long, action)]
+    pub code: bool,
+    /// Overwrite an existing everest.yaml
+    #[arg(long, action)]
+    pub force: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct LintArgs {
+    /// Mod folder, mod zip or manifest file to validate
+    #[arg(default_value = ".")]
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct PreflightArgs {
+    /// Mod zip to check
+    pub zip: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct IdentifyArgs {
+    /// Archives to identify [default: the archives of the mods directory not installed by this tool]
+    pub files: Vec<PathBuf>,
+    /// Record identified archives in the installed index, copying those outside the mods directory into it
+    #[arg(long, action)]
+    pub adopt: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ExtractArgs {
+    /// Mod zip to unpack
+    pub archive: PathBuf,
+    /// Directory to unpack into, which must be empty or missing [default: the archive name without `.zip`]
+    #[arg(short = 'o', long, value_name = "DIR")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct HashArgs {
+    /// Files to hash
+    #[arg(required = true)]
+    pub files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct DepsArgs {
+    #[command(subcommand)]
+    pub command: Option<DepsCommands>,
+    /// Mod name, all installed mods if omitted
+    pub name: Option<String>,
+    /// Print the graph in the Graphviz DOT language, colored by installation state
+    #[arg(long, conflicts_with = "json")]
+    pub dot: bool,
+    /// Print the graph as JSON nodes and edges, with versions and constraint satisfaction
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DepsCommands {
+    /// Install the missing dependencies of a mod archive downloaded manually
+    Install(DepsInstallArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct DepsInstallArgs {
+    /// Path to the mod archive (.zip)
+    #[arg(long)]
+    pub file: PathBuf,
+}
+
+#[derive(Debug, Args)]
+pub struct WhyArgs {
+    /// Mod name
+    pub name: String,
+}
+
+#[derive(Debug, Args)]
+pub struct AuthorsArgs {
+    /// Author name, lists their installed mods and their other mods in the registry
+    pub author: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct VendorArgs {
+    /// Directory to store the archives and the manifest (everest_update.yaml) in
+    pub dir: PathBuf,
+    /// Also download the dependencies declared by installed mods
+    #[arg(long, action)]
+    pub with_deps: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ServeArgs {
+    /// Vendor directory created with the vendor command
+    #[arg(long, value_name = "DIR")]
+    pub dir: PathBuf,
+    /// Address to listen on
+    #[arg(long, default_value = "0.0.0.0")]
+    pub bind: IpAddr,
+    /// Port to listen on
+    #[arg(short, long, default_value_t = 8080)]
+    pub port: u16,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RegistryCommands {
+    /// Show mods added, removed and updated since the previous registry snapshot
+    Diff,
+    /// Add a mod archive to the local registry of unlisted mods, or replace its entry
+    Add(RegistryAddArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct RegistryAddArgs {
+    /// Zip file of the mod, or the URL it is downloaded from
+    pub source: String,
+    /// URL the archive is downloaded from, required when SOURCE is a local file
+    #[arg(long)]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PackCommands {
+    /// Install the mods of a pack and their dependencies
+    Install(PackInstallArgs),
+    /// Check that the mods of a pack are installed, in the pinned versions
+    Verify(PackFileArgs),
+    /// Create a pack from the enabled installed mods
+    Create(PackCreateArgs),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ProfileCommands {
+    /// Save the currently enabled mods as a profile, replacing a profile of the same name
+    Save(ProfileNameArgs),
+    /// Enable the mods of a profile and disable every other installed mod
+    Load(ProfileLoadArgs),
+    /// List the saved profiles
+    List,
+}
+
+#[derive(Debug, Args)]
+pub struct ProfileNameArgs {
+    /// Name of the profile
+    pub name: String,
+}
+
+#[derive(Debug, Args)]
+pub struct ProfileLoadArgs {
+    /// Name of the profile
+    pub name: String,
+    /// Disable mods with maps in the save files (strawberries, a session) without asking
+    #[arg(long, action)]
+    pub ignore_saves: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct PackFileArgs {
+    /// Path or HTTP(S) URL of the pack file
+    pub source: String,
+}
+
+#[derive(Debug, Args)]
+pub struct PackInstallArgs {
+    /// Path or HTTP(S) URL of the pack file
+    #[arg(required_unless_present = "preset")]
+    pub source: Option<String>,
+    /// Install a built-in preset instead, e.g. `essential-helpers` or `quality-of-life`
+    #[arg(long, conflicts_with = "source")]
+    pub preset: Option<String>,
+    /// Install the latest version of mods pinned to another version instead of skipping them
+    #[arg(long, action)]
+    pub ignore_pins: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct PackCreateArgs {
+    /// Name of the pack
+    #[arg(long)]
+    pub name: String,
+    /// Description of the pack
+    #[arg(long)]
+    pub description: Option<String>,
+    /// Do not pin the installed versions
+    #[arg(long, action)]
+    pub unpinned: bool,
+    /// Write the pack to a file instead of the standard output
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
 }
 
 #[derive(Debug, Args)]
 pub struct SearchArgs {
     /// Search query
     pub query: String,
+    /// Order of the results
+    #[arg(long, value_enum, default_value_t = SearchSort::Relevance)]
+    pub sort: SearchSort,
+    /// Print stable tab-separated records for scripts, see the README for the format
+    #[arg(long, action)]
+    pub porcelain: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human readable lines
+    Text,
+    /// Structured JSON, for log pipelines
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SearchSort {
+    /// Exact matches first, then names starting with the query, then most downloaded
+    Relevance,
+    /// Most recently updated first
+    Updated,
+    /// Most downloaded first
+    Downloads,
+    /// Most liked first
+    Likes,
+    /// Alphabetical order
+    Name,
+}
+
+#[derive(Debug, Args)]
+pub struct BrowseArgs {
+    /// Section to browse
+    #[arg(value_enum, default_value_t = BrowseSection::Featured)]
+    pub section: BrowseSection,
+    /// Page of results, starting at 1
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub page: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BrowseSection {
+    /// Submissions featured by GameBanana
+    Featured,
+    /// Most recently added first
+    New,
+    /// Most liked first
+    Top,
 }
 
 #[derive(Debug, Args)]
 pub struct InfoArgs {
     /// Mod name
     pub name: String,
+    /// Also fetch the description, author and screenshots from GameBanana
+    #[arg(long)]
+    pub full: bool,
+    /// Display the first screenshot inline, in terminals supporting kitty, iTerm2 or sixel graphics
+    #[arg(long)]
+    pub preview: bool,
 }
 
 #[derive(Debug, Args)]
 pub struct InstallArgs {
     /// Mod name
     pub name: String,
+    /// Mods directory to install the mod and its missing dependencies into [default: the main one]
+    #[arg(long, value_name = "DIR")]
+    pub into: Option<PathBuf>,
+    /// Also install the optional dependencies of the mod, which often enable extra content
+    #[arg(long, action)]
+    pub with_optional: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ListArgs {
+    /// Only list mods whose name or file name matches this glob (e.g. 'Spring*')
+    #[arg(conflicts_with = "filter")]
+    pub pattern: Option<String>,
+    /// Same as the positional pattern
+    #[arg(long, value_name = "GLOB")]
+    pub filter: Option<String>,
+    /// Search mod names and file names like `search` does, best matches first
+    #[arg(long, value_name = "QUERY", conflicts_with_all = ["pattern", "filter"])]
+    pub search: Option<String>,
+    /// Show when each mod was installed or last updated
+    #[arg(long, action)]
+    pub dates: bool,
+    /// Mark the mods matching no registry entry, which hashes the archives of unknown mods
+    #[arg(long, action)]
+    pub unmanaged: bool,
+    /// Print file name, mod name, version, size, xxHash and enabled status as CSV
+    #[arg(long, action)]
+    pub csv: bool,
+    /// Print one line per mod from a template, e.g. '{name}\t{version}\t{path}'
+    ///
+    /// Fields: {name}, {version}, {file}, {path}, {size} (bytes), {xxhash} and {enabled}.
+    #[arg(long, value_name = "TEMPLATE", conflicts_with = "csv")]
+    pub format: Option<String>,
+    /// Print stable tab-separated records for scripts, see the README for the format
+    #[arg(long, action, conflicts_with_all = ["csv", "format"])]
+    pub porcelain: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct GrepArgs {
+    /// Text to search, e.g. a dialog line or a room name
+    pub text: String,
+    /// Ignore case when matching
+    #[arg(short, long, action)]
+    pub ignore_case: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct LanguagesArgs {
+    /// Language to look for, e.g. 'French' (default: the configured language, or English)
+    #[arg(short, long)]
+    pub language: Option<String>,
 }
 
 #[derive(Debug, Args)]
@@ -54,8 +599,64 @@ pub struct ShowArgs {
 }
 
 #[derive(Debug, Args)]
-pub struct UpdateArgs {
-    /// Install available updates
+pub struct OutdatedArgs {
+    /// Print the pending updates as JSON
     #[arg(long, action)]
+    pub json: bool,
+    /// Also write the available updates, newly released mods and subscription changes as a feed
+    #[arg(long, value_enum, value_name = "FORMAT", requires = "output")]
+    pub feed: Option<FeedFormat>,
+    /// File to write the feed to
+    #[arg(short, long, value_name = "FILE", requires = "feed")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct UpdateArgs {
+    /// Only list the available updates, like `outdated`
+    #[arg(short = 'n', long, action)]
+    pub dry_run: bool,
+    /// Kept for compatibility, updates are installed by default
+    #[arg(long, action, hide = true, conflicts_with = "dry_run")]
     pub install: bool,
+    /// Only update mods of a GameBanana category or type, e.g. 'maps' or 'helpers'
+    #[arg(long, value_name = "CATEGORY")]
+    pub only: Option<String>,
+    /// Install updates of code mods (mods with a DLL) without asking
+    #[arg(long, action)]
+    pub include_code_mods: bool,
+    /// Update maps with progress in the save files (strawberries, a session) without asking
+    #[arg(long, action)]
+    pub ignore_saves: bool,
+    /// Ask before installing the updates
+    #[arg(long, action, conflicts_with = "dry_run")]
+    pub confirm: bool,
+    /// Print stable tab-separated records of the available updates for scripts, see the README for the format
+    #[arg(long, action, conflicts_with_all = ["install", "dry_run", "only"])]
+    pub porcelain: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct LaunchArgs {
+    /// Start the game right away, without checking for updates
+    #[arg(long, action)]
+    pub no_update: bool,
+    /// Install the updates without asking
+    #[arg(short, long, action)]
+    pub yes: bool,
+    /// Install updates of code mods (mods with a DLL) without asking
+    #[arg(long, action)]
+    pub include_code_mods: bool,
+    /// Update maps with progress in the save files without asking
+    #[arg(long, action)]
+    pub ignore_saves: bool,
+    /// Arguments passed to the game executable, e.g. `-- --console`; Steam uses its launch options
+    #[arg(last = true)]
+    pub game_args: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FeedFormat {
+    /// Atom, supported by most feed readers
+    Atom,
 }
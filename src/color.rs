@@ -0,0 +1,35 @@
+use std::{
+    env,
+    io::{self, IsTerminal},
+    sync::OnceLock,
+};
+
+/// Whether stdout is colored: only in a terminal, unless `NO_COLOR` is set or `TERM` is `dumb`
+pub fn enabled() -> bool {
+    static ENABLED: OnceLock<bool> = OnceLock::new();
+    *ENABLED.get_or_init(|| {
+        io::stdout().is_terminal()
+            && env::var_os("NO_COLOR").is_none_or(|value| value.is_empty())
+            && env::var("TERM").map_or(true, |term| term != "dumb")
+    })
+}
+
+pub fn green(text: &str) -> String {
+    paint("32", text)
+}
+
+pub fn red(text: &str) -> String {
+    paint("31", text)
+}
+
+pub fn yellow(text: &str) -> String {
+    paint("33", text)
+}
+
+fn paint(code: &str, text: &str) -> String {
+    if enabled() && !text.is_empty() {
+        format!("\x1b[{}m{}\x1b[0m", code, text)
+    } else {
+        text.to_string()
+    }
+}
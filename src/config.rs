@@ -0,0 +1,147 @@
+use std::{fs, path::PathBuf};
+
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{cli::Cli, error::Error, fileutil::get_config_path, webhook::WebhookFormat};
+
+/// User configuration stored in `~/.config/everest-mod-cli/config.yaml`
+///
+/// Every field is optional. Command-line options take precedence over the values in this file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Proxy server URL used for all outgoing requests (`http://`, `https://` or `socks5://`)
+    pub proxy: Option<String>,
+    /// Time limit in seconds for establishing a connection, `0` disables it
+    pub connect_timeout: Option<u64>,
+    /// Time limit in seconds for each read while receiving a response, `0` disables it
+    pub read_timeout: Option<u64>,
+    /// PEM bundle with extra root certificates to trust, in addition to the system store
+    pub ca_cert: Option<PathBuf>,
+    /// Skip TLS certificate verification entirely
+    pub insecure: bool,
+    /// User-Agent header sent with every request instead of `everest-mod-cli/<version>`
+    pub user_agent: Option<String>,
+    /// URL of the mod registry to use instead of the official one
+    pub registry_url: Option<String>,
+    /// Dialog language checked by the `languages` command, `English` by default
+    pub language: Option<String>,
+    /// How many times a download failing checksum verification is attempted again, 2 by default
+    pub download_retries: Option<u32>,
+    /// Number of concurrent downloads, and of threads hashing installed archives, 4 by default
+    pub jobs: Option<u32>,
+    /// Further mods directories scanned together with the main one by `list` and `update`,
+    /// e.g. a shared network folder
+    pub extra_mods_dirs: Vec<PathBuf>,
+    /// Further registries merged with the main one, e.g. a community overlay of mods removed from
+    /// GameBanana, given as their URL or with a priority and whether they are trusted
+    #[serde(alias = "extra_registry_urls")]
+    pub extra_registries: Vec<ExtraRegistry>,
+    /// URL notified by `update` when updates are found or installed
+    pub webhook_url: Option<String>,
+    /// Payload posted to the webhook, Discord for Discord webhook URLs and generic JSON otherwise
+    pub webhook_format: Option<WebhookFormat>,
+    /// Number of zipped backups of the save files to keep, made before installing updates of
+    /// code mods, which occasionally corrupt the save data of mods; disabled by default
+    pub save_backups: Option<u32>,
+    /// Age in days after which the cached registry used by offline commands is reported as stale,
+    /// 7 by default, `0` disables the warning
+    pub stale_registry_days: Option<u64>,
+}
+
+/// A registry merged with the main one
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "ExtraRegistryEntry")]
+pub struct ExtraRegistry {
+    pub url: String,
+    /// Registries of higher priority win conflicts over the same mod name, the main registry has
+    /// priority 0 and registries listed later win at equal priority
+    pub priority: i32,
+    /// Whether its entries may replace the entries of the main registry, otherwise they only add
+    /// the mods missing from it
+    pub trusted: bool,
+}
+
+/// A registry as written in the configuration file, a plain URL is untrusted with priority 0
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ExtraRegistryEntry {
+    Url(String),
+    Source {
+        url: String,
+        #[serde(default)]
+        priority: i32,
+        #[serde(default)]
+        trusted: bool,
+    },
+}
+
+impl From<ExtraRegistryEntry> for ExtraRegistry {
+    fn from(entry: ExtraRegistryEntry) -> Self {
+        match entry {
+            ExtraRegistryEntry::Url(url) => Self {
+                url,
+                priority: 0,
+                trusted: false,
+            },
+            ExtraRegistryEntry::Source {
+                url,
+                priority,
+                trusted,
+            } => Self {
+                url,
+                priority,
+                trusted,
+            },
+        }
+    }
+}
+
+impl Config {
+    /// Loads the configuration file, returns the default configuration if it does not exist
+    pub fn load() -> Result<Self, Error> {
+        let path = get_config_path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        info!("Loading configuration from {}", path.display());
+        let content = fs::read(&path)?;
+        // An empty file is a valid (default) configuration
+        if content.iter().all(u8::is_ascii_whitespace) {
+            return Ok(Self::default());
+        }
+        serde_yaml_ng::from_slice(&content).map_err(|source| Error::InvalidConfig { path, source })
+    }
+
+    /// Overrides the configured values with the options passed on the command line
+    pub fn merge_cli(&mut self, cli: &Cli) {
+        if let Some(proxy) = &cli.proxy {
+            self.proxy = Some(proxy.clone());
+        }
+        if let Some(secs) = cli.connect_timeout {
+            self.connect_timeout = Some(secs);
+        }
+        if let Some(secs) = cli.read_timeout {
+            self.read_timeout = Some(secs);
+        }
+        if let Some(retries) = cli.retries {
+            self.download_retries = Some(retries);
+        }
+        if let Some(jobs) = cli.jobs {
+            self.jobs = Some(jobs);
+        }
+        if let Some(path) = &cli.ca_cert {
+            self.ca_cert = Some(path.clone());
+        }
+        if cli.insecure {
+            self.insecure = true;
+        }
+        if let Some(url) = &cli.registry_url {
+            self.registry_url = Some(url.clone());
+        }
+        self.extra_mods_dirs
+            .extend(cli.extra_mods_dirs.iter().cloned());
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::{BTreeMap, HashMap};
+
+use tracing::{info, warn};
+
+use crate::{
+    blacklist::Blacklist, constant::CONFLICT_CHECKED_DIRECTORIES, fileutil::list_zip_entries,
+    installed_mods::LocalModInfo,
+};
+
+/// Overlapping asset paths, keyed by pair of mod names (sorted alphabetically)
+pub type AssetConflicts = BTreeMap<(String, String), Vec<String>>;
+
+/// Indexes the asset paths of every enabled mod and reports the paths shipped by several mods
+///
+/// Archives which cannot be read are skipped with a warning.
+pub fn find_asset_conflicts(
+    installed_mods: &[LocalModInfo],
+    blacklist: &Blacklist,
+) -> AssetConflicts {
+    let mut indexed = Vec::new();
+    for mod_info in installed_mods {
+        if blacklist.contains(&mod_info.archive_file_name()) {
+            continue;
+        }
+        info!("Indexing entries of {}", mod_info.archive_path.display());
+        match list_zip_entries(&mod_info.archive_path) {
+            Ok(entries) => indexed.push((mod_info.manifest.name.as_str(), entries)),
+            Err(e) => warn!(
+                "Failed to read entries of {}: {}",
+                mod_info.archive_path.display(),
+                e
+            ),
+        }
+    }
+    group_conflicts(&indexed)
+}
+
+/// Whether a path is an asset which replaces the same path of other mods
+///
+/// XML files directly under `Graphics/` (sprites, portraits...) are merged by Everest.
+fn is_overriding_asset(path: &str) -> bool {
+    CONFLICT_CHECKED_DIRECTORIES
+        .iter()
+        .any(|dir| path.starts_with(dir))
+        && !(path.starts_with("Graphics/")
+            && path.ends_with(".xml")
+            && path.matches('/').count() == 1)
+}
+
+fn group_conflicts(indexed: &[(&str, Vec<String>)]) -> AssetConflicts {
+    // Asset path -> names of the mods shipping it
+    let mut owners: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (name, entries) in indexed {
+        for path in entries.iter().filter(|path| is_overriding_asset(path)) {
+            let mods = owners.entry(path.as_str()).or_default();
+            // Duplicate archives of the same mod are reported by `doctor` instead
+            if !mods.contains(name) {
+                mods.push(name);
+            }
+        }
+    }
+
+    let mut conflicts = AssetConflicts::new();
+    for (path, mut mods) in owners.into_iter().filter(|(_, mods)| mods.len() > 1) {
+        mods.sort_unstable();
+        for (i, first) in mods.iter().enumerate() {
+            for second in &mods[i + 1..] {
+                conflicts
+                    .entry((first.to_string(), second.to_string()))
+                    .or_default()
+                    .push(path.to_string());
+            }
+        }
+    }
+    for paths in conflicts.values_mut() {
+        paths.sort_unstable();
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|path| path.to_string()).collect()
+    }
+
+    #[test]
+    fn test_group_conflicts_by_pair() {
+        let indexed = vec![
+            (
+                "ModB",
+                entries(&[
+                    "everest.yaml",
+                    "Graphics/Atlases/Gameplay/a.png",
+                    "Maps/x.bin",
+                ]),
+            ),
+            (
+                "ModA",
+                entries(&[
+                    "everest.yaml",
+                    "Graphics/Atlases/Gameplay/a.png",
+                    "Dialog/English.txt",
+                ]),
+            ),
+            (
+                "ModC",
+                entries(&[
+                    "Graphics/Atlases/Gameplay/a.png",
+                    "Maps/x.bin",
+                    "Dialog/English.txt",
+                ]),
+            ),
+        ];
+
+        let conflicts = group_conflicts(&indexed);
+
+        let key = |a: &str, b: &str| (a.to_string(), b.to_string());
+        assert_eq!(conflicts.len(), 3);
+        assert_eq!(
+            conflicts[&key("ModA", "ModB")],
+            vec!["Graphics/Atlases/Gameplay/a.png"]
+        );
+        assert_eq!(
+            conflicts[&key("ModA", "ModC")],
+            vec!["Graphics/Atlases/Gameplay/a.png"]
+        );
+        assert_eq!(
+            conflicts[&key("ModB", "ModC")],
+            vec!["Graphics/Atlases/Gameplay/a.png", "Maps/x.bin"]
+        );
+    }
+
+    #[test]
+    fn test_merged_files_are_not_conflicts() {
+        let indexed = vec![
+            (
+                "ModA",
+                entries(&["Graphics/Sprites.xml", "Dialog/English.txt"]),
+            ),
+            (
+                "ModB",
+                entries(&["Graphics/Sprites.xml", "Dialog/English.txt"]),
+            ),
+        ];
+
+        assert!(group_conflicts(&indexed).is_empty());
+    }
+}
@@ -1,8 +1,241 @@
+use std::time::Duration;
+
 /// The directory where the Celeste mods are stored.
 pub const STEAM_MODS_DIRECTORY_PATH: &str = ".local/share/Steam/steamapps/common/Celeste/Mods";
 
+/// The default User-Agent sent with every request, e.g. `everest-mod-cli/0.5.0`.
+pub const DEFAULT_USER_AGENT: &str =
+    concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
 /// The URL to the remote mod registry.
 pub const MOD_REGISTRY_URL: &str = "https://maddie480.ovh/celeste/everest_update.yaml";
 
-/// The name of the mod manifest file.
-pub const MOD_MANIFEST_FILE: &str = "everest.yaml";
+/// The URL to the mirror of the remote mod registry, used when it cannot be fetched from `MOD_REGISTRY_URL`.
+pub const MOD_REGISTRY_MIRROR_URL: &str =
+    "https://everestapi.github.io/updatermirror/everest_update.yaml";
+
+/// The URL to the dependency graph of all mods in the registry, using `everest.yaml` syntax.
+pub const DEPENDENCY_GRAPH_URL: &str =
+    "https://maddie480.ovh/celeste/mod_dependency_graph.yaml?format=everestyaml";
+
+/// The URL to the search database, listing every mod with its GameBanana statistics.
+pub const SEARCH_DATABASE_URL: &str = "https://maddie480.ovh/celeste/mod_search_database.yaml";
+
+/// The file name of the cached search database.
+pub const SEARCH_DATABASE_CACHE_FILE: &str = "mod_search_database.yaml";
+
+/// How long the cached search database is used before fetching it again.
+pub const SEARCH_DATABASE_CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// The URL to the mod files database, a zip archive describing the contents of each GameBanana file.
+pub const MOD_FILES_DATABASE_URL: &str = "https://maddie480.ovh/celeste/mod_files_database.zip";
+
+/// The file name of the cached mod files database.
+pub const MOD_FILES_DATABASE_CACHE_FILE: &str = "mod_files_database.zip";
+
+/// How long the cached mod files database is used before fetching it again.
+pub const MOD_FILES_DATABASE_CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// The URL prefix of GameBanana downloads, followed by the file id.
+pub const GAMEBANANA_DOWNLOAD_URL: &str = "https://gamebanana.com/mmdl/";
+
+/// The base URL of the GameBanana mirror used by Everest, where each file is stored as `<file id>.zip`.
+pub const GAMEBANANA_MIRROR_URL: &str = "https://banana-mirror-mods.celestemods.com/";
+
+/// The base URL of the GameBanana API, used to fetch mod descriptions.
+pub const GAMEBANANA_API_URL: &str = "https://gamebanana.com/apiv11";
+
+/// Identifier of Celeste on GameBanana, to list the submissions of its game section.
+pub const CELESTE_GAMEBANANA_ID: u32 = 6460;
+
+/// Number of submissions per page of the browse command.
+pub const BROWSE_PAGE_SIZE: u32 = 15;
+
+/// The directory of the cache holding GameBanana API responses and images.
+pub const GAMEBANANA_CACHE_DIRECTORY: &str = "gamebanana";
+
+/// How long a cached GameBanana item (description, screenshots...) is used before fetching it again.
+pub const GAMEBANANA_ITEM_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long a cached page of a GameBanana list is used, new submissions show up after it.
+pub const GAMEBANANA_LIST_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// How long a cached GameBanana image is used, they never change once uploaded.
+pub const GAMEBANANA_IMAGE_CACHE_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// The file name of the cached dependency graph.
+pub const DEPENDENCY_GRAPH_CACHE_FILE: &str = "mod_dependency_graph.yaml";
+
+/// How long the cached dependency graph is used before fetching it again.
+pub const DEPENDENCY_GRAPH_CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// The file name of the cached copy of the latest remote mod registry.
+pub const REGISTRY_CACHE_FILE: &str = "everest_update.yaml";
+
+/// The file name of the cached copy of the registry preceding the latest change.
+pub const REGISTRY_PREVIOUS_CACHE_FILE: &str = "everest_update.previous.yaml";
+
+/// The directory of the cached copies of the extra registries, used by offline commands.
+pub const EXTRA_REGISTRY_CACHE_DIRECTORY: &str = "extra_registries";
+
+/// The file name of the URL, validators and check time of the cached registry.
+pub const REGISTRY_CACHE_STATE_FILE: &str = "everest_update.state.yaml";
+
+/// The file name of the state of the previous update check, in the cache directory.
+pub const LAST_CHECK_FILE: &str = "last_check.yaml";
+
+/// The file name of the manifest written to a vendor directory (an offline mirror).
+pub const VENDOR_MANIFEST_FILE: &str = "everest_update.yaml";
+
+/// The name of Everest's list of mods which should not be loaded, in the mods directory.
+pub const BLACKLIST_FILE: &str = "blacklist.txt";
+
+/// File name of the list of archives excluded from updates (in mods directory).
+pub const UPDATER_BLACKLIST_FILE: &str = "updaterblacklist.txt";
+
+/// Dialog language checked by the `languages` command when none is configured.
+pub const DEFAULT_LANGUAGE: &str = "English";
+
+/// File name of the list of favorite mods (in mods directory).
+pub const FAVORITES_FILE: &str = "favorites.txt";
+
+/// Top-level directories of a mod whose files override each other when two mods ship the same path.
+pub const CONFLICT_CHECKED_DIRECTORIES: [&str; 5] =
+    ["Graphics/", "Maps/", "Audio/", "Effects/", "Tutorials/"];
+
+/// Dependency names which refer to the game or the mod loader rather than to installable mods.
+pub const EVEREST_PSEUDO_DEPENDENCIES: [&str; 3] = ["Everest", "EverestCore", "Celeste"];
+
+/// The name of the log written by the game in its directory, which mentions the Everest build.
+pub const GAME_LOG_FILE: &str = "log.txt";
+
+/// The name of the directory of the save files, next to the game on Windows.
+pub const SAVES_DIRECTORY: &str = "Saves";
+
+/// The directory of the save files under the user data directory, used on Linux.
+pub const USER_SAVES_DIRECTORY_PATH: &str = "Celeste/Saves";
+
+/// The name of the game process, without the `.exe` extension of Windows.
+pub const GAME_PROCESS_NAME: &str = "Celeste";
+
+/// Identifier of Celeste on Steam, to start it through the Steam client.
+pub const CELESTE_STEAM_APP_ID: u32 = 504230;
+
+/// Names of the game executable in the game directory, in order of preference.
+#[cfg(windows)]
+pub const GAME_EXECUTABLES: [&str; 1] = ["Celeste.exe"];
+
+/// Names of the game executable in the game directory, in order of preference.
+#[cfg(not(windows))]
+pub const GAME_EXECUTABLES: [&str; 2] = ["Celeste", "Celeste.bin.x86_64"];
+
+/// Minimum EverestCore version declared by manifests generated with the init command.
+pub const EVEREST_CORE_MIN_VERSION: &str = "1.4465.0";
+
+/// File name of the index of installed archives, in the data directory.
+pub const INSTALLED_INDEX_FILE: &str = "installed.yaml";
+
+/// File name of the operation journal, in the data directory.
+pub const JOURNAL_FILE: &str = "journal.yaml";
+
+/// Directory of the archives saved before updates, in the data directory.
+pub const BACKUP_DIRECTORY: &str = "backups";
+
+/// Directory of the zipped save files made before risky updates, in the data directory.
+pub const SAVE_BACKUP_DIRECTORY: &str = "save-backups";
+
+/// File name of the registry entries subscribed to, in the data directory.
+pub const SUBSCRIPTIONS_FILE: &str = "subscriptions.yaml";
+
+/// File name of the entries of the update feed, in the data directory.
+pub const FEED_FILE: &str = "feed.yaml";
+
+/// File name of the updates already announced to the webhook, in the data directory.
+pub const WEBHOOK_HISTORY_FILE: &str = "webhook.yaml";
+
+/// Number of entries kept in the update feed.
+pub const FEED_MAX_ENTRIES: usize = 100;
+
+/// File name of the mods `merge` never offers again, in the data directory.
+pub const MERGE_SKIPPED_FILE: &str = "merge_skipped.yaml";
+
+/// Directory of the saved sets of enabled mods, in the data directory.
+pub const PROFILES_DIRECTORY: &str = "profiles";
+
+/// Directory of the frozen states of mods directories and their archives, in the data directory.
+pub const SNAPSHOTS_DIRECTORY: &str = "snapshots";
+
+/// Name of the snapshot of the state replaced by the last `thaw`, to go back to it.
+pub const PRE_THAW_SNAPSHOT: &str = "pre-thaw";
+
+/// Directory where batches of updates are downloaded before they are installed, in the mods directory.
+pub const STAGING_DIRECTORY: &str = ".everest-mod-cli-staging";
+
+/// The accepted names of the mod manifest file, in order of precedence.
+pub const MOD_MANIFEST_FILES: [&str; 2] = ["everest.yaml", "everest.yml"];
+
+/// Largest mod manifest read from an archive, real ones are a few kilobytes.
+pub const MAX_MANIFEST_SIZE: u64 = 1024 * 1024;
+
+/// Highest compression ratio accepted for a large mod manifest, higher ones are zip bombs.
+pub const MAX_MANIFEST_COMPRESSION_RATIO: u64 = 100;
+
+/// How long reading and parsing the manifest of one large archive may take while scanning mods.
+pub const MANIFEST_READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Archives from this size on are read with `MANIFEST_READ_TIMEOUT`, smaller ones are read directly.
+pub const MANIFEST_READ_TIMEOUT_MIN_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Worker threads reading the manifests of large archives, at most this many can be stuck at once.
+pub const MANIFEST_READ_WORKERS: usize = 4;
+
+/// Times a file locked by another program is attempted again, the delay doubling each time.
+pub const LOCKED_FILE_RETRIES: u32 = 5;
+
+/// Delay before the first new attempt on a locked file, about 3 seconds are waited in total.
+pub const LOCKED_FILE_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// How long the mods directory must stay quiet before `watch` reads the changed archives.
+pub const WATCH_DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// How often `watch` checks for updates to announce when a webhook is configured.
+pub const WATCH_UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Default age in days after which the cached registry is reported as stale.
+pub const DEFAULT_STALE_REGISTRY_DAYS: u64 = 7;
+
+/// Exit status of `outdated` when updates are pending, distinct from the status of errors.
+pub const UPDATES_AVAILABLE_EXIT_CODE: u8 = 100;
+
+/// The name of the directory used for this tool's configuration and data.
+pub const APP_DIRECTORY_NAME: &str = "everest-mod-cli";
+
+/// The name of the configuration file.
+pub const CONFIG_FILE: &str = "config.yaml";
+
+/// The name of the registry maintained by the user next to the configuration file, merged last.
+pub const LOCAL_REGISTRY_FILE: &str = "local_registry.yaml";
+
+/// Default time limit in seconds for establishing a connection.
+pub const DEFAULT_CONNECT_TIMEOUT_SECS: u64 = 30;
+
+/// Default time limit in seconds for a single read from an established connection.
+pub const DEFAULT_READ_TIMEOUT_SECS: u64 = 60;
+
+/// Default number of times a download failing checksum verification is attempted again.
+pub const DEFAULT_DOWNLOAD_RETRIES: u32 = 2;
+
+/// Default number of concurrent downloads, and of threads hashing installed archives.
+pub const DEFAULT_JOBS: u32 = 4;
+
+/// Minimum delay between the start of two requests, spreading bulk downloads over time.
+pub const REQUEST_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Times a request refused with `429 Too Many Requests` is sent again after waiting.
+pub const RATE_LIMIT_RETRIES: u32 = 3;
+
+/// How long to wait after `429 Too Many Requests` when the server does not send `Retry-After`.
+pub const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// Longest `Retry-After` waited for, a longer ban fails the request instead.
+pub const MAX_RETRY_AFTER: Duration = Duration::from_secs(5 * 60);
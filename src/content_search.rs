@@ -0,0 +1,147 @@
+use std::{fs::File, io::Read, path::Path};
+
+use zip::ZipArchive;
+
+use crate::error::Error;
+
+/// Longest part of a matching line which is printed
+const MAX_LINE_LENGTH: usize = 200;
+
+/// A match of the searched text inside an archive entry
+#[derive(Debug, PartialEq, Eq)]
+pub struct ContentMatch {
+    /// Path of the entry inside the archive
+    pub entry: String,
+    /// Line number and content for text files, `None` for binary maps
+    pub line: Option<(usize, String)>,
+}
+
+/// Case sensitivity and searched text
+#[derive(Debug)]
+pub struct ContentQuery {
+    text: String,
+    ignore_case: bool,
+}
+
+impl ContentQuery {
+    pub fn new(text: &str, ignore_case: bool) -> Self {
+        let text = if ignore_case {
+            text.to_lowercase()
+        } else {
+            text.to_string()
+        };
+        Self { text, ignore_case }
+    }
+
+    fn matches_line(&self, line: &str) -> bool {
+        if self.ignore_case {
+            line.to_lowercase().contains(&self.text)
+        } else {
+            line.contains(&self.text)
+        }
+    }
+
+    fn matches_bytes(&self, data: &[u8]) -> bool {
+        let needle = self.text.as_bytes();
+        if needle.is_empty() || needle.len() > data.len() {
+            return needle.is_empty();
+        }
+        data.windows(needle.len()).any(|window| {
+            if self.ignore_case {
+                window.eq_ignore_ascii_case(needle)
+            } else {
+                window == needle
+            }
+        })
+    }
+}
+
+/// Whether an archive entry is searched: dialog files, map metadata and binary maps
+///
+/// Room names are only stored in the binary maps, which are searched as raw bytes.
+fn is_searched_entry(path: &str) -> bool {
+    let lower = path.to_lowercase();
+    (lower.starts_with("dialog/") && lower.ends_with(".txt"))
+        || (lower.starts_with("maps/")
+            && (lower.ends_with(".meta.yaml") || lower.ends_with(".bin")))
+}
+
+/// Searches the dialog files and maps of an archive
+pub fn search_archive(
+    archive_path: &Path,
+    query: &ContentQuery,
+) -> Result<Vec<ContentMatch>, Error> {
+    let mut archive = ZipArchive::new(File::open(archive_path)?)?;
+    let mut matches = Vec::new();
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if file.is_dir() || !is_searched_entry(file.name()) {
+            continue;
+        }
+        let entry = file.name().to_string();
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)?;
+        matches.extend(search_entry(&entry, &data, query));
+    }
+    Ok(matches)
+}
+
+fn search_entry(entry: &str, data: &[u8], query: &ContentQuery) -> Vec<ContentMatch> {
+    if entry.to_lowercase().ends_with(".bin") {
+        return if query.matches_bytes(data) {
+            vec![ContentMatch {
+                entry: entry.to_string(),
+                line: None,
+            }]
+        } else {
+            Vec::new()
+        };
+    }
+
+    String::from_utf8_lossy(data)
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| query.matches_line(line))
+        .map(|(number, line)| ContentMatch {
+            entry: entry.to_string(),
+            line: Some((number + 1, truncate(line.trim()))),
+        })
+        .collect()
+}
+
+fn truncate(line: &str) -> String {
+    match line.char_indices().nth(MAX_LINE_LENGTH) {
+        Some((end, _)) => format!("{}...", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_entry() {
+        let dialog = b"# Comment\nMYMOD_ROOM_A= Crystal Cave\nMYMOD_ROOM_B= Windy Peak\n";
+        let query = ContentQuery::new("crystal", true);
+
+        assert_eq!(
+            search_entry("Dialog/English.txt", dialog, &query),
+            vec![ContentMatch {
+                entry: String::from("Dialog/English.txt"),
+                line: Some((2, String::from("MYMOD_ROOM_A= Crystal Cave"))),
+            }]
+        );
+        assert!(
+            search_entry(
+                "Dialog/English.txt",
+                dialog,
+                &ContentQuery::new("crystal", false)
+            )
+            .is_empty()
+        );
+
+        let map = b"\x0bCelesteMap\x00\x05lvl_crystal-01\x00";
+        assert_eq!(search_entry("Maps/Me/MyMod/1.bin", map, &query).len(), 1);
+    }
+}
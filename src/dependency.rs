@@ -0,0 +1,398 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    path::Path,
+};
+
+use serde::Deserialize;
+use tracing::info;
+
+use crate::{
+    cache,
+    constant::{DEPENDENCY_GRAPH_CACHE_FILE, DEPENDENCY_GRAPH_CACHE_TTL},
+    download::ModDownloader,
+    error::Error,
+    game::{GameVersions, is_pseudo_dependency},
+    installed_mods::{Dependency, LocalModInfo},
+    version::EverestVersion,
+};
+
+/// Each entry in `mod_dependency_graph.yaml`
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct DependencyGraphEntry {
+    /// Required dependencies
+    #[serde(rename = "Dependencies", default)]
+    pub dependencies: Vec<Dependency>,
+    /// Dependencies which are only loaded first when installed, often enabling extra content
+    #[serde(rename = "OptionalDependencies", default)]
+    pub optional_dependencies: Vec<Dependency>,
+}
+
+/// Dependency graph of every mod in the registry, published by the Everest update checker server
+///
+/// Knowing dependencies upfront avoids downloading each zip just to read its `everest.yaml`.
+#[derive(Debug, Deserialize, Default)]
+pub struct DependencyGraph {
+    #[serde(flatten)]
+    pub entries: HashMap<String, DependencyGraphEntry>,
+}
+
+impl DependencyGraph {
+    /// Loads the dependency graph from the cache, fetching a fresh copy when it is outdated
+    ///
+    /// If the fetch fails, an outdated cache is used rather than failing the command.
+    pub async fn load(downloader: &ModDownloader, cache_dir: &Path) -> Result<Self, Error> {
+        let cache_path = cache_dir.join(DEPENDENCY_GRAPH_CACHE_FILE);
+        let data = cache::fetch_cached(&cache_path, DEPENDENCY_GRAPH_CACHE_TTL, || {
+            downloader.fetch_dependency_graph()
+        })
+        .await?;
+        Self::parse(&data)
+    }
+
+    /// Loads the dependency graph, an empty one when it is unavailable so that installs go on without dependencies
+    pub async fn load_or_default(downloader: &ModDownloader, cache_dir: &Path) -> Self {
+        Self::load(downloader, cache_dir).await.unwrap_or_else(|e| {
+            eprintln!(
+                "Could not load the dependency graph, dependencies will not be installed: {}",
+                e
+            );
+            Self::default()
+        })
+    }
+
+    /// Parses the raw YAML data of the dependency graph
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        info!("Parsing dependency graph");
+        Ok(serde_yaml_ng::from_slice(data)?)
+    }
+
+    /// Returns the direct dependencies of a mod, empty if the mod is unknown
+    pub fn dependencies_of(&self, name: &str) -> &[Dependency] {
+        self.entries
+            .get(name)
+            .map(|entry| entry.dependencies.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns the direct optional dependencies of a mod, empty if the mod is unknown
+    pub fn optional_dependencies_of(&self, name: &str) -> &[Dependency] {
+        self.entries
+            .get(name)
+            .map(|entry| entry.optional_dependencies.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Collects every transitive dependency of a mod, dependencies come before their dependents
+    ///
+    /// The mod itself is not included. Cycles in the graph are tolerated.
+    pub fn resolve(&self, name: &str) -> Vec<String> {
+        fn visit(
+            graph: &DependencyGraph,
+            name: &str,
+            visited: &mut HashSet<String>,
+            order: &mut Vec<String>,
+        ) {
+            for dependency in graph.dependencies_of(name) {
+                if visited.insert(dependency.name.clone()) {
+                    visit(graph, &dependency.name, visited, order);
+                    order.push(dependency.name.clone());
+                }
+            }
+        }
+
+        let mut visited = HashSet::from([name.to_string()]);
+        let mut order = Vec::new();
+        visit(self, name, &mut visited, &mut order);
+        order
+    }
+
+    /// Finds the chains of mods through which each of `candidates` requires `name`
+    ///
+    /// Every returned chain starts with a candidate and ends with `name`,
+    /// e.g. `[SpringCollab2020, CollabUtils2, name]`.
+    pub fn dependent_chains(&self, name: &str, candidates: &HashSet<&str>) -> Vec<Vec<String>> {
+        // Reverse edges: dependency name -> dependent names
+        let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (dependent, entry) in &self.entries {
+            for dependency in &entry.dependencies {
+                dependents
+                    .entry(dependency.name.as_str())
+                    .or_default()
+                    .push(dependent.as_str());
+            }
+        }
+
+        // Breadth-first search keeps the shortest chain for each dependent
+        let mut parent: HashMap<&str, &str> = HashMap::new();
+        let mut queue = VecDeque::from([name]);
+        let mut chains = Vec::new();
+        while let Some(current) = queue.pop_front() {
+            let mut next: Vec<&str> = dependents.get(current).cloned().unwrap_or_default();
+            next.sort_unstable();
+            for dependent in next {
+                if dependent == name || parent.contains_key(dependent) {
+                    continue;
+                }
+                parent.insert(dependent, current);
+                queue.push_back(dependent);
+
+                if candidates.contains(dependent) {
+                    let mut chain = vec![dependent.to_string()];
+                    let mut node = dependent;
+                    while let Some(&up) = parent.get(node) {
+                        chain.push(up.to_string());
+                        node = up;
+                    }
+                    chains.push(chain);
+                }
+            }
+        }
+        chains
+    }
+}
+
+/// An installed dependency older than (or incompatible with) what its dependents require
+#[derive(Debug)]
+pub struct UnsatisfiedDependency<'a> {
+    pub installed_version: &'a str,
+    /// Pairs of (dependent name, required version)
+    pub required_by: Vec<(&'a str, &'a str)>,
+}
+
+/// Results of checking the dependencies declared by installed mods
+#[derive(Debug, Default)]
+pub struct DepcheckReport<'a> {
+    /// Missing dependency name -> names of the installed mods requiring it
+    pub missing: BTreeMap<&'a str, Vec<&'a str>>,
+    /// Installed dependency name -> constraints it does not satisfy
+    pub unsatisfied: BTreeMap<&'a str, UnsatisfiedDependency<'a>>,
+    /// Missing optional dependency name -> names of the installed mods which would use it
+    pub optional_missing: BTreeMap<&'a str, Vec<&'a str>>,
+}
+
+impl DepcheckReport<'_> {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.unsatisfied.is_empty()
+    }
+}
+
+/// Checks that every dependency declared by installed mods is installed in a compatible version
+///
+/// Optional dependencies may be missing, but Everest still requires a compatible version when installed.
+/// Versions that cannot be parsed are not checked, as Everest would not be able to either.
+/// Dependencies on the game or Everest are checked against `game`, and skipped when its version is unknown.
+pub fn check_installed_dependencies<'a>(
+    installed_mods: &'a [LocalModInfo],
+    game: &'a GameVersions,
+) -> DepcheckReport<'a> {
+    let installed_versions: HashMap<&str, &str> = installed_mods
+        .iter()
+        .map(|m| (m.manifest.name.as_str(), m.manifest.version.as_str()))
+        .collect();
+
+    let mut report = DepcheckReport::default();
+    for mod_info in installed_mods {
+        let dependent = mod_info.manifest.name.as_str();
+        let required = mod_info.manifest.dependencies.iter().flatten();
+        let optional = mod_info.manifest.optional_dependencies.iter().flatten();
+        for (dependency, is_optional) in required
+            .map(|dependency| (dependency, false))
+            .chain(optional.map(|dependency| (dependency, true)))
+        {
+            let name = dependency.name.as_str();
+            let installed_version = if is_pseudo_dependency(name) {
+                match game.version_of(name) {
+                    Some(version) => version,
+                    None => {
+                        info!(
+                            "Skipping check of {} required by {}: version of the game unknown",
+                            name, dependent
+                        );
+                        continue;
+                    }
+                }
+            } else {
+                let Some(&installed_version) = installed_versions.get(name) else {
+                    let missing = if is_optional {
+                        &mut report.optional_missing
+                    } else {
+                        &mut report.missing
+                    };
+                    missing.entry(name).or_default().push(dependent);
+                    continue;
+                };
+                installed_version
+            };
+            let Some(required_version) = dependency.version.as_deref() else {
+                continue;
+            };
+            match (
+                EverestVersion::parse(installed_version),
+                EverestVersion::parse(required_version),
+            ) {
+                (Some(installed), Some(required)) if !installed.satisfies(&required) => {
+                    report
+                        .unsatisfied
+                        .entry(name)
+                        .or_insert_with(|| UnsatisfiedDependency {
+                            installed_version,
+                            required_by: Vec::new(),
+                        })
+                        .required_by
+                        .push((dependent, required_version));
+                }
+                (Some(_), Some(_)) => {}
+                _ => info!(
+                    "Skipping version check of {} required by {}: unparsable version",
+                    name, dependent
+                ),
+            }
+        }
+    }
+    report
+}
+
+/// Prints the dependency tree of a mod, marking each dependency as installed or missing
+///
+/// `installed` maps installed mod names to their versions. Subtrees already printed are
+/// abbreviated with `(*)`.
+pub fn print_dependency_tree(graph: &DependencyGraph, name: &str, installed: &HashMap<&str, &str>) {
+    fn walk(
+        graph: &DependencyGraph,
+        name: &str,
+        prefix: &str,
+        installed: &HashMap<&str, &str>,
+        printed: &mut HashSet<String>,
+    ) {
+        let dependencies = graph.dependencies_of(name);
+        for (i, dependency) in dependencies.iter().enumerate() {
+            let is_last = i + 1 == dependencies.len();
+            let branch = if is_last { "└── " } else { "├── " };
+            let constraint = dependency
+                .version
+                .as_deref()
+                .map(|version| format!(" >= {}", version))
+                .unwrap_or_default();
+            let status = match installed.get(dependency.name.as_str()) {
+                Some(version) => format!("installed {}", version),
+                None => String::from("missing"),
+            };
+            let is_repeated = !printed.insert(dependency.name.clone());
+            println!(
+                "{}{}{}{} [{}]{}",
+                prefix,
+                branch,
+                dependency.name,
+                constraint,
+                status,
+                if is_repeated { " (*)" } else { "" }
+            );
+            if !is_repeated {
+                let child_prefix = format!("{}{}", prefix, if is_last { "    " } else { "│   " });
+                walk(graph, &dependency.name, &child_prefix, installed, printed);
+            }
+        }
+    }
+
+    println!("{}", name);
+    walk(graph, name, "", installed, &mut HashSet::new());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAPH: &str = r#"
+SpringCollab2020:
+  URL: https://gamebanana.com/mmdl/1
+  Dependencies:
+  - Name: CollabUtils2
+    Version: 1.3.0
+  - Name: MaxHelpingHand
+    Version: 1.9.0
+CollabUtils2:
+  URL: https://gamebanana.com/mmdl/2
+  Dependencies:
+  - Name: Everest
+    Version: 1.3000.0
+  - Name: MaxHelpingHand
+    Version: 1.0.0
+MaxHelpingHand:
+  URL: https://gamebanana.com/mmdl/3
+  Dependencies:
+  - Name: Everest
+    Version: 1.3000.0
+CycleA:
+  Dependencies:
+  - Name: CycleB
+CycleB:
+  Dependencies:
+  - Name: CycleA
+"#;
+
+    #[test]
+    fn test_resolve_orders_dependencies_first() {
+        let graph = DependencyGraph::parse(GRAPH.as_bytes()).unwrap();
+
+        let resolved = graph.resolve("SpringCollab2020");
+
+        assert_eq!(resolved, vec!["Everest", "MaxHelpingHand", "CollabUtils2"]);
+    }
+
+    #[test]
+    fn test_resolve_tolerates_cycles() {
+        let graph = DependencyGraph::parse(GRAPH.as_bytes()).unwrap();
+
+        assert_eq!(graph.resolve("CycleA"), vec!["CycleB"]);
+    }
+
+    #[test]
+    fn test_check_reports_missing_optional_dependencies_separately() {
+        let manifest = |yaml: &str| {
+            crate::installed_mods::ModManifest::parse_mod_manifest_from_yaml(yaml.as_bytes())
+                .unwrap()
+        };
+        let installed = vec![
+            LocalModInfo::new(
+                "Collab.zip".into(),
+                manifest(
+                    "- Name: Collab\n  Version: 1.0.0\n  OptionalDependencies:\n    \
+                    - Name: ExtraContent\n    - Name: Helper\n      Version: 1.2.0\n",
+                ),
+            ),
+            LocalModInfo::new(
+                "Helper.zip".into(),
+                manifest("- Name: Helper\n  Version: 1.1.0\n"),
+            ),
+        ];
+        let game = GameVersions::default();
+
+        let report = check_installed_dependencies(&installed, &game);
+
+        assert!(report.missing.is_empty());
+        assert_eq!(
+            report.optional_missing,
+            BTreeMap::from([("ExtraContent", vec!["Collab"])])
+        );
+        assert_eq!(
+            report.unsatisfied["Helper"].required_by,
+            [("Collab", "1.2.0")]
+        );
+    }
+
+    #[test]
+    fn test_dependent_chains() {
+        let graph = DependencyGraph::parse(GRAPH.as_bytes()).unwrap();
+        let installed = HashSet::from(["SpringCollab2020", "CollabUtils2"]);
+
+        let chains = graph.dependent_chains("MaxHelpingHand", &installed);
+
+        assert_eq!(
+            chains,
+            vec![
+                vec!["CollabUtils2", "MaxHelpingHand"],
+                vec!["SpringCollab2020", "MaxHelpingHand"],
+            ]
+        );
+    }
+}
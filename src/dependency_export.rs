@@ -0,0 +1,219 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+use serde::Serialize;
+
+use crate::{dependency::DependencyGraph, error::Error, version::EverestVersion};
+
+/// State of a mod in an exported dependency graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeStatus {
+    Installed,
+    /// Installed, but older than what one of its dependents requires
+    Outdated,
+    Missing,
+}
+
+impl NodeStatus {
+    fn color(&self) -> &'static str {
+        match self {
+            NodeStatus::Installed => "palegreen",
+            NodeStatus::Outdated => "orange",
+            NodeStatus::Missing => "lightcoral",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphNode {
+    pub name: String,
+    pub installed_version: Option<String>,
+    pub status: NodeStatus,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphEdge {
+    /// Dependent mod
+    pub from: String,
+    /// Dependency
+    pub to: String,
+    pub required_version: Option<String>,
+    /// Whether the dependency is installed in a version compatible with the requirement
+    pub satisfied: bool,
+}
+
+/// Dependency graph reachable from a set of mods, annotated with the installation state
+#[derive(Debug, Serialize)]
+pub struct DependencyExport {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<GraphEdge>,
+}
+
+impl DependencyExport {
+    /// Collects the mods reachable from `roots` in the dependency graph
+    ///
+    /// `installed` maps installed mod names to their versions. Nodes are sorted by name.
+    pub fn build(graph: &DependencyGraph, roots: &[&str], installed: &HashMap<&str, &str>) -> Self {
+        let mut nodes: BTreeMap<String, GraphNode> = BTreeMap::new();
+        let mut edges = Vec::new();
+        let mut queue: VecDeque<String> = roots.iter().map(|root| root.to_string()).collect();
+
+        while let Some(name) = queue.pop_front() {
+            if nodes.contains_key(&name) {
+                continue;
+            }
+            let installed_version = installed.get(name.as_str()).map(|v| v.to_string());
+            let status = match installed_version {
+                Some(_) => NodeStatus::Installed,
+                None => NodeStatus::Missing,
+            };
+            for dependency in graph.dependencies_of(&name) {
+                let satisfied = installed
+                    .get(dependency.name.as_str())
+                    .is_some_and(|version| satisfies(version, dependency.version.as_deref()));
+                edges.push(GraphEdge {
+                    from: name.clone(),
+                    to: dependency.name.clone(),
+                    required_version: dependency.version.clone(),
+                    satisfied,
+                });
+                queue.push_back(dependency.name.clone());
+            }
+            nodes.insert(
+                name.clone(),
+                GraphNode {
+                    name,
+                    installed_version,
+                    status,
+                },
+            );
+        }
+
+        for edge in edges.iter().filter(|edge| !edge.satisfied) {
+            if let Some(node) = nodes.get_mut(&edge.to)
+                && node.status == NodeStatus::Installed
+            {
+                node.status = NodeStatus::Outdated;
+            }
+        }
+
+        Self {
+            nodes: nodes.into_values().collect(),
+            edges,
+        }
+    }
+
+    /// Serializes the nodes and edges as JSON, for external tools
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Renders the graph in the Graphviz DOT language
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph dependencies {\n");
+        dot.push_str("    rankdir=LR;\n");
+        dot.push_str("    node [shape=box, style=filled];\n");
+        for node in &self.nodes {
+            let label = match &node.installed_version {
+                Some(version) => format!("{}\\n{}", escape(&node.name), escape(version)),
+                None => escape(&node.name),
+            };
+            dot.push_str(&format!(
+                "    \"{}\" [label=\"{}\", fillcolor={}];\n",
+                escape(&node.name),
+                label,
+                node.status.color()
+            ));
+        }
+        for edge in &self.edges {
+            let mut attributes = Vec::new();
+            if let Some(version) = &edge.required_version {
+                attributes.push(format!("label=\">= {}\"", escape(version)));
+            }
+            if !edge.satisfied {
+                attributes.push(String::from("color=red"));
+            }
+            let attributes = if attributes.is_empty() {
+                String::new()
+            } else {
+                format!(" [{}]", attributes.join(", "))
+            };
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\"{};\n",
+                escape(&edge.from),
+                escape(&edge.to),
+                attributes
+            ));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Versions that cannot be parsed are considered compatible, as Everest would not check them either
+fn satisfies(installed_version: &str, required_version: Option<&str>) -> bool {
+    let Some(required_version) = required_version else {
+        return true;
+    };
+    match (
+        EverestVersion::parse(installed_version),
+        EverestVersion::parse(required_version),
+    ) {
+        (Some(installed), Some(required)) => installed.satisfies(&required),
+        _ => true,
+    }
+}
+
+/// Escapes a string for a quoted DOT identifier
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRAPH: &str = r#"
+SpringCollab2020:
+  Dependencies:
+  - Name: CollabUtils2
+    Version: 1.3.0
+  - Name: MaxHelpingHand
+    Version: 1.9.0
+CollabUtils2:
+  Dependencies:
+  - Name: MaxHelpingHand
+    Version: 1.0.0
+"#;
+
+    #[test]
+    fn test_build_marks_missing_and_outdated_mods() {
+        let graph = DependencyGraph::parse(GRAPH.as_bytes()).unwrap();
+        let installed = HashMap::from([("SpringCollab2020", "1.0.0"), ("MaxHelpingHand", "1.5.0")]);
+
+        let export = DependencyExport::build(&graph, &["SpringCollab2020"], &installed);
+
+        let statuses: Vec<_> = export
+            .nodes
+            .iter()
+            .map(|node| (node.name.as_str(), node.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("CollabUtils2", NodeStatus::Missing),
+                ("MaxHelpingHand", NodeStatus::Outdated),
+                ("SpringCollab2020", NodeStatus::Installed),
+            ]
+        );
+        assert_eq!(export.edges.len(), 3);
+
+        let json: serde_json::Value = serde_json::from_str(&export.to_json().unwrap()).unwrap();
+        assert_eq!(json["nodes"][1]["status"], "outdated");
+        assert_eq!(json["nodes"][1]["installed_version"], "1.5.0");
+        assert_eq!(json["edges"][0]["satisfied"], false);
+        assert!(export.to_dot().contains(
+            "\"SpringCollab2020\" -> \"MaxHelpingHand\" [label=\">= 1.9.0\", color=red];"
+        ));
+    }
+}
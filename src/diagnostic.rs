@@ -0,0 +1,187 @@
+use std::{error::Error as _, fmt, io::ErrorKind};
+
+use reqwest::StatusCode;
+
+use crate::{error::Error, pack::PRESETS};
+
+/// An error as shown to the user: code, message, underlying causes and a hint to solve it
+///
+/// ```text
+/// error[E404]: No mods directory found
+///   help: pass the mods directory of your Celeste installation with `--mods-dir <DIR>`
+/// ```
+pub struct Diagnostic<'a> {
+    error: &'a Error,
+}
+
+impl<'a> Diagnostic<'a> {
+    pub fn new(error: &'a Error) -> Self {
+        Self { error }
+    }
+
+    /// Messages of the underlying errors, except those already part of the message
+    pub fn causes(&self) -> Vec<String> {
+        let message = self.error.to_string();
+        let mut causes: Vec<String> = Vec::new();
+        let mut source = self.error.source();
+        while let Some(cause) = source {
+            let text = cause.to_string();
+            if !message.contains(&text) && !causes.iter().any(|known| known.contains(&text)) {
+                causes.push(text);
+            }
+            source = cause.source();
+        }
+        causes
+    }
+
+    /// What the user can try, if anything
+    pub fn hint(&self) -> Option<String> {
+        hint(self.error)
+    }
+}
+
+impl fmt::Display for Diagnostic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "error[{}]: {}", self.error.code(), self.error)?;
+        for cause in self.causes() {
+            write!(f, "\n  caused by: {}", cause)?;
+        }
+        if let Some(hint) = self.hint() {
+            write!(f, "\n  help: {}", hint)?;
+        }
+        Ok(())
+    }
+}
+
+fn hint(error: &Error) -> Option<String> {
+    let hint = match error {
+        Error::InFile { source, .. }
+        | Error::InMod { source, .. }
+        | Error::BatchFailed { source, .. } => return hint(source),
+        Error::Request(e) if e.is_timeout() => {
+            "the server is slow to respond, run the command again or raise `--read-timeout`"
+        }
+        Error::Request(e) if e.is_connect() => {
+            "check your internet connection, or set a proxy with `--proxy <URL>`"
+        }
+        Error::Request(e) if e.status() == Some(StatusCode::TOO_MANY_REQUESTS) => {
+            "the server limits how many requests it accepts, wait a few minutes before running the command again"
+        }
+        Error::Request(e) if e.status().is_some_and(|status| status.is_server_error()) => {
+            "the server has trouble at the moment, try again later"
+        }
+        Error::InvalidRegistry(_) => {
+            "the registry was probably cut short while downloading, run the command again"
+        }
+        Error::MissingManifest(_) | Error::InvalidManifest(_) => {
+            "check the mod manifest with `everest-mod-cli lint <path>`"
+        }
+        Error::UnsafeManifest(_) => {
+            "the archive is corrupt or malicious, delete it or download it again"
+        }
+        Error::Io(e) if e.kind() == ErrorKind::PermissionDenied => {
+            "check the permissions of the file and of the mods directory"
+        }
+        Error::Zip(_) | Error::CorruptArchiveEntry { .. } | Error::CorruptArchives(_) => {
+            "the archive is damaged, reinstall the mod with `everest-mod-cli install <mod_name>`"
+        }
+        Error::CouldNotDetermineHomeDir | Error::MissingModsDirectory => {
+            "pass the mods directory of your Celeste installation with `--mods-dir <DIR>`"
+        }
+        Error::BrokenLink(_) => "check that the drive the link points to is mounted",
+        Error::InterruptedBatch(_) => {
+            "move the archives you want to keep back to the mods directory, then remove that directory"
+        }
+        Error::FileLocked {
+            holder: Some(holder),
+            ..
+        } => {
+            return Some(format!("close {} and run the command again", holder));
+        }
+        Error::FileLocked { .. } => {
+            "wait for the antivirus scan or the program using the file to finish, then run the command again"
+        }
+        Error::Watch(_) => {
+            "on Linux, the limit of watched files may be reached, raise `fs.inotify.max_user_watches`"
+        }
+        Error::EmptyVendorDirectory(_) => {
+            "download the archives first with `everest-mod-cli vendor <dir>`"
+        }
+        Error::InvalidChecksum { .. } => {
+            "the file was corrupted during every download attempt, run the command again later \
+            or with more attempts, e.g. `--retries 5`"
+        }
+        Error::PackVerificationFailed(_) => {
+            "install the missing mods with `everest-mod-cli pack install <pack>`"
+        }
+        Error::UnsafeArchiveEntry { .. } => {
+            "the archive may be malicious, nothing was extracted from it"
+        }
+        Error::InvalidConfig { .. } => "fix or remove the configuration file",
+        Error::InvalidCertificate { .. } => "the file must contain PEM encoded certificates",
+        Error::InvalidPattern(_) => "patterns support `*` and `?` wildcards, e.g. `Spring*`",
+        Error::ManifestAlreadyExists(_) => "pass `--force` to overwrite it",
+        Error::ArchiveAlreadyExists(_) => "rename or remove the existing archive first",
+        Error::UnknownProfile(_) => "list the saved profiles with `everest-mod-cli profile list`",
+        Error::InvalidProfileName(_) => {
+            "profile names cannot start with `.` or contain path separators or `:*?\"<>|`"
+        }
+        Error::UnknownPreset(_) => {
+            let names: Vec<_> = PRESETS.iter().map(|(name, _)| *name).collect();
+            return Some(format!("available presets: {}", names.join(", ")));
+        }
+        Error::DirectoryNotEmpty(_) => {
+            "choose another directory with `--output`, or empty it first"
+        }
+        Error::GameRunning => "close the game first, or pass `--force` to continue anyway",
+        Error::InvalidTemplate(_) => {
+            "write fields in braces, e.g. `{name}\\t{version}`, and `{{` or `}}` for literal braces"
+        }
+        Error::InvalidLocalRegistry { .. } => {
+            "entries use the format of `everest_update.yaml`: Version, URL, xxHash, Size, ..."
+        }
+        Error::MissingDownloadUrl(_) => "pass the URL with `--url <URL>`",
+        Error::GameNotFound(_) => {
+            "pass the Mods folder inside the game directory with `--mods-dir`, or start the game \
+            with your launcher"
+        }
+        Error::UnknownSnapshot(_) => "list the snapshots with `everest-mod-cli thaw`",
+        Error::InvalidSnapshotName(_) => {
+            "snapshot names cannot start with `.` or contain path separators or `:*?\"<>|`"
+        }
+        Error::NoEntriesInModManifest(_) | Error::FileIsNotHashed | Error::TaskFailed(_) => {
+            return Some(format!(
+                "this is a bug, please report it with the command you ran at {}/issues",
+                env!("CARGO_PKG_REPOSITORY")
+            ));
+        }
+        _ => return None,
+    };
+    Some(hint.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Context;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_diagnostic_of_wrapped_error() {
+        let error = Err::<(), _>(Error::InvalidChecksum {
+            file: PathBuf::from("Mods/SpringCollab2020.zip"),
+            computed: String::from("0123456789abcdef"),
+            expected: vec![String::from("fedcba9876543210")],
+        })
+        .in_file(Path::new("Mods/SpringCollab2020.zip"))
+        .unwrap_err();
+
+        let rendered = Diagnostic::new(&error).to_string();
+
+        assert!(rendered.starts_with("error[E501]: 'Mods/SpringCollab2020.zip': Checksum"));
+        assert!(!rendered.contains("caused by"));
+        assert!(
+            rendered.contains("\n  help: the file was corrupted during every download attempt")
+        );
+    }
+}
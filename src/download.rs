@@ -1,13 +1,89 @@
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use futures_util::StreamExt;
-use indicatif::{ProgressBar, ProgressStyle};
-use reqwest::Client;
-use std::path::{Path, PathBuf};
-use tokio::{fs, io::AsyncWriteExt};
-use tracing::info;
-use xxhash_rust::xxh64::Xxh64;
+use indicatif::{HumanBytes, HumanDuration, MultiProgress, ProgressBar, ProgressStyle};
+use reqwest::{
+    Certificate, Client, Proxy, RequestBuilder, Response, StatusCode,
+    header::{CONNECTION, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use tokio::{fs, io::AsyncWriteExt, sync::Semaphore};
+use tracing::{Instrument, info, info_span, warn};
 
-use crate::{constant::MOD_REGISTRY_URL, error::Error};
+use crate::{
+    checksum::{Digests, Hasher},
+    config::Config,
+    constant::{
+        DEFAULT_CONNECT_TIMEOUT_SECS, DEFAULT_DOWNLOAD_RETRIES, DEFAULT_JOBS,
+        DEFAULT_READ_TIMEOUT_SECS, DEFAULT_USER_AGENT, DEPENDENCY_GRAPH_URL, GAMEBANANA_API_URL,
+        MOD_FILES_DATABASE_URL, MOD_REGISTRY_MIRROR_URL, MOD_REGISTRY_URL, RATE_LIMIT_RETRIES,
+        SEARCH_DATABASE_URL,
+    },
+    error::Error,
+    progress,
+    rate_limit::{self, RateLimiter},
+};
+
+/// Build the HTTP client used for every request according to the user configuration
+pub fn build_client(config: &Config) -> Result<Client, Error> {
+    let user_agent = config.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT);
+    info!("User-Agent: {}", user_agent);
+    // Registry and database files are large YAML documents which compress very well,
+    // responses are transparently decompressed according to `Content-Encoding`
+    let mut builder = Client::builder()
+        .user_agent(user_agent)
+        .gzip(true)
+        .brotli(true)
+        .zstd(true);
+
+    // Without an explicit proxy, reqwest picks up the *_PROXY environment variables
+    if let Some(proxy_url) = &config.proxy {
+        info!("Using proxy: {}", proxy_url);
+        builder = builder.proxy(Proxy::all(proxy_url)?);
+    }
+
+    let connect_timeout = config
+        .connect_timeout
+        .unwrap_or(DEFAULT_CONNECT_TIMEOUT_SECS);
+    if connect_timeout > 0 {
+        builder = builder.connect_timeout(Duration::from_secs(connect_timeout));
+    }
+    // A read timeout (unlike a total timeout) does not abort large downloads on slow links
+    let read_timeout = config.read_timeout.unwrap_or(DEFAULT_READ_TIMEOUT_SECS);
+    if read_timeout > 0 {
+        builder = builder.read_timeout(Duration::from_secs(read_timeout));
+    }
+
+    if let Some(path) = &config.ca_cert {
+        info!("Loading extra root certificates from {}", path.display());
+        let pem_bundle = std::fs::read(path)?;
+        let certificates = Certificate::from_pem_bundle(&pem_bundle).map_err(|source| {
+            Error::InvalidCertificate {
+                path: path.clone(),
+                source,
+            }
+        })?;
+        for certificate in certificates {
+            builder = builder.add_root_certificate(certificate);
+        }
+    }
+
+    if config.insecure {
+        warn!("TLS certificate verification is disabled");
+        eprintln!(
+            "WARNING: TLS certificate verification is DISABLED.\n\
+            Downloads can be tampered with by anyone on the network. Use this only as a last resort."
+        );
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    Ok(builder.build()?)
+}
 
 /// Manage mod downloads
 #[derive(Debug, Clone)]
@@ -15,88 +91,622 @@ pub struct ModDownloader {
     client: Client,
     registry_url: String,
     download_dir: PathBuf,
+    batch_progress: Option<BatchProgress>,
+    /// Times a download failing verification is attempted again
+    retries: u32,
+    /// Keep downloads failing verification for inspection instead of deleting them
+    keep_corrupt: bool,
+    /// Whether downloads are verified against the registry checksums
+    verify: bool,
+    /// Limits the number of concurrent downloads, shared by the clones of the downloader
+    download_slots: Arc<Semaphore>,
+    /// Spaces out requests and honors `429 Too Many Requests`, shared by the clones of the downloader
+    rate_limiter: RateLimiter,
 }
 
 impl ModDownloader {
-    pub fn new(download_dir: &Path) -> Self {
+    pub fn new(download_dir: &Path, client: Client, registry_url: &str) -> Self {
         Self {
-            client: Client::new(),
-            registry_url: String::from(MOD_REGISTRY_URL),
+            client,
+            registry_url: registry_url.to_string(),
             download_dir: download_dir.to_path_buf(),
+            batch_progress: None,
+            retries: DEFAULT_DOWNLOAD_RETRIES,
+            keep_corrupt: false,
+            verify: true,
+            download_slots: Arc::new(Semaphore::new(DEFAULT_JOBS as usize)),
+            rate_limiter: RateLimiter::new(),
+        }
+    }
+
+    /// Returns a downloader running at most `jobs` downloads at once
+    pub fn with_jobs(&self, jobs: u32) -> Self {
+        Self {
+            download_slots: Arc::new(Semaphore::new(jobs.max(1) as usize)),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a downloader attempting corrupted downloads `retries` more times
+    pub fn with_retries(&self, retries: u32) -> Self {
+        Self {
+            retries,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a downloader sharing the same client which saves files into another directory
+    pub fn with_download_dir(&self, download_dir: &Path) -> Self {
+        Self {
+            download_dir: download_dir.to_path_buf(),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a downloader sharing the same client which adds its downloads to a batch
+    /// Keeps the combined progress of a batch right when a download fails partway
+    fn discard_progress(&self, bytes: u64) {
+        if let Some(batch_progress) = &self.batch_progress {
+            batch_progress.discard(bytes);
+        }
+    }
+
+    pub fn with_batch_progress(&self, batch_progress: &BatchProgress) -> Self {
+        Self {
+            batch_progress: Some(batch_progress.clone()),
+            ..self.clone()
+        }
+    }
+
+    /// Returns a downloader renaming downloads failing verification instead of deleting them
+    pub fn with_keep_corrupt(&self, keep_corrupt: bool) -> Self {
+        Self {
+            keep_corrupt,
+            ..self.clone()
+        }
+    }
+
+    /// Returns a downloader which skips checksum verification if `verify` is false
+    pub fn with_verify(&self, verify: bool) -> Self {
+        Self {
+            verify,
+            ..self.clone()
         }
     }
 
     /// Fetch remote mod registry, returns bytes of response
     pub async fn fetch_mod_registry(&self) -> Result<Bytes, Error> {
         info!("Fetching remote mod registry...");
-        let response = self.client.get(&self.registry_url).send().await?;
-        let yaml_data = response.bytes().await?;
-        Ok(yaml_data)
+        let response = self.send_registry_request(|url| self.client.get(url)).await;
+        read_with_progress(response, "Fetching mod registry")
+            .instrument(info_span!("registry fetch"))
+            .await
+    }
+
+    /// Fetch remote mod registry unless it is unchanged since it was fetched with `previous`
+    /// validators, returns the bytes of the response and its validators
+    pub async fn fetch_mod_registry_if_changed(
+        &self,
+        previous: Option<&RegistryValidators>,
+    ) -> Result<Option<(Bytes, RegistryValidators)>, Error> {
+        info!("Fetching remote mod registry...");
+        let request = |url: &str| {
+            let mut request = self.client.get(url);
+            if let Some(previous) = previous {
+                if let Some(etag) = &previous.etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &previous.last_modified {
+                    request = request.header(IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+            request
+        };
+        let response = self
+            .send_registry_request(request)
+            .instrument(info_span!("registry fetch"))
+            .await;
+        if let Ok(response) = &response
+            && response.status() == StatusCode::NOT_MODIFIED
+        {
+            return Ok(None);
+        }
+        let validators = match &response {
+            Ok(response) => RegistryValidators::of(response),
+            Err(_) => RegistryValidators::default(),
+        };
+        let data = read_with_progress(response, "Fetching mod registry")
+            .instrument(info_span!("registry fetch"))
+            .await?;
+        Ok(Some((data, validators)))
+    }
+
+    /// Fetch a registry merged into the main one, returns bytes of response
+    pub async fn fetch_extra_registry(&self, url: &str) -> Result<Bytes, Error> {
+        info!("Fetching extra mod registry {}...", url);
+        let response = self.send(self.client.get(url)).await;
+        read_with_progress(response, "Fetching extra mod registry")
+            .instrument(info_span!("registry fetch"))
+            .await
+    }
+
+    pub fn registry_url(&self) -> &str {
+        &self.registry_url
+    }
+
+    /// Fetch the dependency graph of all mods, returns bytes of response
+    pub async fn fetch_dependency_graph(&self) -> Result<Bytes, Error> {
+        info!("Fetching mod dependency graph...");
+        self.fetch_bytes(DEPENDENCY_GRAPH_URL).await
+    }
+
+    /// Fetch the search database with the statistics of all mods, returns bytes of response
+    pub async fn fetch_search_database(&self) -> Result<Bytes, Error> {
+        info!("Fetching mod search database...");
+        self.fetch_bytes(SEARCH_DATABASE_URL).await
+    }
+
+    /// Fetch the mod files database, returns bytes of response
+    pub async fn fetch_mod_files_database(&self) -> Result<Bytes, Error> {
+        info!("Fetching mod files database...");
+        self.fetch_bytes(MOD_FILES_DATABASE_URL).await
     }
 
-    /// Download mod file and verify checksum
+    /// Fetch the profile page of a GameBanana item, returns bytes of the JSON response
+    pub async fn fetch_gamebanana_item(
+        &self,
+        item_type: &str,
+        item_id: u32,
+    ) -> Result<Bytes, Error> {
+        info!("Fetching GameBanana item {} {}...", item_type, item_id);
+        let url = format!(
+            "{}/{}/{}/ProfilePage",
+            GAMEBANANA_API_URL, item_type, item_id
+        );
+        self.fetch_bytes(&url).await
+    }
+
+    /// Fetch a page of submissions of a GameBanana list endpoint, returns bytes of the JSON response
+    pub async fn fetch_gamebanana_list(&self, endpoint: &str) -> Result<Bytes, Error> {
+        info!("Fetching GameBanana list {}...", endpoint);
+        self.fetch_bytes(&format!("{}/{}", GAMEBANANA_API_URL, endpoint))
+            .await
+    }
+
+    /// Fetch a pack definition hosted by a community, returns bytes of response
+    pub async fn fetch_pack(&self, url: &str) -> Result<Bytes, Error> {
+        info!("Fetching pack {}...", url);
+        self.fetch_bytes(url).await
+    }
+
+    /// Fetch a mod archive without verifying it, returns bytes of response
+    pub async fn fetch_archive(&self, url: &str) -> Result<Bytes, Error> {
+        info!("Fetching archive {}...", url);
+        let response = self.send(self.client.get(url)).await;
+        read_with_progress(response, "Downloading archive").await
+    }
+
+    /// Fetch an image such as a screenshot, returns bytes of response
+    pub async fn fetch_image(&self, url: &str) -> Result<Bytes, Error> {
+        info!("Fetching image {}...", url);
+        self.fetch_bytes(url).await
+    }
+
+    /// Post a JSON document, e.g. a webhook notification
+    pub async fn post_json(&self, url: &str, body: &serde_json::Value) -> Result<(), Error> {
+        info!("Posting to {}...", url);
+        let request = self
+            .client
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.to_string());
+        self.send(request).await?.error_for_status()?;
+        Ok(())
+    }
+
+    async fn fetch_bytes(&self, url: &str) -> Result<Bytes, Error> {
+        let response = self.send(self.client.get(url)).await?.error_for_status()?;
+        let data = response.bytes().await?;
+        Ok(data)
+    }
+
+    /// Sends a request once the rate limiter allows it, sending it again after waiting as long as
+    /// the server asks when it answers `429 Too Many Requests`
+    async fn send(&self, request: RequestBuilder) -> reqwest::Result<Response> {
+        let mut attempt = 0;
+        loop {
+            self.rate_limiter.wait().await;
+            // Bodies of the requests sent here are never streams, so they can always be cloned
+            let Some(retry) = request.try_clone() else {
+                return request.send().await;
+            };
+            let response = retry.send().await?;
+            let delay = match rate_limit::retry_delay(&response) {
+                Some(delay) if attempt < RATE_LIMIT_RETRIES => delay,
+                _ => return Ok(response),
+            };
+            attempt += 1;
+            self.rate_limiter.pause(delay);
+            eprintln!(
+                "Too many requests to {}, waiting {} before trying again...",
+                response.url().host_str().unwrap_or_default(),
+                HumanDuration(delay)
+            );
+        }
+    }
+
+    /// Sends the registry request built by `request` for a URL, to the mirror of the official
+    /// registry when its host cannot be reached or fails with a server error
+    async fn send_registry_request(
+        &self,
+        request: impl Fn(&str) -> RequestBuilder,
+    ) -> reqwest::Result<Response> {
+        let response = self.send(request(&self.registry_url)).await;
+        let failure = match &response {
+            Ok(response) if response.status().is_server_error() => response.status().to_string(),
+            Err(e) if e.is_timeout() => String::from("timed out"),
+            Err(e) if e.is_connect() => String::from("connection failed"),
+            Err(e) if !e.is_builder() => e.to_string(),
+            _ => {
+                info!("Fetched the mod registry from {}", self.registry_url);
+                return response;
+            }
+        };
+        // Custom registries have no known mirror
+        if self.registry_url != MOD_REGISTRY_URL {
+            return response;
+        }
+        eprintln!(
+            "Warning: the mod registry could not be fetched from {} ({}), trying its mirror...",
+            self.registry_url, failure
+        );
+        match self.send(request(MOD_REGISTRY_MIRROR_URL)).await {
+            Ok(mirror)
+                if mirror.status().is_success() || mirror.status() == StatusCode::NOT_MODIFIED =>
+            {
+                eprintln!("Using the mod registry from {}", MOD_REGISTRY_MIRROR_URL);
+                Ok(mirror)
+            }
+            mirror => {
+                warn!(
+                    "The mirror of the mod registry failed too: {}",
+                    match mirror {
+                        Ok(mirror) => mirror.status().to_string(),
+                        Err(e) => e.to_string(),
+                    }
+                );
+                response
+            }
+        }
+    }
+
+    /// Download mod file and verify checksum, returns the path and digests of the downloaded file
+    ///
+    /// The SHA-256 checksum is verified too when the registry entry provides one. Downloads
+    /// failing verification are attempted again, alternating with `mirror_url` when provided.
     pub async fn download_mod(
         &self,
         url: &str,
+        mirror_url: Option<&str>,
         name: &str,
         expected_hash: &[String],
-    ) -> Result<(), Error> {
+        expected_sha256: Option<&str>,
+    ) -> Result<DownloadedArchive, Error> {
+        // Wait for a running download to finish when `jobs` downloads are already running
+        let _slot = self.download_slots.acquire().await;
+        let mut attempt = 0;
+        loop {
+            let source = match mirror_url {
+                Some(mirror_url) if attempt % 2 == 1 => mirror_url,
+                _ => url,
+            };
+            let result = self
+                .download_once(source, name, expected_hash, expected_sha256)
+                .await;
+            match result {
+                Err(Error::InvalidChecksum { .. }) if attempt < self.retries => {
+                    attempt += 1;
+                    warn!("Corrupted download of {} from {}", name, source);
+                    println!(
+                        "  Downloading again (attempt {} of {})...",
+                        attempt + 1,
+                        self.retries + 1
+                    );
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn download_once(
+        &self,
+        url: &str,
+        name: &str,
+        expected_hash: &[String],
+        expected_sha256: Option<&str>,
+    ) -> Result<DownloadedArchive, Error> {
         info!("Start downloading mod: {}", name);
+        // Spans are not entered across await points, they are only timed from creation to drop
+        let download_span = info_span!("download");
 
-        let response = self.client.get(url).send().await?.error_for_status()?;
+        // Never reuse the connection of a previous download: a retry after a corrupted
+        // download gets a fresh connection, possibly to another server of the CDN
+        let response = self
+            .send(self.client.get(url).header(CONNECTION, "close"))
+            .await?
+            .error_for_status()?;
         info!("Status code: {}", response.status().as_u16());
 
         let filename = util::determine_filename(&response)?;
         let download_path = self.download_dir.join(format!("{}.zip", filename));
         info!("Destination: {}", download_path.display());
 
-        let total_size = response.content_length().unwrap_or(0);
-        info!("Total file size: {}", total_size);
+        let total_size = response.content_length();
+        info!("Total file size: {:?}", total_size);
 
-        let pb = ProgressBar::new(total_size);
-        pb.set_style(ProgressStyle::default_bar()
-            .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({eta})")
-            .unwrap()
-            .progress_chars("#>-"));
+        let pb = download_bar(total_size);
+        let pb = match &self.batch_progress {
+            Some(batch_progress) => batch_progress.add(pb),
+            None => pb,
+        };
 
         let mut stream = response.bytes_stream();
 
-        let mut hasher = Xxh64::new(0);
+        let mut hasher = Hasher::for_checksums(expected_hash);
+        if expected_sha256.is_some() {
+            hasher = hasher.with_sha256();
+        }
         let mut file = fs::File::create(&download_path).await?;
         let mut downloaded: u64 = 0;
 
         while let Some(chunk) = stream.next().await {
-            let chunk = chunk?;
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    self.discard_progress(downloaded);
+                    return Err(e.into());
+                }
+            };
             file.write_all(&chunk).await?;
             hasher.update(&chunk);
-            let new = std::cmp::min(downloaded + (chunk.len() as u64), total_size);
-            downloaded = new;
-            pb.set_position(new);
+            if let Some(batch_progress) = &self.batch_progress {
+                batch_progress.total.inc(chunk.len() as u64);
+            }
+            downloaded += chunk.len() as u64;
+            match total_size {
+                Some(total_size) => pb.set_position(downloaded.min(total_size)),
+                None => pb.set_position(downloaded),
+            }
         }
 
         pb.finish_with_message("Download complete");
+        drop(download_span);
 
         // Verify checksum
-        let hash = hasher.digest();
-        let hash_str = format!("{:016x}", hash);
-        info!("xxhash of downloaded file: {}", hash_str);
+        let digests = hasher.finish();
+        info!("Digests of downloaded file: {:?}", digests);
 
+        if !self.verify {
+            println!(
+                "\n  WARNING: checksum verification skipped, {} is unverified",
+                name
+            );
+            return Ok(DownloadedArchive {
+                path: download_path,
+                digests,
+                verified: false,
+            });
+        }
+
+        let _verify_span = info_span!("verify");
         println!("\n  Verifying checksum...");
-        if expected_hash.contains(&hash_str) {
+        if digests.matches(expected_hash) {
             println!("  Checksum verified!");
         } else {
             println!("  Checksum verification failed!");
-            fs::remove_file(&download_path).await?;
-            println!("  Downloaded file removed");
+            self.discard_progress(downloaded);
             return Err(Error::InvalidChecksum {
-                file: download_path,
-                computed: hash_str,
+                file: self.discard_corrupt(&download_path).await?,
+                computed: digests.display_for(expected_hash),
                 expected: expected_hash.to_vec(),
             });
         }
 
-        Ok(())
+        if let Some(expected_sha256) = expected_sha256 {
+            if digests.matches_sha256(expected_sha256) {
+                println!("  SHA-256 verified!");
+            } else {
+                println!("  SHA-256 verification failed!");
+                self.discard_progress(downloaded);
+                return Err(Error::InvalidChecksum {
+                    file: self.discard_corrupt(&download_path).await?,
+                    computed: digests.sha256.unwrap_or_default(),
+                    expected: vec![expected_sha256.to_string()],
+                });
+            }
+        }
+
+        Ok(DownloadedArchive {
+            path: download_path,
+            digests,
+            verified: true,
+        })
+    }
+
+    /// Removes a download which failed verification, or renames it to `<name>.zip.corrupt`
+    /// with `keep_corrupt`, returns the path of the kept file
+    async fn discard_corrupt(&self, path: &Path) -> Result<PathBuf, Error> {
+        if self.keep_corrupt {
+            let mut kept = path.as_os_str().to_owned();
+            kept.push(".corrupt");
+            let kept = PathBuf::from(kept);
+            fs::rename(path, &kept).await?;
+            println!("  Corrupted file kept as {}", kept.display());
+            Ok(kept)
+        } else {
+            fs::remove_file(path).await?;
+            println!("  Downloaded file removed");
+            Ok(path.to_path_buf())
+        }
+    }
+}
+
+/// Reads the body of a response with a progress bar labelled with `message`
+async fn read_with_progress(
+    response: reqwest::Result<Response>,
+    message: &'static str,
+) -> Result<Bytes, Error> {
+    let response = response?.error_for_status()?;
+    let size = response.content_length();
+    let bar = progress::fetch_bar(size, message);
+    let mut data = BytesMut::with_capacity(size.unwrap_or_default() as usize);
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.inspect_err(|_| bar.finish_and_clear())?;
+        data.extend_from_slice(&chunk);
+        bar.inc(chunk.len() as u64);
     }
+    bar.finish_and_clear();
+    Ok(data.freeze())
+}
+
+/// Headers identifying a version of the registry, sent back to only download it again if it changed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegistryValidators {
+    #[serde(rename = "ETag", default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(
+        rename = "LastModified",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub last_modified: Option<String>,
+}
+
+impl RegistryValidators {
+    fn of(response: &Response) -> Self {
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(String::from)
+        };
+        Self {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+}
+
+/// Progress bar of a download with its speed and ETA, a spinner if the size is unknown
+///
+/// The ETA of indicatif is computed from an exponentially weighted average of the recent speed,
+/// so it does not jump around with every chunk.
+fn download_bar(size: Option<u64>) -> ProgressBar {
+    match size {
+        Some(size) => ProgressBar::with_draw_target(Some(size), progress::draw_target()).with_style(
+            ProgressStyle::default_bar()
+                .template("{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} {binary_bytes_per_sec} ({eta})")
+                .unwrap()
+                .progress_chars("#>-"),
+        ),
+        None => {
+            let bar = ProgressBar::with_draw_target(None, progress::draw_target()).with_style(
+                ProgressStyle::default_spinner()
+                    .template("{spinner:.green} [{elapsed_precise}] {bytes} {binary_bytes_per_sec}")
+                    .unwrap(),
+            );
+            // Keep spinning while waiting for data
+            bar.enable_steady_tick(Duration::from_millis(100));
+            bar
+        }
+    }
+}
+
+/// Combined progress of several downloads, displayed below their own progress bars
+#[derive(Debug, Clone)]
+pub struct BatchProgress {
+    bars: MultiProgress,
+    total: ProgressBar,
+    started: Instant,
+}
+
+impl BatchProgress {
+    /// Starts the combined progress of downloads totalling `total_bytes`, their sizes in the registry
+    pub fn new(total_bytes: u64) -> Self {
+        let bars = MultiProgress::with_draw_target(progress::draw_target());
+        let total = bars.add(ProgressBar::new(total_bytes));
+        total.set_style(
+            ProgressStyle::default_bar()
+                .template("Total: [{elapsed_precise}] {bytes}/{total_bytes} at {binary_bytes_per_sec} ({eta})")
+                .unwrap(),
+        );
+        Self {
+            bars,
+            total,
+            started: Instant::now(),
+        }
+    }
+
+    /// Adds the progress bar of a download above the combined progress line
+    fn add(&self, bar: ProgressBar) -> ProgressBar {
+        self.bars.insert_before(&self.total, bar)
+    }
+
+    /// Accounts for the bytes of a failed download, which are downloaded again or not at all
+    fn discard(&self, bytes: u64) {
+        self.total.inc_length(bytes);
+    }
+
+    /// Removes the combined progress line, returns the final totals
+    pub fn finish(&self) -> TransferSummary {
+        self.total.finish_and_clear();
+        TransferSummary {
+            bytes: self.total.position(),
+            elapsed: self.started.elapsed(),
+        }
+    }
+}
+
+/// Totals of a batch of downloads
+#[derive(Debug)]
+pub struct TransferSummary {
+    pub bytes: u64,
+    pub elapsed: Duration,
+}
+
+impl TransferSummary {
+    /// Average speed in bytes per second
+    pub fn average_speed(&self) -> u64 {
+        let seconds = self.elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            (self.bytes as f64 / seconds) as u64
+        } else {
+            self.bytes
+        }
+    }
+}
+
+impl fmt::Display for TransferSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Downloaded {} in {} ({}/s)",
+            HumanBytes(self.bytes),
+            HumanDuration(self.elapsed),
+            HumanBytes(self.average_speed())
+        )
+    }
+}
+
+/// A mod archive downloaded and verified by `ModDownloader::download_mod`
+#[derive(Debug)]
+pub struct DownloadedArchive {
+    pub path: PathBuf,
+    pub digests: Digests,
+    /// False if checksum verification was skipped
+    pub verified: bool,
 }
 
 mod util {
@@ -137,3 +747,22 @@ mod util {
             .map(|etag| format!("{}.zip", etag))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_summary() {
+        let summary = TransferSummary {
+            bytes: 10 * 1024 * 1024,
+            elapsed: Duration::from_secs(4),
+        };
+
+        assert_eq!(summary.average_speed(), 2621440);
+        assert_eq!(
+            summary.to_string(),
+            "Downloaded 10.00 MiB in 4 seconds (2.50 MiB/s)"
+        );
+    }
+}
@@ -1,45 +1,292 @@
-use std::{collections::VecDeque, path::PathBuf};
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+};
 
 use thiserror::Error;
 
 use crate::installed_mods::ModManifest;
 
+/// Broad kind of an error, to tell whether the network, the data or the user is at fault
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    Network,
+    RegistryParse,
+    Manifest,
+    Filesystem,
+    Verification,
+    UserInput,
+    /// Bugs of this tool
+    Internal,
+}
+
+impl ErrorCategory {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorCategory::Network => "network",
+            ErrorCategory::RegistryParse => "registry-parse",
+            ErrorCategory::Manifest => "manifest",
+            ErrorCategory::Filesystem => "filesystem",
+            ErrorCategory::Verification => "verification",
+            ErrorCategory::UserInput => "user-input",
+            ErrorCategory::Internal => "internal",
+        }
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum Error {
+    // Network
     #[error(transparent)]
-    Io(#[from] std::io::Error),
-    #[error(transparent)]
-    Zip(#[from] zip::result::ZipError),
+    Request(#[from] reqwest::Error),
+    #[error("Invalid Host header in request: '{0}'")]
+    InvalidHostHeader(String),
+
+    // Registry and other remote data
+    #[error("Failed to parse the mod registry: {0}")]
+    InvalidRegistry(serde_yaml_ng::Error),
     #[error(transparent)]
     Yaml(#[from] serde_yaml_ng::Error),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Image(#[from] image::ImageError),
 
-    #[error(
-        "Could not determine home directory location!\
-        Please specify the mods directory using --mods-dir"
-    )]
+    // Mod manifests
+    #[error("No mod manifest (everest.yaml) found in '{0}'")]
+    MissingManifest(PathBuf),
+    #[error("Invalid mod manifest (everest.yaml): {0}")]
+    InvalidManifest(serde_yaml_ng::Error),
+    #[error("Refusing to read the mod manifest (everest.yaml): {0}")]
+    UnsafeManifest(String),
+
+    // Filesystem
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Could not determine the location of the home directory")]
     CouldNotDetermineHomeDir,
-    #[error(
-        "No mods directory found.\
-        Please verify that Everest is properly installed"
-    )]
+    #[error("No mods directory found, is Everest installed?")]
     MissingModsDirectory,
+    #[error("'{0}' is a symbolic link to a missing location")]
+    BrokenLink(PathBuf),
+    #[error("'{}' is in use by {}", path.display(), holder.unwrap_or("another program"))]
+    FileLocked {
+        path: PathBuf,
+        /// Program which most likely holds the file open, when it can be told
+        holder: Option<&'static str>,
+        source: std::io::Error,
+    },
+    #[error("No vendored mods found in '{0}'")]
+    EmptyVendorDirectory(PathBuf),
+    #[error("Could not watch the mods directory: {0}")]
+    Watch(#[from] notify::Error),
+    #[error("An interrupted batch left outdated archives in '{0}' which could not be put back")]
+    InterruptedBatch(PathBuf),
+
+    // Verification
     #[error(
-        "CRITICAL BUG!! NEVER GONNA HAPPEN!!\
-        SINCE PARSING SUCCEEDED!!\n{0:#?}"
-    )]
-    NoEntriesInModManifest(VecDeque<ModManifest>),
-    #[error(
-        "Checksum verification failed for '{file}':\
-        computed checksum '{computed}' does not match\
-        expected checksums {expected:#?}"
+        "Checksum verification failed for '{file}': \
+        computed checksum '{computed}' does not match expected checksums {expected:?}"
     )]
     InvalidChecksum {
         file: PathBuf,
         computed: String,
         expected: Vec<String>,
     },
-    #[error(transparent)]
-    Request(#[from] reqwest::Error),
+    #[error("Entry '{entry}' is corrupted: {source}")]
+    CorruptArchiveEntry {
+        entry: String,
+        source: std::io::Error,
+    },
+    #[error("{0} archive(s) are corrupted")]
+    CorruptArchives(usize),
+    #[error("Validation found {0} error(s)")]
+    LintFailed(usize),
+    #[error("{0} mod(s) of the pack are missing or not in the pinned version")]
+    PackVerificationFailed(usize),
+    #[error("Refusing to extract the entry '{entry}': {reason}")]
+    UnsafeArchiveEntry { entry: String, reason: &'static str },
+
+    // User input
+    #[error("Failed to parse the configuration file '{path}': {source}")]
+    InvalidConfig {
+        path: PathBuf,
+        source: serde_yaml_ng::Error,
+    },
+    #[error("Failed to load the CA certificates from '{path}': {source}")]
+    InvalidCertificate {
+        path: PathBuf,
+        source: reqwest::Error,
+    },
+    #[error("Invalid pattern: {0}")]
+    InvalidPattern(#[from] glob::PatternError),
+    #[error("A mod manifest already exists at '{0}'")]
+    ManifestAlreadyExists(PathBuf),
+    #[error("'{0}' is not a zip file")]
+    NotAZipFile(PathBuf),
+    #[error("Cannot undo this operation: {0}")]
+    CannotUndo(String),
+    #[error("A different archive already exists at '{0}'")]
+    ArchiveAlreadyExists(PathBuf),
+    #[error("No profile named '{0}'")]
+    UnknownProfile(String),
+    #[error("Invalid profile name '{0}'")]
+    InvalidProfileName(String),
+    #[error("No preset named '{0}'")]
+    UnknownPreset(String),
+    #[error("'{0}' already exists and is not empty")]
+    DirectoryNotEmpty(PathBuf),
+    #[error("Celeste is running, changing its mods now may crash it or fail on locked files")]
+    GameRunning,
+    #[error("Invalid format template: {0}")]
+    InvalidTemplate(String),
+    #[error("'{0}' is a local file, the registry needs the URL it is downloaded from")]
+    MissingDownloadUrl(PathBuf),
+    #[error("No snapshot named '{0}'")]
+    UnknownSnapshot(String),
+    #[error("Invalid snapshot name '{0}'")]
+    InvalidSnapshotName(String),
+    #[error("Could not find the game next to the mods directory '{0}'")]
+    GameNotFound(PathBuf),
+    #[error("Failed to parse the local registry '{path}': {source}")]
+    InvalidLocalRegistry {
+        path: PathBuf,
+        source: serde_yaml_ng::Error,
+    },
+
+    // Internal
+    #[error(
+        "CRITICAL BUG!! NEVER GONNA HAPPEN!!\
+        SINCE PARSING SUCCEEDED!!\n{0:#?}"
+    )]
+    NoEntriesInModManifest(VecDeque<ModManifest>),
     #[error("The file is not hashed. It seems developer's fault")]
     FileIsNotHashed,
+    #[error("A background task failed: {0}")]
+    TaskFailed(#[from] tokio::task::JoinError),
+
+    // Context, the category and code are those of the source
+    #[error("'{}': {source}", path.display())]
+    InFile { path: PathBuf, source: Box<Error> },
+    #[error("{name}: {source}")]
+    InMod { name: String, source: Box<Error> },
+    #[error("{failed} of {total} mod(s) failed, first: {source}")]
+    BatchFailed {
+        failed: usize,
+        total: usize,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Stable code of the error, e.g. `E401`, to search for or refer to in bug reports
+    ///
+    /// The first digit is the category. Codes are never reused once published.
+    pub fn code(&self) -> &'static str {
+        self.classify().1
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        self.classify().0
+    }
+
+    fn classify(&self) -> (ErrorCategory, &'static str) {
+        use ErrorCategory::*;
+        match self {
+            Error::Request(_) => (Network, "E101"),
+            Error::InvalidHostHeader(_) => (Network, "E102"),
+            Error::InvalidRegistry(_) => (RegistryParse, "E201"),
+            Error::Yaml(_) => (RegistryParse, "E202"),
+            Error::Json(_) => (RegistryParse, "E203"),
+            Error::Image(_) => (RegistryParse, "E204"),
+            Error::MissingManifest(_) => (Manifest, "E301"),
+            Error::InvalidManifest(_) => (Manifest, "E302"),
+            Error::UnsafeManifest(_) => (Manifest, "E303"),
+            Error::Io(_) => (Filesystem, "E401"),
+            Error::Zip(_) => (Filesystem, "E402"),
+            Error::CouldNotDetermineHomeDir => (Filesystem, "E403"),
+            Error::MissingModsDirectory => (Filesystem, "E404"),
+            Error::EmptyVendorDirectory(_) => (Filesystem, "E405"),
+            Error::BrokenLink(_) => (Filesystem, "E406"),
+            Error::FileLocked { .. } => (Filesystem, "E407"),
+            Error::Watch(_) => (Filesystem, "E408"),
+            Error::InterruptedBatch(_) => (Filesystem, "E409"),
+            Error::InvalidChecksum { .. } => (Verification, "E501"),
+            Error::CorruptArchiveEntry { .. } => (Verification, "E502"),
+            Error::CorruptArchives(_) => (Verification, "E503"),
+            Error::LintFailed(_) => (Verification, "E504"),
+            Error::PackVerificationFailed(_) => (Verification, "E505"),
+            Error::UnsafeArchiveEntry { .. } => (Verification, "E506"),
+            Error::InvalidConfig { .. } => (UserInput, "E601"),
+            Error::InvalidCertificate { .. } => (UserInput, "E602"),
+            Error::InvalidPattern(_) => (UserInput, "E603"),
+            Error::ManifestAlreadyExists(_) => (UserInput, "E604"),
+            Error::NotAZipFile(_) => (UserInput, "E605"),
+            Error::CannotUndo(_) => (UserInput, "E606"),
+            Error::ArchiveAlreadyExists(_) => (UserInput, "E607"),
+            Error::UnknownProfile(_) => (UserInput, "E608"),
+            Error::InvalidProfileName(_) => (UserInput, "E609"),
+            Error::UnknownPreset(_) => (UserInput, "E610"),
+            Error::DirectoryNotEmpty(_) => (UserInput, "E611"),
+            Error::GameRunning => (UserInput, "E612"),
+            Error::InvalidTemplate(_) => (UserInput, "E613"),
+            Error::InvalidLocalRegistry { .. } => (UserInput, "E614"),
+            Error::MissingDownloadUrl(_) => (UserInput, "E615"),
+            Error::UnknownSnapshot(_) => (UserInput, "E616"),
+            Error::InvalidSnapshotName(_) => (UserInput, "E617"),
+            Error::GameNotFound(_) => (UserInput, "E618"),
+            Error::NoEntriesInModManifest(_) => (Internal, "E901"),
+            Error::FileIsNotHashed => (Internal, "E902"),
+            Error::TaskFailed(_) => (Internal, "E903"),
+            Error::InFile { source, .. }
+            | Error::InMod { source, .. }
+            | Error::BatchFailed { source, .. } => source.classify(),
+        }
+    }
+}
+
+/// Attaches the offending file or mod to errors
+pub trait Context<T> {
+    fn in_file(self, path: &Path) -> Result<T, Error>;
+    fn in_mod(self, name: &str) -> Result<T, Error>;
+}
+
+impl<T, E: Into<Error>> Context<T> for Result<T, E> {
+    fn in_file(self, path: &Path) -> Result<T, Error> {
+        self.map_err(|e| Error::InFile {
+            path: path.to_path_buf(),
+            source: Box::new(e.into()),
+        })
+    }
+
+    fn in_mod(self, name: &str) -> Result<T, Error> {
+        self.map_err(|e| Error::InMod {
+            name: name.to_string(),
+            source: Box::new(e.into()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_keeps_code_and_category() {
+        let result: Result<(), std::io::Error> = Err(std::io::Error::other("disk on fire"));
+
+        let error = result
+            .in_file(Path::new("Mods/SpringCollab2020.zip"))
+            .in_mod("SpringCollab2020")
+            .unwrap_err();
+
+        assert_eq!(error.code(), "E401");
+        assert_eq!(error.category(), ErrorCategory::Filesystem);
+        assert_eq!(
+            error.to_string(),
+            "SpringCollab2020: 'Mods/SpringCollab2020.zip': disk on fire"
+        );
+    }
 }
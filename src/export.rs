@@ -0,0 +1,118 @@
+use std::io::Write;
+
+use crate::{
+    blacklist::Blacklist, checksum, error::Error, installed_mods::LocalModInfo, template::Template,
+};
+
+pub const CSV_HEADER: [&str; 6] = ["filename", "name", "version", "size", "xxhash", "enabled"];
+
+/// Fields of installed mods available to `list --format`
+pub const INSTALLED_FIELDS: [&str; 7] = [
+    "name", "version", "file", "path", "size", "xxhash", "enabled",
+];
+
+/// Writes installed mods as CSV, one row per archive
+pub fn write_installed_csv<W: Write>(
+    writer: &mut W,
+    installed_mods: &[&LocalModInfo],
+    blacklist: &Blacklist,
+) -> Result<(), Error> {
+    write_csv_row(writer, &CSV_HEADER)?;
+    for mod_info in installed_mods {
+        let file_name = mod_info.archive_file_name();
+        let size = std::fs::metadata(&mod_info.archive_path)?.len().to_string();
+        let hash = checksum::hash_file(&mod_info.archive_path, &[])?.xxh64;
+        let enabled = (!blacklist.contains(&file_name)).to_string();
+        write_csv_row(
+            writer,
+            &[
+                &file_name,
+                &mod_info.manifest.name,
+                &mod_info.manifest.version,
+                &size,
+                &hash,
+                &enabled,
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Writes installed mods rendered with a `--format` template, one line per archive
+///
+/// Archives are only hashed when the template contains `{xxhash}`.
+pub fn write_installed_template<W: Write>(
+    writer: &mut W,
+    installed_mods: &[&LocalModInfo],
+    blacklist: &Blacklist,
+    template: &Template,
+) -> Result<(), Error> {
+    for mod_info in installed_mods {
+        let line = template.render(|field| {
+            Ok(match field {
+                "name" => mod_info.manifest.name.clone(),
+                "version" => mod_info.manifest.version.clone(),
+                "file" => mod_info.archive_file_name(),
+                "path" => mod_info.archive_path.display().to_string(),
+                "size" => std::fs::metadata(&mod_info.archive_path)?.len().to_string(),
+                "xxhash" => checksum::hash_file(&mod_info.archive_path, &[])?.xxh64,
+                "enabled" => (!blacklist.contains(&mod_info.archive_file_name())).to_string(),
+                _ => unreachable!("unknown field {}", field),
+            })
+        })?;
+        writeln!(writer, "{}", line)?;
+    }
+    Ok(())
+}
+
+fn write_csv_row<W: Write>(writer: &mut W, fields: &[&str]) -> Result<(), Error> {
+    let row: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+    writeln!(writer, "{}", row.join(","))?;
+    Ok(())
+}
+
+/// Splits a row written by `write_csv_row` into its fields, unquoting them
+pub fn parse_csv_row(row: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = row.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Quotes a CSV field when it contains separators, quotes or line breaks (RFC 4180)
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field("SpeedrunTool.zip"), "SpeedrunTool.zip");
+        assert_eq!(csv_field("Collab, Part 1.zip"), "\"Collab, Part 1.zip\"");
+        assert_eq!(csv_field("The \"Best\" Mod"), "\"The \"\"Best\"\" Mod\"");
+        assert_eq!(
+            parse_csv_row("\"Collab, Part 1.zip\",\"The \"\"Best\"\" Mod\",1.0.0"),
+            vec!["Collab, Part 1.zip", "The \"Best\" Mod", "1.0.0"]
+        );
+    }
+}
@@ -0,0 +1,31 @@
+use std::{
+    ops::{Deref, DerefMut},
+    path::Path,
+};
+
+use crate::{archive_list::ArchiveList, constant::FAVORITES_FILE, error::Error};
+
+/// Everest's `Mods/favorites.txt`, listing the archives highlighted in the mod list
+#[derive(Debug)]
+pub struct Favorites(ArchiveList);
+
+impl Favorites {
+    /// Loads the favorites of a mods directory, an absent file means no favorites
+    pub fn load(mods_dir: &Path) -> Result<Self, Error> {
+        ArchiveList::load(mods_dir.join(FAVORITES_FILE)).map(Self)
+    }
+}
+
+impl Deref for Favorites {
+    type Target = ArchiveList;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Favorites {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    constant::{APP_DIRECTORY_NAME, FEED_FILE, FEED_MAX_ENTRIES},
+    error::Error,
+    timeutil,
+};
+
+/// An entry of a feed, e.g. an available update
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedEntry {
+    /// Unique and stable identifier, so that feed readers show each event once
+    #[serde(rename = "Id")]
+    pub id: String,
+    #[serde(rename = "Title")]
+    pub title: String,
+    #[serde(rename = "Link", default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+    /// Unix timestamp of the event
+    #[serde(rename = "Updated")]
+    pub updated: u64,
+}
+
+/// Entries of the previous feeds, stored as `feed.yaml` in the data directory
+///
+/// Events such as subscription changes are only detected once, keeping them lets feed readers
+/// polling less often than the update checks still see them.
+#[derive(Debug)]
+pub struct FeedHistory {
+    path: PathBuf,
+    entries: Vec<FeedEntry>,
+}
+
+impl FeedHistory {
+    pub fn load(data_dir: &Path) -> Result<Self, Error> {
+        let path = data_dir.join(FEED_FILE);
+        let entries = if path.exists() {
+            serde_yaml_ng::from_slice(&fs::read(&path)?)?
+        } else {
+            Vec::new()
+        };
+        Ok(Self { path, entries })
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        info!("Writing {}", self.path.display());
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_yaml_ng::to_string(&self.entries)?)?;
+        Ok(())
+    }
+
+    /// Adds the entries not seen before and keeps the newest `FEED_MAX_ENTRIES`
+    pub fn merge(&mut self, entries: Vec<FeedEntry>) {
+        for entry in entries {
+            if !self.entries.iter().any(|known| known.id == entry.id) {
+                self.entries.push(entry);
+            }
+        }
+        self.entries
+            .sort_by(|a, b| b.updated.cmp(&a.updated).then_with(|| a.id.cmp(&b.id)));
+        self.entries.truncate(FEED_MAX_ENTRIES);
+    }
+
+    pub fn entries(&self) -> &[FeedEntry] {
+        &self.entries
+    }
+}
+
+impl FeedEntry {
+    /// An entry about a version of a registry entry, identified by the kind of event, name and version
+    pub fn new(kind: &str, name: &str, version: &str, title: String, updated: u64) -> Self {
+        Self {
+            id: format!("urn:{}:{}:{}:{}", APP_DIRECTORY_NAME, kind, name, version),
+            title,
+            link: None,
+            updated,
+        }
+    }
+
+    pub fn with_link(mut self, link: Option<String>) -> Self {
+        self.link = link;
+        self
+    }
+}
+
+/// Renders an Atom feed
+pub fn atom(title: &str, entries: &[FeedEntry], now: u64) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("  <id>urn:{}:feed</id>\n", APP_DIRECTORY_NAME));
+    xml.push_str(&format!("  <title>{}</title>\n", escape(title)));
+    xml.push_str(&format!(
+        "  <updated>{}</updated>\n",
+        timeutil::format_rfc3339(now)
+    ));
+    xml.push_str(&format!(
+        "  <author><name>{}</name></author>\n",
+        APP_DIRECTORY_NAME
+    ));
+    for entry in entries {
+        xml.push_str("  <entry>\n");
+        xml.push_str(&format!("    <id>{}</id>\n", escape(&entry.id)));
+        xml.push_str(&format!("    <title>{}</title>\n", escape(&entry.title)));
+        if let Some(link) = &entry.link {
+            xml.push_str(&format!("    <link href=\"{}\"/>\n", escape(link)));
+        }
+        xml.push_str(&format!(
+            "    <updated>{}</updated>\n",
+            timeutil::format_rfc3339(entry.updated)
+        ));
+        xml.push_str(&format!(
+            "    <summary>{}</summary>\n",
+            escape(&entry.title)
+        ));
+        xml.push_str("  </entry>\n");
+    }
+    xml.push_str("</feed>\n");
+    xml
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_history_keeps_previous_entries_and_escapes_them() {
+        let dir = TempDir::new().unwrap();
+        let mut history = FeedHistory::load(dir.path()).unwrap();
+        history.merge(vec![FeedEntry::new(
+            "update",
+            "OldMod",
+            "1.0.0",
+            String::from("OldMod 1.0.0"),
+            100,
+        )]);
+        history.save().unwrap();
+        let mut history = FeedHistory::load(dir.path()).unwrap();
+        let release = FeedEntry::new(
+            "release",
+            "Tom&Jerry",
+            "2.0.0",
+            String::from("Tom&Jerry <2.0.0>"),
+            200,
+        )
+        .with_link(Some(String::from("https://gamebanana.com/mods/1")));
+        history.merge(vec![release.clone(), release]);
+
+        let xml = atom("Mod updates", history.entries(), 300);
+
+        assert_eq!(history.entries().len(), 2);
+        assert!(xml.contains("<updated>1970-01-01T00:05:00Z</updated>"));
+        assert!(xml.contains("<title>Tom&amp;Jerry &lt;2.0.0&gt;</title>"));
+        assert!(xml.contains("<id>urn:everest-mod-cli:release:Tom&amp;Jerry:2.0.0</id>"));
+        assert!(xml.find("Tom&amp;Jerry").unwrap() < xml.find("OldMod").unwrap());
+    }
+}
@@ -2,16 +2,21 @@
 use std::{
     env::home_dir,
     fs::{self, File},
-    io::{BufReader, Read},
+    io::{self, BufReader, Read},
     path::{Path, PathBuf},
+    thread,
 };
 
-use tracing::info;
-use xxhash_rust::xxh64::Xxh64;
+use tracing::{info, warn};
 use zip::{ZipArchive, result::ZipError};
 
-use crate::constant::{MOD_MANIFEST_FILE, STEAM_MODS_DIRECTORY_PATH};
+use crate::constant::{
+    APP_DIRECTORY_NAME, CONFIG_FILE, LOCAL_REGISTRY_FILE, LOCKED_FILE_RETRIES,
+    LOCKED_FILE_RETRY_DELAY, MAX_MANIFEST_COMPRESSION_RATIO, MAX_MANIFEST_SIZE, MOD_MANIFEST_FILES,
+    SAVES_DIRECTORY, STEAM_MODS_DIRECTORY_PATH, USER_SAVES_DIRECTORY_PATH,
+};
 use crate::error::Error;
+use crate::game;
 
 /// Returns the path to the user's mods directory based on platform-specific conventions
 pub fn get_mods_directory() -> Result<PathBuf, Error> {
@@ -22,6 +27,146 @@ pub fn get_mods_directory() -> Result<PathBuf, Error> {
         .ok_or(Error::CouldNotDetermineHomeDir)
 }
 
+/// Returns the path to the configuration file, honoring `XDG_CONFIG_HOME`
+pub fn get_config_path() -> Result<PathBuf, Error> {
+    xdg_directory("XDG_CONFIG_HOME", ".config").map(|dir| dir.join(CONFIG_FILE))
+}
+
+/// Returns the path to the registry of unlisted mods maintained by the user, next to the
+/// configuration file
+pub fn get_local_registry_path() -> Result<PathBuf, Error> {
+    xdg_directory("XDG_CONFIG_HOME", ".config").map(|dir| dir.join(LOCAL_REGISTRY_FILE))
+}
+
+/// Returns the directory used for cached downloads, honoring `XDG_CACHE_HOME`
+pub fn get_cache_directory() -> Result<PathBuf, Error> {
+    xdg_directory("XDG_CACHE_HOME", ".cache")
+}
+
+/// Returns the directory for persistent application data, honoring `XDG_DATA_HOME`
+pub fn get_data_directory() -> Result<PathBuf, Error> {
+    xdg_directory("XDG_DATA_HOME", ".local/share")
+}
+
+/// Returns the existing directories of the game's save files: next to the game, where Windows
+/// and portable installs keep them, and in the user data directory, where Linux builds keep them
+pub fn get_save_directories(mods_directory: &Path) -> Vec<PathBuf> {
+    let next_to_game = mods_directory
+        .parent()
+        .map(|game_directory| game_directory.join(SAVES_DIRECTORY));
+    let user_data = xdg_base("XDG_DATA_HOME", ".local/share")
+        .ok()
+        .map(|dir| dir.join(USER_SAVES_DIRECTORY_PATH));
+    let mut directories: Vec<_> = next_to_game
+        .into_iter()
+        .chain(user_data)
+        .filter(|dir| dir.is_dir())
+        .collect();
+    directories.dedup();
+    directories
+}
+
+/// Resolves `$<env_var>/everest-mod-cli`, falling back to `~/<fallback>/everest-mod-cli`
+fn xdg_directory(env_var: &str, fallback: &str) -> Result<PathBuf, Error> {
+    xdg_base(env_var, fallback).map(|base| base.join(APP_DIRECTORY_NAME))
+}
+
+/// Resolves `$<env_var>`, falling back to `~/<fallback>`
+fn xdg_base(env_var: &str, fallback: &str) -> Result<PathBuf, Error> {
+    match std::env::var_os(env_var) {
+        Some(dir) if !dir.is_empty() => Ok(PathBuf::from(dir)),
+        _ => home_dir()
+            .map(|home_path| home_path.join(fallback))
+            .ok_or(Error::CouldNotDetermineHomeDir),
+    }
+}
+
+/// Resolves the mods directory when it is a symbolic link, e.g. to a bigger drive
+///
+/// Archive paths are then the same whether the directory is reached through the link or not.
+pub fn resolve_mods_directory(mods_directory: PathBuf) -> Result<PathBuf, Error> {
+    if !mods_directory.is_symlink() {
+        return Ok(mods_directory);
+    }
+    info!("Following the link {}", mods_directory.display());
+    fs::canonicalize(&mods_directory).map_err(|_| Error::BrokenLink(mods_directory))
+}
+
+/// Whether a name chosen by the user can be used as a file name on every platform, e.g. the
+/// name of a profile
+pub fn is_plain_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && !name.contains(['/', '\\', ':', '*', '?', '"', '<', '>', '|'])
+}
+
+/// Runs a file operation, attempting it again while another program holds the file open
+///
+/// On Windows, an antivirus scanning a new archive or the game still closing makes removing
+/// or renaming it fail for a moment. Other errors are returned right away.
+pub fn retry_locked<T>(
+    path: &Path,
+    mut operation: impl FnMut() -> io::Result<T>,
+) -> Result<T, Error> {
+    let mut attempt = 0;
+    loop {
+        match operation() {
+            Err(e) if is_sharing_violation(&e) && attempt < LOCKED_FILE_RETRIES => {
+                let delay = LOCKED_FILE_RETRY_DELAY * 2u32.pow(attempt);
+                info!("{} is locked, trying again in {:?}", path.display(), delay);
+                thread::sleep(delay);
+                attempt += 1;
+            }
+            Err(e) if is_sharing_violation(&e) => {
+                return Err(Error::FileLocked {
+                    path: path.to_path_buf(),
+                    holder: game::is_running().then_some("Celeste"),
+                    source: e,
+                });
+            }
+            result => return Ok(result?),
+        }
+    }
+}
+
+/// Removes a file, attempting it again while another program holds it open
+pub fn remove_file(path: &Path) -> Result<(), Error> {
+    retry_locked(path, || fs::remove_file(path))
+}
+
+/// Whether an error is a sharing or lock violation, access denied errors are permission problems
+/// which waiting does not solve
+#[cfg(windows)]
+fn is_sharing_violation(e: &io::Error) -> bool {
+    // ERROR_SHARING_VIOLATION, ERROR_LOCK_VIOLATION
+    matches!(e.raw_os_error(), Some(32 | 33))
+}
+
+/// Open files can be removed and renamed on other platforms
+#[cfg(not(windows))]
+fn is_sharing_violation(_: &io::Error) -> bool {
+    false
+}
+
+/// Whether two paths name the same file, e.g. names differing by case on a case-insensitive drive
+#[cfg(unix)]
+pub fn is_same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+/// Whether two paths name the same file, canonical paths have the case of the file on Windows
+#[cfg(not(unix))]
+pub fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
 /// Scans the mods directory and returns a list of all installed mod archive files (.zip)
 pub fn find_installed_mod_archives(mods_directory: &Path) -> Result<Vec<PathBuf>, Error> {
     if !mods_directory.exists() {
@@ -35,25 +180,87 @@ pub fn find_installed_mod_archives(mods_directory: &Path) -> Result<Vec<PathBuf>
     for entry in entries {
         let entry = entry?;
         let path = entry.path();
-        if path.is_file() && path.extension().is_some_and(|ext| ext == "zip") {
+        if path.extension().is_none_or(|ext| ext != "zip") {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_file() {
             mod_archives.push(path);
+        } else if file_type.is_symlink() {
+            // Archives linked from another drive are installed mods too
+            match fs::metadata(&path) {
+                Ok(metadata) if metadata.is_file() => mod_archives.push(path),
+                Ok(_) => {}
+                Err(e) => warn!("Skipping {}, the link is broken: {}", path.display(), e),
+            }
         }
     }
 
     Ok(mod_archives)
 }
 
+/// Finds the entry name of the mod manifest among the entries of a ZIP archive.
+///
+/// Both `everest.yaml` and `everest.yml` are accepted case-insensitively, either at the top level
+/// or nested one directory deep. Top-level manifests take precedence, then `everest.yaml`.
+fn find_manifest_entry<'a>(entry_names: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    entry_names
+        .filter_map(|name| {
+            let (depth, file_name) = match name.split(['/', '\\']).collect::<Vec<_>>()[..] {
+                [file_name] => (0, file_name),
+                [_, file_name] => (1, file_name),
+                _ => return None,
+            };
+            let precedence = MOD_MANIFEST_FILES
+                .iter()
+                .position(|manifest| file_name.eq_ignore_ascii_case(manifest))?;
+            Some(((depth, precedence), name))
+        })
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, name)| name)
+}
+
 /// Reads the mod manifest file from a given ZIP archive.
+///
+/// Oversized manifests and suspicious compression ratios are refused rather than inflated.
 pub fn read_manifest_file_from_zip(zip_path: &Path) -> Result<Option<Vec<u8>>, Error> {
     let zip_file = File::open(zip_path)?;
     let reader = BufReader::new(zip_file);
     let mut zip_archive = ZipArchive::new(reader)?;
 
-    match zip_archive.by_name(MOD_MANIFEST_FILE) {
-        Ok(mut file) => {
+    let Some(manifest_entry) = find_manifest_entry(zip_archive.file_names()).map(String::from)
+    else {
+        return Ok(None);
+    };
+    match zip_archive.by_name(&manifest_entry) {
+        Ok(file) => {
+            let size = file.size();
+            if size > MAX_MANIFEST_SIZE {
+                return Err(Error::UnsafeManifest(format!(
+                    "{} is {} bytes, more than the {} bytes allowed",
+                    manifest_entry, size, MAX_MANIFEST_SIZE
+                )));
+            }
+            // Small manifests may legitimately compress well, only large ones are suspicious
+            let compressed_size = file.compressed_size().max(1);
+            if size > 64 * 1024 && size / compressed_size > MAX_MANIFEST_COMPRESSION_RATIO {
+                return Err(Error::UnsafeManifest(format!(
+                    "{} is compressed {} times, a zip bomb?",
+                    manifest_entry,
+                    size / compressed_size
+                )));
+            }
+
             // NOTE: Max file size of `everest.yaml` should be under 10KB
             let mut buffer = Vec::with_capacity(12 * 1024);
-            file.read_to_end(&mut buffer)?;
+            // The sizes in the headers may lie, never inflate more than allowed
+            file.take(MAX_MANIFEST_SIZE + 1).read_to_end(&mut buffer)?;
+            if buffer.len() as u64 > MAX_MANIFEST_SIZE {
+                return Err(Error::UnsafeManifest(format!(
+                    "{} inflates to more than the {} bytes allowed",
+                    manifest_entry, MAX_MANIFEST_SIZE
+                )));
+            }
 
             // Check for UTF-8 BOM and remove if present
             if buffer.len() >= 3 && buffer[0] == 0xEF && buffer[1] == 0xBB && buffer[2] == 0xBF {
@@ -67,21 +274,100 @@ pub fn read_manifest_file_from_zip(zip_path: &Path) -> Result<Option<Vec<u8>>, E
     }
 }
 
-/// Compute xxhash of a given file, return hexadicimal string
-pub fn hash_file(file_path: &Path) -> Result<String, Error> {
-    let file = std::fs::File::open(file_path)?;
-    let mut reader = std::io::BufReader::new(file);
-    let mut hasher = Xxh64::new(0);
-    let mut buffer = [0u8; 8192]; // Read in 8 KB chunks
-    loop {
-        let bytes_read = reader.read(&mut buffer)?;
-        if bytes_read == 0 {
-            break;
+/// Unpacks a ZIP archive into a directory, returns the number of files written
+///
+/// Every entry is checked before anything is written: entries escaping the directory
+/// (`../`, absolute paths, drive letters) or storing symbolic links make the whole archive refused.
+pub fn extract_zip(zip_path: &Path, destination: &Path) -> Result<usize, Error> {
+    let mut zip_archive = ZipArchive::new(BufReader::new(File::open(zip_path)?))?;
+
+    let mut entries = Vec::with_capacity(zip_archive.len());
+    for i in 0..zip_archive.len() {
+        let entry = zip_archive.by_index_raw(i)?;
+        let unsafe_entry = |reason| Error::UnsafeArchiveEntry {
+            entry: entry.name().to_string(),
+            reason,
+        };
+        if entry.is_symlink() {
+            return Err(unsafe_entry("symbolic links are not extracted"));
+        }
+        let relative_path = sanitize_entry_name(entry.name())
+            .ok_or_else(|| unsafe_entry("the path leaves the destination directory"))?;
+        entries.push((i, relative_path, entry.is_dir()));
+    }
+
+    info!(
+        "Extracting {} entries to {}",
+        entries.len(),
+        destination.display()
+    );
+    fs::create_dir_all(destination)?;
+    let mut extracted = 0;
+    for (i, relative_path, is_dir) in entries {
+        let path = destination.join(relative_path);
+        if is_dir {
+            fs::create_dir_all(&path)?;
+            continue;
         }
-        hasher.update(&buffer[..bytes_read]);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut entry = zip_archive.by_index(i)?;
+        std::io::copy(&mut entry, &mut File::create(&path)?)?;
+        extracted += 1;
     }
-    let hash_str = format!("{:016x}", hasher.digest());
-    Ok(hash_str)
+    Ok(extracted)
+}
+
+/// Turns the name of a ZIP entry into a relative path, `None` if it could escape the destination
+///
+/// Both `/` and `\` are separators, since archives made on Windows may use either.
+fn sanitize_entry_name(name: &str) -> Option<PathBuf> {
+    if name.starts_with(['/', '\\']) {
+        return None;
+    }
+    let mut path = PathBuf::new();
+    for component in name.split(['/', '\\']) {
+        match component {
+            "" | "." => {}
+            ".." => return None,
+            // Drive letters such as `C:` and alternate data streams
+            component if component.contains(':') => return None,
+            component => path.push(component),
+        }
+    }
+    (!path.as_os_str().is_empty()).then_some(path)
+}
+
+/// Lists the paths of all files (not directories) stored in a ZIP archive, using `/` separators
+pub fn list_zip_entries(zip_path: &Path) -> Result<Vec<String>, Error> {
+    let zip_file = File::open(zip_path)?;
+    let zip_archive = ZipArchive::new(BufReader::new(zip_file))?;
+
+    Ok(zip_archive
+        .file_names()
+        .filter(|name| !name.ends_with('/'))
+        .map(|name| name.replace('\\', "/"))
+        .collect())
+}
+
+/// Reads every entry of a ZIP archive to validate their CRC-32, returns the number of entries
+///
+/// Opening the archive validates the central directory, and each entry is checked against
+/// its local header while being read.
+pub fn verify_zip_integrity(zip_path: &Path) -> Result<usize, Error> {
+    let reader = BufReader::new(File::open(zip_path)?);
+    let mut zip_archive = ZipArchive::new(reader)?;
+    for i in 0..zip_archive.len() {
+        let mut entry = zip_archive.by_index(i)?;
+        if let Err(source) = std::io::copy(&mut entry, &mut std::io::sink()) {
+            return Err(Error::CorruptArchiveEntry {
+                entry: entry.name().to_string(),
+                source,
+            });
+        }
+    }
+    Ok(zip_archive.len())
 }
 
 #[cfg(test)]
@@ -97,13 +383,20 @@ mod tests {
 
     // Helper function to create a zip file with a manifest
     fn create_test_zip(manifest_content: Option<&[u8]>) -> NamedTempFile {
+        match manifest_content {
+            Some(content) => create_zip_with_entries(&[(MOD_MANIFEST_FILE, content)]),
+            None => create_zip_with_entries(&[]),
+        }
+    }
+
+    // Helper function to create a zip file with arbitrary entries
+    fn create_zip_with_entries(entries: &[(&str, &[u8])]) -> NamedTempFile {
         let temp_file = NamedTempFile::new().unwrap();
         let file = File::create(temp_file.path()).unwrap();
         let mut zip = ZipWriter::new(file);
 
-        if let Some(content) = manifest_content {
-            zip.start_file(MOD_MANIFEST_FILE, SimpleFileOptions::default())
-                .unwrap();
+        for (name, content) in entries {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
             zip.write_all(content).unwrap();
         }
 
@@ -111,6 +404,107 @@ mod tests {
         temp_file
     }
 
+    #[test]
+    fn test_retry_locked_returns_other_errors_right_away() {
+        let path = Path::new("/nonexistent/Missing.zip");
+        let mut attempts = 0;
+
+        let result = retry_locked(path, || {
+            attempts += 1;
+            fs::remove_file(path)
+        });
+
+        assert!(matches!(result, Err(Error::Io(e)) if e.kind() == io::ErrorKind::NotFound));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn test_extract_zip_refuses_malicious_entries() {
+        let destination = tempfile::TempDir::new().unwrap();
+        for name in [
+            "../evil.txt",
+            "Maps/../../evil.txt",
+            "/etc/evil",
+            "C:\\evil.txt",
+        ] {
+            let temp_zip = create_zip_with_entries(&[("everest.yaml", b"ok"), (name, b"evil")]);
+
+            let result = extract_zip(temp_zip.path(), destination.path());
+
+            assert!(
+                matches!(result, Err(Error::UnsafeArchiveEntry { .. })),
+                "{} was extracted",
+                name
+            );
+        }
+        assert_eq!(fs::read_dir(destination.path()).unwrap().count(), 0);
+
+        let temp_zip = NamedTempFile::new().unwrap();
+        let mut zip = ZipWriter::new(File::create(temp_zip.path()).unwrap());
+        zip.add_symlink("Dialog", "/home", SimpleFileOptions::default())
+            .unwrap();
+        zip.finish().unwrap();
+        assert!(matches!(
+            extract_zip(temp_zip.path(), destination.path()),
+            Err(Error::UnsafeArchiveEntry { .. })
+        ));
+
+        let temp_zip = create_zip_with_entries(&[
+            ("everest.yaml", b"- Name: Test"),
+            ("Maps\\Test.bin", b"map"),
+        ]);
+        assert_eq!(extract_zip(temp_zip.path(), destination.path()).unwrap(), 2);
+        assert!(destination.path().join("Maps/Test.bin").is_file());
+    }
+
+    #[test]
+    fn test_read_manifest_file_refuses_zip_bombs() {
+        // Deflated by default, millions of spaces compress to a few kilobytes
+        let padding = vec![b' '; 2 * MAX_MANIFEST_SIZE as usize];
+        let temp_zip = create_test_zip(Some(&padding));
+
+        let result = read_manifest_file_from_zip(temp_zip.path());
+
+        assert!(
+            matches!(result, Err(Error::UnsafeManifest(reason)) if reason.contains("bytes allowed"))
+        );
+    }
+
+    #[test]
+    fn test_read_manifest_file_refuses_high_compression_ratios() {
+        // Under the size limit, but compressed about a thousand times
+        let padding = vec![b' '; MAX_MANIFEST_SIZE as usize / 2];
+        let temp_zip = create_test_zip(Some(&padding));
+
+        let result = read_manifest_file_from_zip(temp_zip.path());
+
+        assert!(
+            matches!(result, Err(Error::UnsafeManifest(reason)) if reason.contains("zip bomb"))
+        );
+    }
+
+    #[test]
+    fn test_read_manifest_file_refuses_understated_sizes() {
+        let padding = vec![b' '; 2 * MAX_MANIFEST_SIZE as usize];
+        let temp_zip = create_test_zip(Some(&padding));
+        // Declare 100 bytes in the local and central headers, so that only the inflating is bounded
+        let mut data = std::fs::read(temp_zip.path()).unwrap();
+        for (signature, offset) in [(b"PK\x03\x04", 22), (b"PK\x01\x02", 24)] {
+            let header = data
+                .windows(4)
+                .position(|window| window == signature)
+                .unwrap();
+            data[header + offset..header + offset + 4].copy_from_slice(&100u32.to_le_bytes());
+        }
+        std::fs::write(temp_zip.path(), data).unwrap();
+
+        let result = read_manifest_file_from_zip(temp_zip.path());
+
+        assert!(
+            matches!(result, Err(Error::UnsafeManifest(reason)) if reason.contains("inflates"))
+        );
+    }
+
     #[test]
     fn test_read_manifest_file_success() {
         let content = b"test manifest content".to_vec();
@@ -145,6 +539,59 @@ mod tests {
         assert_eq!(result.unwrap(), None);
     }
 
+    #[test]
+    fn test_read_manifest_file_with_alternative_names() {
+        let temp_zip = create_zip_with_entries(&[("Everest.YML", b"yml manifest")]);
+        let result = read_manifest_file_from_zip(temp_zip.path()).unwrap();
+        assert_eq!(result, Some(b"yml manifest".to_vec()));
+
+        let temp_zip = create_zip_with_entries(&[("MyMod/everest.yaml", b"nested manifest")]);
+        let result = read_manifest_file_from_zip(temp_zip.path()).unwrap();
+        assert_eq!(result, Some(b"nested manifest".to_vec()));
+
+        let temp_zip = create_zip_with_entries(&[("a/b/everest.yaml", b"too deep")]);
+        let result = read_manifest_file_from_zip(temp_zip.path()).unwrap();
+        assert_eq!(result, None);
+    }
+
+    #[test]
+    fn test_find_manifest_entry_precedence() {
+        let names = ["MyMod/everest.yaml", "everest.yml", "Maps/everest.yaml"];
+        assert_eq!(find_manifest_entry(names.into_iter()), Some("everest.yml"));
+
+        let names = ["MyMod/everest.yml", "MyMod/everest.yaml"];
+        assert_eq!(
+            find_manifest_entry(names.into_iter()),
+            Some("MyMod/everest.yaml")
+        );
+    }
+
+    #[test]
+    fn test_verify_zip_integrity_detects_corrupted_entry() {
+        let content = b"some asset content which gets corrupted";
+        let temp_zip = NamedTempFile::new().unwrap();
+        let mut zip = ZipWriter::new(File::create(temp_zip.path()).unwrap());
+        let options =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        zip.start_file("Graphics/asset.bin", options).unwrap();
+        zip.write_all(content).unwrap();
+        zip.finish().unwrap();
+        assert_eq!(verify_zip_integrity(temp_zip.path()).unwrap(), 1);
+
+        let mut bytes = fs::read(temp_zip.path()).unwrap();
+        let offset = bytes
+            .windows(content.len())
+            .position(|window| window == content)
+            .unwrap();
+        bytes[offset] ^= 0xff;
+        fs::write(temp_zip.path(), bytes).unwrap();
+
+        assert!(matches!(
+            verify_zip_integrity(temp_zip.path()),
+            Err(Error::CorruptArchiveEntry { .. })
+        ));
+    }
+
     #[test]
     fn test_read_invalid_zip_file() {
         let temp_file = NamedTempFile::new().unwrap();
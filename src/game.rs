@@ -0,0 +1,260 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use tracing::info;
+
+use crate::{
+    constant::{
+        CELESTE_STEAM_APP_ID, EVEREST_PSEUDO_DEPENDENCIES, GAME_EXECUTABLES, GAME_LOG_FILE,
+        GAME_PROCESS_NAME,
+    },
+    error::Error,
+    prompt,
+    version::EverestVersion,
+};
+
+/// Versions of the game and of Everest, which mods declare as `Celeste`, `Everest` and `EverestCore` dependencies
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GameVersions {
+    /// Version of Celeste, e.g. `1.4.0.0`
+    pub celeste: Option<String>,
+    /// Version of Everest as dependencies declare it, e.g. `1.4465.0` for build 4465
+    pub everest: Option<String>,
+}
+
+impl GameVersions {
+    /// Reads the versions from the log of the last run of the game, next to the mods directory
+    ///
+    /// Both are unknown when the game was never started with Everest.
+    pub fn detect(mods_directory: &Path) -> Self {
+        let Some(log_path) = mods_directory
+            .parent()
+            .map(|game_directory| game_directory.join(GAME_LOG_FILE))
+        else {
+            return Self::default();
+        };
+        match fs::read(&log_path) {
+            Ok(data) => Self::parse_log(&String::from_utf8_lossy(&data)),
+            Err(e) => {
+                info!("Could not read {}: {}", log_path.display(), e);
+                Self::default()
+            }
+        }
+    }
+
+    /// Parses the version line Everest logs on startup, e.g. `1.4.0.0-fna [Everest: 4465-github-8ba4b2a]`
+    pub fn parse_log(log: &str) -> Self {
+        for line in log.lines() {
+            let Some((before, after)) = line.split_once("[Everest: ") else {
+                continue;
+            };
+            let build: String = after.chars().take_while(char::is_ascii_digit).collect();
+            let celeste = before
+                .split_whitespace()
+                .next_back()
+                .and_then(EverestVersion::parse)
+                .map(|version| version.to_string());
+            return Self {
+                celeste,
+                everest: (!build.is_empty()).then(|| format!("1.{}.0", build)),
+            };
+        }
+        Self::default()
+    }
+
+    /// Installed version a pseudo-dependency is checked against, `None` when it is unknown
+    pub fn version_of(&self, name: &str) -> Option<&str> {
+        match name {
+            "Celeste" => self.celeste.as_deref(),
+            "Everest" | "EverestCore" => self.everest.as_deref(),
+            _ => None,
+        }
+    }
+}
+
+/// How the game is started by `launch`
+#[derive(Debug, PartialEq, Eq)]
+pub enum Launcher {
+    /// Through the Steam client, which applies the launch options set in Steam
+    Steam,
+    /// The executable in the game directory, e.g. for itch.io or Epic Games installs
+    Executable(PathBuf),
+}
+
+impl Launcher {
+    /// Detects the install of the game from its mods directory, `None` without an executable
+    ///
+    /// A game directory under `steamapps` belongs to Steam, which must start the game for the
+    /// overlay and cloud saves.
+    pub fn detect(mods_directory: &Path) -> Option<Self> {
+        let game_directory = mods_directory.parent()?;
+        if game_directory
+            .components()
+            .any(|component| component.as_os_str().eq_ignore_ascii_case("steamapps"))
+        {
+            return Some(Self::Steam);
+        }
+        GAME_EXECUTABLES
+            .iter()
+            .map(|name| game_directory.join(name))
+            .find(|path| path.is_file())
+            .map(Self::Executable)
+    }
+
+    /// Starts the game without waiting for it to exit, `args` are only passed to an executable
+    pub fn launch(&self, args: &[String]) -> Result<(), Error> {
+        let mut command = match self {
+            Self::Steam => open_command(&format!("steam://rungameid/{}", CELESTE_STEAM_APP_ID)),
+            Self::Executable(path) => {
+                let mut command = Command::new(path);
+                command.args(args);
+                if let Some(game_directory) = path.parent() {
+                    command.current_dir(game_directory);
+                }
+                command
+            }
+        };
+        info!("Starting {:?}", command);
+        command
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        Ok(())
+    }
+}
+
+/// Command opening a URL with the handler registered for its scheme
+#[cfg(windows)]
+fn open_command(url: &str) -> Command {
+    let mut command = Command::new("cmd");
+    command.args(["/C", "start", "", url]);
+    command
+}
+
+/// Command opening a URL with the handler registered for its scheme
+#[cfg(target_os = "macos")]
+fn open_command(url: &str) -> Command {
+    let mut command = Command::new("open");
+    command.arg(url);
+    command
+}
+
+/// Command opening a URL with the handler registered for its scheme
+#[cfg(not(any(windows, target_os = "macos")))]
+fn open_command(url: &str) -> Command {
+    let mut command = Command::new("xdg-open");
+    command.arg(url);
+    command
+}
+
+/// Whether a dependency refers to the game or Everest rather than to an installable mod
+pub fn is_pseudo_dependency(name: &str) -> bool {
+    EVEREST_PSEUDO_DEPENDENCIES.contains(&name)
+}
+
+/// Makes sure the game is not running before the mods directory is modified
+///
+/// Replacing or deleting archives the game has open crashes it, or fails on locked files on Windows.
+/// The user may continue anyway when asked, or with `force`.
+pub fn ensure_not_running(force: bool) -> Result<(), Error> {
+    if force || !is_running() {
+        return Ok(());
+    }
+    if prompt::is_interactive()
+        && prompt::confirm(
+            "Celeste is running, changing its mods now may crash it. Continue anyway? [y/N]: ",
+        )?
+    {
+        return Ok(());
+    }
+    Err(Error::GameRunning)
+}
+
+/// Whether a Celeste process is running, natively or through Wine/Proton
+#[cfg(target_os = "linux")]
+pub fn is_running() -> bool {
+    let Ok(entries) = fs::read_dir("/proc") else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        fs::read_to_string(entry.path().join("comm"))
+            .is_ok_and(|comm| is_game_process_name(comm.trim_end()))
+    })
+}
+
+/// Whether a Celeste process is running
+#[cfg(windows)]
+pub fn is_running() -> bool {
+    let filter = format!("IMAGENAME eq {}.exe", GAME_PROCESS_NAME);
+    std::process::Command::new("tasklist")
+        .args(["/FI", &filter, "/NH", "/FO", "CSV"])
+        .output()
+        .is_ok_and(|output| {
+            String::from_utf8_lossy(&output.stdout).lines().any(|line| {
+                let image_name = line.split(',').next().unwrap_or_default();
+                is_game_process_name(image_name.trim_matches('"'))
+            })
+        })
+}
+
+/// Whether a Celeste process is running
+#[cfg(not(any(target_os = "linux", windows)))]
+pub fn is_running() -> bool {
+    std::process::Command::new("pgrep")
+        .args(["-x", GAME_PROCESS_NAME])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// Matches `Celeste`, `Celeste.exe` and the `Celeste.bin.x86_64` launcher of older Linux builds
+fn is_game_process_name(name: &str) -> bool {
+    name.strip_prefix(GAME_PROCESS_NAME)
+        .is_some_and(|suffix| suffix.is_empty() || suffix.starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_game_versions_and_processes() {
+        let log = "(10/15/2026 18:02:11) [Everest] [Info] [core] Booting Everest\n\
+            (10/15/2026 18:02:11) [Everest] [Info] [core] VersionCelesteString: 1.4.0.0-fna [Everest: 4465-github-8ba4b2a]\n";
+
+        let versions = GameVersions::parse_log(log);
+
+        assert_eq!(versions.version_of("Celeste"), Some("1.4.0.0"));
+        assert_eq!(versions.version_of("EverestCore"), Some("1.4465.0"));
+        assert_eq!(versions.version_of("MaxHelpingHand"), None);
+        assert_eq!(
+            GameVersions::parse_log("no Everest here"),
+            GameVersions::default()
+        );
+
+        assert!(is_game_process_name("Celeste.bin.x86"));
+        assert!(is_game_process_name("Celeste.exe"));
+        assert!(!is_game_process_name("CelesteNet.Serve"));
+    }
+
+    #[test]
+    fn test_detects_the_launcher() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let steam_mods = dir.path().join("steamapps/common/Celeste/Mods");
+        let itch_mods = dir.path().join("itch/Celeste/Mods");
+        fs::create_dir_all(&steam_mods).unwrap();
+        fs::create_dir_all(&itch_mods).unwrap();
+
+        assert_eq!(Launcher::detect(&steam_mods), Some(Launcher::Steam));
+        assert_eq!(Launcher::detect(&itch_mods), None);
+        let executable = dir.path().join("itch/Celeste").join(GAME_EXECUTABLES[0]);
+        fs::write(&executable, "").unwrap();
+        assert_eq!(
+            Launcher::detect(&itch_mods),
+            Some(Launcher::Executable(executable))
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use std::path::{Path, PathBuf};
+
+use bytes::Bytes;
+use serde::Deserialize;
+use tracing::info;
+use xxhash_rust::xxh64::xxh64;
+
+use crate::{
+    cache,
+    cli::BrowseSection,
+    constant::{
+        BROWSE_PAGE_SIZE, CELESTE_GAMEBANANA_ID, GAMEBANANA_CACHE_DIRECTORY,
+        GAMEBANANA_IMAGE_CACHE_TTL, GAMEBANANA_ITEM_CACHE_TTL, GAMEBANANA_LIST_CACHE_TTL,
+    },
+    download::ModDownloader,
+    error::Error,
+};
+
+/// Profile page of a GameBanana item, as returned by `apiv11/{type}/{id}/ProfilePage`
+#[derive(Debug, Deserialize)]
+pub struct GameBananaItem {
+    #[serde(rename = "_sName")]
+    pub name: String,
+    #[serde(rename = "_aSubmitter")]
+    pub submitter: Option<Submitter>,
+    /// Description of the item, in HTML
+    #[serde(rename = "_sText", default)]
+    pub text: String,
+    #[serde(rename = "_aPreviewMedia", default)]
+    pub preview_media: PreviewMedia,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Submitter {
+    #[serde(rename = "_sName")]
+    pub name: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PreviewMedia {
+    #[serde(rename = "_aImages", default)]
+    pub images: Vec<Image>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Image {
+    #[serde(rename = "_sBaseUrl")]
+    pub base_url: String,
+    #[serde(rename = "_sFile")]
+    pub file: String,
+    /// Thumbnail 220 pixels wide
+    #[serde(rename = "_sFile220")]
+    pub file_220: Option<String>,
+}
+
+impl GameBananaItem {
+    /// Loads an item from the cache, fetching a fresh copy when it is outdated
+    pub async fn load(
+        downloader: &ModDownloader,
+        cache_dir: &Path,
+        item_type: &str,
+        item_id: u32,
+    ) -> Result<Self, Error> {
+        let cache_path = cache_path(cache_dir, &format!("{}-{}.json", item_type, item_id));
+        let data = cache::fetch_cached(&cache_path, GAMEBANANA_ITEM_CACHE_TTL, || {
+            downloader.fetch_gamebanana_item(item_type, item_id)
+        })
+        .await?;
+        Self::from(data)
+    }
+
+    /// Parse the JSON response of the GameBanana API
+    pub fn from(data: Bytes) -> Result<Self, Error> {
+        info!("Parsing GameBanana item data");
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Name of the user who submitted the item
+    pub fn author(&self) -> Option<&str> {
+        self.submitter
+            .as_ref()
+            .map(|submitter| submitter.name.as_str())
+    }
+
+    /// Description converted to plain text
+    pub fn description(&self) -> String {
+        html_to_text(&self.text)
+    }
+
+    /// Full size URLs of the screenshots
+    pub fn screenshot_urls(&self) -> Vec<String> {
+        self.preview_media
+            .images
+            .iter()
+            .map(|image| format!("{}/{}", image.base_url, image.file))
+            .collect()
+    }
+
+    /// URL of the thumbnail of the first screenshot, or the screenshot itself without thumbnail
+    pub fn thumbnail_url(&self) -> Option<String> {
+        self.preview_media.images.first().map(|image| {
+            format!(
+                "{}/{}",
+                image.base_url,
+                image.file_220.as_deref().unwrap_or(&image.file)
+            )
+        })
+    }
+}
+
+/// A page of submissions, as returned by the list endpoints of the GameBanana API
+#[derive(Debug, Deserialize)]
+pub struct SubmissionList {
+    #[serde(rename = "_aMetadata")]
+    pub metadata: ListMetadata,
+    #[serde(rename = "_aRecords", default)]
+    pub records: Vec<Submission>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListMetadata {
+    /// Whether this is the last page
+    #[serde(rename = "_bIsComplete", default)]
+    pub is_complete: bool,
+}
+
+/// A submission of a list, e.g. a mod or a map
+#[derive(Debug, Deserialize)]
+pub struct Submission {
+    #[serde(rename = "_idRow")]
+    pub id: u32,
+    /// Type of the submission, e.g. `Mod`, as in the `GameBananaType` of the registry
+    #[serde(rename = "_sModelName")]
+    pub model_name: String,
+    #[serde(rename = "_sName")]
+    pub name: String,
+    #[serde(rename = "_aSubmitter")]
+    pub submitter: Option<Submitter>,
+}
+
+impl SubmissionList {
+    /// Loads a page of a section from the cache, fetching a fresh copy when it is outdated
+    pub async fn load(
+        downloader: &ModDownloader,
+        cache_dir: &Path,
+        section: BrowseSection,
+        page: u32,
+    ) -> Result<Self, Error> {
+        let file_name = format!("{:?}-{}.json", section, page).to_lowercase();
+        let endpoint = section_endpoint(section, page);
+        let data = cache::fetch_cached(
+            &cache_path(cache_dir, &file_name),
+            GAMEBANANA_LIST_CACHE_TTL,
+            || downloader.fetch_gamebanana_list(&endpoint),
+        )
+        .await?;
+        Self::from(data)
+    }
+
+    /// Parse the JSON response of the GameBanana API
+    pub fn from(data: Bytes) -> Result<Self, Error> {
+        info!("Parsing GameBanana submission list");
+        Ok(serde_json::from_slice(&data)?)
+    }
+}
+
+impl Submission {
+    /// Name of the user who submitted the item
+    pub fn author(&self) -> Option<&str> {
+        self.submitter
+            .as_ref()
+            .map(|submitter| submitter.name.as_str())
+    }
+}
+
+/// Path and query of the API endpoint listing a page of a section of the Celeste game section
+pub fn section_endpoint(section: BrowseSection, page: u32) -> String {
+    match section {
+        BrowseSection::Featured => format!(
+            "Util/List/Featured?_nPage={}&_idGameRow={}",
+            page, CELESTE_GAMEBANANA_ID
+        ),
+        BrowseSection::New | BrowseSection::Top => format!(
+            "Mod/Index?_nPage={}&_nPerpage={}&_aFilters[Generic_Game]={}&_sSort={}",
+            page,
+            BROWSE_PAGE_SIZE,
+            CELESTE_GAMEBANANA_ID,
+            if section == BrowseSection::New {
+                "Generic_Newest"
+            } else {
+                "Generic_MostLiked"
+            }
+        ),
+    }
+}
+
+/// Loads an image such as a screenshot from the cache, fetching it when it is missing or outdated
+pub async fn load_image(
+    downloader: &ModDownloader,
+    cache_dir: &Path,
+    url: &str,
+) -> Result<Bytes, Error> {
+    // Image URLs may contain characters which are not allowed in file names
+    let file_name = format!("images/{:016x}", xxh64(url.as_bytes(), 0));
+    cache::fetch_cached(
+        &cache_path(cache_dir, &file_name),
+        GAMEBANANA_IMAGE_CACHE_TTL,
+        || downloader.fetch_image(url),
+    )
+    .await
+}
+
+fn cache_path(cache_dir: &Path, file_name: &str) -> PathBuf {
+    cache_dir.join(GAMEBANANA_CACHE_DIRECTORY).join(file_name)
+}
+
+/// Strips HTML tags, turning line breaks and block ends into new lines and decoding common entities
+fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            rest = &rest[start..];
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim().to_ascii_lowercase();
+        let tag_name = tag.split_whitespace().next().unwrap_or_default();
+        if matches!(
+            tag_name,
+            "br" | "br/" | "/p" | "/div" | "/li" | "/h1" | "/h2" | "/h3" | "/ul"
+        ) {
+            text.push('\n');
+        } else if tag_name == "li" {
+            text.push_str("- ");
+        }
+        rest = &rest[start + end + 1..];
+    }
+    text.push_str(rest);
+
+    let text = text
+        .replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+
+    // Collapse the blank lines left by nested blocks
+    let mut lines: Vec<&str> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|last| last.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_submission_list() {
+        let data = Bytes::from_static(
+            br#"{
+                "_aMetadata": { "_nRecordCount": 31, "_bIsComplete": false, "_nPerpage": 15 },
+                "_aRecords": [
+                    { "_idRow": 150813, "_sModelName": "Mod", "_sName": "Strawberry Jam Collab", "_aSubmitter": { "_sName": "Lunaris" }},
+                    { "_idRow": 1, "_sModelName": "Wip", "_sName": "Untitled" }
+                ]
+            }"#,
+        );
+
+        let list = SubmissionList::from(data).unwrap();
+
+        assert!(!list.metadata.is_complete);
+        assert_eq!(list.records[0].author(), Some("Lunaris"));
+        assert_eq!(list.records[1].author(), None);
+    }
+
+    #[test]
+    fn test_parse_profile_page() {
+        let data = Bytes::from_static(
+            br#"{
+                "_idRow": 53697,
+                "_sName": "Speedrun Tool",
+                "_aSubmitter": { "_idRow": 1, "_sName": "DemoJameson" },
+                "_sText": "<p>Save &amp; load states.</p><ul><li>Timer</li><li>Rooms</li></ul>",
+                "_aPreviewMedia": {
+                    "_aImages": [
+                        { "_sType": "screenshot", "_sBaseUrl": "https://images.gamebanana.com/img/ss/mods", "_sFile": "abc.jpg", "_sFile220": "220-90_abc.jpg" }
+                    ]
+                }
+            }"#,
+        );
+
+        let item = GameBananaItem::from(data).unwrap();
+
+        assert_eq!(item.author(), Some("DemoJameson"));
+        assert_eq!(item.description(), "Save & load states.\n- Timer\n- Rooms");
+        assert_eq!(
+            item.screenshot_urls(),
+            vec!["https://images.gamebanana.com/img/ss/mods/abc.jpg"]
+        );
+        assert_eq!(
+            item.thumbnail_url().as_deref(),
+            Some("https://images.gamebanana.com/img/ss/mods/220-90_abc.jpg")
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use tracing::info;
+
+use crate::{
+    checksum::{self, Digests},
+    error::{Context, Error},
+    installed_index::InstalledIndex,
+    journal::{Journal, JournalEntry, Operation},
+    mod_registry::RemoteModInfo,
+    source::ModSource,
+};
+
+/// A local archive looked up in the registry by its hash
+#[derive(Debug)]
+pub struct Identification<'a> {
+    pub path: PathBuf,
+    pub digests: Digests,
+    /// Registry entry of the archive, `None` if no entry has its hash
+    pub remote: Option<&'a RemoteModInfo>,
+}
+
+/// What adopting an identified archive did
+#[derive(Debug, PartialEq, Eq)]
+pub enum Adoption {
+    /// Recorded in the installed index, at this path in the mods directory
+    Adopted(PathBuf),
+    /// Already recorded with the same hash
+    AlreadyManaged,
+}
+
+/// Identifies archives from their hash, whatever their file name, hashing up to `jobs` at once
+///
+/// The results are in the order of `files`.
+pub fn identify<'a>(
+    files: &[PathBuf],
+    source: &'a dyn ModSource,
+    jobs: u32,
+) -> Vec<Result<Identification<'a>, Error>> {
+    let targets: Vec<_> = files.iter().map(|path| (path.as_path(), &[][..])).collect();
+    files
+        .iter()
+        .zip(checksum::hash_files(&targets, jobs))
+        .map(|(path, digests)| {
+            let digests = digests.in_file(path)?;
+            Ok(Identification {
+                path: path.clone(),
+                remote: source.identify(&digests),
+                digests,
+            })
+        })
+        .collect()
+}
+
+/// Records an identified archive in the installed index and the journal, so that it is kept updated
+///
+/// Archives outside the mods directory are copied into it as `<mod name>.zip` first, the original
+/// is left untouched.
+pub fn adopt(
+    identification: &Identification,
+    remote: &RemoteModInfo,
+    mods_dir: &Path,
+    installed_index: &mut InstalledIndex,
+    journal: &Journal,
+) -> Result<Adoption, Error> {
+    let in_mods_dir = identification
+        .path
+        .parent()
+        .and_then(|parent| parent.canonicalize().ok())
+        .is_some_and(|parent| mods_dir.canonicalize().is_ok_and(|dir| dir == parent));
+    let archive_path = if in_mods_dir {
+        identification.path.clone()
+    } else {
+        let destination = mods_dir.join(format!("{}.zip", remote.name));
+        if destination.exists() {
+            let existing = checksum::hash_file(&destination, &remote.checksums)?;
+            if existing.xxh64 != identification.digests.xxh64 {
+                return Err(Error::ArchiveAlreadyExists(destination));
+            }
+        } else {
+            info!(
+                "Copying {} to {}",
+                identification.path.display(),
+                destination.display()
+            );
+            fs::copy(&identification.path, &destination)?;
+        }
+        destination
+    };
+
+    if installed_index
+        .get(&archive_path)
+        .is_some_and(|entry| entry.xxhash == identification.digests.xxh64)
+    {
+        return Ok(Adoption::AlreadyManaged);
+    }
+    installed_index.record(&archive_path, remote, &identification.digests);
+    installed_index.save()?;
+    journal.append(
+        &JournalEntry::new(Operation::Install, &remote.name, &archive_path)
+            .with_versions(None, &remote.version)
+            .with_hash(&identification.digests.xxh64),
+    )?;
+    Ok(Adoption::Adopted(archive_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{download::ModDownloader, mod_registry::ModRegistry, source::RegistrySource};
+    use reqwest::Client;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_identify_and_adopt_archive_with_junk_name() {
+        let dir = TempDir::new().unwrap();
+        let mods_dir = dir.path().join("Mods");
+        fs::create_dir(&mods_dir).unwrap();
+        let download = dir.path().join("download (3).zip");
+        fs::write(&download, "registered archive").unwrap();
+        let xxhash = checksum::hash_file(&download, &[]).unwrap().xxh64;
+        let mut registry: ModRegistry = serde_yaml_ng::from_str(&format!(
+            "MyMod:\n  Version: 1.2.0\n  Size: 18\n  LastUpdate: 0\n  URL: ''\n  \
+            xxHash: ['{}']\n  GameBananaType: Mod\n  GameBananaId: 1\n",
+            xxhash
+        ))
+        .unwrap();
+        registry.entries.get_mut("MyMod").unwrap().name = String::from("MyMod");
+        let source =
+            RegistrySource::new(&registry, ModDownloader::new(dir.path(), Client::new(), ""));
+
+        let identified = identify(std::slice::from_ref(&download), &source, 1)
+            .pop()
+            .unwrap()
+            .unwrap();
+        let remote = identified.remote.unwrap();
+        assert_eq!(remote.version, "1.2.0");
+
+        let mut installed_index = InstalledIndex::load(dir.path()).unwrap();
+        let journal = Journal::new(dir.path());
+        let adoption = adopt(
+            &identified,
+            remote,
+            &mods_dir,
+            &mut installed_index,
+            &journal,
+        )
+        .unwrap();
+
+        let adopted = mods_dir.join("MyMod.zip");
+        assert_eq!(adoption, Adoption::Adopted(adopted.clone()));
+        assert!(download.exists());
+        assert_eq!(installed_index.get(&adopted).unwrap().name, "MyMod");
+        assert_eq!(
+            adopt(
+                &identified,
+                remote,
+                &mods_dir,
+                &mut installed_index,
+                &journal
+            )
+            .unwrap(),
+            Adoption::AlreadyManaged
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::HashSet;
+
+use tracing::info;
+
+use crate::{
+    dependency::DependencyGraph,
+    download::BatchProgress,
+    error::Error,
+    fileutil,
+    game::is_pseudo_dependency,
+    installed_index::InstalledIndex,
+    installed_mods::Dependency,
+    journal::{Journal, JournalEntry, Operation},
+    mod_registry::RemoteModInfo,
+    source::ModSource,
+    summary::BatchSummary,
+};
+
+/// Collects the entries of the dependencies which are not installed yet
+///
+/// Transitive dependencies are resolved with the dependency graph and come before their
+/// dependents. Dependencies missing from the source are skipped, and so are the game and Everest.
+pub fn missing_dependencies<'a>(
+    dependencies: &[Dependency],
+    dependency_graph: &DependencyGraph,
+    source: &'a dyn ModSource,
+    installed_names: &HashSet<&str>,
+) -> Vec<&'a RemoteModInfo> {
+    let mut seen = HashSet::new();
+    let mut missing = Vec::new();
+    for dependency in dependencies {
+        let names = dependency_graph
+            .resolve(&dependency.name)
+            .into_iter()
+            .chain(std::iter::once(dependency.name.clone()));
+        for name in names {
+            if is_pseudo_dependency(&name)
+                || installed_names.contains(name.as_str())
+                || !seen.insert(name.clone())
+            {
+                continue;
+            }
+            match source.resolve(&name) {
+                // Names declared inside an archive resolve to the archive registered under another name
+                Some(mod_info)
+                    if installed_names.contains(mod_info.name.as_str())
+                        || missing
+                            .iter()
+                            .any(|m: &&RemoteModInfo| m.name == mod_info.name) => {}
+                Some(mod_info) => missing.push(mod_info),
+                None => info!("Dependency '{}' is not in {}", name, source.name()),
+            }
+        }
+    }
+    missing
+}
+
+/// Downloads mods from a source in order, recording each one in the installed index and the journal
+///
+/// A mod failing to download does not stop the batch; the failures are listed in the summary
+/// printed at the end and returned as an error. The mods depending on a failed one are skipped,
+/// they would not load without it.
+pub async fn install_from_source(
+    source: &dyn ModSource,
+    install_queue: &[&RemoteModInfo],
+    dependency_graph: &DependencyGraph,
+) -> Result<(), Error> {
+    let data_dir = fileutil::get_data_directory()?;
+    let mut installed_index = InstalledIndex::load(&data_dir)?;
+    let journal = Journal::new(&data_dir);
+    let mut summary = BatchSummary::new("installed");
+    let batch_progress = (install_queue.len() > 1).then(|| {
+        BatchProgress::new(
+            install_queue
+                .iter()
+                .map(|mod_info| mod_info.file_size)
+                .sum(),
+        )
+    });
+    let batch_source = batch_progress
+        .as_ref()
+        .map(|batch_progress| source.with_batch_progress(batch_progress));
+    let source = batch_source.as_deref().unwrap_or(source);
+    let mut failed = HashSet::new();
+    for remote_mod in install_queue {
+        let failed_dependency = dependency_graph
+            .resolve(&remote_mod.name)
+            .into_iter()
+            .map(|name| source.resolve(&name).map_or(name, |m| m.name.clone()))
+            .find(|name| failed.contains(name));
+        if let Some(dependency) = failed_dependency {
+            println!(
+                "\nSkipping {}, its dependency {} failed",
+                remote_mod.name, dependency
+            );
+            summary.skipped(
+                &remote_mod.name,
+                format!("dependency {} failed", dependency),
+            );
+            failed.insert(remote_mod.name.clone());
+            continue;
+        }
+        println!("\nDownloading {}...", remote_mod.name);
+        let archive = match source.download(remote_mod).await {
+            Ok(archive) => archive,
+            Err(e) => {
+                eprintln!("[Error] Failed to install {}: {}", remote_mod.name, e);
+                summary.failed(&remote_mod.name, e);
+                failed.insert(remote_mod.name.clone());
+                continue;
+            }
+        };
+        installed_index.record(&archive.path, remote_mod, &archive.digests);
+        if !archive.verified {
+            installed_index.mark_unverified(&archive.path);
+        }
+        installed_index.save()?;
+        journal.append(
+            &JournalEntry::new(Operation::Install, &remote_mod.name, &archive.path)
+                .with_versions(None, &remote_mod.version)
+                .with_hash(&archive.digests.xxh64),
+        )?;
+        summary.done(&remote_mod.name, &remote_mod.version);
+    }
+    if let Some(batch_progress) = batch_progress {
+        summary.set_transfer(batch_progress.finish());
+    }
+    if summary.len() > 1 {
+        println!("\n{}", summary);
+    }
+    summary.into_result()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{download::ModDownloader, mod_registry::ModRegistry, source::RegistrySource};
+    use reqwest::Client;
+    use std::path::Path;
+
+    #[test]
+    fn test_missing_dependencies_are_resolved_transitively() {
+        let graph = DependencyGraph::parse(
+            b"CollabUtils2:\n  Dependencies:\n  - Name: MaxHelpingHand\n  - Name: Everest\n",
+        )
+        .unwrap();
+        let mut mod_registry: ModRegistry = serde_yaml_ng::from_str(
+            "CollabUtils2: { Version: 1.0.0, Size: 0, LastUpdate: 0, URL: '', xxHash: [], GameBananaType: Mod, GameBananaId: 1 }\n\
+             MaxHelpingHand: { Version: 1.0.0, Size: 0, LastUpdate: 0, URL: '', xxHash: [], GameBananaType: Mod, GameBananaId: 2 }\n\
+             SkinModHelper: { Version: 1.0.0, Size: 0, LastUpdate: 0, URL: '', xxHash: [], GameBananaType: Mod, GameBananaId: 3 }\n",
+        )
+        .unwrap();
+        for (name, mod_info) in mod_registry.entries.iter_mut() {
+            mod_info.name = name.clone();
+        }
+        let dependencies: Vec<Dependency> = serde_yaml_ng::from_str(
+            "[{ Name: CollabUtils2 }, { Name: SkinModHelper }, { Name: MaxHelpingHand }]",
+        )
+        .unwrap();
+
+        let downloader = ModDownloader::new(Path::new("."), Client::new(), "");
+        let source = RegistrySource::new(&mod_registry, downloader);
+        let missing = missing_dependencies(
+            &dependencies,
+            &graph,
+            &source,
+            &HashSet::from(["SkinModHelper"]),
+        );
+
+        let names: Vec<_> = missing.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["MaxHelpingHand", "CollabUtils2"]);
+    }
+}
@@ -0,0 +1,200 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    checksum::Digests, constant::INSTALLED_INDEX_FILE, error::Error, mod_registry::RemoteModInfo,
+    timeutil,
+};
+
+/// What is known about an archive installed by this tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexEntry {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Version")]
+    pub version: String,
+    #[serde(rename = "xxHash")]
+    pub xxhash: String,
+    #[serde(rename = "SHA256", default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    /// Unix timestamp of the first installation of the mod
+    #[serde(rename = "InstalledAt", default)]
+    pub installed_at: u64,
+    /// Unix timestamp of the last update, if the mod was updated since
+    #[serde(rename = "UpdatedAt", default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<u64>,
+    /// Whether the archive was installed without checksum verification (`--no-verify`)
+    #[serde(
+        rename = "Unverified",
+        default,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub unverified: bool,
+}
+
+/// Index of the archives installed by this tool, stored as `installed.yaml` in the data directory
+///
+/// Entries are keyed by the absolute path of the archive, so that several mods directories
+/// can share the same index.
+#[derive(Debug, Default)]
+pub struct InstalledIndex {
+    path: PathBuf,
+    pub entries: BTreeMap<PathBuf, IndexEntry>,
+}
+
+impl InstalledIndex {
+    /// Loads the index from a data directory, an absent file is an empty index
+    pub fn load(data_dir: &Path) -> Result<Self, Error> {
+        let path = data_dir.join(INSTALLED_INDEX_FILE);
+        let entries = if path.exists() {
+            serde_yaml_ng::from_slice(&fs::read(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self { path, entries })
+    }
+
+    /// Looks up an installed archive
+    pub fn get(&self, archive_path: &Path) -> Option<&IndexEntry> {
+        self.entries.get(&index_key(archive_path))
+    }
+
+    /// Records an archive downloaded from the registry
+    ///
+    /// If the same mod was already recorded in the same directory, this is an update and the
+    /// original installation date is kept.
+    pub fn record(&mut self, archive_path: &Path, remote: &RemoteModInfo, digests: &Digests) {
+        let key = index_key(archive_path);
+        let previous = self
+            .entries
+            .iter()
+            .find(|(path, entry)| entry.name == remote.name && path.parent() == key.parent());
+        let now = timeutil::now();
+        let (installed_at, updated_at) = match previous {
+            Some((_, entry)) => (entry.installed_at, Some(now)),
+            None => (now, None),
+        };
+        self.entries.insert(
+            key,
+            IndexEntry {
+                name: remote.name.clone(),
+                version: remote.version.clone(),
+                xxhash: digests.xxh64.clone(),
+                sha256: digests.sha256.clone(),
+                installed_at,
+                updated_at,
+                unverified: false,
+            },
+        );
+    }
+
+    /// Flags a recorded archive as installed without checksum verification
+    pub fn mark_unverified(&mut self, archive_path: &Path) {
+        if let Some(entry) = self.entries.get_mut(&index_key(archive_path)) {
+            entry.unverified = true;
+        }
+    }
+
+    /// Forgets an archive which was replaced or removed, returns what was known about it
+    pub fn remove(&mut self, archive_path: &Path) -> Option<IndexEntry> {
+        self.entries.remove(&index_key(archive_path))
+    }
+
+    /// Records an archive with known details, e.g. one restored from a backup
+    pub fn insert(&mut self, archive_path: &Path, entry: IndexEntry) {
+        self.entries.insert(index_key(archive_path), entry);
+    }
+
+    /// Writes the index back to the data directory
+    pub fn save(&self) -> Result<(), Error> {
+        info!("Writing {}", self.path.display());
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_yaml_ng::to_string(&self.entries)?)?;
+        Ok(())
+    }
+}
+
+/// Absolute path of an archive, resolved through its directory so that it works for removed files
+fn index_key(archive_path: &Path) -> PathBuf {
+    match (archive_path.parent(), archive_path.file_name()) {
+        (Some(dir), Some(file_name)) => dir
+            .canonicalize()
+            .map(|dir| dir.join(file_name))
+            .unwrap_or_else(|_| archive_path.to_path_buf()),
+        _ => archive_path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn remote_mod(name: &str, version: &str) -> RemoteModInfo {
+        serde_yaml_ng::from_str(&format!(
+            "Version: {}\nSize: 0\nLastUpdate: 0\nURL: ''\nxxHash: []\nGameBananaType: Mod\nGameBananaId: 0",
+            version
+        ))
+        .map(|mut remote: RemoteModInfo| {
+            remote.name = name.to_string();
+            remote
+        })
+        .unwrap()
+    }
+
+    fn digests() -> Digests {
+        Digests {
+            xxh64: String::from("0000000000000000"),
+            md5: None,
+            sha256: None,
+        }
+    }
+
+    #[test]
+    fn test_update_keeps_installation_date() {
+        let dir = TempDir::new().unwrap();
+        let mut index = InstalledIndex::load(dir.path()).unwrap();
+        let old_path = dir.path().join("MyMod.zip");
+        index.record(&old_path, &remote_mod("MyMod", "1.0.0"), &digests());
+        index
+            .entries
+            .values_mut()
+            .for_each(|entry| entry.installed_at = 42);
+
+        let new_path = dir.path().join("MyMod (1).zip");
+        index.record(&new_path, &remote_mod("MyMod", "1.1.0"), &digests());
+        index.remove(&old_path);
+
+        let entry = index.get(&new_path).unwrap();
+        assert_eq!(entry.version, "1.1.0");
+        assert_eq!(entry.installed_at, 42);
+        assert!(entry.updated_at.is_some());
+        assert_eq!(index.entries.len(), 1);
+    }
+
+    #[test]
+    fn test_unverified_flag_is_saved() {
+        let dir = TempDir::new().unwrap();
+        let mut index = InstalledIndex::load(dir.path()).unwrap();
+        let verified = dir.path().join("Verified.zip");
+        let unverified = dir.path().join("Hotfix.zip");
+        index.record(&verified, &remote_mod("Verified", "1.0.0"), &digests());
+        index.record(&unverified, &remote_mod("Hotfix", "1.0.1"), &digests());
+        index.mark_unverified(&unverified);
+        index.save().unwrap();
+
+        let saved = fs::read_to_string(dir.path().join(INSTALLED_INDEX_FILE)).unwrap();
+        assert_eq!(saved.matches("Unverified: true").count(), 1);
+        let index = InstalledIndex::load(dir.path()).unwrap();
+        assert!(index.get(&unverified).unwrap().unverified);
+        assert!(!index.get(&verified).unwrap().unverified);
+    }
+}
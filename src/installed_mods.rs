@@ -1,15 +1,29 @@
+use glob::{MatchOptions, Pattern};
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::VecDeque,
+    collections::{BTreeMap, VecDeque},
+    fs,
     path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, OnceLock,
+        mpsc::{self, RecvTimeoutError, Sender},
+    },
+    thread,
 };
-use tracing::{info, warn};
+use tracing::{info, info_span, warn};
 
 use crate::{
-    error::{Error, Error::FileIsNotHashed},
-    fileutil::hash_file,
+    blacklist::Blacklist,
+    checksum,
+    constant::{MANIFEST_READ_TIMEOUT, MANIFEST_READ_TIMEOUT_MIN_SIZE, MANIFEST_READ_WORKERS},
+    error::{Context, Error},
     fileutil::{find_installed_mod_archives, read_manifest_file_from_zip},
-    mod_registry::ModRegistry,
+    last_check::LastCheck,
+    mod_registry::match_relevance,
+    normalize::canonical_file_name,
+    progress,
+    source::ModSource,
+    updater_blacklist::UpdaterBlacklist,
 };
 
 /// Represents the `everest.yaml` manifest file that defines a mod
@@ -39,7 +53,8 @@ pub struct Dependency {
 impl ModManifest {
     /// Parses the mod manifest YAML buffer into a structured `ModManifest` object.
     pub fn parse_mod_manifest_from_yaml(yaml_buffer: &[u8]) -> Result<Self, Error> {
-        let mut manifest_entries = serde_yaml_ng::from_slice::<VecDeque<ModManifest>>(yaml_buffer)?;
+        let mut manifest_entries = serde_yaml_ng::from_slice::<VecDeque<ModManifest>>(yaml_buffer)
+            .map_err(Error::InvalidManifest)?;
 
         // Attempt to retrieve the first entry without unnecessary cloning.
         manifest_entries
@@ -52,16 +67,13 @@ impl ModManifest {
 pub type InstalledModList = Vec<LocalModInfo>;
 
 /// Information about a locally installed mod
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct LocalModInfo {
     /// Path to the zip file which contains the mod's assets and manifest
     #[serde(rename = "Filename")]
     pub archive_path: PathBuf,
     /// Mod manifest
     pub manifest: ModManifest,
-    /// Computed XXH64 hash of the mod archive for update verification
-    #[serde(rename = "xxHash")]
-    checksum: Option<String>,
 }
 
 impl LocalModInfo {
@@ -69,41 +81,48 @@ impl LocalModInfo {
         Self {
             archive_path,
             manifest,
-            checksum: None,
         }
     }
 
-    pub fn checksum(&mut self) -> Result<&str, Error> {
-        if self.checksum.is_none() {
-            match hash_file(&self.archive_path){
-                Ok(checksum) => self.checksum = Some(checksum),
-                Err(E) => return Err(E)
-            }
-        }
-        // unwrap is fine here 
-        Ok(self.checksum.as_deref().unwrap())
-    }
-}
-
-pub fn update_mod_hashes(installed_mods: &mut [LocalModInfo]) {
-    for mod_info in installed_mods.iter_mut() {
-        // Compute the hash for the mod archive file.
-        if let Ok(hash) = hash_file(&mod_info.archive_path) {
-            mod_info.checksum = Some(hash);
-        }
+    /// File name of the archive, as referenced by `blacklist.txt`
+    pub fn archive_file_name(&self) -> String {
+        self.archive_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
     }
 }
 
 /// List installed mods which has valid manifest file
 pub fn list_installed_mods(mods_dir: &Path) -> Result<InstalledModList, Error> {
+    let _span = info_span!("scan").entered();
     let archive_paths = find_installed_mod_archives(mods_dir)?;
     let mut installed_mods = Vec::with_capacity(archive_paths.len());
 
+    let progress = progress::scan_bar(archive_paths.len() as u64);
     for archive_path in archive_paths {
-        let manifest_content = read_manifest_file_from_zip(&archive_path)?;
-        match manifest_content {
-            Some(buffer) => {
-                let manifest = ModManifest::parse_mod_manifest_from_yaml(&buffer)?;
+        progress.set_message(
+            archive_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+        );
+        let manifest = match read_manifest_in_time(&archive_path) {
+            // One hostile archive must not prevent managing the others
+            Err(e @ Error::UnsafeManifest(_)) => {
+                progress
+                    .suspend(|| eprintln!("Warning: skipping {}: {}", archive_path.display(), e));
+                progress.inc(1);
+                continue;
+            }
+            // Clear the bar before the error is printed
+            result => result
+                .in_file(&archive_path)
+                .inspect_err(|_| progress.finish_and_clear())?,
+        };
+        match manifest {
+            Some(manifest) => {
                 let mod_info = LocalModInfo::new(archive_path, manifest);
                 installed_mods.push(mod_info);
             }
@@ -114,14 +133,15 @@ pub fn list_installed_mods(mods_dir: &Path) -> Result<InstalledModList, Error> {
                     .expect("File name shoud be exist");
                 warn!(
                     "No mod manifest file (everest.yaml) found in {}.\n\
-                \t# The file might be named 'everest.yml' or located in a subdirectory.\n\
                 \t# Please contact the mod creator about this issue or just ignore this message.\n\
                 \t# Updates will be skipped for this mod.",
                     debug_path
                 )
             }
         }
+        progress.inc(1);
     }
+    progress.finish_and_clear();
     // Sort by name
     info!("Sorting results by name...");
     installed_mods.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
@@ -129,6 +149,221 @@ pub fn list_installed_mods(mods_dir: &Path) -> Result<InstalledModList, Error> {
     Ok(installed_mods)
 }
 
+/// Reads and parses the manifest of an archive
+fn read_manifest(archive_path: &Path) -> Result<Option<ModManifest>, Error> {
+    read_manifest_file_from_zip(archive_path).and_then(|content| {
+        content
+            .map(|buffer| ModManifest::parse_mod_manifest_from_yaml(&buffer))
+            .transpose()
+    })
+}
+
+type ManifestJob = Box<dyn FnOnce() + Send>;
+
+/// Queue of the worker threads reading the manifests of large archives, started on first use
+fn manifest_readers() -> &'static Sender<ManifestJob> {
+    static READERS: OnceLock<Sender<ManifestJob>> = OnceLock::new();
+    READERS.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<ManifestJob>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..MANIFEST_READ_WORKERS {
+            let receiver = Arc::clone(&receiver);
+            thread::spawn(move || {
+                loop {
+                    let job = match receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => return,
+                    };
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => return,
+                    }
+                }
+            });
+        }
+        sender
+    })
+}
+
+/// Reads and parses the manifest of an archive, giving up after a while on large archives
+///
+/// Large archives are read by a few worker threads so that a pathological one cannot wedge a scan,
+/// a worker stuck on one is left to finish on its own. Smaller archives are read directly.
+pub fn read_manifest_in_time(archive_path: &Path) -> Result<Option<ModManifest>, Error> {
+    let large = fs::metadata(archive_path)
+        .is_ok_and(|metadata| metadata.len() >= MANIFEST_READ_TIMEOUT_MIN_SIZE);
+    if !large {
+        return read_manifest(archive_path);
+    }
+    let (sender, receiver) = mpsc::channel();
+    let path = archive_path.to_path_buf();
+    let job = Box::new(move || {
+        // The scan may have given up on this archive already
+        let _ = sender.send(read_manifest(&path));
+    });
+    if manifest_readers().send(job).is_err() {
+        return Err(Error::UnsafeManifest(String::from("the reader crashed")));
+    }
+    match receiver.recv_timeout(MANIFEST_READ_TIMEOUT) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => Err(Error::UnsafeManifest(format!(
+            "reading it took more than {} seconds",
+            MANIFEST_READ_TIMEOUT.as_secs()
+        ))),
+        Err(RecvTimeoutError::Disconnected) => {
+            Err(Error::UnsafeManifest(String::from("the reader crashed")))
+        }
+    }
+}
+
+/// List the installed mods of several mods directories as one, sorted by name
+pub fn list_installed_mods_in(mods_dirs: &[PathBuf]) -> Result<InstalledModList, Error> {
+    let mut installed_mods = Vec::new();
+    for mods_dir in mods_dirs {
+        installed_mods.extend(list_installed_mods(mods_dir)?);
+    }
+    installed_mods.sort_by(|a, b| a.manifest.name.cmp(&b.manifest.name));
+    Ok(installed_mods)
+}
+
+/// Case-insensitive glob matching installed mods by name or archive file name
+///
+/// A pattern without wildcards matches any name containing it.
+#[derive(Debug)]
+pub struct ModFilter {
+    pattern: Pattern,
+}
+
+impl ModFilter {
+    pub fn new(pattern: &str) -> Result<Self, Error> {
+        let pattern = if pattern.contains(['*', '?', '[']) {
+            Pattern::new(pattern)?
+        } else {
+            Pattern::new(&format!("*{}*", Pattern::escape(pattern)))?
+        };
+        Ok(Self { pattern })
+    }
+
+    pub fn matches(&self, mod_info: &LocalModInfo) -> bool {
+        let options = MatchOptions {
+            case_sensitive: false,
+            ..MatchOptions::new()
+        };
+        self.pattern.matches_with(&mod_info.manifest.name, options)
+            || self
+                .pattern
+                .matches_with(&mod_info.archive_file_name(), options)
+    }
+}
+
+/// Finds installed mods whose name or archive file name contains the query, best matches first
+///
+/// Matches are ranked like registry search results, then ordered by name.
+pub fn search_installed<'a>(
+    installed_mods: &'a [LocalModInfo],
+    query: &str,
+) -> Vec<&'a LocalModInfo> {
+    let mut results: Vec<_> = installed_mods
+        .iter()
+        .filter_map(|mod_info| {
+            let file_stem = mod_info
+                .archive_path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy();
+            let relevance = [
+                match_relevance(&mod_info.manifest.name, query),
+                match_relevance(&file_stem, query),
+            ]
+            .into_iter()
+            .flatten()
+            .min()?;
+            Some((relevance, mod_info))
+        })
+        .collect();
+    results.sort_by_cached_key(|(relevance, mod_info)| {
+        (*relevance, mod_info.manifest.name.to_lowercase())
+    });
+    results.into_iter().map(|(_, mod_info)| mod_info).collect()
+}
+
+/// Groups the enabled archives declaring the same mod name, Everest refuses to load duplicates
+///
+/// Archives disabled in the blacklist are ignored since they do not conflict.
+pub fn find_duplicate_mods<'a>(
+    installed_mods: &'a [LocalModInfo],
+    blacklist: &Blacklist,
+) -> BTreeMap<&'a str, Vec<&'a LocalModInfo>> {
+    let mut by_name: BTreeMap<&str, Vec<&LocalModInfo>> = BTreeMap::new();
+    for mod_info in installed_mods {
+        if !blacklist.contains(&mod_info.archive_file_name()) {
+            by_name
+                .entry(mod_info.manifest.name.as_str())
+                .or_default()
+                .push(mod_info);
+        }
+    }
+    by_name.retain(|_, archives| archives.len() > 1);
+    by_name
+}
+
+/// Groups of archives which are byte-identical copies of each other, the archive to keep first
+///
+/// Only archives of the same mod and size are hashed, reusing the digests of `last_check` for
+/// archives unchanged since. The enabled archive named after its mod is kept preferably, and never
+/// a symbolic link, which could point to one of the copies to delete.
+pub fn find_identical_archives<'a>(
+    installed_mods: &'a [LocalModInfo],
+    blacklist: &Blacklist,
+    jobs: u32,
+    last_check: &mut LastCheck,
+) -> Vec<Vec<&'a LocalModInfo>> {
+    let mut by_size: BTreeMap<(&str, u64), Vec<&LocalModInfo>> = BTreeMap::new();
+    for mod_info in installed_mods {
+        if let Ok(metadata) = fs::metadata(&mod_info.archive_path) {
+            by_size
+                .entry((mod_info.manifest.name.as_str(), metadata.len()))
+                .or_default()
+                .push(mod_info);
+        }
+    }
+    let candidates: Vec<_> = by_size
+        .into_values()
+        .filter(|archives| archives.len() > 1)
+        .flatten()
+        .collect();
+    let files: Vec<_> = candidates
+        .iter()
+        .map(|mod_info| (mod_info.archive_path.as_path(), &[][..]))
+        .collect();
+    let hashes = hash_archives(&files, jobs, last_check);
+
+    let mut by_hash: BTreeMap<String, Vec<&LocalModInfo>> = BTreeMap::new();
+    for (mod_info, digests) in candidates.into_iter().zip(hashes) {
+        match digests {
+            Ok(digests) => by_hash.entry(digests.xxh64).or_default().push(mod_info),
+            Err(e) => warn!("Failed to hash {}: {}", mod_info.archive_path.display(), e),
+        }
+    }
+    by_hash
+        .into_values()
+        .filter(|archives| archives.len() > 1)
+        .map(|mut archives| {
+            archives.sort_by_cached_key(|mod_info| {
+                let file_name = mod_info.archive_file_name();
+                (
+                    mod_info.archive_path.is_symlink(),
+                    blacklist.contains(&file_name),
+                    file_name != canonical_file_name(&mod_info.manifest.name),
+                    file_name.len(),
+                    file_name,
+                )
+            });
+            archives
+        })
+        .collect()
+}
+
 /// Update information about the mod
 #[derive(Debug)]
 pub struct AvailableUpdateInfo {
@@ -138,42 +373,295 @@ pub struct AvailableUpdateInfo {
     pub current_version: String,
     /// Available version (from RemoteModInfo)
     pub available_version: String,
-    /// Download URL of the Mod
-    pub url: String,
-    /// xxHashes of the file
-    pub hash: Vec<String>,
+    /// Size of the file in bytes, as listed in the registry
+    pub size: u64,
     /// Outdated file
     pub existing_path: PathBuf,
+    /// GameBanana type of the mod, e.g. `Mod` or `Tool`
+    pub gamebanana_type: String,
+    /// GameBanana id of the mod
+    pub gamebanana_id: u32,
+    /// Code assembly declared by the installed manifest, if any
+    pub dll: Option<String>,
 }
 
-/// Check available updates for all installed mods
-pub fn check_updates(
-    mods_dir: &Path,
-    mod_registry: &ModRegistry,
-) -> Result<Vec<AvailableUpdateInfo>, Error> {
-    let mut installed_mods = list_installed_mods(mods_dir)?;
-
-    let mut available_updates = Vec::new();
-    for mut local_mod in installed_mods {
-        if let Some(remote_mod) = mod_registry.get_mod_info(&local_mod.manifest.name) {
-            if let Ok(computed_hash) = &local_mod.checksum() {
-                if remote_mod.has_matching_hash(computed_hash) {
-                    continue; // No update avilable
-                };
-                let available_mod = remote_mod.clone();
-                available_updates.push(AvailableUpdateInfo {
-                    name: local_mod.manifest.name,
-                    current_version: local_mod.manifest.version,
-                    available_version: available_mod.version,
-                    url: available_mod.download_url,
-                    hash: available_mod.checksums,
-                    existing_path: local_mod.archive_path,
-                });
-            } else {
-                return Err(Error::FileIsNotHashed);
+impl AvailableUpdateInfo {
+    /// Whether the mod ships code, which may break other mods or saves when updated
+    pub fn is_code_mod(&self) -> bool {
+        self.dll.is_some()
+    }
+}
+
+/// Available updates of the installed mods
+#[derive(Debug, Default)]
+pub struct UpdateCheck {
+    pub updates: Vec<AvailableUpdateInfo>,
+    /// Updates of the mods excluded by `updaterblacklist.txt`, e.g. pinned mods
+    pub excluded: Vec<AvailableUpdateInfo>,
+    /// Mods matching no registry entry, which cannot be kept updated
+    pub unmanaged: Vec<LocalModInfo>,
+}
+
+/// Digests of `files`, reused from `last_check` for the archives unchanged since
+///
+/// Changed archives are hashed on up to `jobs` threads and recorded in `last_check`.
+fn hash_archives(
+    files: &[(&Path, &[String])],
+    jobs: u32,
+    last_check: &mut LastCheck,
+) -> Vec<Result<checksum::Digests, Error>> {
+    let mut hashes: Vec<_> = files
+        .iter()
+        .map(|(path, expected)| last_check.digests(path, expected).map(Ok))
+        .collect();
+    let changed: Vec<_> = files
+        .iter()
+        .zip(&hashes)
+        .filter(|(_, cached)| cached.is_none())
+        .map(|(file, _)| *file)
+        .collect();
+    info!(
+        "Hashing {} archive(s), {} unchanged since the last check",
+        changed.len(),
+        files.len() - changed.len()
+    );
+    let mut fresh = checksum::hash_files(&changed, jobs).into_iter();
+    for ((path, _), digests) in files.iter().zip(hashes.iter_mut()) {
+        if digests.is_none() {
+            let result = fresh.next().expect("one result per changed archive");
+            if let Ok(result) = &result {
+                last_check.record_digests(path, result);
             }
+            *digests = Some(result);
+        }
+    }
+    hashes.into_iter().flatten().collect()
+}
+
+/// Installed mods whose name and hash match no registry entry
+///
+/// These were installed by hand, renamed, or removed from GameBanana, so the tool cannot
+/// keep them updated. A mod whose name is unknown but whose archive is identical to a
+/// registered one is not unmanaged, only its manifest name differs.
+pub fn find_unmanaged<'a>(
+    installed_mods: &'a [LocalModInfo],
+    source: &dyn ModSource,
+    jobs: u32,
+    last_check: &mut LastCheck,
+) -> Vec<&'a LocalModInfo> {
+    let unknown: Vec<_> = installed_mods
+        .iter()
+        .filter(|local_mod| source.resolve(&local_mod.manifest.name).is_none())
+        .collect();
+    let files: Vec<_> = unknown
+        .iter()
+        .map(|local_mod| (local_mod.archive_path.as_path(), &[][..]))
+        .collect();
+    let hashes = hash_archives(&files, jobs, last_check);
+    unknown
+        .into_iter()
+        .zip(hashes)
+        .filter(|(local_mod, digests)| match digests {
+            Ok(digests) => source.identify(digests).is_none(),
+            Err(e) => {
+                warn!("Failed to hash {}: {}", local_mod.archive_path.display(), e);
+                true
+            }
+        })
+        .map(|(local_mod, _)| local_mod)
+        .collect()
+}
+
+/// Check available updates for the mods installed in all `mods_dirs`, setting aside those
+/// excluded by the `updaterblacklist.txt` of their directory
+///
+/// Archives are hashed on up to `jobs` threads, unless they are unchanged since `last_check`.
+pub fn check_updates(
+    mods_dirs: &[PathBuf],
+    source: &dyn ModSource,
+    jobs: u32,
+    last_check: &mut LastCheck,
+) -> Result<UpdateCheck, Error> {
+    let installed_mods = list_installed_mods_in(mods_dirs)?;
+    let updater_blacklists = mods_dirs
+        .iter()
+        .map(|mods_dir| Ok((mods_dir.as_path(), UpdaterBlacklist::load(mods_dir)?)))
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    let unmanaged: Vec<_> = find_unmanaged(&installed_mods, source, jobs, last_check)
+        .into_iter()
+        .cloned()
+        .collect();
+    let registered: Vec<_> = installed_mods
+        .into_iter()
+        .filter_map(|local_mod| {
+            let remote_mod = source.resolve(&local_mod.manifest.name)?;
+            Some((local_mod, remote_mod))
+        })
+        .collect();
+    let files: Vec<_> = registered
+        .iter()
+        .map(|(local_mod, remote_mod)| {
+            (
+                local_mod.archive_path.as_path(),
+                remote_mod.checksums.as_slice(),
+            )
+        })
+        .collect();
+    let hashes = hash_archives(&files, jobs, last_check);
+    let mut paths: Vec<_> = files.iter().map(|(path, _)| *path).collect();
+    paths.extend(
+        unmanaged
+            .iter()
+            .map(|local_mod| local_mod.archive_path.as_path()),
+    );
+    last_check.retain_archives(&paths);
+
+    let mut update_check = UpdateCheck {
+        unmanaged,
+        ..UpdateCheck::default()
+    };
+    for ((local_mod, remote_mod), digests) in registered.into_iter().zip(hashes) {
+        let Ok(digests) = digests else {
+            return Err(Error::FileIsNotHashed);
+        };
+        if source.verify(remote_mod, &digests) {
+            continue; // No update avilable
+        };
+        let excluded = updater_blacklists
+            .iter()
+            .find(|(mods_dir, _)| local_mod.archive_path.parent() == Some(mods_dir))
+            .is_some_and(|(_, updater_blacklist)| {
+                updater_blacklist.is_excluded(&local_mod.archive_file_name())
+            });
+        let available_mod = remote_mod.clone();
+        let update = AvailableUpdateInfo {
+            name: local_mod.manifest.name,
+            current_version: local_mod.manifest.version,
+            available_version: available_mod.version,
+            size: available_mod.file_size,
+            existing_path: local_mod.archive_path,
+            gamebanana_type: available_mod.gamebanana_type,
+            gamebanana_id: available_mod.gamebanana_id,
+            dll: local_mod.manifest.dll,
+        };
+        if excluded {
+            info!("Skipping {}: excluded by updaterblacklist.txt", update.name);
+            update_check.excluded.push(update);
+        } else {
+            update_check.updates.push(update);
         }
     }
 
-    Ok(available_updates)
+    Ok(update_check)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{download::ModDownloader, mod_registry::ModRegistry, source::RegistrySource};
+    use reqwest::Client;
+
+    fn local_mod(name: &str, file_name: &str) -> LocalModInfo {
+        let manifest = ModManifest::parse_mod_manifest_from_yaml(
+            format!("- Name: {}\n  Version: 1.0.0\n", name).as_bytes(),
+        )
+        .unwrap();
+        LocalModInfo::new(PathBuf::from(file_name), manifest)
+    }
+
+    #[test]
+    fn test_mod_filter() {
+        let collab = local_mod("SpringCollab2020", "Spring Collab 2020.zip");
+        let helper = local_mod("MaxHelpingHand", "maxhelpinghand.zip");
+
+        let glob = ModFilter::new("spring*").unwrap();
+        assert!(glob.matches(&collab));
+        assert!(!glob.matches(&helper));
+
+        let substring = ModFilter::new("helping").unwrap();
+        assert!(substring.matches(&helper));
+        assert!(!substring.matches(&collab));
+
+        // Matches the archive file name too
+        assert!(ModFilter::new("Spring Collab*").unwrap().matches(&collab));
+    }
+
+    #[test]
+    fn test_search_installed_ranks_matches() {
+        let installed = vec![
+            local_mod("ExtendedVariantMode", "ExtendedVariants.zip"),
+            local_mod("CassetteBlockHelper", "CassetteBlockHelper.zip"),
+            local_mod("Cassette", "cassette_mod.zip"),
+            local_mod("AnotherCassetteMod", "Another.zip"),
+        ];
+
+        let names: Vec<_> = search_installed(&installed, "CASSETTE")
+            .iter()
+            .map(|m| m.manifest.name.as_str())
+            .collect();
+
+        assert_eq!(
+            names,
+            vec!["Cassette", "CassetteBlockHelper", "AnotherCassetteMod"]
+        );
+    }
+
+    #[test]
+    fn test_find_unmanaged_recognizes_renamed_archives() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let archive = |file_name: &str, content: &str| {
+            let path = dir.path().join(file_name);
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+        let renamed = archive("Renamed.zip", "registered archive");
+        let manual = archive("Manual.zip", "hand made archive");
+        let digests = checksum::hash_file(&renamed, &[]).unwrap();
+        let registry: ModRegistry = serde_yaml_ng::from_str(&format!(
+            "Registered:\n  Version: 1.0.0\n  Size: 18\n  LastUpdate: 0\n  URL: ''\n  \
+            xxHash: ['{}']\n  GameBananaType: Mod\n  GameBananaId: 1\n",
+            digests.xxh64
+        ))
+        .unwrap();
+        let installed = vec![
+            LocalModInfo::new(renamed, local_mod("MyFork", "").manifest),
+            LocalModInfo::new(manual, local_mod("MyMod", "").manifest),
+        ];
+        let mut last_check = LastCheck::load(dir.path());
+        let source =
+            RegistrySource::new(&registry, ModDownloader::new(dir.path(), Client::new(), ""));
+
+        let unmanaged = find_unmanaged(&installed, &source, 1, &mut last_check);
+
+        let names: Vec<_> = unmanaged.iter().map(|m| m.manifest.name.as_str()).collect();
+        assert_eq!(names, vec!["MyMod"]);
+    }
+
+    #[test]
+    fn test_identical_archives_keep_the_canonical_name() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let installed: Vec<_> = [
+            ("1298450.zip", "same bytes"),
+            ("MyMod.zip", "same bytes"),
+            ("MyMod_v2.zip", "different"),
+        ]
+        .into_iter()
+        .map(|(file_name, content)| {
+            let path = dir.path().join(file_name);
+            std::fs::write(&path, content).unwrap();
+            LocalModInfo::new(path, local_mod("MyMod", "").manifest)
+        })
+        .collect();
+        let blacklist = Blacklist::load(dir.path()).unwrap();
+        let mut last_check = LastCheck::load(dir.path());
+
+        let identical = find_identical_archives(&installed, &blacklist, 1, &mut last_check);
+
+        let file_names: Vec<Vec<_>> = identical
+            .iter()
+            .map(|archives| archives.iter().map(|m| m.archive_file_name()).collect())
+            .collect();
+        assert_eq!(file_names, vec![vec!["MyMod.zip", "1298450.zip"]]);
+    }
 }
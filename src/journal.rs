@@ -0,0 +1,343 @@
+use std::{
+    fmt,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    constant::{BACKUP_DIRECTORY, JOURNAL_FILE},
+    error::Error,
+    timeutil,
+};
+
+/// Kinds of operations modifying the mods directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Operation {
+    Install,
+    Update,
+    Disable,
+    /// Removal of an extra copy of an archive
+    Remove,
+    Undo,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Install => write!(f, "install"),
+            Operation::Update => write!(f, "update"),
+            Operation::Disable => write!(f, "disable"),
+            Operation::Remove => write!(f, "remove"),
+            Operation::Undo => write!(f, "undo"),
+        }
+    }
+}
+
+/// A single operation recorded in the journal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    /// Unix timestamp of the operation
+    #[serde(rename = "Time")]
+    pub timestamp: u64,
+    #[serde(rename = "Operation")]
+    pub operation: Operation,
+    /// Mod name
+    #[serde(rename = "Name")]
+    pub name: String,
+    /// Version before the operation, for updates
+    #[serde(
+        rename = "FromVersion",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub from_version: Option<String>,
+    /// Version after the operation
+    #[serde(rename = "Version", default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// Archive affected by the operation
+    #[serde(rename = "Archive")]
+    pub archive: PathBuf,
+    /// xxHash of the archive after the operation
+    #[serde(rename = "xxHash", default, skip_serializing_if = "Option::is_none")]
+    pub xxhash: Option<String>,
+    /// Copy of the archive replaced by an update, and where it was located
+    #[serde(rename = "Backup", default, skip_serializing_if = "Option::is_none")]
+    pub backup: Option<Backup>,
+    /// Identical archive which was kept when this one was removed
+    #[serde(rename = "CopyOf", default, skip_serializing_if = "Option::is_none")]
+    pub copy_of: Option<PathBuf>,
+    /// Position in the journal of the entry reverted by an undo
+    #[serde(rename = "Undoes", default, skip_serializing_if = "Option::is_none")]
+    pub undoes: Option<usize>,
+}
+
+/// An archive saved before being replaced, so that the operation can be undone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Backup {
+    #[serde(rename = "Path")]
+    pub path: PathBuf,
+    #[serde(rename = "OriginalPath")]
+    pub original_path: PathBuf,
+}
+
+impl JournalEntry {
+    pub fn new(operation: Operation, name: &str, archive: &Path) -> Self {
+        Self {
+            timestamp: timeutil::now(),
+            operation,
+            name: name.to_string(),
+            from_version: None,
+            version: None,
+            archive: archive.to_path_buf(),
+            xxhash: None,
+            backup: None,
+            copy_of: None,
+            undoes: None,
+        }
+    }
+
+    pub fn with_versions(mut self, from_version: Option<&str>, version: &str) -> Self {
+        self.from_version = from_version.map(String::from);
+        self.version = Some(version.to_string());
+        self
+    }
+
+    pub fn with_hash(mut self, xxhash: &str) -> Self {
+        self.xxhash = Some(xxhash.to_string());
+        self
+    }
+
+    pub fn with_backup(mut self, backup: Option<Backup>) -> Self {
+        self.backup = backup;
+        self
+    }
+
+    pub fn with_copy_of(mut self, kept: &Path) -> Self {
+        self.copy_of = Some(kept.to_path_buf());
+        self
+    }
+
+    /// Describes the steps reverting this operation
+    pub fn undo_steps(&self) -> Result<Vec<UndoStep>, Error> {
+        match self.operation {
+            Operation::Install => Ok(vec![UndoStep::RemoveArchive(self.archive.clone())]),
+            Operation::Update => {
+                let backup = self.backup.as_ref().ok_or_else(|| {
+                    Error::CannotUndo(String::from("no backup of the previous archive was made"))
+                })?;
+                let mut steps = Vec::new();
+                if self.archive != backup.original_path {
+                    steps.push(UndoStep::RemoveArchive(self.archive.clone()));
+                }
+                steps.push(UndoStep::RestoreArchive {
+                    backup: backup.path.clone(),
+                    to: backup.original_path.clone(),
+                });
+                Ok(steps)
+            }
+            Operation::Disable => Ok(vec![UndoStep::Enable(self.archive.clone())]),
+            Operation::Remove => {
+                let kept = self.copy_of.as_ref().ok_or_else(|| {
+                    Error::CannotUndo(String::from("no copy of the removed archive was kept"))
+                })?;
+                Ok(vec![UndoStep::CopyArchive {
+                    from: kept.clone(),
+                    to: self.archive.clone(),
+                }])
+            }
+            Operation::Undo => Err(Error::CannotUndo(String::from(
+                "undo operations cannot be undone",
+            ))),
+        }
+    }
+}
+
+/// A single change made to revert an operation
+#[derive(Debug, PartialEq, Eq)]
+pub enum UndoStep {
+    /// Delete an archive which was installed
+    RemoveArchive(PathBuf),
+    /// Put back an archive which was replaced
+    RestoreArchive { backup: PathBuf, to: PathBuf },
+    /// Remove an archive from the blacklist of its mods directory
+    Enable(PathBuf),
+    /// Copy back an archive which was removed, from the identical one which was kept
+    CopyArchive { from: PathBuf, to: PathBuf },
+}
+
+impl fmt::Display for UndoStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UndoStep::RemoveArchive(path) => write!(f, "remove {}", path.display()),
+            UndoStep::RestoreArchive { backup, to } => {
+                write!(f, "restore {} from {}", to.display(), backup.display())
+            }
+            UndoStep::Enable(path) => write!(f, "remove {} from the blacklist", path.display()),
+            UndoStep::CopyArchive { from, to } => {
+                write!(f, "copy {} back from {}", to.display(), from.display())
+            }
+        }
+    }
+}
+
+/// Finds the most recent operation which was not undone yet, with its position in the journal
+pub fn last_undoable(entries: &[JournalEntry]) -> Option<(usize, &JournalEntry)> {
+    let undone: Vec<usize> = entries.iter().filter_map(|entry| entry.undoes).collect();
+    entries
+        .iter()
+        .enumerate()
+        .rev()
+        .find(|(position, entry)| entry.operation != Operation::Undo && !undone.contains(position))
+}
+
+/// Copies an archive into the backup directory before it is replaced
+pub fn backup_archive(data_dir: &Path, archive: &Path) -> Result<Backup, Error> {
+    let backup_dir = data_dir.join(BACKUP_DIRECTORY);
+    fs::create_dir_all(&backup_dir)?;
+    let file_name = archive.file_name().unwrap_or_default().to_string_lossy();
+    let path = backup_dir.join(format!("{}-{}", timeutil::now(), file_name));
+    info!("Backing up {} to {}", archive.display(), path.display());
+    fs::copy(archive, &path)?;
+    Ok(Backup {
+        path,
+        original_path: archive.to_path_buf(),
+    })
+}
+
+impl fmt::Display for JournalEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}  {:<8} {}",
+            timeutil::format_timestamp(self.timestamp),
+            self.operation.to_string(),
+            self.name
+        )?;
+        match (&self.from_version, &self.version) {
+            (Some(from), Some(to)) => write!(f, " {} -> {}", from, to)?,
+            (None, Some(version)) => write!(f, " {}", version)?,
+            _ => {}
+        }
+        if let Some(file_name) = self.archive.file_name() {
+            write!(f, " ({})", file_name.to_string_lossy())?;
+        }
+        Ok(())
+    }
+}
+
+/// Append-only log of the operations performed on the mods directory, stored as `journal.yaml`
+///
+/// Each entry is appended as a one-item YAML sequence, so the file as a whole stays a valid
+/// YAML list without rewriting it.
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+}
+
+impl Journal {
+    pub fn new(data_dir: &Path) -> Self {
+        Self {
+            path: data_dir.join(JOURNAL_FILE),
+        }
+    }
+
+    /// Appends an entry to the journal
+    pub fn append(&self, entry: &JournalEntry) -> Result<(), Error> {
+        info!("Journal: {}", entry);
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(serde_yaml_ng::to_string(&[entry])?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Reads every entry, oldest first
+    pub fn entries(&self) -> Result<Vec<JournalEntry>, Error> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read(&self.path)?;
+        Ok(serde_yaml_ng::from_slice::<Option<Vec<JournalEntry>>>(&content)?.unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_appended_entries_are_read_back_in_order() {
+        let dir = TempDir::new().unwrap();
+        let journal = Journal::new(dir.path());
+        assert!(journal.entries().unwrap().is_empty());
+
+        let install = JournalEntry::new(Operation::Install, "MyMod", Path::new("MyMod.zip"))
+            .with_versions(None, "1.0.0")
+            .with_hash("0123456789abcdef");
+        let update = JournalEntry::new(Operation::Update, "MyMod", Path::new("MyMod.zip"))
+            .with_versions(Some("1.0.0"), "1.1.0");
+        journal.append(&install).unwrap();
+        journal.append(&update).unwrap();
+
+        let entries = journal.entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].operation, Operation::Install);
+        assert_eq!(entries[0].xxhash.as_deref(), Some("0123456789abcdef"));
+        assert_eq!(entries[1].from_version.as_deref(), Some("1.0.0"));
+    }
+
+    #[test]
+    fn test_last_undoable_skips_undone_operations() {
+        let install = JournalEntry::new(Operation::Install, "A", Path::new("A.zip"));
+        let disable = JournalEntry::new(Operation::Disable, "B", Path::new("B.zip"));
+        let mut undo = JournalEntry::new(Operation::Undo, "B", Path::new("B.zip"));
+        undo.undoes = Some(1);
+        let entries = vec![install, disable, undo];
+
+        let (position, entry) = last_undoable(&entries).unwrap();
+
+        assert_eq!(position, 0);
+        assert_eq!(entry.name, "A");
+    }
+
+    #[test]
+    fn test_update_without_backup_cannot_be_undone() {
+        let update = JournalEntry::new(Operation::Update, "A", Path::new("A.zip"));
+        assert!(update.undo_steps().is_err());
+
+        let update = update.with_backup(Some(Backup {
+            path: PathBuf::from("backups/1-A.zip"),
+            original_path: PathBuf::from("A.zip"),
+        }));
+        assert_eq!(
+            update.undo_steps().unwrap(),
+            vec![UndoStep::RestoreArchive {
+                backup: PathBuf::from("backups/1-A.zip"),
+                to: PathBuf::from("A.zip"),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_removed_copy_is_copied_back_from_the_kept_archive() {
+        let remove = JournalEntry::new(Operation::Remove, "A", Path::new("A (1).zip"))
+            .with_copy_of(Path::new("A.zip"));
+
+        assert_eq!(
+            remove.undo_steps().unwrap(),
+            vec![UndoStep::CopyArchive {
+                from: PathBuf::from("A.zip"),
+                to: PathBuf::from("A (1).zip"),
+            }]
+        );
+    }
+}
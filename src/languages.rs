@@ -0,0 +1,78 @@
+use std::path::Path;
+
+use crate::{error::Error, fileutil::list_zip_entries};
+
+/// Dialog languages shipped by a mod
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct DialogCoverage {
+    /// Whether the mod contains maps, as opposed to helpers or skins
+    pub has_maps: bool,
+    /// Names of the dialog files directly under `Dialog/`, e.g. `English`, sorted
+    pub languages: Vec<String>,
+}
+
+impl DialogCoverage {
+    /// Reads the entries of an archive
+    pub fn of_archive(archive_path: &Path) -> Result<Self, Error> {
+        Ok(Self::from_entries(&list_zip_entries(archive_path)?))
+    }
+
+    pub fn from_entries(entries: &[String]) -> Self {
+        let mut coverage = Self::default();
+        for entry in entries {
+            let lower = entry.to_lowercase();
+            if lower.starts_with("maps/") && lower.ends_with(".bin") {
+                coverage.has_maps = true;
+            }
+            // Everest looks up dialog files ignoring case
+            if let Some((dir, file_name)) = entry.split_once('/')
+                && dir.eq_ignore_ascii_case("Dialog")
+                && !file_name.contains('/')
+                && let Some((language, extension)) = file_name.rsplit_once('.')
+                && extension.eq_ignore_ascii_case("txt")
+            {
+                coverage.languages.push(language.to_string());
+            }
+        }
+        coverage.languages.sort_unstable();
+        coverage.languages.dedup();
+        coverage
+    }
+
+    /// Whether a language is supported, ignoring case (`english` matches `English.txt`)
+    pub fn supports(&self, language: &str) -> bool {
+        self.languages
+            .iter()
+            .any(|supported| supported.eq_ignore_ascii_case(language))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dialog_coverage_from_entries() {
+        let entries: Vec<String> = [
+            "everest.yaml",
+            "Dialog/English.txt",
+            "dialog/Simplified Chinese.TXT",
+            "DIALOG/French.txt",
+            "Dialog/Fonts/japanese.fnt",
+            "Maps/Me/MyMod/1-Forest.bin",
+        ]
+        .iter()
+        .map(|entry| entry.to_string())
+        .collect();
+
+        let coverage = DialogCoverage::from_entries(&entries);
+
+        assert!(coverage.has_maps);
+        assert_eq!(
+            coverage.languages,
+            vec!["English", "French", "Simplified Chinese"]
+        );
+        assert!(coverage.supports("simplified chinese"));
+        assert!(!coverage.supports("Japanese"));
+    }
+}
@@ -0,0 +1,158 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::{
+    checksum::{ChecksumKind, Digests},
+    constant::LAST_CHECK_FILE,
+    error::Error,
+};
+
+/// What the previous run learned, stored as `last_check.yaml` in the cache directory
+///
+/// Repeated update checks only hash the archives whose size or modification time changed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct LastCheck {
+    #[serde(skip)]
+    cache_dir: PathBuf,
+    #[serde(rename = "Archives", default)]
+    archives: BTreeMap<PathBuf, ArchiveState>,
+}
+
+/// Digests of an archive, valid while its size and modification time are unchanged
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ArchiveState {
+    #[serde(rename = "Size")]
+    size: u64,
+    /// Nanoseconds since the Unix epoch
+    #[serde(rename = "Modified")]
+    modified: u64,
+    #[serde(rename = "xxHash")]
+    xxhash: String,
+    #[serde(rename = "MD5", default, skip_serializing_if = "Option::is_none")]
+    md5: Option<String>,
+}
+
+impl ArchiveState {
+    /// Size and modification time of an archive, with the digests left empty
+    fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Self {
+            size: metadata.len(),
+            modified: modified.as_nanos() as u64,
+            xxhash: String::new(),
+            md5: None,
+        })
+    }
+
+    fn is_same_file(&self, other: &Self) -> bool {
+        self.size == other.size && self.modified == other.modified
+    }
+}
+
+impl LastCheck {
+    /// Loads the state of the previous run, an absent or unreadable state is an empty one
+    pub fn load(cache_dir: &Path) -> Self {
+        let path = cache_dir.join(LAST_CHECK_FILE);
+        let last_check = match fs::read(&path) {
+            Ok(content) => serde_yaml_ng::from_slice(&content).unwrap_or_else(|e| {
+                warn!("Ignoring the invalid {}: {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        };
+        Self {
+            cache_dir: cache_dir.to_path_buf(),
+            ..last_check
+        }
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        fs::create_dir_all(&self.cache_dir)?;
+        fs::write(
+            self.cache_dir.join(LAST_CHECK_FILE),
+            serde_yaml_ng::to_string(self)?,
+        )?;
+        Ok(())
+    }
+
+    /// Digests of an archive computed by a previous run, if the archive is unchanged since
+    /// and they are enough to verify it against `expected`
+    pub fn digests(&self, path: &Path, expected: &[String]) -> Option<Digests> {
+        let state = self.archives.get(path)?;
+        if !state.is_same_file(&ArchiveState::of(path)?) {
+            return None;
+        }
+        let needs_md5 = expected
+            .iter()
+            .any(|checksum| ChecksumKind::detect(checksum) == Some(ChecksumKind::Md5));
+        if needs_md5 && state.md5.is_none() {
+            return None;
+        }
+        Some(Digests {
+            xxh64: state.xxhash.clone(),
+            md5: state.md5.clone(),
+            sha256: None,
+        })
+    }
+
+    /// Remembers the digests of an archive as it is now
+    pub fn record_digests(&mut self, path: &Path, digests: &Digests) {
+        if let Some(state) = ArchiveState::of(path) {
+            self.archives.insert(
+                path.to_path_buf(),
+                ArchiveState {
+                    xxhash: digests.xxh64.clone(),
+                    md5: digests.md5.clone(),
+                    ..state
+                },
+            );
+        }
+    }
+
+    /// Forgets the archives which are not in `paths`, e.g. removed mods
+    pub fn retain_archives(&mut self, paths: &[&Path]) {
+        self.archives
+            .retain(|path, _| paths.contains(&path.as_path()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_digests_are_reused_until_the_archive_changes() {
+        let dir = TempDir::new().unwrap();
+        let archive = dir.path().join("MyMod.zip");
+        fs::write(&archive, "1.0.0").unwrap();
+        let mut last_check = LastCheck::load(dir.path());
+        let digests = Digests {
+            xxh64: String::from("0123456789abcdef"),
+            md5: None,
+            sha256: None,
+        };
+
+        last_check.record_digests(&archive, &digests);
+        last_check.save().unwrap();
+        let last_check = LastCheck::load(dir.path());
+
+        let xxhash = [String::from("fedcba9876543210")];
+        let md5 = [String::from("900150983cd24fb0d6963f7d28e17f72")];
+        assert_eq!(
+            last_check.digests(&archive, &xxhash).map(|d| d.xxh64),
+            Some(digests.xxh64)
+        );
+        assert!(last_check.digests(&archive, &md5).is_none());
+        fs::write(&archive, "1.1.0-beta").unwrap();
+        assert!(last_check.digests(&archive, &xxhash).is_none());
+    }
+}
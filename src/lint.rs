@@ -0,0 +1,462 @@
+use std::{
+    collections::HashSet,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use serde_yaml_ng::Value;
+
+use crate::{
+    constant::MOD_MANIFEST_FILES,
+    download::ModDownloader,
+    error::Error,
+    fileutil::{list_zip_entries, read_manifest_file_from_zip},
+    game::is_pseudo_dependency,
+    mod_registry::ModRegistry,
+    version::EverestVersion,
+};
+
+const ENTRY_KEYS: [&str; 5] = [
+    "Name",
+    "Version",
+    "DLL",
+    "Dependencies",
+    "OptionalDependencies",
+];
+const DEPENDENCY_KEYS: [&str; 2] = ["Name", "Version"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found in a mod manifest, with the 1-based line it was found on if known
+#[derive(Debug)]
+pub struct LintIssue {
+    pub severity: Severity,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+/// A manifest to lint, along with the files shipped next to it when known
+#[derive(Debug)]
+pub struct ManifestSource {
+    /// Path to display in the report
+    pub path: PathBuf,
+    pub content: String,
+    /// Paths of the files in the mod, relative to its root, `None` for a standalone manifest
+    pub files: Option<Vec<String>>,
+}
+
+impl ManifestSource {
+    /// Reads the manifest of a mod folder, a mod zip, or a standalone YAML file
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        if path.is_dir() {
+            let manifest_path = MOD_MANIFEST_FILES
+                .iter()
+                .map(|name| path.join(name))
+                .find(|manifest_path| manifest_path.is_file())
+                .ok_or_else(|| Error::MissingManifest(path.to_path_buf()))?;
+            let mut files = Vec::new();
+            collect_relative_files(path, path, &mut files)?;
+            return Ok(Self {
+                content: read_text(&fs::read(&manifest_path)?),
+                path: manifest_path,
+                files: Some(files),
+            });
+        }
+
+        if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+        {
+            let buffer = read_manifest_file_from_zip(path)?
+                .ok_or_else(|| Error::MissingManifest(path.to_path_buf()))?;
+            return Ok(Self {
+                path: path.to_path_buf(),
+                content: read_text(&buffer),
+                files: Some(list_zip_entries(path)?),
+            });
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            content: read_text(&fs::read(path)?),
+            files: None,
+        })
+    }
+}
+
+/// Decodes a manifest, dropping the UTF-8 BOM if present
+fn read_text(buffer: &[u8]) -> String {
+    let text = String::from_utf8_lossy(buffer);
+    text.strip_prefix('\u{feff}').unwrap_or(&text).to_string()
+}
+
+fn collect_relative_files(root: &Path, dir: &Path, files: &mut Vec<String>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_relative_files(root, &path, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    Ok(())
+}
+
+/// Fetches the registry to check dependency names against, `None` if it is unavailable
+///
+/// Validation should still work offline, so failures are only reported.
+pub async fn fetch_registry_for_checks(downloader: &ModDownloader) -> Option<ModRegistry> {
+    let result = match downloader.fetch_mod_registry().await {
+        Ok(data) => ModRegistry::from(data).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(registry) => Some(registry),
+        Err(e) => {
+            eprintln!(
+                "Could not fetch the mod registry, dependency names will not be checked: {}",
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Counts the issues which should fail the validation
+pub fn count_errors(issues: &[LintIssue]) -> usize {
+    issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count()
+}
+
+/// Validates a manifest against what Everest expects
+///
+/// Dependency names are checked against the registry when one is given.
+pub fn lint_manifest(source: &ManifestSource, registry: Option<&ModRegistry>) -> Vec<LintIssue> {
+    let mut linter = Linter {
+        lines: source.content.lines().collect(),
+        issues: Vec::new(),
+    };
+
+    let root: Value = match serde_yaml_ng::from_str(&source.content) {
+        Ok(root) => root,
+        Err(e) => {
+            let line = e.location().map(|location| location.line());
+            linter.report(Severity::Error, line, format!("invalid YAML: {}", e));
+            return linter.issues;
+        }
+    };
+    let Some(entries) = root.as_sequence() else {
+        linter.report(
+            Severity::Error,
+            Some(1),
+            "the manifest must be a list of mod entries (each starting with `- Name:`)",
+        );
+        return linter.issues;
+    };
+    if entries.is_empty() {
+        linter.report(Severity::Error, Some(1), "the manifest declares no mod");
+    }
+
+    let mut seen_names = HashSet::new();
+    // Line of the current entry, searches for its fields start there
+    let mut cursor = 0;
+    for entry in entries {
+        let name = entry.get("Name").and_then(Value::as_str);
+        let line = name.and_then(|name| linter.find_line(&format!("Name: {}", name), cursor + 1));
+        if let Some(line) = line {
+            cursor = line;
+        }
+
+        let Some(mapping) = entry.as_mapping() else {
+            linter.report(
+                Severity::Error,
+                line,
+                "each entry must be a mapping of fields",
+            );
+            continue;
+        };
+        for key in mapping.keys() {
+            let key = key.as_str().unwrap_or_default();
+            if !ENTRY_KEYS.contains(&key) {
+                let key_line = linter.find_line(&format!("{}:", key), cursor);
+                linter.report(
+                    Severity::Warning,
+                    key_line,
+                    format!("unknown field `{}`", key),
+                );
+            }
+        }
+
+        match name {
+            Some(name) if !name.trim().is_empty() => {
+                if !seen_names.insert(name.to_string()) {
+                    linter.report(
+                        Severity::Error,
+                        line,
+                        format!("duplicate entry for `{}`, Everest will only load one", name),
+                    );
+                }
+            }
+            _ => linter.report(Severity::Error, line, "missing or empty `Name`"),
+        }
+        linter.check_version(entry.get("Version"), line, "`Version`");
+
+        if let Some(dll) = entry.get("DLL") {
+            match (dll.as_str(), &source.files) {
+                (None, _) => linter.report(Severity::Error, line, "`DLL` must be a path string"),
+                (Some(dll), Some(files)) => {
+                    let dll = dll.replace('\\', "/");
+                    if !files.iter().any(|file| {
+                        file.eq_ignore_ascii_case(&dll) || file.ends_with(&format!("/{}", dll))
+                    }) {
+                        let dll_line = linter.find_line("DLL:", cursor);
+                        linter.report(
+                            Severity::Error,
+                            dll_line,
+                            format!("`DLL` points to `{}` which is not part of the mod", dll),
+                        );
+                    }
+                }
+                (Some(_), None) => {}
+            }
+        }
+
+        for field in ["Dependencies", "OptionalDependencies"] {
+            linter.check_dependencies(entry.get(field), field, cursor, registry);
+        }
+    }
+
+    linter.issues
+}
+
+struct Linter<'a> {
+    lines: Vec<&'a str>,
+    issues: Vec<LintIssue>,
+}
+
+impl Linter<'_> {
+    fn report(&mut self, severity: Severity, line: Option<usize>, message: impl Into<String>) {
+        self.issues.push(LintIssue {
+            severity,
+            line,
+            message: message.into(),
+        });
+    }
+
+    /// Finds the first line at or after `from` (1-based) containing `needle`
+    fn find_line(&self, needle: &str, from: usize) -> Option<usize> {
+        self.lines
+            .iter()
+            .enumerate()
+            .skip(from.saturating_sub(1))
+            .find(|(_, line)| line.contains(needle))
+            .map(|(index, _)| index + 1)
+    }
+
+    fn check_version(&mut self, version: Option<&Value>, line: Option<usize>, what: &str) {
+        let Some(version) = version else {
+            self.report(Severity::Error, line, format!("missing {}", what));
+            return;
+        };
+        // Versions such as `1.0` are often parsed as numbers by YAML
+        let text = match version {
+            Value::String(text) => text.clone(),
+            Value::Number(number) => number.to_string(),
+            _ => String::new(),
+        };
+        if EverestVersion::parse(&text).is_none() {
+            let version_line = self
+                .find_line(&format!("Version: {}", text), line.unwrap_or(1))
+                .or(line);
+            self.report(
+                Severity::Error,
+                version_line,
+                format!(
+                    "{} `{}` is not a valid version (expected e.g. 1.0.0)",
+                    what, text
+                ),
+            );
+        }
+    }
+
+    fn check_dependencies(
+        &mut self,
+        dependencies: Option<&Value>,
+        field: &str,
+        cursor: usize,
+        registry: Option<&ModRegistry>,
+    ) {
+        let Some(dependencies) = dependencies else {
+            return;
+        };
+        let field_line = self.find_line(&format!("{}:", field), cursor);
+        let Some(dependencies) = dependencies.as_sequence() else {
+            self.report(
+                Severity::Error,
+                field_line,
+                format!("`{}` must be a list", field),
+            );
+            return;
+        };
+
+        let mut seen = HashSet::new();
+        for dependency in dependencies {
+            let name = dependency.get("Name").and_then(Value::as_str);
+            let line = name
+                .and_then(|name| {
+                    self.find_line(&format!("Name: {}", name), field_line.unwrap_or(cursor))
+                })
+                .or(field_line);
+            let Some(name) = name else {
+                self.report(
+                    Severity::Error,
+                    line,
+                    format!("dependency in `{}` without `Name`", field),
+                );
+                continue;
+            };
+
+            if let Some(mapping) = dependency.as_mapping() {
+                for key in mapping.keys().filter_map(Value::as_str) {
+                    if !DEPENDENCY_KEYS.contains(&key) {
+                        self.report(
+                            Severity::Warning,
+                            line,
+                            format!("unknown dependency field `{}`", key),
+                        );
+                    }
+                }
+            }
+            if !seen.insert(name) {
+                self.report(
+                    Severity::Warning,
+                    line,
+                    format!("`{}` is listed twice in `{}`", name, field),
+                );
+            }
+            match dependency.get("Version") {
+                Some(version) => {
+                    self.check_version(Some(version), line, &format!("version of `{}`", name))
+                }
+                None => self.report(
+                    Severity::Warning,
+                    line,
+                    format!(
+                        "dependency `{}` has no `Version`, any version will be accepted",
+                        name
+                    ),
+                ),
+            }
+            if let Some(registry) = registry
+                && !is_pseudo_dependency(name)
+                && registry.get_mod_info(name).is_none()
+            {
+                self.report(
+                    Severity::Warning,
+                    line,
+                    format!("dependency `{}` is not in the mod registry, players will not be able to install it automatically", name),
+                );
+            }
+        }
+    }
+}
+
+/// Prints issues in a compiler-like format with the offending line
+pub fn print_issues(source: &ManifestSource, issues: &[LintIssue]) {
+    let lines: Vec<&str> = source.content.lines().collect();
+    for issue in issues {
+        match issue.line {
+            Some(line) => {
+                println!(
+                    "{}:{}: {}: {}",
+                    source.path.display(),
+                    line,
+                    issue.severity,
+                    issue.message
+                );
+                if let Some(text) = lines.get(line - 1) {
+                    println!("{:>5} | {}", line, text);
+                }
+            }
+            None => println!(
+                "{}: {}: {}",
+                source.path.display(),
+                issue.severity,
+                issue.message
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lint(content: &str) -> Vec<LintIssue> {
+        let source = ManifestSource {
+            path: PathBuf::from("everest.yaml"),
+            content: content.to_string(),
+            files: Some(vec![String::from("Code/MyMod.dll")]),
+        };
+        lint_manifest(&source, None)
+    }
+
+    #[test]
+    fn test_valid_manifest_has_no_issues() {
+        let issues = lint(
+            "- Name: MyMod\n  Version: 1.0.0\n  DLL: Code/MyMod.dll\n  Dependencies:\n    - Name: EverestCore\n      Version: 1.4465.0\n",
+        );
+
+        assert!(issues.is_empty(), "{:?}", issues);
+    }
+
+    #[test]
+    fn test_reports_missing_fields_and_bad_versions_with_lines() {
+        let issues = lint(
+            "- Name: MyMod\n  Version: v1\n  DLL: Code/Missing.dll\n  Dependecies: []\n- Version: 1.0.0\n",
+        );
+
+        let summary: Vec<_> = issues.iter().map(|i| (i.severity, i.line)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Severity::Warning, Some(4)),
+                (Severity::Error, Some(2)),
+                (Severity::Error, Some(3)),
+                (Severity::Error, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reports_duplicate_entries() {
+        let issues = lint("- Name: MyMod\n  Version: 1.0.0\n- Name: MyMod\n  Version: 1.0.1\n");
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line, Some(3));
+        assert!(issues[0].message.contains("duplicate"));
+    }
+
+    #[test]
+    fn test_rejects_non_list_manifest() {
+        let issues = lint("Name: MyMod\nVersion: 1.0.0\n");
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, Severity::Error);
+    }
+}
@@ -1,51 +1,836 @@
 use clap::Parser;
 
+mod archive_list;
+mod bench;
+mod blacklist;
+mod cache;
+mod checksum;
 mod cli;
+mod color;
+mod config;
+mod conflicts;
 mod constant;
+mod content_search;
+mod dependency;
+mod dependency_export;
+mod diagnostic;
 mod download;
 mod error;
+mod export;
+mod favorites;
+mod feed;
 mod fileutil;
+mod game;
+mod gamebanana;
+mod identify;
+mod install;
+mod installed_index;
 mod installed_mods;
+mod journal;
+mod languages;
+mod last_check;
+mod lint;
+mod merge;
+mod mod_files_database;
 mod mod_registry;
+mod normalize;
+mod outdated;
+mod pack;
+mod porcelain;
+mod preflight;
+mod preview;
+mod profile;
+mod progress;
+mod prompt;
+mod rate_limit;
+mod saves;
+mod scaffold;
+mod search_database;
+mod serve;
+mod setup_diff;
+mod snapshot;
+mod source;
+mod staging;
+mod subscriptions;
+mod summary;
+mod template;
+mod timeutil;
+mod timings;
+mod updater_blacklist;
+mod vendor;
+mod version;
+mod watch;
+mod webhook;
 
-use cli::{Cli, Commands};
-use download::ModDownloader;
-use installed_mods::{check_updates, list_installed_mods};
-use mod_registry::ModRegistry;
-use tracing::{debug, info};
+use blacklist::Blacklist;
+use bytes::Bytes;
+use cache::RegistryCache;
+use cli::{
+    Cli, Commands, DepsArgs, DepsCommands, FeedFormat, LogFormat, PackCommands, ProfileCommands,
+    RegistryCommands, SearchSort, UpdateArgs,
+};
+use config::{Config, ExtraRegistry};
+use constant::{
+    DEFAULT_DOWNLOAD_RETRIES, DEFAULT_JOBS, DEFAULT_LANGUAGE, DEFAULT_STALE_REGISTRY_DAYS,
+    EVEREST_PSEUDO_DEPENDENCIES, MOD_REGISTRY_URL, PRE_THAW_SNAPSHOT, SAVE_BACKUP_DIRECTORY,
+    UPDATES_AVAILABLE_EXIT_CODE, WATCH_UPDATE_CHECK_INTERVAL,
+};
+use content_search::ContentQuery;
+use dependency::{DependencyGraph, check_installed_dependencies, print_dependency_tree};
+use dependency_export::DependencyExport;
+use diagnostic::Diagnostic;
+use download::{BatchProgress, ModDownloader};
+use error::Error;
+use favorites::Favorites;
+use feed::{FeedEntry, FeedHistory};
+use game::{GameVersions, Launcher, is_pseudo_dependency};
+use gamebanana::{GameBananaItem, SubmissionList};
+use identify::{Adoption, adopt, identify};
+use indicatif::{HumanBytes, HumanDuration};
+use installed_index::InstalledIndex;
+use installed_mods::{
+    AvailableUpdateInfo, Dependency, ModFilter, ModManifest, check_updates, find_duplicate_mods,
+    find_identical_archives, find_unmanaged, list_installed_mods, list_installed_mods_in,
+    search_installed,
+};
+use journal::{Journal, JournalEntry, Operation};
+use languages::DialogCoverage;
+use last_check::LastCheck;
+use merge::{MergeChoice, SkippedMods};
+use mod_files_database::ModFilesDatabase;
+use mod_registry::{ModRegistry, RemoteModInfo};
+use normalize::{apply_renames, plan_renames};
+use outdated::OutdatedReport;
+use pack::Pack;
+use preview::GraphicsProtocol;
+use profile::Profile;
+use saves::{MapInSave, SaveFiles};
+use search_database::SearchDatabase;
+use setup_diff::Setup;
+use snapshot::Snapshot;
+use source::{ModSource, RegistrySource};
+use staging::{StagedArchive, Staging};
+use std::{
+    borrow::Borrow,
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+    process::ExitCode,
+};
+use subscriptions::{SubscriptionChange, Subscriptions};
+use summary::BatchSummary;
+use template::Template;
+use timings::{Timings, TimingsReport};
+use tracing::{debug, info, warn};
+use tracing_subscriber::{Layer, layer::SubscriberExt, util::SubscriberInitExt};
+use updater_blacklist::UpdaterBlacklist;
+use vendor::{VendorManifest, collect_vendor_targets};
+use version::{EverestVersion, VersionChange};
+use watch::ModsWatch;
+use webhook::WebhookEvent;
 
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tracing_subscriber::fmt()
-        .compact()
-        .with_max_level(tracing::Level::ERROR)
-        .with_file(true)
-        .with_line_number(true)
-        .with_thread_ids(true)
-        .with_target(true)
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    if cli.quiet {
+        progress::hide();
+    }
+
+    let timings = cli.timings.then(Timings::default);
+    let log_layer = match cli.log_format {
+        LogFormat::Text => tracing_subscriber::fmt::layer()
+            .compact()
+            .with_file(true)
+            .with_line_number(true)
+            .with_thread_ids(true)
+            .with_target(true)
+            .boxed(),
+        // Keep stdout free for the output of the command
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .with_writer(std::io::stderr)
+            .with_file(true)
+            .with_line_number(true)
+            .with_thread_ids(true)
+            .with_target(true)
+            .boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(log_layer.with_filter(cli.log_level))
+        .with(timings.clone().map(Timings::layer))
         .init();
+    let _timings_report = timings.map(TimingsReport);
 
     info!("Application starts");
-
-    let cli = Cli::parse();
     debug!("Command passed: {:#?}", &cli.command);
 
+    let log_format = cli.log_format;
+    let result = match &cli.command {
+        Commands::Launch(_) => launch(cli).await,
+        _ => run(cli).await,
+    };
+    match result {
+        Ok(exit_code) => exit_code,
+        Err(e) => {
+            report_error(&e, log_format);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn report_error(e: &Error, log_format: LogFormat) {
+    let diagnostic = Diagnostic::new(e);
+    match log_format {
+        LogFormat::Text => eprintln!("{}", diagnostic),
+        // Written directly, `--log-level off` must not hide why the command failed
+        LogFormat::Json => eprintln!(
+            "{}",
+            serde_json::json!({
+                "level": "ERROR",
+                "message": e.to_string(),
+                "code": e.code(),
+                "category": e.category().name(),
+                "causes": diagnostic.causes(),
+                "hint": diagnostic.hint(),
+            })
+        ),
+    }
+}
+
+/// Runs `update` with confirmation, then starts the game whether the update succeeded or not
+async fn launch(mut cli: Cli) -> Result<ExitCode, Error> {
+    let Commands::Launch(args) = &cli.command else {
+        unreachable!("only called for the launch command");
+    };
+    if game::is_running() {
+        println!("Celeste is already running");
+        return Ok(ExitCode::SUCCESS);
+    }
+    let mods_dir = fileutil::resolve_mods_directory(
+        cli.mods_dir
+            .clone()
+            .unwrap_or(fileutil::get_mods_directory()?),
+    )?;
+    let launcher = Launcher::detect(&mods_dir).ok_or_else(|| Error::GameNotFound(mods_dir))?;
+    let game_args = args.game_args.clone();
+
+    if !args.no_update {
+        let log_format = cli.log_format;
+        cli.command = Commands::Update(UpdateArgs {
+            dry_run: false,
+            install: false,
+            only: None,
+            include_code_mods: args.include_code_mods,
+            ignore_saves: args.ignore_saves,
+            confirm: !args.yes,
+            porcelain: false,
+        });
+        if let Err(e) = run(cli).await {
+            report_error(&e, log_format);
+            eprintln!("Warning: the mods could not be updated, starting the game anyway");
+        }
+    }
+
+    if launcher == Launcher::Steam && !game_args.is_empty() {
+        println!("Warning: arguments are not passed through Steam, set them in its launch options");
+    }
+    println!("\nStarting Celeste...");
+    launcher.launch(&game_args)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+async fn run(cli: Cli) -> Result<ExitCode, Error> {
+    let mut config = Config::load()?;
+    config.merge_cli(&cli);
+
     // Initialize downloader early for list and update commands
-    let mods_dir = cli.mods_dir.unwrap_or(fileutil::get_mods_directory()?);
+    let mods_dir = fileutil::resolve_mods_directory(
+        cli.mods_dir
+            .clone()
+            .unwrap_or(fileutil::get_mods_directory()?),
+    )?;
+    // `list` and `update` see the mods of the extra directories as if they were in the main one
+    let mut mods_dirs = vec![mods_dir.clone()];
+    for extra_mods_dir in &config.extra_mods_dirs {
+        mods_dirs.push(fileutil::resolve_mods_directory(extra_mods_dir.clone())?);
+    }
 
     match &cli.command {
-        Commands::List => {
-            let installed_mods = list_installed_mods(&mods_dir)?;
+        Commands::List(args) => {
+            let installed_mods = list_installed_mods_in(&mods_dirs)?;
+            let filter = match args.pattern.as_ref().or(args.filter.as_ref()) {
+                Some(pattern) => Some(ModFilter::new(pattern)?),
+                None => None,
+            };
+            let listed: Vec<_> = match &args.search {
+                Some(query) => search_installed(&installed_mods, query),
+                None => installed_mods
+                    .iter()
+                    .filter(|mod_info| {
+                        filter
+                            .as_ref()
+                            .is_none_or(|filter| filter.matches(mod_info))
+                    })
+                    .collect(),
+            };
+            let blacklist = Blacklist::load(&mods_dir)?;
+            if args.csv {
+                export::write_installed_csv(&mut std::io::stdout().lock(), &listed, &blacklist)?;
+                return Ok(ExitCode::SUCCESS);
+            }
+            if let Some(format) = &args.format {
+                let template = Template::parse(format, &export::INSTALLED_FIELDS)?;
+                export::write_installed_template(
+                    &mut std::io::stdout().lock(),
+                    &listed,
+                    &blacklist,
+                    &template,
+                )?;
+                return Ok(ExitCode::SUCCESS);
+            }
+            if args.porcelain {
+                for mod_info in &listed {
+                    let enabled = if blacklist.contains(&mod_info.archive_file_name()) {
+                        "disabled"
+                    } else {
+                        "enabled"
+                    };
+                    println!(
+                        "{}",
+                        porcelain::record(
+                            "mod",
+                            &[
+                                &mod_info.manifest.name,
+                                &mod_info.manifest.version,
+                                enabled,
+                                &mod_info.archive_path.to_string_lossy(),
+                            ]
+                        )
+                    );
+                }
+                return Ok(ExitCode::SUCCESS);
+            }
+
             if installed_mods.is_empty() {
                 println!("No mods are currently installed.");
-                return Ok(());
+                return Ok(ExitCode::SUCCESS);
+            }
+            if listed.is_empty() {
+                if args.search.is_some() {
+                    println!("No installed mods match the query.");
+                } else {
+                    println!("No installed mods match the pattern.");
+                }
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            let favorites = Favorites::load(&mods_dir)?;
+            // Without network access, only the copy of the registry fetched by the last run can tell
+            let cache_dir = fileutil::get_cache_directory()?;
+            let registry_cache = RegistryCache::load(&cache_dir);
+            let mut last_check = LastCheck::load(&cache_dir);
+            let registry_url = config.registry_url.as_deref().unwrap_or(MOD_REGISTRY_URL);
+            let cached_registry = args.unmanaged.then(|| registry_cache.cached(registry_url));
+            let unmanaged: Vec<_> = match cached_registry {
+                None => Vec::new(),
+                Some(Some(data)) => {
+                    if let Some(warning) =
+                        stale_registry_warning(&registry_cache, registry_url, &config)
+                    {
+                        eprintln!("Warning: {}", warning);
+                    }
+                    let mod_registry =
+                        merged_registry(data, registry_url, &config, None, &cache_dir).await?;
+                    let downloader = ModDownloader::new(
+                        &mods_dir,
+                        download::build_client(&config)?,
+                        registry_url,
+                    );
+                    let source = RegistrySource::new(&mod_registry, downloader);
+                    let jobs = config.jobs.unwrap_or(DEFAULT_JOBS);
+                    let unmanaged = find_unmanaged(&installed_mods, &source, jobs, &mut last_check);
+                    if let Err(e) = last_check.save() {
+                        warn!("Failed to save the state of this run: {}", e);
+                    }
+                    unmanaged
+                        .into_iter()
+                        .map(|mod_info| mod_info.archive_path.as_path())
+                        .collect()
+                }
+                Some(None) => {
+                    println!(
+                        "Note: no copy of the registry yet, unmanaged mods cannot be told. \
+                        Run `everest-mod-cli outdated` once to fetch it."
+                    );
+                    Vec::new()
+                }
+            };
+            let installed_index = if args.dates {
+                Some(InstalledIndex::load(&fileutil::get_data_directory()?)?)
+            } else {
+                None
+            };
+            println!("\nInstalled mods ({} found):", listed.len());
+            for mod_info in &listed {
+                let date = installed_index
+                    .as_ref()
+                    .map(|index| match index.get(&mod_info.archive_path) {
+                        Some(entry) => format!(
+                            " [{} {}]",
+                            if entry.updated_at.is_some() {
+                                "updated"
+                            } else {
+                                "installed"
+                            },
+                            timeutil::format_timestamp(
+                                entry.updated_at.unwrap_or(entry.installed_at)
+                            )
+                        ),
+                        None => String::from(" [installed on unknown date]"),
+                    })
+                    .unwrap_or_default();
+                let star = if favorites.contains(&mod_info.archive_file_name()) {
+                    " ★"
+                } else {
+                    ""
+                };
+                let size = std::fs::metadata(&mod_info.archive_path)
+                    .map(|metadata| format!(", {}", HumanBytes(metadata.len())))
+                    .unwrap_or_default();
+                let unmanaged_mark = if unmanaged.contains(&mod_info.archive_path.as_path()) {
+                    " [unmanaged]"
+                } else {
+                    ""
+                };
+                let location = match mod_info.archive_path.parent() {
+                    Some(dir) if dir != mods_dir => format!(" [in {}]", dir.display()),
+                    _ => String::new(),
+                };
+                println!(
+                    "- {} (version {}{}){}{}{}{}",
+                    mod_info.manifest.name,
+                    mod_info.manifest.version,
+                    size,
+                    location,
+                    date,
+                    unmanaged_mark,
+                    star
+                );
+            }
+            if !unmanaged.is_empty() {
+                println!(
+                    "\n{} mod(s) are unmanaged: they match no registry entry, so `update` cannot keep them updated",
+                    unmanaged.len()
+                );
+            }
+
+            let duplicates = find_duplicate_mods(&installed_mods, &blacklist);
+            if !duplicates.is_empty() {
+                println!(
+                    "\nWarning: {} mods are installed more than once, Everest will refuse to load them:",
+                    duplicates.len()
+                );
+                for (name, archives) in &duplicates {
+                    let file_names: Vec<_> =
+                        archives.iter().map(|m| m.archive_file_name()).collect();
+                    println!("- {}: {}", name, file_names.join(", "));
+                }
+                println!("Run `doctor` to resolve them");
+            }
+        }
+
+        Commands::Normalize(args) => {
+            let installed_mods = list_installed_mods(&mods_dir)?;
+            let plan = plan_renames(&installed_mods);
+            let file_name = |path: &std::path::Path| {
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            };
+            for (path, reason) in &plan.skipped {
+                println!("Skipping {}: {}", file_name(path), reason);
+            }
+            if plan.renames.is_empty() {
+                println!("All archives are named after their mods.");
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            println!("Renaming {} archive(s):", plan.renames.len());
+            for rename in &plan.renames {
+                println!("- {} -> {}", file_name(&rename.from), file_name(&rename.to));
+            }
+            if args.dry_run {
+                println!("Dry run, nothing was changed.");
+                return Ok(ExitCode::SUCCESS);
+            }
+            game::ensure_not_running(cli.force)?;
+            let mut installed_index = InstalledIndex::load(&fileutil::get_data_directory()?)?;
+            apply_renames(&mods_dir, &plan.renames, &mut installed_index)?;
+            println!("Done.");
+        }
+
+        Commands::Status => {
+            let given_mods_dir = match &cli.mods_dir {
+                Some(dir) => (dir.clone(), "from --mods-dir"),
+                None => (fileutil::get_mods_directory()?, "default Steam location"),
+            };
+            match given_mods_dir {
+                (dir, origin) if dir != mods_dir => println!(
+                    "Mods directory: {} ({}, link to {})",
+                    dir.display(),
+                    origin,
+                    mods_dir.display()
+                ),
+                (_, origin) => println!("Mods directory: {} ({})", mods_dir.display(), origin),
+            }
+            for extra_mods_dir in &mods_dirs[1..] {
+                println!("Extra mods directory: {}", extra_mods_dir.display());
+            }
+            if !mods_dir.is_dir() {
+                println!("The mods directory does not exist, pass another one with --mods-dir");
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            let game = GameVersions::detect(&mods_dir);
+            match (&game.celeste, &game.everest) {
+                (_, None) => println!(
+                    "Everest: unknown, start the game once with Everest to detect its version"
+                ),
+                (celeste, Some(everest)) => println!(
+                    "Everest: {} (Celeste {})",
+                    everest,
+                    celeste.as_deref().unwrap_or("unknown")
+                ),
+            }
+            if game::is_running() {
+                println!("Celeste is running");
+            }
+
+            let installed_mods = list_installed_mods_in(&mods_dirs)?;
+            let blacklist = Blacklist::load(&mods_dir)?;
+            let disabled = installed_mods
+                .iter()
+                .filter(|mod_info| blacklist.contains(&mod_info.archive_file_name()))
+                .count();
+            println!(
+                "Installed mods: {} ({} enabled, {} disabled)",
+                installed_mods.len(),
+                installed_mods.len() - disabled,
+                disabled
+            );
+
+            // Only the copy of the registry fetched by the last online command is used, status never downloads
+            let cache_dir = fileutil::get_cache_directory()?;
+            let registry_cache = RegistryCache::load(&cache_dir);
+            let mut last_check = LastCheck::load(&cache_dir);
+            let registry_url = config.registry_url.as_deref().unwrap_or(MOD_REGISTRY_URL);
+            let jobs = config.jobs.unwrap_or(DEFAULT_JOBS);
+            let mut warnings = Vec::new();
+            match (
+                registry_cache.cached(registry_url),
+                registry_cache.age(registry_url),
+            ) {
+                (Some(data), age) => {
+                    let stale = stale_registry_warning(&registry_cache, registry_url, &config);
+                    if let Some(age) = age {
+                        println!(
+                            "Registry: checked {} ago{}",
+                            HumanDuration(age),
+                            if stale.is_some() { " (stale)" } else { "" }
+                        );
+                    }
+                    if let Some(warning) = stale {
+                        eprintln!("Warning: {}", warning);
+                    }
+                    let mod_registry =
+                        merged_registry(data, registry_url, &config, None, &cache_dir).await?;
+                    let downloader = ModDownloader::new(
+                        &mods_dir,
+                        download::build_client(&config)?,
+                        registry_url,
+                    );
+                    let source = RegistrySource::new(&mod_registry, downloader);
+                    let update_check = check_updates(&mods_dirs, &source, jobs, &mut last_check)?;
+                    match update_check.updates.len() {
+                        0 => println!("Updates: none pending"),
+                        count => println!(
+                            "Updates: {} pending, list them with `outdated`, install them with `update`",
+                            count
+                        ),
+                    }
+                    let files_database = ModFilesDatabase::load_cached(&cache_dir);
+                    let provided_twice = files_database.find_provided_twice(
+                        &installed_mods,
+                        &blacklist,
+                        &mod_registry,
+                    );
+                    if !provided_twice.is_empty() {
+                        warnings.push(format!(
+                            "{} mod(s) provided by another archive",
+                            provided_twice.len()
+                        ));
+                    }
+                }
+                (None, _) => {
+                    println!("Registry: no copy cached, run `outdated` to check for updates")
+                }
+            }
+
+            let duplicates = find_duplicate_mods(&installed_mods, &blacklist);
+            if !duplicates.is_empty() {
+                warnings.push(format!(
+                    "{} mod(s) installed more than once, Everest refuses to load them",
+                    duplicates.len()
+                ));
+            }
+            let identical =
+                find_identical_archives(&installed_mods, &blacklist, jobs, &mut last_check);
+            if !identical.is_empty() {
+                warnings.push(format!(
+                    "{} archive(s) stored more than once",
+                    identical.len()
+                ));
+            }
+            if let Err(e) = last_check.save() {
+                warn!("Failed to save the state of this run: {}", e);
+            }
+            if warnings.is_empty() {
+                println!("No problems found");
+            } else {
+                println!("\nWarnings:");
+                for warning in &warnings {
+                    println!("- {}", warning);
+                }
+                println!("Run `doctor` to resolve them");
+            }
+        }
+
+        Commands::Doctor(args) => {
+            println!("Checking installed mods...");
+            let mut installed_mods = list_installed_mods(&mods_dir)?;
+            let mut blacklist = Blacklist::load(&mods_dir)?;
+            let interactive = prompt::is_interactive();
+
+            let mut last_check = LastCheck::load(&fileutil::get_cache_directory()?);
+            let jobs = config.jobs.unwrap_or(DEFAULT_JOBS);
+            let identical =
+                find_identical_archives(&installed_mods, &blacklist, jobs, &mut last_check);
+            if let Err(e) = last_check.save() {
+                warn!("Failed to save the state of this run: {}", e);
+            }
+            let extras: Vec<_> = identical
+                .iter()
+                .flat_map(|archives| &archives[1..])
+                .map(|mod_info| mod_info.archive_path.clone())
+                .collect();
+            let found_identical = !identical.is_empty();
+            if found_identical {
+                let size = |path: &std::path::Path| {
+                    std::fs::metadata(path).map_or(0, |metadata| metadata.len())
+                };
+                println!("\nIdentical archives ({}):", identical.len());
+                for archives in &identical {
+                    println!(
+                        "\n{} is stored {} times:",
+                        archives[0].manifest.name,
+                        archives.len()
+                    );
+                    println!("  keep    {}", archives[0].archive_file_name());
+                    for mod_info in &archives[1..] {
+                        println!(
+                            "  delete  {} ({})",
+                            mod_info.archive_file_name(),
+                            HumanBytes(size(&mod_info.archive_path))
+                        );
+                    }
+                }
+                let reclaimable: u64 = extras.iter().map(|path| size(path)).sum();
+                let remaining: Vec<_> = installed_mods
+                    .iter()
+                    .filter(|mod_info| {
+                        !extras.contains(&mod_info.archive_path)
+                            && !blacklist.contains(&mod_info.archive_file_name())
+                    })
+                    .map(|mod_info| mod_info.archive_path.clone())
+                    .collect();
+                if interactive
+                    && prompt::confirm(&format!(
+                        "Delete the {} extra copies to reclaim {}? [y/N]: ",
+                        extras.len(),
+                        HumanBytes(reclaimable)
+                    ))?
+                    && (args.ignore_saves || confirm_progress_loss(&mods_dir, &extras, &remaining)?)
+                {
+                    game::ensure_not_running(cli.force)?;
+                    let mut updater_blacklist = UpdaterBlacklist::load(&mods_dir)?;
+                    let mut favorites = Favorites::load(&mods_dir)?;
+                    let data_dir = fileutil::get_data_directory()?;
+                    let mut installed_index = InstalledIndex::load(&data_dir)?;
+                    let journal = Journal::new(&data_dir);
+                    let mut summary = BatchSummary::new("deleted");
+                    let mut journal_entries = Vec::new();
+                    let mut modified = [false; 3];
+                    for archives in &identical {
+                        let kept = archives[0].archive_file_name();
+                        // Undoing checks that the copy left is still the same
+                        let xxhash = last_check
+                            .digests(&archives[0].archive_path, &[])
+                            .map(|digests| digests.xxh64);
+                        for mod_info in &archives[1..] {
+                            let path = &mod_info.archive_path;
+                            info!("Deleting {}", path.display());
+                            // A copy failing to be deleted must not leave the lists out of date
+                            if let Err(e) = fileutil::remove_file(path) {
+                                eprintln!("[Error] Failed to delete {}: {}", path.display(), e);
+                                summary.failed(&mod_info.archive_file_name(), e);
+                                continue;
+                            }
+                            summary
+                                .done(&mod_info.archive_file_name(), format!("copy of {}", kept));
+                            let mut journal_entry =
+                                JournalEntry::new(Operation::Remove, &mod_info.manifest.name, path)
+                                    .with_versions(None, &mod_info.manifest.version)
+                                    .with_copy_of(&archives[0].archive_path);
+                            journal_entry.xxhash = xxhash.clone();
+                            journal_entries.push(journal_entry);
+                            let file_name = mod_info.archive_file_name();
+                            modified[0] |= blacklist.remove(&file_name);
+                            modified[1] |= updater_blacklist.remove(&file_name);
+                            // A favorite stays one through the copy which is kept
+                            if favorites.remove(&file_name) {
+                                favorites.add(&kept);
+                                modified[2] = true;
+                            }
+                            installed_index.remove(path);
+                        }
+                    }
+                    let lists = [&*blacklist, &*updater_blacklist, &*favorites];
+                    let saved = lists
+                        .into_iter()
+                        .zip(modified)
+                        .filter(|(_, modified)| *modified)
+                        .map(|(list, _)| list.save())
+                        .chain([installed_index.save()])
+                        .fold(Ok(()), Result::and);
+                    for journal_entry in &journal_entries {
+                        journal.append(journal_entry)?;
+                    }
+                    saved?;
+                    installed_mods.retain(|mod_info| mod_info.archive_path.exists());
+                    if journal_entries.len() == extras.len() {
+                        println!(
+                            "Deleted {} archive(s), reclaimed {}",
+                            extras.len(),
+                            HumanBytes(reclaimable)
+                        );
+                    } else {
+                        println!("\n{}", summary);
+                        summary.into_result()?;
+                    }
+                } else {
+                    println!(
+                        "\n{} extra copies take {}{}",
+                        extras.len(),
+                        HumanBytes(reclaimable),
+                        if interactive {
+                            ""
+                        } else {
+                            ", run this command in a terminal to delete them"
+                        }
+                    );
+                }
+            }
+
+            // Archives declaring several mods are only known from the registry and the files database
+            let cache_dir = fileutil::get_cache_directory()?;
+            let registry_url = config.registry_url.as_deref().unwrap_or(MOD_REGISTRY_URL);
+            let registry_cache = RegistryCache::load(&cache_dir);
+            let mut found_provided_twice = false;
+            if let Some(data) = registry_cache.cached(registry_url) {
+                if let Some(warning) =
+                    stale_registry_warning(&registry_cache, registry_url, &config)
+                {
+                    eprintln!("Warning: {}", warning);
+                }
+                let mod_registry =
+                    merged_registry(data, registry_url, &config, None, &cache_dir).await?;
+                let files_database = ModFilesDatabase::load_cached(&cache_dir);
+                let provided_twice =
+                    files_database.find_provided_twice(&installed_mods, &blacklist, &mod_registry);
+                found_provided_twice = !provided_twice.is_empty();
+                if found_provided_twice {
+                    println!(
+                        "\nMods provided by another archive ({}):",
+                        provided_twice.len()
+                    );
+                    for found in &provided_twice {
+                        println!(
+                            "- {} is provided by {} and {}",
+                            found.name,
+                            found.provider.archive_file_name(),
+                            found.other.archive_file_name()
+                        );
+                    }
+                    println!("Disable the standalone archives in blacklist.txt");
+                }
+            }
+
+            let duplicates = find_duplicate_mods(&installed_mods, &blacklist);
+            if duplicates.is_empty() {
+                if !found_identical && !found_provided_twice {
+                    println!("No problems found!");
+                }
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            let mut disabled = Vec::new();
+            let mut journal_entries = Vec::new();
+            println!("\nDuplicate mods ({}):", duplicates.len());
+            for (name, archives) in &duplicates {
+                println!("\n{} is provided by {} archives:", name, archives.len());
+                for (i, mod_info) in archives.iter().enumerate() {
+                    println!(
+                        "  {}) {} (version {})",
+                        i + 1,
+                        mod_info.archive_file_name(),
+                        mod_info.manifest.version
+                    );
+                }
+                if !interactive {
+                    continue;
+                }
+                let question = format!(
+                    "Which one should be kept? The others will be disabled [1-{}, Enter to skip]: ",
+                    archives.len()
+                );
+                if let Some(keep) = prompt::choose(&question, archives.len())? {
+                    for (i, mod_info) in archives.iter().enumerate() {
+                        let file_name = mod_info.archive_file_name();
+                        if i != keep && blacklist.add(&file_name) {
+                            disabled.push(file_name);
+                            journal_entries.push(
+                                JournalEntry::new(
+                                    Operation::Disable,
+                                    &mod_info.manifest.name,
+                                    &mod_info.archive_path,
+                                )
+                                .with_versions(None, &mod_info.manifest.version),
+                            );
+                        }
+                    }
+                }
             }
 
-            println!("\nInstalled mods ({} found):", installed_mods.len());
-            for mod_info in installed_mods {
+            if !disabled.is_empty() {
+                blacklist.save()?;
+                let journal = Journal::new(&fileutil::get_data_directory()?);
+                for entry in &journal_entries {
+                    journal.append(entry)?;
+                }
+                println!("\nDisabled in blacklist.txt: {}", disabled.join(", "));
+            } else if !interactive {
                 println!(
-                    "- {} (version {})",
-                    mod_info.manifest.name, mod_info.manifest.version
+                    "\nRun this command in a terminal to choose which archives to keep, or add the extra archives to blacklist.txt"
                 );
             }
         }
@@ -57,139 +842,2769 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 println!("Mod Information:");
                 println!("- Name: {}", mod_info.manifest.name);
                 println!("- Version: {}", mod_info.manifest.version);
+                if let Ok(metadata) = std::fs::metadata(&mod_info.archive_path) {
+                    println!("- Size: {}", HumanBytes(metadata.len()));
+                }
+                let installed_index = InstalledIndex::load(&fileutil::get_data_directory()?)?;
+                match installed_index.get(&mod_info.archive_path) {
+                    Some(entry) => {
+                        println!(
+                            "- Installed on: {}",
+                            timeutil::format_timestamp(entry.installed_at)
+                        );
+                        if let Some(updated_at) = entry.updated_at {
+                            println!("- Updated on: {}", timeutil::format_timestamp(updated_at));
+                        }
+                        if entry.unverified {
+                            println!("- Checksum: not verified (installed with --no-verify)");
+                        }
+                    }
+                    None => println!("- Installed on: unknown (not installed by this tool)"),
+                }
             } else {
                 println!("The mod '{}' is not currently installed.", args.name);
             }
         }
 
-        // For remaining commands, fetch the remote mod registry
-        _ => {
-            let downloader = ModDownloader::new(&mods_dir);
-            let mod_registry_data = downloader.fetch_mod_registry().await?;
-            let mod_registry = ModRegistry::from(mod_registry_data).await?;
+        Commands::Conflicts => {
+            println!("Indexing the contents of installed mods...");
+            let installed_mods = list_installed_mods(&mods_dir)?;
+            let blacklist = Blacklist::load(&mods_dir)?;
+            let conflicts = conflicts::find_asset_conflicts(&installed_mods, &blacklist);
+            if conflicts.is_empty() {
+                println!("No asset conflicts found!");
+                return Ok(ExitCode::SUCCESS);
+            }
 
-            match &cli.command {
-                Commands::Search(args) => {
-                    println!("Searching for mods matching '{}'...", args.query);
-                    let results = mod_registry.search(&args.query);
-                    if results.is_empty() {
-                        println!("No mods found matching the query: '{}'", args.query);
-                    } else {
-                        println!("Found {} matching mods:", results.len());
-                        for mod_info in results {
-                            println!("\n{} (version {})", mod_info.name, mod_info.version);
-                            println!(" - Updated at: {}", mod_info.updated_at);
-                            println!(
-                                " - Page: https://gamebanana.com/mods/{}",
-                                mod_info.gamebanana_id
-                            );
-                            println!(" - Download: {}", mod_info.download_url);
+            println!(
+                "\nAsset conflicts between {} pairs of mods:",
+                conflicts.len()
+            );
+            for ((first, second), paths) in &conflicts {
+                println!("\n{} <-> {} ({} files)", first, second, paths.len());
+                for path in paths {
+                    println!("  - {}", path);
+                }
+            }
+        }
+
+        Commands::Grep(args) => {
+            let installed_mods = list_installed_mods(&mods_dir)?;
+            let query = ContentQuery::new(&args.text, args.ignore_case);
+            let mut total = 0;
+            for mod_info in &installed_mods {
+                let matches = match content_search::search_archive(&mod_info.archive_path, &query) {
+                    Ok(matches) => matches,
+                    Err(e) => {
+                        warn!(
+                            "Failed to search {}: {}",
+                            mod_info.archive_path.display(),
+                            e
+                        );
+                        continue;
+                    }
+                };
+                if matches.is_empty() {
+                    continue;
+                }
+                total += matches.len();
+                println!(
+                    "\n{} ({})",
+                    mod_info.manifest.name,
+                    mod_info.archive_file_name()
+                );
+                for content_match in matches {
+                    match content_match.line {
+                        Some((number, line)) => {
+                            println!("  {}:{}: {}", content_match.entry, number, line)
                         }
+                        None => println!("  {}: binary map matches", content_match.entry),
                     }
                 }
-                Commands::Info(args) => {
-                    println!("Looking up information for the mod '{}'...", args.name);
-                    if let Some(mod_info) = mod_registry.get_mod_info(&args.name) {
-                        println!("\n{} (version {})", mod_info.name, mod_info.version);
-                        println!(" - Updated at: {}", mod_info.updated_at);
-                        println!(
-                            " - Page: https://gamebanana.com/mods/{}",
-                            mod_info.gamebanana_id
+            }
+            if total == 0 {
+                println!("No installed mod contains '{}'.", args.text);
+            }
+        }
+
+        Commands::Languages(args) => {
+            let language = args
+                .language
+                .as_deref()
+                .or(config.language.as_deref())
+                .unwrap_or(DEFAULT_LANGUAGE);
+            let mut installed_mods = list_installed_mods(&mods_dir)?;
+            installed_mods.sort_by_cached_key(|mod_info| mod_info.manifest.name.to_lowercase());
+
+            let mut maps = 0;
+            let mut missing = Vec::new();
+            for mod_info in &installed_mods {
+                let coverage = match DialogCoverage::of_archive(&mod_info.archive_path) {
+                    Ok(coverage) => coverage,
+                    Err(e) => {
+                        warn!(
+                            "Failed to read entries of {}: {}",
+                            mod_info.archive_path.display(),
+                            e
                         );
-                        println!(" - Download: {}", mod_info.download_url);
-                        println!(" - Hashes: {}", mod_info.checksums.join(", "));
-                    } else {
-                        println!("Mod '{}' not found", args.name);
+                        continue;
                     }
+                };
+                if !coverage.has_maps {
+                    continue;
                 }
-                Commands::Install(args) => {
-                    println!("Starting installation of the mod '{}'...", args.name);
-                    if let Some(mod_info) = mod_registry.get_mod_info(&args.name) {
-                        println!("Downloading mod files...");
-                        downloader
-                            .download_mod(
-                                &mod_info.download_url,
-                                &mod_info.name,
-                                &mod_info.checksums,
-                            )
-                            .await?;
-                        println!("Installation finished successfully!");
-                    } else {
-                        println!("The mod '{}' could not be found.", args.name);
-                    }
+                maps += 1;
+                let languages = if coverage.languages.is_empty() {
+                    String::from("no dialog")
+                } else {
+                    coverage.languages.join(", ")
+                };
+                println!("- {}: {}", mod_info.manifest.name, languages);
+                if !coverage.supports(language) {
+                    missing.push(mod_info.manifest.name.as_str());
                 }
-                Commands::Update(args) => {
-                    println!("Checking mod updates...");
-                    let available_updates = check_updates(&mods_dir, &mod_registry)?;
-                    if available_updates.is_empty() {
-                        println!("All mods are up to date!");
-                    } else {
-                        println!("Available updates:");
-                        for update_info in &available_updates {
-                            println!("\n{}", update_info.name);
-                            println!(" - Current version: {}", update_info.current_version);
-                            println!(" - Available version: {}", update_info.available_version);
-                        }
-                        if args.install {
-                            println!("\nInstalling updates...");
-                            let mut handles = Vec::new();
+            }
 
-                            for update in available_updates {
-                                let downloader = downloader.clone();
-                                println!("\nUpdating {}:", update.name);
+            if maps == 0 {
+                println!("No installed mods contain maps.");
+            } else if missing.is_empty() {
+                println!("\nEvery installed map has {} dialog.", language);
+            } else {
+                println!(
+                    "\n{} of {} maps have no {} dialog:",
+                    missing.len(),
+                    maps,
+                    language
+                );
+                for name in missing {
+                    println!("- {}", name);
+                }
+            }
+        }
 
-                                let handle = tokio::spawn(async move {
-                                    let result = downloader
-                                        .download_mod(&update.url, &update.name, &update.hash)
-                                        .await;
-
-                                    match result {
-                                        Ok(_) => {
-                                            println!(
-                                                "[Successs] Updated {} to version {}\n",
-                                                update.name, update.available_version
-                                            );
-                                            if update.existing_path.exists() {
-                                                if let Err(e) =
-                                                    tokio::fs::remove_file(&update.existing_path)
-                                                        .await
-                                                {
-                                                    eprintln!(
-                                                        "Failed to remove outdated file: {}.\nPlease remove it manually. File path: {}",
-                                                        e,
-                                                        update.existing_path.display()
-                                                    );
-                                                }
-                                            }
-                                        }
-                                        Err(e) => {
-                                            eprintln!(
-                                                "[Error] Failed to update {}: {}",
-                                                update.name, e
-                                            );
-                                        }
-                                    }
-                                });
-                                handles.push(handle);
-                            }
+        Commands::Bench => {
+            println!("Benchmarking {}...", mods_dir.display());
+            let scan = bench::bench_scan(&mods_dir)?;
+            println!("{}", scan);
+            let hashing = bench::bench_hashing(&mods_dir)?;
+            println!("{}", hashing);
+            let mut total = scan.elapsed + hashing.elapsed;
+            match bench::bench_registry_parsing(&fileutil::get_cache_directory()?).await? {
+                Some(registry_parsing) => {
+                    println!("{}", registry_parsing);
+                    total += registry_parsing.elapsed;
+                }
+                None => println!(
+                    "{:<18} skipped, no cached registry (run `search` or `update` first)",
+                    "Registry parsing"
+                ),
+            }
+            println!("{:<18} {:>10.1} ms", "Total", total.as_secs_f64() * 1000.0);
+        }
 
-                            for handle in handles {
-                                handle.await?;
-                            }
+        Commands::Pack(PackCommands::Verify(args)) => {
+            let registry_url = config.registry_url.as_deref().unwrap_or(MOD_REGISTRY_URL);
+            let downloader =
+                ModDownloader::new(&mods_dir, download::build_client(&config)?, registry_url);
+            let pack = Pack::load_from(&args.source, &downloader).await?;
+            let installed_mods = list_installed_mods(&mods_dir)?;
+            let installed_versions: HashMap<&str, &str> = installed_mods
+                .iter()
+                .map(|m| (m.manifest.name.as_str(), m.manifest.version.as_str()))
+                .collect();
+            let issues = pack.verify(&installed_versions);
+            if !issues.is_empty() {
+                println!("Pack '{}':", pack.name);
+                for issue in &issues {
+                    println!("- {}", issue);
+                }
+                return Err(Error::PackVerificationFailed(issues.len()));
+            }
+            println!(
+                "All {} mods of the pack '{}' are installed!",
+                pack.mods.len(),
+                pack.name
+            );
+        }
 
-                            println!("\nAll updates installed successfully!");
-                        } else {
-                            println!("\nRun with --install to install these updates");
-                        }
-                    }
+        Commands::Pack(PackCommands::Create(args)) => {
+            let installed_mods = list_installed_mods(&mods_dir)?;
+            let blacklist = Blacklist::load(&mods_dir)?;
+            let enabled: Vec<_> = installed_mods
+                .iter()
+                .filter(|mod_info| !blacklist.contains(&mod_info.archive_file_name()))
+                .collect();
+            let pack = Pack::from_installed(
+                &args.name,
+                args.description.as_deref(),
+                &enabled,
+                !args.unpinned,
+            );
+            let yaml = pack.to_yaml()?;
+            match &args.output {
+                Some(path) => {
+                    std::fs::write(path, yaml)?;
+                    println!(
+                        "Wrote the pack '{}' with {} mods to {}",
+                        pack.name,
+                        pack.mods.len(),
+                        path.display()
+                    );
                 }
-                // Catch-all arm (should not be reached because all subcommands are handled)
-                _ => {
-                    println!("Use --help to see available commands");
+                None => print!("{}", yaml),
+            }
+        }
+
+        Commands::Profile(ProfileCommands::Save(args)) => {
+            let installed_mods = list_installed_mods(&mods_dir)?;
+            let blacklist = Blacklist::load(&mods_dir)?;
+            let profile = Profile::capture(&installed_mods, &blacklist);
+            profile.save(&fileutil::get_data_directory()?, &args.name)?;
+            println!(
+                "Saved the profile '{}' with {} of {} mods enabled",
+                args.name,
+                profile.enabled.len(),
+                installed_mods.len()
+            );
+        }
+
+        Commands::Profile(ProfileCommands::Load(args)) => {
+            let profile = Profile::load(&fileutil::get_data_directory()?, &args.name)?;
+            let installed_mods = list_installed_mods(&mods_dir)?;
+            let mut blacklist = Blacklist::load(&mods_dir)?;
+            let changes = profile.apply(&installed_mods, &mut blacklist);
+            if !args.ignore_saves && !changes.disabled.is_empty() {
+                let disabled: Vec<_> = changes
+                    .disabled
+                    .iter()
+                    .map(|file_name| mods_dir.join(file_name))
+                    .collect();
+                let enabled: Vec<_> = installed_mods
+                    .iter()
+                    .filter(|mod_info| !blacklist.contains(&mod_info.archive_file_name()))
+                    .map(|mod_info| mod_info.archive_path.clone())
+                    .collect();
+                if !confirm_progress_loss(&mods_dir, &disabled, &enabled)? {
+                    println!(
+                        "The profile '{}' was not loaded, pass --ignore-saves to load it anyway",
+                        args.name
+                    );
+                    return Ok(ExitCode::SUCCESS);
+                }
+            }
+            for file_name in &changes.enabled {
+                println!("Enabled {}", file_name);
+            }
+            for file_name in &changes.disabled {
+                println!("Disabled {}", file_name);
+            }
+            for file_name in &changes.missing {
+                println!("Warning: {} is in the profile but not installed", file_name);
+            }
+            if changes.enabled.is_empty() && changes.disabled.is_empty() {
+                println!("The profile '{}' is already loaded", args.name);
+            } else {
+                blacklist.save()?;
+                println!(
+                    "Loaded the profile '{}': {} mods enabled, {} disabled. \
+                    Restart Celeste to apply the changes.",
+                    args.name,
+                    changes.enabled.len(),
+                    changes.disabled.len()
+                );
+            }
+        }
+
+        Commands::Profile(ProfileCommands::List) => {
+            let profiles = Profile::list(&fileutil::get_data_directory()?)?;
+            if profiles.is_empty() {
+                println!("No profiles saved yet, create one with `profile save <name>`");
+            }
+            for name in profiles {
+                println!("- {}", name);
+            }
+        }
+
+        Commands::Favorite(args) | Commands::Unfavorite(args) => {
+            let installed_mods = list_installed_mods(&mods_dir)?;
+            let mut favorites = Favorites::load(&mods_dir)?;
+            let add = matches!(cli.command, Commands::Favorite(_));
+            if archive_list::apply_changes(
+                &mut favorites,
+                &installed_mods,
+                &args.names,
+                add,
+                "favorites",
+            ) {
+                favorites.save()?;
+            }
+        }
+
+        Commands::Pin(args) | Commands::Unpin(args) => {
+            let installed_mods = list_installed_mods(&mods_dir)?;
+            let mut updater_blacklist = UpdaterBlacklist::load(&mods_dir)?;
+            let add = matches!(cli.command, Commands::Pin(_));
+            if archive_list::apply_changes(
+                &mut updater_blacklist,
+                &installed_mods,
+                &args.names,
+                add,
+                "pinned mods",
+            ) {
+                updater_blacklist.save()?;
+            }
+        }
+
+        Commands::Unsubscribe(args) => {
+            let mut subscriptions = Subscriptions::load(&fileutil::get_data_directory()?)?;
+            let mut modified = false;
+            for name in &args.names {
+                if subscriptions.remove(name) {
+                    println!("Unsubscribed from {}", name);
+                    modified = true;
+                } else {
+                    println!("Not subscribed to {}", name);
                 }
             }
+            if modified {
+                subscriptions.save()?;
+            }
         }
-    }
 
-    Ok(())
+        Commands::History(args) => {
+            let journal = Journal::new(&fileutil::get_data_directory()?);
+            let entries: Vec<_> = journal
+                .entries()?
+                .into_iter()
+                .filter(|entry| args.name.as_ref().is_none_or(|name| &entry.name == name))
+                .collect();
+            if entries.is_empty() {
+                println!("No operations recorded yet.");
+                return Ok(ExitCode::SUCCESS);
+            }
+            let skipped = args
+                .limit
+                .map_or(0, |limit| entries.len().saturating_sub(limit));
+            for entry in &entries[skipped..] {
+                println!("{}", entry);
+            }
+        }
+
+        Commands::Undo(args) => {
+            let data_dir = fileutil::get_data_directory()?;
+            let journal = Journal::new(&data_dir);
+            let entries = journal.entries()?;
+            let Some((position, entry)) = journal::last_undoable(&entries) else {
+                println!("Nothing to undo.");
+                return Ok(ExitCode::SUCCESS);
+            };
+
+            println!("Undoing: {}", entry);
+            let steps = entry.undo_steps()?;
+            for step in &steps {
+                println!("- {}", step);
+            }
+            if args.dry_run {
+                println!("Dry run, nothing was changed.");
+                return Ok(ExitCode::SUCCESS);
+            }
+
+            game::ensure_not_running(cli.force)?;
+            let mut installed_index = InstalledIndex::load(&data_dir)?;
+            // The archive restored by undoing an update keeps its installation date
+            let mut replaced_entry = match entry.operation {
+                Operation::Update => installed_index.remove(&entry.archive),
+                _ => None,
+            };
+            for step in &steps {
+                match step {
+                    journal::UndoStep::RemoveArchive(path) => {
+                        if path.exists() {
+                            fileutil::remove_file(path)?;
+                        }
+                        installed_index.remove(path);
+                    }
+                    journal::UndoStep::RestoreArchive { backup, to } => {
+                        fileutil::retry_locked(to, || std::fs::copy(backup, to))?;
+                        if let Some(mut index_entry) = replaced_entry.take() {
+                            index_entry.version =
+                                entry.from_version.clone().unwrap_or(index_entry.version);
+                            index_entry.xxhash = checksum::hash_file(to, &[])?.xxh64;
+                            index_entry.sha256 = None;
+                            index_entry.updated_at = None;
+                            installed_index.insert(to, index_entry);
+                        }
+                        fileutil::remove_file(backup)?;
+                    }
+                    journal::UndoStep::CopyArchive { from, to } => {
+                        // The kept archive may have been updated since
+                        if let Some(xxhash) = &entry.xxhash
+                            && checksum::hash_file(from, &[])?.xxh64 != *xxhash
+                        {
+                            return Err(Error::CannotUndo(format!(
+                                "{} changed since the copy was removed",
+                                from.display()
+                            )));
+                        }
+                        fileutil::retry_locked(to, || std::fs::copy(from, to))?;
+                    }
+                    journal::UndoStep::Enable(path) => {
+                        let mods_dir = path.parent().unwrap_or(&mods_dir);
+                        let mut blacklist = Blacklist::load(mods_dir)?;
+                        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                        if blacklist.remove(&file_name) {
+                            blacklist.save()?;
+                        }
+                    }
+                }
+            }
+            installed_index.save()?;
+
+            let mut undo_entry = JournalEntry::new(Operation::Undo, &entry.name, &entry.archive);
+            undo_entry.undoes = Some(position);
+            journal.append(&undo_entry)?;
+            println!("Done.");
+        }
+
+        Commands::Freeze(args) => {
+            let data_dir = fileutil::get_data_directory()?;
+            let name = match &args.name {
+                Some(name) => name.clone(),
+                None => timeutil::format_file_stamp(timeutil::now()),
+            };
+            snapshot::validate_name(&name)?;
+            let snapshot = capture_snapshot(&mods_dir, &data_dir, &config)?;
+            let stored = snapshot.store_archives(&data_dir, &mods_dir)?;
+            snapshot.save(&data_dir, &name)?;
+            println!(
+                "Froze {} archives as the snapshot '{}', {} of them were not stored yet",
+                snapshot.archives.len(),
+                name,
+                stored
+            );
+        }
+
+        Commands::DeleteSnapshot(args) => {
+            let data_dir = fileutil::get_data_directory()?;
+            let (count, size) = Snapshot::delete(&data_dir, &args.name)?;
+            println!(
+                "Deleted the snapshot '{}' and {} archive(s) no other snapshot contains, reclaimed {}",
+                args.name,
+                count,
+                HumanBytes(size)
+            );
+        }
+
+        Commands::Thaw(args) => {
+            let data_dir = fileutil::get_data_directory()?;
+            let Some(name) = &args.name else {
+                let snapshots = Snapshot::list(&data_dir)?;
+                if snapshots.is_empty() {
+                    println!("No snapshots saved yet, create one with `freeze [name]`");
+                }
+                for (name, snapshot) in snapshots {
+                    let everest = snapshot
+                        .everest
+                        .map(|version| format!(", Everest {}", version))
+                        .unwrap_or_default();
+                    println!(
+                        "- {}  {}  {} archives{}",
+                        name,
+                        timeutil::format_timestamp(snapshot.created_at),
+                        snapshot.archives.len(),
+                        everest
+                    );
+                }
+                return Ok(ExitCode::SUCCESS);
+            };
+
+            let snapshot = Snapshot::load(&data_dir, name)?;
+            snapshot.check_archives(&data_dir)?;
+            let current = capture_snapshot(&mods_dir, &data_dir, &config)?;
+            if snapshot.everest.is_some() && snapshot.everest != current.everest {
+                println!(
+                    "Warning: the snapshot was taken with Everest {}, {} is installed; \
+                    thaw does not change Everest",
+                    snapshot.everest.as_deref().unwrap_or_default(),
+                    current.everest.as_deref().unwrap_or("an unknown version")
+                );
+            }
+            let plan = snapshot.plan(&current);
+            if plan.is_empty() {
+                println!("The mods directory already matches the snapshot '{}'", name);
+                return Ok(ExitCode::SUCCESS);
+            }
+            for file_name in &plan.restored {
+                println!("- restore {}", file_name);
+            }
+            for file_name in &plan.removed {
+                println!("- remove {}", file_name);
+            }
+            for file_name in &plan.lists {
+                println!("- rewrite {}", file_name);
+            }
+            if args.dry_run {
+                println!("Dry run, nothing was changed.");
+                return Ok(ExitCode::SUCCESS);
+            }
+            if !args.ignore_saves {
+                // Replaced archives may move strawberries and rooms as updates do
+                let (changed, remaining): (Vec<_>, Vec<_>) =
+                    current.archives.keys().partition(|file_name| {
+                        plan.removed.contains(*file_name) || plan.restored.contains(*file_name)
+                    });
+                let paths = |file_names: Vec<&String>| -> Vec<_> {
+                    file_names
+                        .into_iter()
+                        .map(|file_name| mods_dir.join(file_name))
+                        .collect()
+                };
+                if !confirm_progress_loss(&mods_dir, &paths(changed), &paths(remaining))? {
+                    println!("Thaw cancelled, pass --ignore-saves to thaw anyway");
+                    return Ok(ExitCode::SUCCESS);
+                }
+            }
+
+            game::ensure_not_running(cli.force)?;
+            // Thawing the previous state saves the thawed one in its place, so two thaws swap
+            current.store_archives(&data_dir, &mods_dir)?;
+            current.save(&data_dir, PRE_THAW_SNAPSHOT)?;
+            let mut installed_index = InstalledIndex::load(&data_dir)?;
+            // The archives changed before a failure are recorded as well
+            let thawed = snapshot.thaw(&plan, &data_dir, &mods_dir, &mut installed_index);
+            let saved = installed_index.save();
+            thawed?;
+            saved?;
+            println!(
+                "Thawed the snapshot '{}': {} archives restored, {} removed. \
+                The previous state was saved as '{}'.",
+                name,
+                plan.restored.len(),
+                plan.removed.len(),
+                PRE_THAW_SNAPSHOT
+            );
+        }
+
+        Commands::Diff(args) => {
+            let first = Setup::load(&args.first)?;
+            let (second, second_label) = match &args.second {
+                Some(path) => (Setup::load(path)?, path.display().to_string()),
+                None => {
+                    let installed_mods = list_installed_mods(&mods_dir)?;
+                    let blacklist = Blacklist::load(&mods_dir)?;
+                    let enabled: Vec<_> = installed_mods
+                        .iter()
+                        .filter(|mod_info| !blacklist.contains(&mod_info.archive_file_name()))
+                        .collect();
+                    (
+                        Setup::from_installed(&enabled),
+                        String::from("the installed mods"),
+                    )
+                }
+            };
+            println!(
+                "Comparing {} ({} mods) with {} ({} mods)",
+                args.first.display(),
+                first.mods.len(),
+                second_label,
+                second.mods.len()
+            );
+            let diff = first.diff(&second);
+            if diff.is_empty() {
+                println!("The setups have the same mods.");
+                return Ok(ExitCode::SUCCESS);
+            }
+            let with_version = |name: &str, version: &Option<String>| match version {
+                Some(version) => format!("{} {}", name, version),
+                None => name.to_string(),
+            };
+            for (name, version) in &diff.removed {
+                println!(
+                    "{}",
+                    color::red(&format!("- {}", with_version(name, version)))
+                );
+            }
+            for (name, version) in &diff.added {
+                println!(
+                    "{}",
+                    color::green(&format!("+ {}", with_version(name, version)))
+                );
+            }
+            for (name, from, to) in &diff.changed {
+                println!(
+                    "{}",
+                    color::yellow(&format!("~ {} {} -> {}", name, from, to))
+                );
+            }
+            println!(
+                "{} added, {} removed, {} in another version",
+                diff.added.len(),
+                diff.removed.len(),
+                diff.changed.len()
+            );
+        }
+
+        Commands::Watch => {
+            let data_dir = fileutil::get_data_directory()?;
+            // Identifying dropped archives only needs the copy of the registry fetched by the last run
+            let cache_dir = fileutil::get_cache_directory()?;
+            let registry_cache = RegistryCache::load(&cache_dir);
+            let registry_url = config.registry_url.as_deref().unwrap_or(MOD_REGISTRY_URL);
+            let mod_registry = match registry_cache.cached(registry_url) {
+                Some(data) => {
+                    Some(merged_registry(data, registry_url, &config, None, &cache_dir).await?)
+                }
+                None => {
+                    println!(
+                        "Note: no copy of the registry yet, added archives will not be identified. \
+                        Run `everest-mod-cli update --dry-run` once to fetch it."
+                    );
+                    None
+                }
+            };
+            let mut mods_watch = ModsWatch::new(
+                &mods_dir,
+                &data_dir,
+                list_installed_mods(&mods_dir)?,
+                mod_registry.as_ref().map(ModRegistry::hash_index),
+            );
+            // Announcing updates needs a fresh registry from time to time
+            let webhook = match &config.webhook_url {
+                Some(url) => {
+                    let client = download::build_client(&config)?;
+                    Some((url, ModDownloader::new(&mods_dir, client, registry_url)))
+                }
+                None => None,
+            };
+            println!(
+                "Watching {} for changes, press Ctrl+C to stop",
+                mods_dir.display()
+            );
+            if webhook.is_some() {
+                println!(
+                    "Checking for updates every {} to announce them to the webhook",
+                    HumanDuration(WATCH_UPDATE_CHECK_INTERVAL)
+                );
+            }
+            let announce = || {
+                let Some((url, downloader)) = &webhook else {
+                    return;
+                };
+                // The watch loop blocks this thread, the check runs on it to completion
+                let result = tokio::task::block_in_place(|| {
+                    tokio::runtime::Handle::current().block_on(announce_updates(
+                        downloader, url, &config, &mods_dirs, &cache_dir, &data_dir,
+                    ))
+                });
+                if let Err(e) = result {
+                    let now = timeutil::format_timestamp(timeutil::now());
+                    eprintln!("[{}] Could not check for updates: {}", now, e);
+                }
+            };
+            watch::watch(
+                &mods_dir,
+                &mut mods_watch,
+                |report| {
+                    let now = timeutil::format_timestamp(timeutil::now());
+                    for line in report {
+                        println!("[{}] {}", now, line);
+                    }
+                },
+                webhook.as_ref().map(|_| WATCH_UPDATE_CHECK_INTERVAL),
+                announce,
+            )?;
+        }
+
+        Commands::CheckArchives => {
+            let mut archives = fileutil::find_installed_mod_archives(&mods_dir)?;
+            archives.sort();
+            println!("Checking {} archives...", archives.len());
+
+            let mut corrupted = 0;
+            for archive in &archives {
+                if let Err(e) = fileutil::verify_zip_integrity(archive) {
+                    corrupted += 1;
+                    let file_name = archive.file_name().unwrap_or_default().to_string_lossy();
+                    println!("- {}: {}", file_name, e);
+                }
+            }
+            if corrupted > 0 {
+                return Err(Error::CorruptArchives(corrupted));
+            }
+            println!("All archives are intact!");
+        }
+
+        Commands::Init(args) => {
+            let dir = std::env::current_dir()?;
+            let name = match &args.name {
+                Some(name) => name.clone(),
+                None => dir
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| String::from("MyMod")),
+            };
+            let options = scaffold::ScaffoldOptions {
+                name: &name,
+                version: &args.mod_version,
+                with_code: args.code,
+            };
+            scaffold::init_mod(&dir, &options, args.force)?;
+
+            println!("Created everest.yaml for {} {}", name, args.mod_version);
+            for directory in scaffold::layout_directories(&options) {
+                println!("  {}/", directory);
+            }
+            if args.code {
+                println!(
+                    "Build your code to bin/{}.dll, the DLL field points there.",
+                    name
+                );
+            }
+            println!("Run `everest-mod-cli lint` to validate the manifest after editing it.");
+        }
+
+        Commands::Lint(args) => {
+            let source = lint::ManifestSource::read(&args.path)?;
+
+            let registry_url = config.registry_url.as_deref().unwrap_or(MOD_REGISTRY_URL);
+            let downloader =
+                ModDownloader::new(&mods_dir, download::build_client(&config)?, registry_url);
+            let mod_registry = lint::fetch_registry_for_checks(&downloader).await;
+
+            let issues = lint::lint_manifest(&source, mod_registry.as_ref());
+            lint::print_issues(&source, &issues);
+            let errors = lint::count_errors(&issues);
+            if errors > 0 {
+                return Err(Error::LintFailed(errors));
+            }
+            println!(
+                "{}: no errors, {} warning(s)",
+                source.path.display(),
+                issues.len()
+            );
+        }
+
+        Commands::Preflight(args) => {
+            if !args
+                .zip
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+            {
+                return Err(Error::NotAZipFile(args.zip.clone()));
+            }
+            let files = fileutil::list_zip_entries(&args.zip)?;
+            let mut issues = preflight::check_zip_structure(&files);
+
+            // A missing manifest is already reported by the structure checks
+            let source = match lint::ManifestSource::read(&args.zip) {
+                Ok(source) => {
+                    let registry_url = config.registry_url.as_deref().unwrap_or(MOD_REGISTRY_URL);
+                    let downloader = ModDownloader::new(
+                        &mods_dir,
+                        download::build_client(&config)?,
+                        registry_url,
+                    );
+                    let mod_registry = lint::fetch_registry_for_checks(&downloader).await;
+                    issues.extend(lint::lint_manifest(&source, mod_registry.as_ref()));
+                    source
+                }
+                Err(Error::MissingManifest(_)) => lint::ManifestSource {
+                    path: args.zip.clone(),
+                    content: String::new(),
+                    files: Some(files),
+                },
+                Err(e) => return Err(e),
+            };
+
+            lint::print_issues(&source, &issues);
+            let errors = lint::count_errors(&issues);
+            if errors > 0 {
+                return Err(Error::LintFailed(errors));
+            }
+            println!(
+                "{}: ready to upload, {} warning(s)",
+                args.zip.display(),
+                issues.len()
+            );
+        }
+
+        Commands::Extract(args) => {
+            let output = match &args.output {
+                Some(dir) => dir.clone(),
+                None => args
+                    .archive
+                    .file_stem()
+                    .map(std::path::PathBuf::from)
+                    .ok_or_else(|| Error::NotAZipFile(args.archive.clone()))?,
+            };
+            if std::fs::read_dir(&output).is_ok_and(|mut entries| entries.next().is_some()) {
+                return Err(Error::DirectoryNotEmpty(output));
+            }
+            let extracted = fileutil::extract_zip(&args.archive, &output)?;
+            println!("Extracted {} file(s) to {}", extracted, output.display());
+        }
+
+        Commands::Hash(args) => {
+            for file in &args.files {
+                println!(
+                    "{}  {}",
+                    checksum::hash_file(file, &[])?.xxh64,
+                    file.display()
+                );
+            }
+        }
+
+        Commands::Serve(args) => {
+            let addr = std::net::SocketAddr::new(args.bind, args.port);
+            serve::serve(&args.dir, addr).await?;
+        }
+
+        Commands::Registry(RegistryCommands::Add(args)) => {
+            let path = fileutil::get_local_registry_path()?;
+            let mod_info = if pack::is_url(&args.source) {
+                let cache_dir = fileutil::get_cache_directory()?;
+                let registry_url = config.registry_url.as_deref().unwrap_or(MOD_REGISTRY_URL);
+                let downloader =
+                    ModDownloader::new(&cache_dir, download::build_client(&config)?, registry_url);
+                let data = downloader.fetch_archive(&args.source).await?;
+                let archive = cache_dir.join("registry_add.zip");
+                std::fs::create_dir_all(&cache_dir)?;
+                std::fs::write(&archive, &data)?;
+                let url = args.url.as_deref().unwrap_or(&args.source);
+                let mod_info = RemoteModInfo::for_archive(&archive, url);
+                if let Err(e) = std::fs::remove_file(&archive) {
+                    warn!("Failed to remove {}: {}", archive.display(), e);
+                }
+                mod_info?
+            } else {
+                let archive = std::path::Path::new(&args.source);
+                let url = args
+                    .url
+                    .as_deref()
+                    .ok_or_else(|| Error::MissingDownloadUrl(archive.to_path_buf()))?;
+                RemoteModInfo::for_archive(archive, url)?
+            };
+            let (name, version) = (mod_info.name.clone(), mod_info.version.clone());
+            let replaced = ModRegistry::add_local(&path, mod_info)?;
+            println!(
+                "{} {} (version {}) in {}",
+                if replaced { "Replaced" } else { "Added" },
+                name,
+                version,
+                path.display()
+            );
+        }
+
+        // For remaining commands, fetch the remote mod registry
+        _ => {
+            let registry_url = config.registry_url.as_deref().unwrap_or(MOD_REGISTRY_URL);
+            let jobs = config.jobs.unwrap_or(DEFAULT_JOBS);
+            let downloader =
+                ModDownloader::new(&mods_dir, download::build_client(&config)?, registry_url)
+                    .with_retries(config.download_retries.unwrap_or(DEFAULT_DOWNLOAD_RETRIES))
+                    .with_jobs(jobs)
+                    .with_keep_corrupt(cli.keep_corrupt)
+                    .with_verify(!cli.no_verify);
+            if cli.no_verify {
+                eprintln!(
+                    "WARNING: checksum verification is DISABLED.\n\
+                    Corrupted or tampered downloads will be installed. Use this only if the registry lags behind a fix."
+                );
+            }
+            let cache_dir = fileutil::get_cache_directory()?;
+            let mut registry_cache = RegistryCache::load(&cache_dir);
+            let mod_registry_data = registry_cache.fetch(&downloader).await?;
+            let mut last_check = LastCheck::load(&cache_dir);
+            let mod_registry = merged_registry(
+                mod_registry_data,
+                registry_url,
+                &config,
+                Some(&downloader),
+                &cache_dir,
+            )
+            .await?;
+            let files_database = ModFilesDatabase::load_or_default(&downloader, &cache_dir).await;
+            let source = RegistrySource::new(&mod_registry, downloader.clone())
+                .with_files_database(&files_database);
+
+            match &cli.command {
+                Commands::Search(args) => {
+                    if !args.porcelain {
+                        println!("Searching for mods matching '{}'...", args.query);
+                    }
+                    let search_database =
+                        SearchDatabase::load_or_default(&downloader, &cache_dir).await;
+                    let mut results = source.search(&args.query, &|mod_info| {
+                        search_database
+                            .get(mod_info)
+                            .map(|entry| (entry.downloads, entry.likes))
+                    });
+                    // Sorting is stable, so relevance still orders equal results
+                    match args.sort {
+                        SearchSort::Relevance => {}
+                        SearchSort::Updated => {
+                            results.sort_by_key(|mod_info| Reverse(mod_info.updated_at))
+                        }
+                        SearchSort::Downloads => results.sort_by_key(|mod_info| {
+                            Reverse(search_database.get(mod_info).map(|entry| entry.downloads))
+                        }),
+                        SearchSort::Likes => results.sort_by_key(|mod_info| {
+                            Reverse(search_database.get(mod_info).map(|entry| entry.likes))
+                        }),
+                        SearchSort::Name => {
+                            results.sort_by_cached_key(|mod_info| mod_info.name.to_lowercase())
+                        }
+                    }
+                    if args.porcelain {
+                        for mod_info in results {
+                            let downloads = search_database
+                                .get(mod_info)
+                                .map(|entry| entry.downloads.to_string())
+                                .unwrap_or_default();
+                            println!(
+                                "{}",
+                                porcelain::record(
+                                    "result",
+                                    &[
+                                        &mod_info.name,
+                                        &mod_info.version,
+                                        &mod_info.gamebanana_type,
+                                        &mod_info.gamebanana_id.to_string(),
+                                        &downloads,
+                                    ]
+                                )
+                            );
+                        }
+                    } else if results.is_empty() {
+                        println!("No mods found matching the query: '{}'", args.query);
+                    } else {
+                        println!("Found {} matching mods:", results.len());
+                        for mod_info in results {
+                            println!("\n{} (version {})", mod_info.name, mod_info.version);
+                            print_search_result(mod_info, &search_database);
+                        }
+                    }
+                }
+                Commands::Browse(args) => {
+                    let section = format!("{:?}", args.section).to_lowercase();
+                    println!("Browsing GameBanana ({}, page {})...", section, args.page);
+                    let list =
+                        SubmissionList::load(&downloader, &cache_dir, args.section, args.page)
+                            .await?;
+                    if list.records.is_empty() {
+                        println!("No submissions on this page.");
+                        return Ok(ExitCode::SUCCESS);
+                    }
+                    let search_database =
+                        SearchDatabase::load_or_default(&downloader, &cache_dir).await;
+                    let mut choices = Vec::new();
+                    for submission in &list.records {
+                        let author = submission
+                            .author()
+                            .map(|author| format!(" by {}", author))
+                            .unwrap_or_default();
+                        let entries =
+                            source.find_by_gamebanana(&submission.model_name, submission.id);
+                        if entries.is_empty() {
+                            println!("\n    {}{}", submission.name, author);
+                            println!(
+                                " - Not in the registry, e.g. a work in progress or a tool: \
+                                https://gamebanana.com/{}s/{}",
+                                submission.model_name.to_lowercase(),
+                                submission.id
+                            );
+                        }
+                        for mod_info in entries {
+                            choices.push(mod_info);
+                            println!(
+                                "\n[{}] {} (version {}){}",
+                                choices.len(),
+                                mod_info.name,
+                                mod_info.version,
+                                author
+                            );
+                            print_search_result(mod_info, &search_database);
+                        }
+                    }
+                    if !list.metadata.is_complete {
+                        println!(
+                            "\nMore submissions with `browse {} --page {}`",
+                            section,
+                            args.page + 1
+                        );
+                    }
+                    if choices.is_empty() || !prompt::is_interactive() {
+                        return Ok(ExitCode::SUCCESS);
+                    }
+                    let selected = prompt::choose_many(
+                        "\nInstall mods by number (e.g. `1 3`), empty to skip: ",
+                        choices.len(),
+                    )?;
+                    if selected.is_empty() {
+                        return Ok(ExitCode::SUCCESS);
+                    }
+
+                    let dependency_graph =
+                        DependencyGraph::load_or_default(&downloader, &cache_dir).await;
+                    let installed_mods = list_installed_mods_in(&mods_dirs)?;
+                    let installed_names: HashSet<&str> = installed_mods
+                        .iter()
+                        .map(|m| m.manifest.name.as_str())
+                        .collect();
+                    let dependencies: Vec<_> = selected
+                        .into_iter()
+                        .map(|i| Dependency {
+                            name: choices[i].name.clone(),
+                            version: None,
+                        })
+                        .collect();
+                    let install_queue = install::missing_dependencies(
+                        &dependencies,
+                        &dependency_graph,
+                        &source,
+                        &installed_names,
+                    );
+                    if install_queue.is_empty() {
+                        println!("The selected mods are already installed!");
+                        return Ok(ExitCode::SUCCESS);
+                    }
+                    let names: Vec<_> = install_queue.iter().map(|m| m.name.as_str()).collect();
+                    println!("Mods to install: {}", names.join(", "));
+                    game::ensure_not_running(cli.force)?;
+                    install::install_from_source(&source, &install_queue, &dependency_graph)
+                        .await?;
+                    println!("Installation finished successfully!");
+                }
+                Commands::Info(args) => {
+                    println!("Looking up information for the mod '{}'...", args.name);
+                    if let Some(mod_info) = source.resolve(&args.name) {
+                        println!("\n{} (version {})", mod_info.name, mod_info.version);
+                        println!(
+                            " - Updated at: {}",
+                            timeutil::format_date(mod_info.updated_at)
+                        );
+                        println!(" - Size: {}", HumanBytes(mod_info.file_size));
+                        let search_database =
+                            SearchDatabase::load_or_default(&downloader, &cache_dir).await;
+                        if let Some(entry) = search_database.get(mod_info) {
+                            println!(" - Popularity: {}", entry.stats_line());
+                        }
+                        if let Some(file) = files_database.get(mod_info)
+                            && file.mods.len() > 1
+                        {
+                            println!(" - Provides: {}", file.mods.join(", "));
+                        }
+                        println!(
+                            " - Page: https://gamebanana.com/mods/{}",
+                            mod_info.gamebanana_id
+                        );
+                        println!(" - Download: {}", mod_info.download_url);
+                        println!(" - Hashes: {}", mod_info.checksums.join(", "));
+                        if args.full || args.preview {
+                            match GameBananaItem::load(
+                                &downloader,
+                                &cache_dir,
+                                &mod_info.gamebanana_type,
+                                mod_info.gamebanana_id,
+                            )
+                            .await
+                            {
+                                Ok(item) => {
+                                    if args.full {
+                                        println!(" - Title: {}", item.name);
+                                        if let Some(author) = item.author() {
+                                            println!(" - Author: {}", author);
+                                        }
+                                        let screenshots = item.screenshot_urls();
+                                        if !screenshots.is_empty() {
+                                            println!(" - Screenshots:");
+                                            for url in screenshots {
+                                                println!("   {}", url);
+                                            }
+                                        }
+                                        println!("\n{}", item.description());
+                                    }
+                                    if args.preview {
+                                        match (item.thumbnail_url(), GraphicsProtocol::detect()) {
+                                            (None, _) => println!("\nNo screenshot available"),
+                                            (Some(url), None) => println!(
+                                                "\nThis terminal cannot display images inline, see {}",
+                                                url
+                                            ),
+                                            (Some(url), Some(protocol)) => {
+                                                let image_data = gamebanana::load_image(
+                                                    &downloader,
+                                                    &cache_dir,
+                                                    &url,
+                                                )
+                                                .await?;
+                                                println!();
+                                                print!("{}", protocol.render(&image_data)?);
+                                            }
+                                        }
+                                    }
+                                }
+                                Err(e) => eprintln!(
+                                    "Could not fetch the GameBanana page of '{}': {}",
+                                    mod_info.name, e
+                                ),
+                            }
+                        }
+                    } else {
+                        println!("Mod '{}' not found", args.name);
+                    }
+                }
+                Commands::Install(args) => {
+                    println!("Starting installation of the mod '{}'...", args.name);
+                    let Some(mod_info) = source.resolve(&args.name) else {
+                        println!("The mod '{}' could not be found.", args.name);
+                        return Ok(ExitCode::SUCCESS);
+                    };
+
+                    let dependency_graph =
+                        DependencyGraph::load_or_default(&downloader, &cache_dir).await;
+                    let installed_mods = list_installed_mods_in(&mods_dirs)?;
+                    let installed_names: HashSet<&str> = installed_mods
+                        .iter()
+                        .map(|m| m.manifest.name.as_str())
+                        .collect();
+
+                    let mut install_queue: Vec<_> = install::missing_dependencies(
+                        &[Dependency {
+                            name: mod_info.name.clone(),
+                            version: None,
+                        }],
+                        &dependency_graph,
+                        &source,
+                        &installed_names,
+                    )
+                    .into_iter()
+                    // The mod itself is queued last, after the optional dependencies
+                    .filter(|dependency_info| dependency_info.name != mod_info.name)
+                    .collect();
+                    if !install_queue.is_empty() {
+                        let names: Vec<_> = install_queue.iter().map(|m| m.name.as_str()).collect();
+                        println!("Missing dependencies to install: {}", names.join(", "));
+                    }
+
+                    let optional: Vec<_> = dependency_graph
+                        .optional_dependencies_of(&mod_info.name)
+                        .iter()
+                        .filter(|dependency| {
+                            !is_pseudo_dependency(&dependency.name)
+                                && !installed_names.contains(dependency.name.as_str())
+                        })
+                        .cloned()
+                        .collect();
+                    if !optional.is_empty() && args.with_optional {
+                        let optional_queue: Vec<_> = install::missing_dependencies(
+                            &optional,
+                            &dependency_graph,
+                            &source,
+                            &installed_names,
+                        )
+                        .into_iter()
+                        .filter(|optional_info| {
+                            optional_info.name != mod_info.name
+                                && !install_queue.iter().any(|m| m.name == optional_info.name)
+                        })
+                        .collect();
+                        if !optional_queue.is_empty() {
+                            let names: Vec<_> =
+                                optional_queue.iter().map(|m| m.name.as_str()).collect();
+                            println!("Optional dependencies to install: {}", names.join(", "));
+                            install_queue.extend(optional_queue);
+                        }
+                    } else if !optional.is_empty() {
+                        let names: Vec<_> = optional.iter().map(|d| d.name.as_str()).collect();
+                        println!(
+                            "Optional dependencies, pass --with-optional to install them: {}",
+                            names.join(", ")
+                        );
+                    }
+                    install_queue.push(mod_info);
+
+                    let source = match &args.into {
+                        Some(dir) => {
+                            let dir = fileutil::resolve_mods_directory(dir.clone())?;
+                            if !mods_dirs.contains(&dir) {
+                                println!(
+                                    "Note: {} is not a configured mods directory, `list` and `update` will not see it",
+                                    dir.display()
+                                );
+                            }
+                            RegistrySource::new(&mod_registry, downloader.with_download_dir(&dir))
+                                .with_files_database(&files_database)
+                        }
+                        None => source.clone(),
+                    };
+                    game::ensure_not_running(cli.force)?;
+                    install::install_from_source(&source, &install_queue, &dependency_graph)
+                        .await?;
+                    println!("Installation finished successfully!");
+                }
+                Commands::Pack(PackCommands::Install(args)) => {
+                    let pack = match &args.preset {
+                        Some(preset) => Pack::preset(preset)?,
+                        None => {
+                            let source = args.source.as_deref().expect("clap requires a source");
+                            Pack::load_from(source, &downloader).await?
+                        }
+                    };
+                    println!(
+                        "Installing the pack '{}' ({} mods)...",
+                        pack.name,
+                        pack.mods.len()
+                    );
+                    let dependency_graph =
+                        DependencyGraph::load_or_default(&downloader, &cache_dir).await;
+                    let installed_mods = list_installed_mods(&mods_dir)?;
+                    let mut installed_names: HashSet<&str> = installed_mods
+                        .iter()
+                        .map(|m| m.manifest.name.as_str())
+                        .collect();
+
+                    // The registry only provides the latest version, another pinned one cannot be installed
+                    let mut skipped_pins = Vec::new();
+                    for pack_mod in &pack.mods {
+                        match mod_registry.get_mod_info(&pack_mod.name) {
+                            None => println!(
+                                "Warning: {} is not in the registry and will not be installed",
+                                pack_mod.name
+                            ),
+                            Some(remote_mod) => {
+                                if let Some(pinned) = &pack_mod.version
+                                    && pinned != &remote_mod.version
+                                    && !installed_names.contains(pack_mod.name.as_str())
+                                {
+                                    if args.ignore_pins {
+                                        println!(
+                                            "Warning: the pack pins {} {}, version {} will be installed",
+                                            pack_mod.name, pinned, remote_mod.version
+                                        );
+                                    } else {
+                                        println!(
+                                            "Warning: the pack pins {} {}, but only version {} is available, skipping it",
+                                            pack_mod.name, pinned, remote_mod.version
+                                        );
+                                        skipped_pins.push(pack_mod.name.as_str());
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    // Neither installed as a mod of the pack nor as a dependency
+                    installed_names.extend(&skipped_pins);
+
+                    let install_queue = install::missing_dependencies(
+                        &pack.as_dependencies(),
+                        &dependency_graph,
+                        &source,
+                        &installed_names,
+                    );
+                    if !skipped_pins.is_empty() {
+                        println!(
+                            "Skipped {} pinned mod(s): {} (pass --ignore-pins to install their latest version)",
+                            skipped_pins.len(),
+                            skipped_pins.join(", ")
+                        );
+                    }
+                    if install_queue.is_empty() {
+                        if skipped_pins.is_empty() {
+                            println!("All mods of the pack are already installed!");
+                        } else {
+                            println!("The other mods of the pack are already installed.");
+                        }
+                        return Ok(ExitCode::SUCCESS);
+                    }
+                    let names: Vec<_> = install_queue.iter().map(|m| m.name.as_str()).collect();
+                    println!("Mods to install: {}", names.join(", "));
+                    game::ensure_not_running(cli.force)?;
+                    install::install_from_source(&source, &install_queue, &dependency_graph)
+                        .await?;
+                    println!("Installation finished successfully!");
+                }
+                Commands::Merge(args) => {
+                    let setup = Setup::load(&args.source)?;
+                    let data_dir = fileutil::get_data_directory()?;
+                    let mut skipped = SkippedMods::load(&data_dir)?;
+                    let installed_mods = list_installed_mods(&mods_dir)?;
+                    let installed_names: HashSet<&str> = installed_mods
+                        .iter()
+                        .map(|m| m.manifest.name.as_str())
+                        .collect();
+                    let (mut offered, always_skipped) =
+                        merge::missing_mods(&setup, &installed_names, &skipped);
+                    if args.include_skipped {
+                        offered.extend(always_skipped);
+                    } else if !always_skipped.is_empty() {
+                        let names: Vec<_> = always_skipped.iter().map(|(name, _)| *name).collect();
+                        println!(
+                            "Always skipped: {} (offer them again with `--include-skipped`)",
+                            names.join(", ")
+                        );
+                    }
+                    if offered.is_empty() {
+                        println!("You already have every mod of {}", args.source.display());
+                        return Ok(ExitCode::SUCCESS);
+                    }
+                    if !prompt::is_interactive() {
+                        println!("Mods you do not have:");
+                        for (name, _) in &offered {
+                            println!("- {}", name);
+                        }
+                        println!("Run `merge` in a terminal to choose which ones to install.");
+                        return Ok(ExitCode::SUCCESS);
+                    }
+
+                    println!(
+                        "{} mods of {} are not installed. Answer [i]nstall, [s]kip (default), \
+                        [a]lways skip or [q]uit for each:",
+                        offered.len(),
+                        args.source.display()
+                    );
+                    let mut chosen = Vec::new();
+                    let mut skipped_changed = false;
+                    for (name, version) in &offered {
+                        let Some(remote_mod) = source.resolve(name) else {
+                            println!("- {} is not in the registry, skipping it", name);
+                            continue;
+                        };
+                        let version_note = match version {
+                            Some(version) if *version != remote_mod.version => format!(
+                                " ({} in the setup, {} will be installed)",
+                                version, remote_mod.version
+                            ),
+                            _ => format!(" {}", remote_mod.version),
+                        };
+                        let question = format!("- {}{}? ", name, version_note);
+                        let choice = loop {
+                            match MergeChoice::parse(&prompt::ask(&question)?) {
+                                Some(choice) => break choice,
+                                None => println!("Please answer i, s, a or q"),
+                            }
+                        };
+                        match choice {
+                            MergeChoice::Install => {
+                                skipped_changed |= skipped.remove(name);
+                                chosen.push(Dependency {
+                                    name: name.to_string(),
+                                    version: None,
+                                });
+                            }
+                            MergeChoice::Skip => {}
+                            MergeChoice::AlwaysSkip => skipped_changed |= skipped.add(name),
+                            MergeChoice::Quit => break,
+                        }
+                    }
+                    if skipped_changed {
+                        skipped.save()?;
+                    }
+                    if chosen.is_empty() {
+                        println!("Nothing to install.");
+                        return Ok(ExitCode::SUCCESS);
+                    }
+
+                    let dependency_graph =
+                        DependencyGraph::load_or_default(&downloader, &cache_dir).await;
+                    let install_queue = install::missing_dependencies(
+                        &chosen,
+                        &dependency_graph,
+                        &source,
+                        &installed_names,
+                    );
+                    let names: Vec<_> = install_queue.iter().map(|m| m.name.as_str()).collect();
+                    println!("Mods to install: {}", names.join(", "));
+                    game::ensure_not_running(cli.force)?;
+                    install::install_from_source(&source, &install_queue, &dependency_graph)
+                        .await?;
+                    println!("Installation finished successfully!");
+                }
+                Commands::Deps(DepsArgs {
+                    command: Some(DepsCommands::Install(args)),
+                    ..
+                }) => {
+                    let manifest = match fileutil::read_manifest_file_from_zip(&args.file)? {
+                        Some(buffer) => ModManifest::parse_mod_manifest_from_yaml(&buffer)?,
+                        None => return Err(Error::MissingManifest(args.file.clone())),
+                    };
+                    println!(
+                        "Resolving dependencies of {} (version {})...",
+                        manifest.name, manifest.version
+                    );
+                    let dependency_graph = DependencyGraph::load(&downloader, &cache_dir).await?;
+                    let installed_mods = list_installed_mods(&mods_dir)?;
+                    let installed_names: HashSet<&str> = installed_mods
+                        .iter()
+                        .map(|m| m.manifest.name.as_str())
+                        .collect();
+
+                    let install_queue = install::missing_dependencies(
+                        manifest.dependencies.as_deref().unwrap_or_default(),
+                        &dependency_graph,
+                        &source,
+                        &installed_names,
+                    );
+                    if install_queue.is_empty() {
+                        println!("All dependencies are already installed!");
+                        return Ok(ExitCode::SUCCESS);
+                    }
+                    let names: Vec<_> = install_queue.iter().map(|m| m.name.as_str()).collect();
+                    println!("Missing dependencies to install: {}", names.join(", "));
+                    game::ensure_not_running(cli.force)?;
+                    install::install_from_source(&source, &install_queue, &dependency_graph)
+                        .await?;
+                    println!("Installation finished successfully!");
+                }
+                Commands::Identify(args) => {
+                    let data_dir = fileutil::get_data_directory()?;
+                    let mut installed_index = InstalledIndex::load(&data_dir)?;
+                    let files: Vec<_> = if args.files.is_empty() {
+                        list_installed_mods(&mods_dir)?
+                            .into_iter()
+                            .map(|mod_info| mod_info.archive_path)
+                            .filter(|path| installed_index.get(path).is_none())
+                            .collect()
+                    } else {
+                        args.files.clone()
+                    };
+                    if files.is_empty() {
+                        println!("Every installed archive was installed by this tool already.");
+                        return Ok(ExitCode::SUCCESS);
+                    }
+
+                    let journal = Journal::new(&data_dir);
+                    let mut identified = 0;
+                    let mut first_error = None;
+                    for result in identify(&files, &source, jobs) {
+                        let identification = match result {
+                            Ok(identification) => identification,
+                            Err(e) => {
+                                eprintln!("[Error] {}", e);
+                                first_error.get_or_insert(e);
+                                continue;
+                            }
+                        };
+                        let Some(remote) = identification.remote else {
+                            println!("{}: not in the registry", identification.path.display());
+                            continue;
+                        };
+                        identified += 1;
+                        println!(
+                            "{}: {} version {}",
+                            identification.path.display(),
+                            remote.name,
+                            remote.version
+                        );
+                        if args.adopt {
+                            match adopt(
+                                &identification,
+                                remote,
+                                &mods_dir,
+                                &mut installed_index,
+                                &journal,
+                            ) {
+                                Ok(Adoption::Adopted(path)) => {
+                                    println!("  Adopted as {}", path.display())
+                                }
+                                Ok(Adoption::AlreadyManaged) => println!("  Already managed"),
+                                Err(e) => {
+                                    eprintln!("[Error] Failed to adopt {}: {}", remote.name, e);
+                                    first_error.get_or_insert(e);
+                                }
+                            }
+                        }
+                    }
+                    println!("\nIdentified {} of {} archive(s)", identified, files.len());
+                    if !args.adopt && identified > 0 {
+                        println!("Run with --adopt to record them as installed by this tool");
+                    }
+                    if let Some(e) = first_error {
+                        return Err(e);
+                    }
+                }
+                Commands::Deps(args) => {
+                    let dependency_graph = DependencyGraph::load(&downloader, &cache_dir).await?;
+                    if let Some(name) = &args.name
+                        && !dependency_graph.entries.contains_key(name)
+                    {
+                        println!("The mod '{}' is not in the dependency graph.", name);
+                        return Ok(ExitCode::SUCCESS);
+                    }
+                    let installed_mods = list_installed_mods(&mods_dir)?;
+                    let mut installed_versions: HashMap<&str, &str> = installed_mods
+                        .iter()
+                        .map(|m| (m.manifest.name.as_str(), m.manifest.version.as_str()))
+                        .collect();
+                    let mut roots: Vec<&str> = match &args.name {
+                        Some(name) => vec![name.as_str()],
+                        None => installed_versions.keys().copied().collect(),
+                    };
+                    roots.sort_unstable();
+                    // The game and Everest show up as installed rather than missing
+                    let game = GameVersions::detect(&mods_dir);
+                    for name in EVEREST_PSEUDO_DEPENDENCIES {
+                        if let Some(version) = game.version_of(name) {
+                            installed_versions.insert(name, version);
+                        }
+                    }
+
+                    if args.dot || args.json {
+                        let export =
+                            DependencyExport::build(&dependency_graph, &roots, &installed_versions);
+                        if args.dot {
+                            print!("{}", export.to_dot());
+                        } else {
+                            println!("{}", export.to_json()?);
+                        }
+                    } else {
+                        for root in roots {
+                            print_dependency_tree(&dependency_graph, root, &installed_versions);
+                        }
+                    }
+                }
+                Commands::Why(args) => {
+                    let dependency_graph = DependencyGraph::load(&downloader, &cache_dir).await?;
+                    let installed_mods = list_installed_mods(&mods_dir)?;
+                    let installed_names: HashSet<&str> = installed_mods
+                        .iter()
+                        .map(|m| m.manifest.name.as_str())
+                        .collect();
+
+                    let chains = dependency_graph.dependent_chains(&args.name, &installed_names);
+                    if chains.is_empty() {
+                        println!("No installed mod requires '{}'.", args.name);
+                    } else {
+                        println!(
+                            "'{}' is required by {} installed mods:",
+                            args.name,
+                            chains.len()
+                        );
+                        for chain in chains {
+                            println!("- {}", chain.join(" -> "));
+                        }
+                    }
+                }
+                Commands::Authors(args) => {
+                    let search_database = SearchDatabase::load(&downloader, &cache_dir).await?;
+                    let mut installed_mods = list_installed_mods(&mods_dir)?;
+                    installed_mods
+                        .sort_by_cached_key(|mod_info| mod_info.manifest.name.to_lowercase());
+                    let (groups, unknown) =
+                        search_database.group_by_author(&installed_mods, &mod_registry);
+
+                    match &args.author {
+                        None => {
+                            if groups.is_empty() {
+                                println!("No installed mod has a known author.");
+                            }
+                            for group in &groups {
+                                println!("{} ({})", group.author, group.mods.len());
+                                for mod_info in &group.mods {
+                                    println!("- {}", mod_info.manifest.name);
+                                }
+                            }
+                            if !unknown.is_empty() {
+                                println!(
+                                    "\nUnknown author, not in the registry ({}):",
+                                    unknown.len()
+                                );
+                                for mod_info in &unknown {
+                                    println!("- {}", mod_info.manifest.name);
+                                }
+                            }
+                        }
+                        Some(author) => {
+                            let installed_names: HashSet<&str> = installed_mods
+                                .iter()
+                                .map(|mod_info| mod_info.manifest.name.as_str())
+                                .collect();
+                            let by_author = |name: &str| name.eq_ignore_ascii_case(author);
+                            match groups.iter().find(|group| by_author(group.author)) {
+                                Some(group) => {
+                                    println!("Installed mods by {}:", group.author);
+                                    for mod_info in &group.mods {
+                                        println!("- {}", mod_info.manifest.name);
+                                    }
+                                }
+                                None => println!("No installed mod by '{}'.", author),
+                            }
+
+                            let mut others: Vec<&RemoteModInfo> = mod_registry
+                                .entries
+                                .values()
+                                .filter(|mod_info| {
+                                    !installed_names.contains(mod_info.name.as_str())
+                                        && search_database
+                                            .author_of(mod_info)
+                                            .is_some_and(by_author)
+                                })
+                                .collect();
+                            others.sort_by_cached_key(|mod_info| mod_info.name.to_lowercase());
+                            if !others.is_empty() {
+                                println!("\nOther mods by {} in the registry:", author);
+                                for mod_info in others {
+                                    println!(
+                                        "- {} (https://gamebanana.com/mods/{})",
+                                        mod_info.name, mod_info.gamebanana_id
+                                    );
+                                }
+                            }
+                        }
+                    }
+                }
+                Commands::Depcheck => {
+                    println!("Checking dependencies of installed mods...");
+                    let installed_mods = list_installed_mods(&mods_dir)?;
+                    let installed_names: HashSet<&str> = installed_mods
+                        .iter()
+                        .map(|m| m.manifest.name.as_str())
+                        .collect();
+
+                    let game = GameVersions::detect(&mods_dir);
+                    let report = check_installed_dependencies(&installed_mods, &game);
+                    if report.is_ok() && report.optional_missing.is_empty() {
+                        println!("All dependencies are installed and up to date!");
+                        return Ok(ExitCode::SUCCESS);
+                    }
+
+                    if !report.missing.is_empty() {
+                        // Dependencies of the missing mods will be missing as well
+                        let dependency_graph =
+                            DependencyGraph::load(&downloader, &cache_dir).await?;
+                        println!("\nMissing dependencies ({}):", report.missing.len());
+                        for (name, dependents) in &report.missing {
+                            let availability = if mod_registry.get_mod_info(name).is_some() {
+                                ""
+                            } else {
+                                " (not in the registry)"
+                            };
+                            println!("- {}{}", name, availability);
+                            println!("  Required by: {}", dependents.join(", "));
+                            let transitive: Vec<_> = dependency_graph
+                                .resolve(name)
+                                .into_iter()
+                                .filter(|dependency| {
+                                    !is_pseudo_dependency(dependency)
+                                        && !installed_names.contains(dependency.as_str())
+                                        && !report.missing.contains_key(dependency.as_str())
+                                })
+                                .collect();
+                            if !transitive.is_empty() {
+                                println!("  Also pulls in: {}", transitive.join(", "));
+                            }
+                        }
+                        println!(
+                            "\nRun `install <mod_name>` to install a missing dependency along with its own dependencies"
+                        );
+                    }
+
+                    if !report.unsatisfied.is_empty() {
+                        println!(
+                            "\nDependencies too old for their dependents ({}):",
+                            report.unsatisfied.len()
+                        );
+                        for (name, unsatisfied) in &report.unsatisfied {
+                            println!(
+                                "- {} (version {} installed)",
+                                name, unsatisfied.installed_version
+                            );
+                            for (dependent, required_version) in &unsatisfied.required_by {
+                                println!("  {} requires version {}", dependent, required_version);
+                            }
+                            if is_pseudo_dependency(name) {
+                                println!(
+                                    "  Suggestion: update {}, e.g. with Olympus",
+                                    if *name == "Celeste" {
+                                        "the game"
+                                    } else {
+                                        "Everest"
+                                    }
+                                );
+                                continue;
+                            }
+
+                            let remote_version = mod_registry
+                                .get_mod_info(name)
+                                .map(|remote| remote.version.as_str());
+                            let fixes_all = remote_version
+                                .and_then(EverestVersion::parse)
+                                .is_some_and(|available| {
+                                    unsatisfied.required_by.iter().all(|(_, required)| {
+                                        EverestVersion::parse(required)
+                                            .is_none_or(|required| available.satisfies(&required))
+                                    })
+                                });
+                            match remote_version {
+                                Some(available) if fixes_all => println!(
+                                    "  Suggestion: update to version {} with `update`",
+                                    available
+                                ),
+                                Some(available) => println!(
+                                    "  The latest version {} does not satisfy every dependent",
+                                    available
+                                ),
+                                None => println!("  This mod is not in the registry"),
+                            }
+                        }
+                    }
+
+                    if !report.optional_missing.is_empty() {
+                        println!(
+                            "\nOptional dependencies not installed ({}):",
+                            report.optional_missing.len()
+                        );
+                        for (name, dependents) in &report.optional_missing {
+                            let availability = if mod_registry.get_mod_info(name).is_some() {
+                                ""
+                            } else {
+                                " (not in the registry)"
+                            };
+                            println!("- {}{}", name, availability);
+                            println!("  Used by: {}", dependents.join(", "));
+                        }
+                        println!(
+                            "\nThese are not required, but often enable extra content. Install them with `install <mod_name>`"
+                        );
+                    }
+                }
+                Commands::Subscribe(args) => {
+                    let mut subscriptions = Subscriptions::load(&fileutil::get_data_directory()?)?;
+                    if args.names.is_empty() {
+                        if subscriptions.is_empty() {
+                            println!("No subscriptions yet, add one with `subscribe <name>`");
+                        }
+                        for (name, seen) in subscriptions.iter() {
+                            match seen {
+                                Some(seen) => println!(
+                                    "- {} (version {}, updated at {})",
+                                    name,
+                                    seen.version,
+                                    timeutil::format_date(seen.updated_at)
+                                ),
+                                None => println!("- {} (not in the registry yet)", name),
+                            }
+                        }
+                        return Ok(ExitCode::SUCCESS);
+                    }
+                    let mut modified = false;
+                    for name in &args.names {
+                        if !subscriptions.add(name, &mod_registry) {
+                            println!("Already subscribed to {}", name);
+                            continue;
+                        }
+                        modified = true;
+                        match mod_registry.get_mod_info(name) {
+                            Some(mod_info) => {
+                                println!("Subscribed to {} (version {})", name, mod_info.version)
+                            }
+                            None => println!(
+                                "Subscribed to {}, which is not in the registry yet: `update` will report when it is released",
+                                name
+                            ),
+                        }
+                    }
+                    if modified {
+                        subscriptions.save()?;
+                    }
+                }
+                Commands::Outdated(args) => {
+                    let update_check = check_updates(&mods_dirs, &source, jobs, &mut last_check)?;
+                    if let Err(e) = last_check.save() {
+                        warn!("Failed to save the state of this run: {}", e);
+                    }
+                    if let (Some(FeedFormat::Atom), Some(path)) = (args.feed, &args.output) {
+                        // Subscription changes go to the feed once, `update` does not report them again
+                        let mut subscriptions =
+                            Subscriptions::load(&fileutil::get_data_directory()?)?;
+                        let changes = subscriptions.check(&mod_registry);
+                        write_update_feed(
+                            path,
+                            &mod_registry,
+                            &registry_cache,
+                            &update_check.updates,
+                            &changes,
+                        )
+                        .await?;
+                        if !changes.is_empty() {
+                            subscriptions.save()?;
+                        }
+                        if !args.json {
+                            println!("Wrote the feed to {}", path.display());
+                        }
+                    }
+                    let search_database =
+                        if update_check.updates.is_empty() && update_check.excluded.is_empty() {
+                            SearchDatabase::default()
+                        } else {
+                            SearchDatabase::load_or_default(&downloader, &cache_dir).await
+                        };
+                    if args.json {
+                        let report = OutdatedReport::new(&update_check, &search_database);
+                        println!("{}", report.to_json()?);
+                    } else if update_check.updates.is_empty() {
+                        println!("All mods are up to date!");
+                    } else {
+                        let (code_mod_updates, content_updates): (Vec<_>, Vec<_>) = update_check
+                            .updates
+                            .iter()
+                            .partition(|update| update.is_code_mod());
+                        print_updates("Available updates", &content_updates, &search_database);
+                        print_updates(
+                            "Code mod updates (may break other mods or saves)",
+                            &code_mod_updates,
+                            &search_database,
+                        );
+                    }
+                    if !args.json && !update_check.excluded.is_empty() {
+                        let names: Vec<_> = update_check
+                            .excluded
+                            .iter()
+                            .map(|update| update.name.as_str())
+                            .collect();
+                        println!(
+                            "Pinned mods with updates, skipped by `update`: {}",
+                            names.join(", ")
+                        );
+                    }
+                    if let Some(url) = &config.webhook_url {
+                        let updates: Vec<_> = update_check.updates.iter().collect();
+                        webhook::notify_updates_found(
+                            &downloader,
+                            url,
+                            config.webhook_format,
+                            &fileutil::get_data_directory()?,
+                            &updates,
+                        )
+                        .await?;
+                    }
+                    if !update_check.updates.is_empty() {
+                        return Ok(ExitCode::from(UPDATES_AVAILABLE_EXIT_CODE));
+                    }
+                }
+                Commands::Update(args) => {
+                    // `--porcelain` only reports updates, for scripts
+                    let install = !args.dry_run && !args.porcelain;
+                    if !args.porcelain {
+                        println!("Checking mod updates...");
+                    }
+                    let update_check = check_updates(&mods_dirs, &source, jobs, &mut last_check)?;
+                    if let Err(e) = last_check.save() {
+                        warn!("Failed to save the state of this run: {}", e);
+                    }
+                    if args.porcelain {
+                        let updates = update_check.updates.iter().map(|update| ("update", update));
+                        let pinned = update_check
+                            .excluded
+                            .iter()
+                            .map(|update| ("pinned", update));
+                        for (kind, update) in updates.chain(pinned) {
+                            println!(
+                                "{}",
+                                porcelain::record(
+                                    kind,
+                                    &[
+                                        &update.name,
+                                        &update.current_version,
+                                        &update.available_version,
+                                        if update.is_code_mod() {
+                                            "code"
+                                        } else {
+                                            "content"
+                                        },
+                                        &update.existing_path.to_string_lossy(),
+                                    ]
+                                )
+                            );
+                        }
+                        for local_mod in &update_check.unmanaged {
+                            println!(
+                                "{}",
+                                porcelain::record(
+                                    "unmanaged",
+                                    &[
+                                        &local_mod.manifest.name,
+                                        &local_mod.manifest.version,
+                                        &local_mod.archive_path.to_string_lossy(),
+                                    ]
+                                )
+                            );
+                        }
+                        return Ok(ExitCode::SUCCESS);
+                    }
+                    let mut subscriptions = Subscriptions::load(&fileutil::get_data_directory()?)?;
+                    let changes = subscriptions.check(&mod_registry);
+                    if !changes.is_empty() {
+                        println!("Subscriptions:");
+                        for change in &changes {
+                            println!("- {}", change);
+                        }
+                        println!();
+                        subscriptions.save()?;
+                    }
+                    let mut summary = BatchSummary::new("updated");
+                    for update in &update_check.excluded {
+                        summary.skipped(&update.name, "pinned or in updaterblacklist.txt");
+                    }
+                    if !update_check.unmanaged.is_empty() {
+                        println!(
+                            "Unmanaged mods (not in the registry, installed by hand, renamed or removed from GameBanana):"
+                        );
+                        for local_mod in &update_check.unmanaged {
+                            println!(
+                                "- {} ({})",
+                                local_mod.manifest.name,
+                                local_mod.archive_file_name()
+                            );
+                            summary.skipped(
+                                &local_mod.manifest.name,
+                                "unmanaged, not in the registry",
+                            );
+                        }
+                        println!();
+                    }
+                    let mut available_updates = update_check.updates;
+                    // Categories are only needed to filter and group pending updates
+                    let search_database = if available_updates.is_empty() {
+                        SearchDatabase::default()
+                    } else if args.only.is_some() {
+                        // Without categories, every update would be held back without a word
+                        SearchDatabase::load(&downloader, &cache_dir).await?
+                    } else {
+                        SearchDatabase::load_or_default(&downloader, &cache_dir).await
+                    };
+                    if let Some(category) = &args.only {
+                        let held_back;
+                        (available_updates, held_back) =
+                            available_updates.into_iter().partition(|update| {
+                                search_database.is_in_category(
+                                    &update.gamebanana_type,
+                                    update.gamebanana_id,
+                                    category,
+                                )
+                            });
+                        for update in &held_back {
+                            summary.skipped(
+                                &update.name,
+                                format!("not in the '{}' category", category),
+                            );
+                        }
+                        if !held_back.is_empty() {
+                            println!(
+                                "Holding back {} update(s) of mods outside the '{}' category",
+                                held_back.len(),
+                                category
+                            );
+                        }
+                    }
+                    if available_updates.is_empty() {
+                        match &args.only {
+                            Some(category) => {
+                                println!("All mods in the '{}' category are up to date!", category)
+                            }
+                            None => println!("All mods are up to date!"),
+                        }
+                    } else {
+                        let (code_mod_updates, content_updates): (Vec<_>, Vec<_>) =
+                            available_updates
+                                .into_iter()
+                                .partition(|update| update.is_code_mod());
+                        let groups = [
+                            ("Available updates", &content_updates),
+                            (
+                                "Code mod updates (may break other mods or saves)",
+                                &code_mod_updates,
+                            ),
+                        ];
+                        for (heading, updates) in groups {
+                            print_updates(heading, updates, &search_database);
+                        }
+
+                        let mut available_updates = content_updates;
+                        if install && !code_mod_updates.is_empty() {
+                            let include_code_mods = args.include_code_mods
+                                || (prompt::is_interactive()
+                                    && prompt::confirm(&format!(
+                                        "Also update {} code mod(s)? [y/N]: ",
+                                        code_mod_updates.len()
+                                    ))?);
+                            if include_code_mods {
+                                available_updates.extend(code_mod_updates);
+                            } else {
+                                println!(
+                                    "Skipping {} code mod update(s), pass --include-code-mods to install them",
+                                    code_mod_updates.len()
+                                );
+                                for update in &code_mod_updates {
+                                    summary.skipped(
+                                        &update.name,
+                                        "code mod, pass --include-code-mods to update it",
+                                    );
+                                }
+                            }
+                        }
+                        // Map updates may move strawberries and rooms, resetting progress or breaking runs
+                        if install && !args.ignore_saves && !available_updates.is_empty() {
+                            let save_files = SaveFiles::load(&mods_dir);
+                            let mut in_saves = Vec::new();
+                            for update in &available_updates {
+                                match save_files.progress_in(&update.existing_path) {
+                                    Ok(maps) if !maps.is_empty() => in_saves.push((
+                                        update.name.clone(),
+                                        maps.iter().map(MapInSave::describe).collect::<Vec<_>>(),
+                                    )),
+                                    Ok(_) => {}
+                                    Err(e) => warn!(
+                                        "Failed to list the maps of {}: {}",
+                                        update.existing_path.display(),
+                                        e
+                                    ),
+                                }
+                            }
+                            if !in_saves.is_empty() {
+                                println!(
+                                    "Warning: {} update(s) change maps with progress in your save files, \
+                                    which may reset room states or break golden runs:",
+                                    in_saves.len()
+                                );
+                                for (name, maps) in &in_saves {
+                                    println!("{}", name);
+                                    for map in maps {
+                                        println!(" - {}", map);
+                                    }
+                                }
+                                let update_anyway = prompt::is_interactive()
+                                    && prompt::confirm("Update them anyway? [y/N]: ")?;
+                                if !update_anyway {
+                                    println!(
+                                        "Skipping {} update(s) of maps in your save files, pass --ignore-saves to install them",
+                                        in_saves.len()
+                                    );
+                                    available_updates.retain(|update| {
+                                        !in_saves.iter().any(|(name, _)| *name == update.name)
+                                    });
+                                    for (name, _) in &in_saves {
+                                        summary.skipped(
+                                            name,
+                                            "maps in save files, pass --ignore-saves to update it",
+                                        );
+                                    }
+                                }
+                            }
+                        }
+                        if install && args.confirm && !available_updates.is_empty() {
+                            let confirmed = prompt::is_interactive()
+                                && prompt::confirm(&format!(
+                                    "Install {} update(s)? [y/N]: ",
+                                    available_updates.len()
+                                ))?;
+                            if !confirmed {
+                                println!("Skipping {} update(s)", available_updates.len());
+                                for update in &available_updates {
+                                    summary.skipped(&update.name, "not confirmed");
+                                }
+                                available_updates.clear();
+                            }
+                        }
+                        if install && !available_updates.is_empty() {
+                            game::ensure_not_running(cli.force)?;
+                            println!("Installing updates...");
+                            let data_dir = fileutil::get_data_directory()?;
+                            // Helper updates occasionally corrupt the save data of mods
+                            if let Some(keep) = config.save_backups.filter(|keep| *keep > 0)
+                                && available_updates.iter().any(|update| update.is_code_mod())
+                            {
+                                let save_directories = fileutil::get_save_directories(&mods_dir);
+                                let backup_dir = data_dir.join(SAVE_BACKUP_DIRECTORY);
+                                match saves::backup(&save_directories, &backup_dir, keep)? {
+                                    Some(path) => {
+                                        println!("Backed up the save files to {}", path.display())
+                                    }
+                                    None => info!("No save files to back up"),
+                                }
+                            }
+                            // Nothing in the mods directory changes until every update is downloaded and verified
+                            let staging = Staging::create(&mods_dir)?;
+                            let batch_progress = (available_updates.len() > 1).then(|| {
+                                BatchProgress::new(
+                                    available_updates.iter().map(|update| update.size).sum(),
+                                )
+                            });
+                            let staging_source = RegistrySource::new(
+                                &mod_registry,
+                                downloader.with_download_dir(staging.dir()),
+                            )
+                            .with_files_database(&files_database);
+                            let batch_source = batch_progress.as_ref().map(|batch_progress| {
+                                staging_source.with_batch_progress(batch_progress)
+                            });
+                            let staging_source = batch_source.as_deref().unwrap_or(&staging_source);
+
+                            let mut downloads = Vec::new();
+                            for update in available_updates {
+                                let Some(remote_mod) = staging_source.resolve(&update.name) else {
+                                    continue;
+                                };
+                                println!("\nUpdating {}:", update.name);
+
+                                downloads.push(async move {
+                                    let result = staging_source.download(remote_mod).await;
+                                    match &result {
+                                        Ok(_) => println!(
+                                            "[Successs] Downloaded {} version {}\n",
+                                            update.name, update.available_version
+                                        ),
+                                        Err(e) => eprintln!(
+                                            "[Error] Failed to update {}: {}",
+                                            update.name, e
+                                        ),
+                                    }
+                                    (update, result)
+                                });
+                            }
+
+                            let mut downloaded = Vec::new();
+                            let mut failed = 0;
+                            for download in futures_util::future::join_all(downloads).await {
+                                match download {
+                                    (update, Ok(archive)) => downloaded.push((update, archive)),
+                                    (update, Err(e)) => {
+                                        summary.failed(&update.name, e);
+                                        failed += 1;
+                                    }
+                                }
+                            }
+                            if failed > 0 {
+                                staging.discard();
+                                eprintln!(
+                                    "\nUpdate aborted, {} of {} download(s) failed and no mod was changed",
+                                    failed,
+                                    failed + downloaded.len()
+                                );
+                                for (update, _) in &downloaded {
+                                    summary.skipped(
+                                        &update.name,
+                                        "not changed as another update failed",
+                                    );
+                                }
+                            } else {
+                                // Keep a copy of the outdated archives so that the updates can be undone
+                                let backups: Vec<_> = downloaded
+                                    .iter()
+                                    .map(|(update, _)| {
+                                        journal::backup_archive(&data_dir, &update.existing_path)
+                                            .inspect_err(|e| {
+                                                warn!(
+                                                    "Failed to back up {}: {}",
+                                                    update.existing_path.display(),
+                                                    e
+                                                )
+                                            })
+                                            .ok()
+                                    })
+                                    .collect();
+                                let staged: Vec<_> = downloaded
+                                    .iter()
+                                    .map(|(update, archive)| StagedArchive {
+                                        path: archive.path.clone(),
+                                        replaces: Some(update.existing_path.clone()),
+                                    })
+                                    .collect();
+                                let paths = match staging.commit(&staged) {
+                                    Ok(paths) => paths,
+                                    Err(e) => {
+                                        for backup in backups.into_iter().flatten() {
+                                            let _ = std::fs::remove_file(backup.path);
+                                        }
+                                        eprintln!(
+                                            "\nUpdate aborted, the previous versions were restored"
+                                        );
+                                        return Err(e);
+                                    }
+                                };
+
+                                let mut installed_index = InstalledIndex::load(&data_dir)?;
+                                let journal = Journal::new(&data_dir);
+                                let mut installed_updates = Vec::new();
+                                for (((update, mut archive), backup), path) in
+                                    downloaded.into_iter().zip(backups).zip(paths)
+                                {
+                                    archive.path = path;
+                                    println!(
+                                        "Updated {} to version {}",
+                                        update.name, update.available_version
+                                    );
+                                    journal.append(
+                                        &JournalEntry::new(
+                                            Operation::Update,
+                                            &update.name,
+                                            &archive.path,
+                                        )
+                                        .with_versions(
+                                            Some(&update.current_version),
+                                            &update.available_version,
+                                        )
+                                        .with_hash(&archive.digests.xxh64)
+                                        .with_backup(backup),
+                                    )?;
+                                    if let Some(remote_mod) =
+                                        mod_registry.get_mod_info(&update.name)
+                                    {
+                                        installed_index.record(
+                                            &archive.path,
+                                            remote_mod,
+                                            &archive.digests,
+                                        );
+                                    }
+                                    if !archive.verified {
+                                        installed_index.mark_unverified(&archive.path);
+                                    }
+                                    if archive.path != update.existing_path {
+                                        installed_index.remove(&update.existing_path);
+                                    }
+                                    summary.done(
+                                        &update.name,
+                                        format!(
+                                            "{} -> {}",
+                                            update.current_version, update.available_version
+                                        ),
+                                    );
+                                    installed_updates.push(update);
+                                }
+                                installed_index.save()?;
+                                if let Some(url) = &config.webhook_url {
+                                    let updates: Vec<_> = installed_updates.iter().collect();
+                                    webhook::notify(
+                                        &downloader,
+                                        url,
+                                        config.webhook_format,
+                                        WebhookEvent::UpdatesInstalled,
+                                        &updates,
+                                    )
+                                    .await;
+                                }
+                            }
+                            if let Some(batch_progress) = batch_progress {
+                                summary.set_transfer(batch_progress.finish());
+                            }
+                            if summary.len() > 1 {
+                                println!("\n{}", summary);
+                            }
+                            summary.into_result()?;
+
+                            println!("\nAll updates installed successfully!");
+                        } else if !install {
+                            println!("Run without --dry-run to install these updates");
+                        }
+                    }
+                }
+                Commands::Vendor(args) => {
+                    let installed_mods = list_installed_mods(&mods_dir)?;
+                    let targets =
+                        collect_vendor_targets(&installed_mods, &mod_registry, args.with_deps);
+                    if targets.is_empty() {
+                        println!("No installed mods found in the remote mod registry.");
+                        return Ok(ExitCode::SUCCESS);
+                    }
+
+                    std::fs::create_dir_all(&args.dir)?;
+                    let mut manifest = VendorManifest::load(&args.dir)?;
+                    let vendor_source =
+                        RegistrySource::new(&mod_registry, downloader.with_download_dir(&args.dir))
+                            .with_files_database(&files_database);
+                    println!(
+                        "Vendoring {} mods into {}...",
+                        targets.len(),
+                        args.dir.display()
+                    );
+                    let (up_to_date, targets): (Vec<_>, Vec<_>) =
+                        targets.into_iter().partition(|remote_mod| {
+                            manifest.is_up_to_date(&args.dir, remote_mod, &vendor_source)
+                        });
+                    for remote_mod in up_to_date {
+                        println!("\n{} is already up to date", remote_mod.name);
+                    }
+                    let batch_progress =
+                        BatchProgress::new(targets.iter().map(|mod_info| mod_info.file_size).sum());
+                    let vendor_source = vendor_source.with_batch_progress(&batch_progress);
+
+                    let mut failed = Vec::new();
+                    for remote_mod in targets {
+                        let outdated_archive = manifest
+                            .entries
+                            .get(&remote_mod.name)
+                            .map(|entry| args.dir.join(&entry.download_url));
+
+                        println!("\nDownloading {}:", remote_mod.name);
+                        match vendor_source.download(remote_mod).await {
+                            Ok(archive) => {
+                                let archive_path = archive.path;
+                                if let Some(outdated) = outdated_archive
+                                    && outdated != archive_path
+                                    && outdated.is_file()
+                                {
+                                    fileutil::remove_file(&outdated)?;
+                                }
+                                manifest.insert(remote_mod, &archive_path);
+                            }
+                            Err(e) => {
+                                eprintln!("[Error] Failed to download {}: {}", remote_mod.name, e);
+                                failed.push(remote_mod.name.as_str());
+                            }
+                        }
+                    }
+                    manifest.save(&args.dir)?;
+                    println!("\n{}", batch_progress.finish());
+
+                    if failed.is_empty() {
+                        println!("\nOffline mirror is ready in {}", args.dir.display());
+                    } else {
+                        println!(
+                            "\nOffline mirror is incomplete, failed to download: {}",
+                            failed.join(", ")
+                        );
+                    }
+                }
+                Commands::Registry(RegistryCommands::Diff) => {
+                    let Some(previous_data) = registry_cache.load_previous()? else {
+                        println!(
+                            "No previous registry snapshot found. Changes will be shown once the registry has been updated since the last run."
+                        );
+                        return Ok(ExitCode::SUCCESS);
+                    };
+                    let previous_registry = ModRegistry::from(previous_data).await?;
+                    let diff = mod_registry.diff(&previous_registry);
+                    if diff.is_empty() {
+                        println!("No changes since the previous registry snapshot.");
+                        return Ok(ExitCode::SUCCESS);
+                    }
+
+                    println!("\nNew mods ({}):", diff.added.len());
+                    for mod_info in &diff.added {
+                        println!("+ {} (version {})", mod_info.name, mod_info.version);
+                    }
+                    println!("\nRemoved mods ({}):", diff.removed.len());
+                    for mod_info in &diff.removed {
+                        println!("- {} (version {})", mod_info.name, mod_info.version);
+                    }
+                    println!("\nUpdated mods ({}):", diff.updated.len());
+                    for (old, new) in &diff.updated {
+                        println!("* {} ({} -> {})", new.name, old.version, new.version);
+                    }
+                }
+                // Catch-all arm (should not be reached because all subcommands are handled)
+                _ => {
+                    println!("Use --help to see available commands");
+                }
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Parses the main registry and merges the extra registries and `local_registry.yaml` into it
+///
+/// Every command reading the registry goes through this, so that they all see the same mods. Without
+/// a downloader, e.g. for offline commands, the cached copies of the extra registries are used.
+async fn merged_registry(
+    data: Bytes,
+    registry_url: &str,
+    config: &Config,
+    downloader: Option<&ModDownloader>,
+    cache_dir: &std::path::Path,
+) -> Result<ModRegistry, Error> {
+    let mod_registry = ModRegistry::from(data).await?;
+    let mut mod_registry = merge_extra_registries(
+        mod_registry,
+        registry_url,
+        &config.extra_registries,
+        downloader,
+        cache_dir,
+    )
+    .await;
+    merge_local_registry(&mut mod_registry)?;
+    Ok(mod_registry)
+}
+
+/// Merges the extra registries with the main one in order of priority, the main registry having
+/// priority 0, a registry which cannot be fetched only loses its own mods
+async fn merge_extra_registries(
+    main_registry: ModRegistry,
+    registry_url: &str,
+    extra_registries: &[ExtraRegistry],
+    downloader: Option<&ModDownloader>,
+    cache_dir: &std::path::Path,
+) -> ModRegistry {
+    if extra_registries.is_empty() {
+        return main_registry;
+    }
+    // Sorting is stable, so registries listed later still win at equal priority
+    let mut extra_registries: Vec<_> = extra_registries.iter().collect();
+    extra_registries.sort_by_key(|extra| extra.priority);
+    let main_names: HashSet<String> = main_registry.entries.keys().cloned().collect();
+    let no_protection = HashSet::new();
+
+    let mut merged = ModRegistry::default();
+    let mut origins: HashMap<String, String> = HashMap::new();
+    let mut merge = |registry: ModRegistry, url: &str, protected: &HashSet<String>| {
+        let names: Vec<_> = registry.entries.keys().cloned().collect();
+        let report = merged.merge(registry, protected);
+        for name in &report.replaced {
+            if origins[name] == registry_url {
+                eprintln!(
+                    "Warning: {} replaces the entry of {} in the main registry",
+                    url, name
+                );
+            } else {
+                info!("{}: {} wins over {}", name, url, origins[name]);
+            }
+        }
+        for name in &report.kept {
+            info!(
+                "{}: keeping the entry of {}, {} is not trusted to replace it",
+                name, origins[name], url
+            );
+        }
+        for name in names.into_iter().filter(|name| !report.kept.contains(name)) {
+            origins.insert(name, url.to_string());
+        }
+    };
+    let mut main_registry = Some(main_registry);
+    for extra in extra_registries {
+        if extra.priority >= 0
+            && let Some(main_registry) = main_registry.take()
+        {
+            merge(main_registry, registry_url, &no_protection);
+        }
+        let overlay = match cache::extra_registry(cache_dir, &extra.url, downloader).await {
+            Ok(Some(data)) => ModRegistry::from(data).await.map(Some),
+            Ok(None) => Ok(None),
+            Err(e) => Err(e),
+        };
+        match overlay {
+            Ok(None) => info!(
+                "Skipping the mod registry {}, it was not fetched yet",
+                extra.url
+            ),
+            Ok(Some(overlay)) => {
+                let protected = if extra.trusted {
+                    &no_protection
+                } else {
+                    &main_names
+                };
+                merge(overlay, &extra.url, protected);
+            }
+            Err(e) => eprintln!("Warning: skipping the mod registry {}: {}", extra.url, e),
+        }
+    }
+    if let Some(main_registry) = main_registry {
+        merge(main_registry, registry_url, &no_protection);
+    }
+    merged
+}
+
+/// Writes the available updates, newly released mods and subscription changes to the Atom feed at `path`
+///
+/// Entries of earlier runs are kept in the feed history, so that the feed does not lose them.
+async fn write_update_feed(
+    path: &std::path::Path,
+    mod_registry: &ModRegistry,
+    registry_cache: &RegistryCache,
+    updates: &[AvailableUpdateInfo],
+    changes: &[SubscriptionChange],
+) -> Result<(), Error> {
+    let entry = |kind: &str, name: &str, version: &str, title: String| {
+        let remote = mod_registry.get_mod_info(name);
+        FeedEntry::new(
+            kind,
+            name,
+            version,
+            title,
+            remote.map_or_else(timeutil::now, |mod_info| mod_info.updated_at),
+        )
+        .with_link(
+            remote
+                .map(|mod_info| format!("https://gamebanana.com/mods/{}", mod_info.gamebanana_id)),
+        )
+    };
+    let mut entries = Vec::new();
+    for update in updates {
+        entries.push(entry(
+            "update",
+            &update.name,
+            &update.available_version,
+            format!(
+                "{} can be updated ({} -> {})",
+                update.name, update.current_version, update.available_version
+            ),
+        ));
+    }
+    if let Some(previous_data) = registry_cache.load_previous()? {
+        let previous_registry = ModRegistry::from(previous_data).await?;
+        for mod_info in mod_registry.diff(&previous_registry).added {
+            entries.push(entry(
+                "release",
+                &mod_info.name,
+                &mod_info.version,
+                format!("New mod: {} (version {})", mod_info.name, mod_info.version),
+            ));
+        }
+    }
+    for change in changes {
+        let (kind, name, version) = match change {
+            SubscriptionChange::Released { name, version } => {
+                ("subscription-release", name, version.as_str())
+            }
+            SubscriptionChange::Updated { name, to, .. } => {
+                ("subscription-update", name, to.as_str())
+            }
+            SubscriptionChange::Reuploaded { name, version } => {
+                ("subscription-reupload", name, version.as_str())
+            }
+            SubscriptionChange::Removed { name } => ("subscription-removal", name, ""),
+        };
+        entries.push(entry(kind, name, version, change.to_string()));
+    }
+    let mut history = FeedHistory::load(&fileutil::get_data_directory()?)?;
+    history.merge(entries);
+    history.save()?;
+    std::fs::write(
+        path,
+        feed::atom("Celeste mod updates", history.entries(), timeutil::now()),
+    )?;
+    Ok(())
+}
+
+/// Fetches the registry and announces the updates not announced yet to the webhook, for `watch`
+async fn announce_updates(
+    downloader: &ModDownloader,
+    url: &str,
+    config: &Config,
+    mods_dirs: &[std::path::PathBuf],
+    cache_dir: &std::path::Path,
+    data_dir: &std::path::Path,
+) -> Result<(), Error> {
+    let mut registry_cache = RegistryCache::load(cache_dir);
+    let data = registry_cache.fetch(downloader).await?;
+    let mod_registry = merged_registry(
+        data,
+        downloader.registry_url(),
+        config,
+        Some(downloader),
+        cache_dir,
+    )
+    .await?;
+    let mut last_check = LastCheck::load(cache_dir);
+    let jobs = config.jobs.unwrap_or(DEFAULT_JOBS);
+    let source = RegistrySource::new(&mod_registry, downloader.clone());
+    let update_check = check_updates(mods_dirs, &source, jobs, &mut last_check)?;
+    if let Err(e) = last_check.save() {
+        warn!("Failed to save the state of this run: {}", e);
+    }
+    let updates: Vec<_> = update_check.updates.iter().collect();
+    webhook::notify_updates_found(downloader, url, config.webhook_format, data_dir, &updates).await
+}
+
+/// Warns about the archives about to be removed or disabled whose maps have progress in the save
+/// files, unless another archive of `remaining` provides them, and asks whether to go on
+///
+/// Everest no longer loads these maps, so the strawberries and the session in them are lost until
+/// the archives are installed again. Outside a terminal, the answer is no.
+fn confirm_progress_loss(
+    mods_dir: &std::path::Path,
+    removed: &[std::path::PathBuf],
+    remaining: &[std::path::PathBuf],
+) -> Result<bool, Error> {
+    let save_files = SaveFiles::load(mods_dir);
+    let mut in_saves = Vec::new();
+    for archive in removed {
+        match save_files.progress_in(archive) {
+            Ok(maps) if !maps.is_empty() => in_saves.push((archive, maps)),
+            Ok(_) => {}
+            Err(e) => warn!("Failed to list the maps of {}: {}", archive.display(), e),
+        }
+    }
+    if in_saves.is_empty() {
+        return Ok(true);
+    }
+    // Maps which another archive provides keep their progress, e.g. with identical copies
+    let mut provided = HashSet::new();
+    for archive in remaining {
+        match saves::map_sids(archive) {
+            Ok(sids) => provided.extend(sids),
+            Err(e) => warn!("Failed to list the maps of {}: {}", archive.display(), e),
+        }
+    }
+    let lost: Vec<_> = in_saves
+        .into_iter()
+        .map(|(archive, maps)| {
+            let maps: Vec<_> = maps
+                .iter()
+                .filter(|map| !provided.contains(map.sid))
+                .map(MapInSave::describe)
+                .collect();
+            (archive, maps)
+        })
+        .filter(|(_, maps)| !maps.is_empty())
+        .collect();
+    if lost.is_empty() {
+        return Ok(true);
+    }
+    println!(
+        "Warning: {} archive(s) have maps with progress in your save files, which Everest will no longer load:",
+        lost.len()
+    );
+    for (archive, maps) in &lost {
+        println!(
+            "{}",
+            archive.file_name().unwrap_or_default().to_string_lossy()
+        );
+        for map in maps {
+            println!(" - {}", map);
+        }
+    }
+    Ok(prompt::is_interactive() && prompt::confirm("Go on anyway? [y/N]: ")?)
+}
+
+/// Captures the current state of the mods directory, hashing its archives on the configured jobs
+fn capture_snapshot(
+    mods_dir: &std::path::Path,
+    data_dir: &std::path::Path,
+    config: &Config,
+) -> Result<Snapshot, Error> {
+    let installed_mods = list_installed_mods(mods_dir)?;
+    let installed_index = InstalledIndex::load(data_dir)?;
+    Snapshot::capture(
+        mods_dir,
+        &installed_mods,
+        &installed_index,
+        &GameVersions::detect(mods_dir),
+        config.jobs.unwrap_or(DEFAULT_JOBS),
+    )
+}
+
+/// Merges the registry of unlisted mods maintained by the user, it takes precedence over the others
+fn merge_local_registry(mod_registry: &mut ModRegistry) -> Result<(), Error> {
+    let path = fileutil::get_local_registry_path()?;
+    if let Some(local) = ModRegistry::load_local(&path)? {
+        let count = local.entries.len();
+        let report = mod_registry.merge(local, &HashSet::new());
+        info!(
+            "Merged {} mods from {}, replacing {:?}",
+            count,
+            path.display(),
+            report.replaced
+        );
+    }
+    Ok(())
+}
+
+/// Warning that the copy of the registry used offline is older than the configured threshold
+fn stale_registry_warning(
+    registry_cache: &RegistryCache,
+    registry_url: &str,
+    config: &Config,
+) -> Option<String> {
+    let days = config
+        .stale_registry_days
+        .unwrap_or(DEFAULT_STALE_REGISTRY_DAYS);
+    let age_days = registry_cache.age(registry_url)?.as_secs() / (24 * 60 * 60);
+    (days > 0 && age_days >= days).then(|| {
+        format!(
+            "the cached mod registry was last checked {} days ago, update information may be outdated; \
+            run `outdated` with network access to refresh it",
+            age_days
+        )
+    })
+}
+
+/// Prints updates under a heading, grouped by GameBanana category, nothing if there are none
+fn print_updates<U: Borrow<AvailableUpdateInfo>>(
+    heading: &str,
+    updates: &[U],
+    search_database: &SearchDatabase,
+) {
+    if updates.is_empty() {
+        return;
+    }
+    println!("{} ({}):", heading, updates.len());
+    for (category, updates) in group_by_category(updates, search_database) {
+        println!("\n{} ({}):", category, updates.len());
+        for update_info in updates {
+            println!("\n{}", update_info.name);
+            println!(
+                " - Version: {}",
+                format_version_change(&update_info.current_version, &update_info.available_version)
+            );
+        }
+    }
+    println!();
+}
+
+/// Groups updates by GameBanana category, the largest groups first and uncategorized mods last
+fn group_by_category<'a, U: Borrow<AvailableUpdateInfo>>(
+    updates: &'a [U],
+    search_database: &'a SearchDatabase,
+) -> Vec<(&'a str, Vec<&'a AvailableUpdateInfo>)> {
+    let mut by_category: BTreeMap<Option<&str>, Vec<&AvailableUpdateInfo>> = BTreeMap::new();
+    for update in updates {
+        let update = update.borrow();
+        let category = search_database.category_of(&update.gamebanana_type, update.gamebanana_id);
+        by_category.entry(category).or_default().push(update);
+    }
+    let mut groups: Vec<_> = by_category.into_iter().collect();
+    groups.sort_by_key(|(category, updates)| (category.is_none(), Reverse(updates.len())));
+    groups
+        .into_iter()
+        .map(|(category, updates)| (category.unwrap_or("Uncategorized"), updates))
+        .collect()
+}
+
+/// Formats an update like `1.4.2 → 1.5.0`, coloring the components which changed, and flags
+/// downgrades and archives re-uploaded without a version change
+fn format_version_change(current: &str, available: &str) -> String {
+    let highlight = |changed: usize, paint: fn(&str) -> String| {
+        let components: Vec<&str> = available.split('.').collect();
+        let (same, bumped) = components.split_at(changed.min(components.len()));
+        let mut same = same.join(".");
+        if !same.is_empty() && !bumped.is_empty() {
+            same.push('.');
+        }
+        format!("{}{}", same, paint(&bumped.join(".")))
+    };
+    match VersionChange::between(current, available) {
+        VersionChange::Upgrade(changed) => {
+            format!("{} → {}", current, highlight(changed, color::green))
+        }
+        VersionChange::Downgrade(changed) => format!(
+            "{} → {} {}",
+            current,
+            highlight(changed, color::red),
+            color::red("(downgrade)")
+        ),
+        VersionChange::Reupload => format!(
+            "{} {}",
+            current,
+            color::yellow("(same version, new archive)")
+        ),
+        VersionChange::Unknown => format!("{} → {}", current, color::yellow(available)),
+    }
+}
+
+/// Prints the details of a registry entry listed by `search` or `browse`, below its name
+fn print_search_result(mod_info: &RemoteModInfo, search_database: &SearchDatabase) {
+    println!(
+        " - Updated at: {}",
+        timeutil::format_date(mod_info.updated_at)
+    );
+    println!(" - Size: {}", HumanBytes(mod_info.file_size));
+    if let Some(entry) = search_database.get(mod_info) {
+        println!(" - Popularity: {}", entry.stats_line());
+    }
+    println!(
+        " - Page: https://gamebanana.com/mods/{}",
+        mod_info.gamebanana_id
+    );
+    println!(" - Download: {}", mod_info.download_url);
 }
@@ -0,0 +1,133 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use tracing::info;
+
+use crate::{constant::MERGE_SKIPPED_FILE, error::Error, setup_diff::Setup};
+
+/// Mods never offered again by `merge`, stored as `merge_skipped.yaml` in the data directory
+#[derive(Debug)]
+pub struct SkippedMods {
+    path: PathBuf,
+    names: BTreeSet<String>,
+}
+
+/// What to do with a mod of the merged setup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeChoice {
+    Install,
+    Skip,
+    AlwaysSkip,
+    /// Stop asking, the mods chosen so far are installed
+    Quit,
+}
+
+impl MergeChoice {
+    /// Parses an answer to the merge prompt, an empty answer skips the mod
+    pub fn parse(answer: &str) -> Option<Self> {
+        match answer.to_lowercase().as_str() {
+            "i" | "install" | "y" | "yes" => Some(Self::Install),
+            "" | "s" | "skip" | "n" | "no" => Some(Self::Skip),
+            "a" | "always" => Some(Self::AlwaysSkip),
+            "q" | "quit" => Some(Self::Quit),
+            _ => None,
+        }
+    }
+}
+
+impl SkippedMods {
+    pub fn load(data_dir: &Path) -> Result<Self, Error> {
+        let path = data_dir.join(MERGE_SKIPPED_FILE);
+        let names = if path.exists() {
+            serde_yaml_ng::from_slice(&fs::read(&path)?)?
+        } else {
+            BTreeSet::new()
+        };
+        Ok(Self { path, names })
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        info!("Writing {}", self.path.display());
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_yaml_ng::to_string(&self.names)?)?;
+        Ok(())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(name)
+    }
+
+    /// Returns whether the mod was not skipped yet
+    pub fn add(&mut self, name: &str) -> bool {
+        self.names.insert(name.to_string())
+    }
+
+    /// Returns whether the mod was skipped
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.names.remove(name)
+    }
+}
+
+/// Name of a mod of a setup and its version, if the setup pins it
+pub type SetupMod<'a> = (&'a str, Option<&'a str>);
+
+/// Mods of a setup which are not installed, with their version in the setup, split into the
+/// mods to offer and the mods always skipped
+pub fn missing_mods<'a>(
+    setup: &'a Setup,
+    installed_names: &HashSet<&str>,
+    skipped: &SkippedMods,
+) -> (Vec<SetupMod<'a>>, Vec<SetupMod<'a>>) {
+    let mut offered = Vec::new();
+    let mut always_skipped = Vec::new();
+    for (name, version) in &setup.mods {
+        if installed_names.contains(name.as_str()) {
+            continue;
+        }
+        let entry = (name.as_str(), version.as_deref());
+        if skipped.contains(name) {
+            always_skipped.push(entry);
+        } else {
+            offered.push(entry);
+        }
+    }
+    (offered, always_skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_missing_mods_leave_out_installed_and_skipped() {
+        let dir = TempDir::new().unwrap();
+        let mut skipped = SkippedMods::load(dir.path()).unwrap();
+        skipped.add("CelesteTAS");
+        skipped.save().unwrap();
+        let skipped = SkippedMods::load(dir.path()).unwrap();
+        let setup = Setup {
+            mods: [
+                ("CelesteTAS", Some("3.40.0")),
+                ("CommunalHelper", Some("1.20.0")),
+                ("SpeedrunTool", None),
+            ]
+            .into_iter()
+            .map(|(name, version)| (name.to_string(), version.map(String::from)))
+            .collect(),
+        };
+
+        let (offered, always_skipped) =
+            missing_mods(&setup, &HashSet::from(["SpeedrunTool"]), &skipped);
+
+        assert_eq!(offered, vec![("CommunalHelper", Some("1.20.0"))]);
+        assert_eq!(always_skipped, vec![("CelesteTAS", Some("3.40.0"))]);
+        assert_eq!(MergeChoice::parse(""), Some(MergeChoice::Skip));
+        assert_eq!(MergeChoice::parse("A"), Some(MergeChoice::AlwaysSkip));
+    }
+}
@@ -0,0 +1,250 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Cursor,
+    path::Path,
+    time::Duration,
+};
+
+use serde::Deserialize;
+use tracing::{info, warn};
+use zip::ZipArchive;
+
+use crate::{
+    blacklist::Blacklist,
+    cache,
+    constant::{MOD_FILES_DATABASE_CACHE_FILE, MOD_FILES_DATABASE_CACHE_TTL},
+    download::ModDownloader,
+    error::Error,
+    installed_mods::LocalModInfo,
+    mod_registry::{ModRegistry, RemoteModInfo},
+};
+
+/// What a GameBanana file contains, as listed in the `<item type>/<item id>/<file id>.yaml` entries
+/// of `mod_files_database.zip`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ModFile {
+    /// Names declared by the `everest.yaml` of the archive, a file can provide several mods
+    #[serde(rename = "Mods", default)]
+    pub mods: Vec<String>,
+}
+
+/// Mod files database published by the Everest update checker server, keyed by GameBanana file id
+///
+/// The registry is keyed by the first mod name of each archive, the database tells the other names
+/// an archive provides, which dependents may reference.
+#[derive(Debug, Default)]
+pub struct ModFilesDatabase {
+    files: HashMap<u32, ModFile>,
+}
+
+/// An enabled archive providing a mod which another enabled archive provides as well
+#[derive(Debug)]
+pub struct ProvidedTwice<'a> {
+    pub name: &'a str,
+    /// Archive whose registry file declares the mod among others
+    pub provider: &'a LocalModInfo,
+    pub other: &'a LocalModInfo,
+}
+
+impl ModFilesDatabase {
+    /// Loads the database from the cache, fetching a fresh copy when it is outdated
+    pub async fn load(downloader: &ModDownloader, cache_dir: &Path) -> Result<Self, Error> {
+        let cache_path = cache_dir.join(MOD_FILES_DATABASE_CACHE_FILE);
+        let data = cache::fetch_cached(&cache_path, MOD_FILES_DATABASE_CACHE_TTL, || {
+            downloader.fetch_mod_files_database()
+        })
+        .await?;
+        Self::parse(&data)
+    }
+
+    /// Loads the database, names only declared inside archives are simply not resolved when it is unavailable
+    pub async fn load_or_default(downloader: &ModDownloader, cache_dir: &Path) -> Self {
+        Self::load(downloader, cache_dir).await.unwrap_or_else(|e| {
+            warn!("Could not load the mod files database: {}", e);
+            Self::default()
+        })
+    }
+
+    /// Loads the cached copy whatever its age, for offline commands
+    pub fn load_cached(cache_dir: &Path) -> Self {
+        let cache_path = cache_dir.join(MOD_FILES_DATABASE_CACHE_FILE);
+        match cache::read_fresh(&cache_path, Duration::MAX) {
+            Ok(Some(data)) => Self::parse(&data).unwrap_or_else(|e| {
+                warn!("Could not parse the cached mod files database: {}", e);
+                Self::default()
+            }),
+            _ => Self::default(),
+        }
+    }
+
+    /// Reads the file entries of the database archive, skipping its other entries such as `list.yaml`
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        info!("Parsing mod files database");
+        let mut archive = ZipArchive::new(Cursor::new(data))?;
+        let mut files = HashMap::new();
+        for index in 0..archive.len() {
+            let entry = archive.by_index(index)?;
+            let Some(file_id) = entry
+                .name()
+                .strip_suffix(".yaml")
+                .and_then(|name| name.rsplit('/').next())
+                .and_then(|file_id| file_id.parse().ok())
+            else {
+                continue;
+            };
+            files.insert(file_id, serde_yaml_ng::from_reader(entry)?);
+        }
+        Ok(Self { files })
+    }
+
+    /// Contents of the file a registry entry is downloaded from
+    pub fn get(&self, mod_info: &RemoteModInfo) -> Option<&ModFile> {
+        self.files.get(&file_id(&mod_info.download_url)?)
+    }
+
+    /// Maps the mod names which are not registry keys to the registry key of the archive providing them
+    pub fn aliases(&self, registry: &ModRegistry) -> HashMap<String, String> {
+        let mut aliases = HashMap::new();
+        for mod_info in registry.entries.values() {
+            let Some(file) = self.get(mod_info) else {
+                continue;
+            };
+            for name in &file.mods {
+                if !registry.entries.contains_key(name) {
+                    aliases
+                        .entry(name.clone())
+                        .or_insert_with(|| mod_info.name.clone());
+                }
+            }
+        }
+        aliases
+    }
+
+    /// Finds the mods provided by two enabled archives, one of which declares several mods
+    ///
+    /// Only the first mod of an `everest.yaml` is read locally, so these are not reported as duplicates
+    /// by name although Everest loads both.
+    pub fn find_provided_twice<'a>(
+        &self,
+        installed_mods: &'a [LocalModInfo],
+        blacklist: &Blacklist,
+        registry: &ModRegistry,
+    ) -> Vec<ProvidedTwice<'a>> {
+        let enabled: Vec<_> = installed_mods
+            .iter()
+            .filter(|mod_info| !blacklist.contains(&mod_info.archive_file_name()))
+            .collect();
+        let by_name: BTreeMap<&str, &LocalModInfo> = enabled
+            .iter()
+            .map(|mod_info| (mod_info.manifest.name.as_str(), *mod_info))
+            .collect();
+
+        let mut found = Vec::new();
+        for provider in &enabled {
+            let Some(file) = registry
+                .get_mod_info(&provider.manifest.name)
+                .and_then(|mod_info| self.get(mod_info))
+            else {
+                continue;
+            };
+            for name in file
+                .mods
+                .iter()
+                .filter(|name| **name != provider.manifest.name)
+            {
+                if let Some((name, other)) = by_name.get_key_value(name.as_str())
+                    && other.archive_path != provider.archive_path
+                {
+                    found.push(ProvidedTwice {
+                        name,
+                        provider,
+                        other,
+                    });
+                }
+            }
+        }
+        found
+    }
+}
+
+/// GameBanana file id of a download URL, e.g. `1298450` for `https://gamebanana.com/mmdl/1298450`
+pub fn file_id(download_url: &str) -> Option<u32> {
+    let (_, id) = download_url.trim_end_matches('/').rsplit_once("/mmdl/")?;
+    id.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constant::BLACKLIST_FILE, installed_mods::ModManifest};
+    use std::{
+        fs,
+        io::{Cursor, Write},
+        path::PathBuf,
+    };
+    use tempfile::TempDir;
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    fn database_archive(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn local_mod(name: &str, file_name: &str) -> LocalModInfo {
+        let manifest = ModManifest::parse_mod_manifest_from_yaml(
+            format!("- Name: {}\n  Version: 1.0.0\n", name).as_bytes(),
+        )
+        .unwrap();
+        LocalModInfo::new(PathBuf::from(file_name), manifest)
+    }
+
+    #[test]
+    fn test_aliases_and_mods_provided_twice() {
+        let mut registry: ModRegistry = serde_yaml_ng::from_str(
+            "StrawberryJam2021:\n  Version: 1.0.9\n  Size: 1\n  LastUpdate: 0\n  \
+            URL: https://gamebanana.com/mmdl/1298450\n  xxHash: []\n  GameBananaType: Mod\n  GameBananaId: 1\n",
+        )
+        .unwrap();
+        registry.entries.get_mut("StrawberryJam2021").unwrap().name =
+            String::from("StrawberryJam2021");
+        let database = ModFilesDatabase::parse(&database_archive(&[
+            ("list.yaml", "- Mod/1\n"),
+            (
+                "Mod/1/info.yaml",
+                "Name: Strawberry Jam\nFiles: [1298450]\n",
+            ),
+            (
+                "Mod/1/1298450.yaml",
+                "Mods: [StrawberryJam2021, StrawberryJam2021Helper]\nSize: 1\n",
+            ),
+        ]))
+        .unwrap();
+
+        assert_eq!(
+            database.aliases(&registry),
+            HashMap::from([(
+                String::from("StrawberryJam2021Helper"),
+                String::from("StrawberryJam2021")
+            )])
+        );
+
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(BLACKLIST_FILE), "").unwrap();
+        let blacklist = Blacklist::load(dir.path()).unwrap();
+        let installed = vec![
+            local_mod("StrawberryJam2021", "StrawberryJam2021.zip"),
+            local_mod("StrawberryJam2021Helper", "SJHelper.zip"),
+        ];
+        let found = database.find_provided_twice(&installed, &blacklist, &registry);
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "StrawberryJam2021Helper");
+        assert_eq!(found[0].other.archive_file_name(), "SJHelper.zip");
+    }
+}
@@ -1,8 +1,24 @@
-use std::collections::HashMap;
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap, HashSet},
+    fs,
+    io::Write,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use tracing::{info, info_span};
+
+use crate::{
+    checksum::{self, Digests, Hasher},
+    constant::{GAMEBANANA_DOWNLOAD_URL, GAMEBANANA_MIRROR_URL},
+    error::Error,
+    fileutil::read_manifest_file_from_zip,
+    installed_mods::ModManifest,
+    progress,
+};
 
 /// Each entry in `everest_update.yaml` containing information about a mod
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -22,9 +38,20 @@ pub struct RemoteModInfo {
     /// Download link
     #[serde(rename = "URL")]
     pub download_url: String,
-    /// Checksums
-    #[serde(rename = "xxHash")]
+    /// Download link on another server, overriding the GameBanana mirror for custom registries
+    #[serde(rename = "MirrorURL", default, skip_serializing_if = "Option::is_none")]
+    pub mirror_url: Option<String>,
+    /// Checksums, xxHash64 or MD5 for registries which provide those instead
+    #[serde(rename = "xxHash", alias = "MD5")]
     pub checksums: Vec<String>,
+    /// Optional SHA-256 checksum, provided by custom or self-hosted registries
+    #[serde(
+        rename = "SHA256",
+        alias = "sha256",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sha256: Option<String>,
     /// Category for a mod
     #[serde(rename = "GameBananaType")]
     pub gamebanana_type: String,
@@ -34,23 +61,60 @@ pub struct RemoteModInfo {
 }
 
 impl RemoteModInfo {
-    /// Checks if the provided hash matches any of the expected checksums.
+    /// Checks if the provided digests match any of the expected checksums.
     ///
     /// # Arguments
     ///
-    /// * `computed_hash` - The hash to check against the mod's checksums.
+    /// * `digests` - The digests of a file, computed with `checksum::hash_file`.
     ///
     /// # Returns
     ///
-    /// Returns `true` if the hash matches any of the checksums, otherwise `false`.
-    pub fn has_matching_hash(&self, computed_hash: &str) -> bool {
-        // Check if the computed hash exists in the list of expected checksums
-        self.checksums.iter().filter(|checksum| *checksum == computed_hash).count() > 0
+    /// Returns `true` if the digests match any of the checksums, otherwise `false`.
+    pub fn has_matching_hash(&self, digests: &Digests) -> bool {
+        digests.matches(&self.checksums)
+    }
+
+    /// Download link used when downloads from `URL` are corrupted
+    ///
+    /// The `MirrorURL` of the entry when it has one, otherwise the GameBanana mirror of the file,
+    /// which Everest also falls back to. `None` for archives hosted elsewhere.
+    pub fn mirror_url(&self) -> Option<String> {
+        if let Some(mirror_url) = &self.mirror_url {
+            return Some(mirror_url.clone());
+        }
+        let file_id: u64 = self
+            .download_url
+            .strip_prefix(GAMEBANANA_DOWNLOAD_URL)?
+            .parse()
+            .ok()?;
+        Some(format!("{GAMEBANANA_MIRROR_URL}{file_id}.zip"))
+    }
+
+    /// Entry for an archive downloaded from `url`, named and versioned after its manifest
+    pub fn for_archive(archive: &Path, url: &str) -> Result<Self, Error> {
+        let buffer = read_manifest_file_from_zip(archive)?
+            .ok_or_else(|| Error::MissingManifest(archive.to_path_buf()))?;
+        let manifest = ModManifest::parse_mod_manifest_from_yaml(&buffer)?;
+        let digests = checksum::hash_file_with(archive, Hasher::for_checksums(&[]).with_sha256())?;
+        Ok(Self {
+            name: manifest.name,
+            version: manifest.version,
+            file_size: fs::metadata(archive)?.len(),
+            updated_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            download_url: url.to_string(),
+            mirror_url: None,
+            checksums: vec![digests.xxh64],
+            sha256: digests.sha256,
+            gamebanana_type: String::from("Mod"),
+            gamebanana_id: 0,
+        })
     }
 }
 
 /// Mod Registry: represents the complete `everest_update.yaml` containing all available remote mods
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct ModRegistry {
     #[serde(flatten)]
     pub entries: HashMap<String, RemoteModInfo>,
@@ -58,25 +122,95 @@ pub struct ModRegistry {
 
 impl ModRegistry {
     /// Initialize ModRegistry instance from raw data
-    pub async fn from(data: Bytes) -> Result<Self, serde_yaml_ng::Error> {
+    pub async fn from(data: Bytes) -> Result<Self, Error> {
         info!("Parsing remote mod registry data");
-        let mut mod_registry: Self = serde_yaml_ng::from_slice(&data)?;
+        let _span = info_span!("registry parse").entered();
+        let spinner = progress::spinner("Parsing mod registry");
+        let parsed = serde_yaml_ng::from_slice(&data);
+        spinner.finish_and_clear();
+        let mut mod_registry: Self = parsed.map_err(Error::InvalidRegistry)?;
+        mod_registry.set_names();
+        Ok(mod_registry)
+    }
 
-        // Set the name field for each ModInfo
-        for (key, mod_info) in mod_registry.entries.iter_mut() {
-            mod_info.name = key.clone();
+    /// Loads the registry of unlisted mods maintained by the user, `None` if there is none
+    pub fn load_local(path: &Path) -> Result<Option<Self>, Error> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        info!("Loading local mod registry from {}", path.display());
+        let content = fs::read(path)?;
+        // An empty file is a valid (empty) registry
+        if content.iter().all(u8::is_ascii_whitespace) {
+            return Ok(None);
         }
+        let mut mod_registry: Self =
+            serde_yaml_ng::from_slice(&content).map_err(|source| Error::InvalidLocalRegistry {
+                path: path.to_path_buf(),
+                source,
+            })?;
+        mod_registry.set_names();
+        Ok(Some(mod_registry))
+    }
 
-        Ok(mod_registry)
+    /// Adds an entry to the local registry at `path`, returns whether it replaced an entry of the
+    /// same name
+    ///
+    /// New entries are appended to keep the comments of the file, replacing one rewrites the file.
+    pub fn add_local(path: &Path, mod_info: RemoteModInfo) -> Result<bool, Error> {
+        let mut local = Self::load_local(path)?.unwrap_or_default();
+        let name = mod_info.name.clone();
+        let entry = BTreeMap::from([(name.clone(), mod_info)]);
+        if local.entries.contains_key(&name) {
+            local.entries.extend(entry);
+            let sorted: BTreeMap<_, _> = local.entries.iter().collect();
+            fs::write(path, serde_yaml_ng::to_string(&sorted)?)?;
+            return Ok(true);
+        }
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let separator = match fs::read(path) {
+            Ok(content) if !content.is_empty() && !content.ends_with(b"\n") => "\n",
+            _ => "",
+        };
+        let mut file = fs::File::options().create(true).append(true).open(path)?;
+        write!(file, "{}{}", separator, serde_yaml_ng::to_string(&entry)?)?;
+        Ok(false)
     }
 
-    /// Search for mods
-    pub fn search(&self, query: &str) -> Vec<&RemoteModInfo> {
+    /// Set the name field of each entry from its key
+    fn set_names(&mut self) {
+        for (key, mod_info) in self.entries.iter_mut() {
+            mod_info.name = key.clone();
+        }
+    }
+
+    /// Search for mods, exact matches first, then names starting with the query
+    ///
+    /// Matches of the same kind are ordered by `popularity`, highest first, then by name.
+    pub fn search<K: Ord>(
+        &self,
+        query: &str,
+        popularity: impl Fn(&RemoteModInfo) -> K,
+    ) -> Vec<&RemoteModInfo> {
         info!("Searching remote mod registry for mod: {}", query);
-        self.entries
+        let mut results: Vec<_> = self
+            .entries
             .values()
-            .filter(|mod_info| mod_info.name.to_lowercase().contains(&query.to_lowercase()))
-            .collect()
+            .filter_map(|mod_info| {
+                match_relevance(&mod_info.name, query).map(|relevance| (relevance, mod_info))
+            })
+            .collect();
+        results.sort_by_cached_key(|(relevance, mod_info)| {
+            (
+                *relevance,
+                Reverse(popularity(mod_info)),
+                mod_info.name.clone(),
+            )
+        });
+        results.into_iter().map(|(_, mod_info)| mod_info).collect()
     }
 
     /// Get mod information
@@ -84,4 +218,299 @@ impl ModRegistry {
         info!("Getting remote mod information for mod: {}", name);
         self.entries.get(name)
     }
+
+    /// Adds the entries of another registry, replacing the entries of the same name unless they
+    /// are `protected`
+    pub fn merge(&mut self, overlay: ModRegistry, protected: &HashSet<String>) -> MergeReport {
+        let mut report = MergeReport::default();
+        for (name, mod_info) in overlay.entries {
+            if !self.entries.contains_key(&name) {
+                self.entries.insert(name, mod_info);
+            } else if protected.contains(&name) {
+                report.kept.push(name);
+            } else {
+                self.entries.insert(name.clone(), mod_info);
+                report.replaced.push(name);
+            }
+        }
+        report.replaced.sort();
+        report.kept.sort();
+        report
+    }
+
+    /// Entries downloaded from a GameBanana submission, sorted by name
+    ///
+    /// A submission can provide several mods, e.g. a map and its audio.
+    pub fn find_by_gamebanana(&self, item_type: &str, item_id: u32) -> Vec<&RemoteModInfo> {
+        let mut entries: Vec<_> = self
+            .entries
+            .values()
+            .filter(|mod_info| {
+                mod_info.gamebanana_id == item_id && mod_info.gamebanana_type == item_type
+            })
+            .collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        entries
+    }
+
+    /// Reverse map from checksums to entries, to recognize archives whatever their file or mod name
+    pub fn hash_index(&self) -> HashIndex<'_> {
+        let mut by_checksum = HashMap::new();
+        for mod_info in self.entries.values() {
+            for checksum in &mod_info.checksums {
+                by_checksum.insert(checksum.to_ascii_lowercase(), mod_info);
+            }
+        }
+        HashIndex { by_checksum }
+    }
+
+    /// Compare this registry against an older snapshot, results are sorted by mod name
+    pub fn diff<'a>(&'a self, previous: &'a ModRegistry) -> RegistryDiff<'a> {
+        let mut added: Vec<_> = self
+            .entries
+            .values()
+            .filter(|mod_info| !previous.entries.contains_key(&mod_info.name))
+            .collect();
+        let mut removed: Vec<_> = previous
+            .entries
+            .values()
+            .filter(|mod_info| !self.entries.contains_key(&mod_info.name))
+            .collect();
+        let mut updated: Vec<_> = self
+            .entries
+            .values()
+            .filter_map(|current| {
+                previous
+                    .entries
+                    .get(&current.name)
+                    .filter(|old| old.version != current.version)
+                    .map(|old| (old, current))
+            })
+            .collect();
+
+        added.sort_by(|a, b| a.name.cmp(&b.name));
+        removed.sort_by(|a, b| a.name.cmp(&b.name));
+        updated.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+
+        RegistryDiff {
+            added,
+            removed,
+            updated,
+        }
+    }
+}
+
+/// Conflicts over mod names met while merging a registry, sorted by name
+#[derive(Debug, Default)]
+pub struct MergeReport {
+    /// Entries replaced by those of the merged registry
+    pub replaced: Vec<String>,
+    /// Protected entries kept instead of those of the merged registry
+    pub kept: Vec<String>,
+}
+
+/// Registry entries by checksum, see `ModRegistry::hash_index`
+#[derive(Debug, Clone)]
+pub struct HashIndex<'a> {
+    by_checksum: HashMap<String, &'a RemoteModInfo>,
+}
+
+impl<'a> HashIndex<'a> {
+    /// Finds the entry of an archive from its digests
+    pub fn find(&self, digests: &Digests) -> Option<&'a RemoteModInfo> {
+        std::iter::once(&digests.xxh64)
+            .chain(&digests.md5)
+            .find_map(|digest| self.by_checksum.get(&digest.to_ascii_lowercase()))
+            .copied()
+    }
+}
+
+/// Changes between two snapshots of the mod registry
+#[derive(Debug)]
+pub struct RegistryDiff<'a> {
+    /// Mods which only exist in the newer snapshot
+    pub added: Vec<&'a RemoteModInfo>,
+    /// Mods which only exist in the older snapshot
+    pub removed: Vec<&'a RemoteModInfo>,
+    /// Mods whose version changed, as pairs of (old, new)
+    pub updated: Vec<(&'a RemoteModInfo, &'a RemoteModInfo)>,
+}
+
+impl RegistryDiff<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.updated.is_empty()
+    }
+}
+
+/// Ranks how well a name matches a search query, ignoring case: 0 for an exact match,
+/// 1 for a name starting with the query, 2 for a name containing it, `None` otherwise
+pub fn match_relevance(name: &str, query: &str) -> Option<u8> {
+    let name = name.to_lowercase();
+    let query = query.to_lowercase();
+    if name == query {
+        Some(0)
+    } else if name.starts_with(&query) {
+        Some(1)
+    } else if name.contains(&query) {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote_mod(name: &str, version: &str) -> RemoteModInfo {
+        RemoteModInfo {
+            name: name.to_string(),
+            version: version.to_string(),
+            file_size: 0,
+            updated_at: 0,
+            download_url: String::new(),
+            mirror_url: None,
+            checksums: Vec::new(),
+            sha256: None,
+            gamebanana_type: String::from("Mod"),
+            gamebanana_id: 0,
+        }
+    }
+
+    fn registry(mods: &[(&str, &str)]) -> ModRegistry {
+        ModRegistry {
+            entries: mods
+                .iter()
+                .map(|(name, version)| (name.to_string(), remote_mod(name, version)))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_diff_detects_added_removed_and_updated() {
+        let previous = registry(&[("Alpha", "1.0.0"), ("Beta", "1.0.0"), ("Gamma", "2.0.0")]);
+        let current = registry(&[("Alpha", "1.0.0"), ("Gamma", "2.1.0"), ("Delta", "0.1.0")]);
+
+        let diff = current.diff(&previous);
+
+        let added: Vec<_> = diff.added.iter().map(|m| m.name.as_str()).collect();
+        let removed: Vec<_> = diff.removed.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(added, vec!["Delta"]);
+        assert_eq!(removed, vec!["Beta"]);
+        assert_eq!(diff.updated.len(), 1);
+        assert_eq!(diff.updated[0].0.version, "2.0.0");
+        assert_eq!(diff.updated[0].1.version, "2.1.0");
+    }
+
+    #[test]
+    fn test_mirror_url_of_gamebanana_downloads() {
+        let mut mod_info = remote_mod("Alpha", "1.0.0");
+        assert_eq!(mod_info.mirror_url(), None);
+
+        mod_info.download_url = String::from("https://gamebanana.com/mmdl/1234567");
+        assert_eq!(
+            mod_info.mirror_url().as_deref(),
+            Some("https://banana-mirror-mods.celestemods.com/1234567.zip")
+        );
+
+        mod_info.mirror_url = Some(String::from("https://example.com/Alpha.zip"));
+        assert_eq!(
+            mod_info.mirror_url().as_deref(),
+            Some("https://example.com/Alpha.zip")
+        );
+    }
+
+    #[test]
+    fn test_merge_overrides_and_supplements() {
+        let mut merged = registry(&[("Alpha", "1.0.0"), ("Beta", "1.0.0")]);
+        let overlay = registry(&[("Beta", "1.1.0"), ("Removed", "0.3.0")]);
+
+        let report = merged.merge(overlay, &HashSet::new());
+
+        assert_eq!(report.replaced, vec!["Beta"]);
+        assert_eq!(merged.entries.len(), 3);
+        assert_eq!(merged.get_mod_info("Beta").unwrap().version, "1.1.0");
+        assert_eq!(merged.get_mod_info("Removed").unwrap().version, "0.3.0");
+
+        let untrusted = registry(&[("Alpha", "6.6.6"), ("Extra", "1.0.0")]);
+        let report = merged.merge(untrusted, &HashSet::from([String::from("Alpha")]));
+
+        assert_eq!(report.kept, vec!["Alpha"]);
+        assert_eq!(merged.get_mod_info("Alpha").unwrap().version, "1.0.0");
+        assert!(merged.get_mod_info("Extra").is_some());
+    }
+
+    #[test]
+    fn test_load_local_registry() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(
+            file.path(),
+            "PrivateBeta:\n  Version: 0.9.0\n  Size: 1024\n  LastUpdate: 0\n  \
+            URL: https://example.com/PrivateBeta.zip\n  xxHash: [0123456789abcdef]\n  \
+            GameBananaType: Mod\n  GameBananaId: 0\n",
+        )
+        .unwrap();
+
+        let local = ModRegistry::load_local(file.path()).unwrap().unwrap();
+
+        assert_eq!(
+            local.get_mod_info("PrivateBeta").unwrap().name,
+            "PrivateBeta"
+        );
+        fs::write(file.path(), "PrivateBeta: [").unwrap();
+        assert!(ModRegistry::load_local(file.path()).is_err());
+        assert!(
+            ModRegistry::load_local(Path::new("missing.yaml"))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_add_local_appends_then_replaces() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("local_registry.yaml");
+        fs::write(&path, "# Betas of the collab").unwrap();
+
+        assert!(!ModRegistry::add_local(&path, remote_mod("Alpha", "0.1.0")).unwrap());
+        assert!(!ModRegistry::add_local(&path, remote_mod("Beta", "0.1.0")).unwrap());
+        assert!(
+            fs::read_to_string(&path)
+                .unwrap()
+                .starts_with("# Betas of the collab\nAlpha:")
+        );
+        assert!(ModRegistry::add_local(&path, remote_mod("Alpha", "0.2.0")).unwrap());
+
+        let local = ModRegistry::load_local(&path).unwrap().unwrap();
+        assert_eq!(local.entries.len(), 2);
+        assert_eq!(local.get_mod_info("Alpha").unwrap().version, "0.2.0");
+    }
+
+    #[test]
+    fn test_search_orders_by_relevance_then_popularity() {
+        let registry = registry(&[
+            ("CollabUtils2", "1.0.0"),
+            ("Collab", "1.0.0"),
+            ("MyCollabLobby", "1.0.0"),
+            ("CollabLobbyUI", "1.0.0"),
+        ]);
+
+        let results = registry.search("collab", |mod_info| {
+            u64::from(mod_info.name == "CollabLobbyUI")
+        });
+
+        let names: Vec<_> = results.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["Collab", "CollabLobbyUI", "CollabUtils2", "MyCollabLobby"]
+        );
+    }
+
+    #[test]
+    fn test_diff_of_identical_registries_is_empty() {
+        let previous = registry(&[("Alpha", "1.0.0")]);
+        let current = registry(&[("Alpha", "1.0.0")]);
+
+        assert!(current.diff(&previous).is_empty());
+    }
 }
@@ -0,0 +1,211 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use tracing::info;
+
+use crate::{
+    archive_list::ArchiveList, blacklist::Blacklist, error::Error, favorites::Favorites, fileutil,
+    installed_index::InstalledIndex, installed_mods::LocalModInfo,
+    updater_blacklist::UpdaterBlacklist,
+};
+
+/// An archive to give its canonical name
+#[derive(Debug, PartialEq, Eq)]
+pub struct Rename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// Renames planned by `plan_renames`, and the archives left as they are with the reason
+#[derive(Debug, Default)]
+pub struct RenamePlan {
+    pub renames: Vec<Rename>,
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+/// Canonical file name of the archive of a mod, e.g. `SpringCollab2020.zip`
+///
+/// Characters which are not allowed in file names on Windows are replaced with `_`.
+pub fn canonical_file_name(mod_name: &str) -> String {
+    let stem: String = mod_name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    // Windows drops trailing dots and spaces
+    let stem = stem.trim_end_matches(['.', ' ']);
+    format!("{}.zip", if stem.is_empty() { "_" } else { stem })
+}
+
+/// Plans renaming each installed archive to the canonical name of its mod
+///
+/// Archives which would end up with the same name, or would replace another file, are skipped.
+pub fn plan_renames(installed_mods: &[LocalModInfo]) -> RenamePlan {
+    let mut by_target: BTreeMap<PathBuf, Vec<&LocalModInfo>> = BTreeMap::new();
+    for mod_info in installed_mods {
+        let target = mod_info
+            .archive_path
+            .with_file_name(canonical_file_name(&mod_info.manifest.name));
+        by_target.entry(target).or_default().push(mod_info);
+    }
+
+    let mut plan = RenamePlan::default();
+    for (target, archives) in by_target {
+        if let [mod_info] = archives[..] {
+            let from = &mod_info.archive_path;
+            if *from == target {
+                continue;
+            }
+            // On case-insensitive file systems, a change of case finds the archive itself
+            if target.exists() && !fileutil::is_same_file(from, &target) {
+                plan.skipped
+                    .push((from.clone(), format!("{} already exists", target.display())));
+            } else {
+                plan.renames.push(Rename {
+                    from: from.clone(),
+                    to: target,
+                });
+            }
+        } else {
+            for mod_info in archives.iter().filter(|m| m.archive_path != target) {
+                plan.skipped.push((
+                    mod_info.archive_path.clone(),
+                    format!(
+                        "{} is installed more than once, run `doctor` first",
+                        mod_info.manifest.name
+                    ),
+                ));
+            }
+        }
+    }
+    plan
+}
+
+/// Renames the archives, updating the lists of Everest and the installed index accordingly
+pub fn apply_renames(
+    mods_dir: &Path,
+    renames: &[Rename],
+    installed_index: &mut InstalledIndex,
+) -> Result<(), Error> {
+    let mut blacklist = Blacklist::load(mods_dir)?;
+    let mut updater_blacklist = UpdaterBlacklist::load(mods_dir)?;
+    let mut favorites = Favorites::load(mods_dir)?;
+    let mut lists: [&mut ArchiveList; 3] = [&mut blacklist, &mut updater_blacklist, &mut favorites];
+    let file_name = |path: &Path| {
+        path.file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    };
+
+    let mut modified = [false; 3];
+    let result = renames.iter().try_for_each(|Rename { from, to }| {
+        // Another program may have created the target since the renames were planned
+        if to.exists() && !fileutil::is_same_file(from, to) {
+            return Err(Error::ArchiveAlreadyExists(to.clone()));
+        }
+        info!("Renaming {} to {}", from.display(), to.display());
+        fileutil::retry_locked(from, || fs::rename(from, to))?;
+        for (list, modified) in lists.iter_mut().zip(&mut modified) {
+            *modified |= list.rename(&file_name(from), &file_name(to));
+        }
+        if let Some(entry) = installed_index.remove(from) {
+            installed_index.insert(to, entry);
+        }
+        Ok::<_, Error>(())
+    });
+    // Keep the lists in line with the archives renamed before a failure
+    for (list, modified) in lists.iter().zip(modified) {
+        if modified {
+            list.save()?;
+        }
+    }
+    installed_index.save()?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constant::BLACKLIST_FILE, installed_mods::ModManifest};
+    use tempfile::TempDir;
+
+    fn local_mod(dir: &Path, name: &str, file_name: &str) -> LocalModInfo {
+        let path = dir.join(file_name);
+        fs::write(&path, name).unwrap();
+        let manifest = ModManifest::parse_mod_manifest_from_yaml(
+            format!("- Name: {}\n  Version: 1.0.0\n", name).as_bytes(),
+        )
+        .unwrap();
+        LocalModInfo::new(path, manifest)
+    }
+
+    #[test]
+    fn test_canonical_file_name() {
+        assert_eq!(
+            canonical_file_name("SpringCollab2020"),
+            "SpringCollab2020.zip"
+        );
+        assert_eq!(
+            canonical_file_name("Mod: The Sequel?"),
+            "Mod_ The Sequel_.zip"
+        );
+        assert_eq!(canonical_file_name("Trailing."), "Trailing.zip");
+    }
+
+    #[test]
+    fn test_renames_skip_duplicates_and_update_blacklist() {
+        let dir = TempDir::new().unwrap();
+        let installed = vec![
+            local_mod(dir.path(), "SpeedrunTool", "1298450.zip"),
+            local_mod(dir.path(), "MaxHelpingHand", "mhh.zip"),
+            local_mod(dir.path(), "MaxHelpingHand", "mhh (1).zip"),
+            local_mod(dir.path(), "CollabUtils2", "CollabUtils2.zip"),
+        ];
+        fs::write(dir.path().join(BLACKLIST_FILE), "# Disabled\n1298450.zip\n").unwrap();
+
+        let plan = plan_renames(&installed);
+
+        assert_eq!(
+            plan.renames,
+            vec![Rename {
+                from: dir.path().join("1298450.zip"),
+                to: dir.path().join("SpeedrunTool.zip"),
+            }]
+        );
+        assert_eq!(plan.skipped.len(), 2);
+
+        let mut installed_index = InstalledIndex::load(dir.path()).unwrap();
+        apply_renames(dir.path(), &plan.renames, &mut installed_index).unwrap();
+
+        assert!(dir.path().join("SpeedrunTool.zip").exists());
+        assert_eq!(
+            fs::read_to_string(dir.path().join(BLACKLIST_FILE)).unwrap(),
+            "# Disabled\nSpeedrunTool.zip\n"
+        );
+    }
+
+    #[test]
+    fn test_renames_never_replace_another_archive() {
+        let dir = TempDir::new().unwrap();
+        let installed = vec![local_mod(dir.path(), "SpeedrunTool", "1298450.zip")];
+
+        let plan = plan_renames(&installed);
+        fs::write(dir.path().join("SpeedrunTool.zip"), "created meanwhile").unwrap();
+        let mut installed_index = InstalledIndex::load(dir.path()).unwrap();
+        let result = apply_renames(dir.path(), &plan.renames, &mut installed_index);
+
+        assert!(matches!(result, Err(Error::ArchiveAlreadyExists(_))));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("SpeedrunTool.zip")).unwrap(),
+            "created meanwhile"
+        );
+        assert!(dir.path().join("1298450.zip").exists());
+    }
+}
@@ -0,0 +1,95 @@
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::{
+    error::Error,
+    installed_mods::{AvailableUpdateInfo, UpdateCheck},
+    search_database::SearchDatabase,
+};
+
+/// A pending update as reported by `outdated --json`
+#[derive(Debug, Serialize)]
+pub struct OutdatedMod<'a> {
+    pub name: &'a str,
+    pub current_version: &'a str,
+    pub available_version: &'a str,
+    /// Whether the installed manifest declares a DLL, such updates may break other mods or saves
+    pub code_mod: bool,
+    /// GameBanana category, e.g. `Maps`, if known
+    pub category: Option<&'a str>,
+    pub path: &'a Path,
+}
+
+/// Pending updates of the installed mods, without installing anything
+#[derive(Debug, Serialize)]
+pub struct OutdatedReport<'a> {
+    pub updates: Vec<OutdatedMod<'a>>,
+    /// Updates of the mods excluded by `updaterblacklist.txt`, which `update` skips
+    pub pinned: Vec<OutdatedMod<'a>>,
+}
+
+impl<'a> OutdatedReport<'a> {
+    pub fn new(update_check: &'a UpdateCheck, search_database: &'a SearchDatabase) -> Self {
+        let entries = |updates: &'a [AvailableUpdateInfo]| {
+            updates
+                .iter()
+                .map(|update| OutdatedMod {
+                    name: &update.name,
+                    current_version: &update.current_version,
+                    available_version: &update.available_version,
+                    code_mod: update.is_code_mod(),
+                    category: search_database
+                        .category_of(&update.gamebanana_type, update.gamebanana_id),
+                    path: &update.existing_path,
+                })
+                .collect()
+        };
+        Self {
+            updates: entries(&update_check.updates),
+            pinned: entries(&update_check.excluded),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_outdated_report_json() {
+        let update_check = UpdateCheck {
+            updates: vec![AvailableUpdateInfo {
+                name: String::from("SpeedrunTool"),
+                current_version: String::from("3.20.0"),
+                available_version: String::from("3.21.0"),
+                size: 1024,
+                existing_path: PathBuf::from("Mods/SpeedrunTool.zip"),
+                gamebanana_type: String::from("Tool"),
+                gamebanana_id: 6597,
+                dll: Some(String::from("SpeedrunTool.dll")),
+            }],
+            excluded: Vec::new(),
+            unmanaged: Vec::new(),
+        };
+        let search_database = SearchDatabase::default();
+
+        let json: serde_json::Value = serde_json::from_str(
+            &OutdatedReport::new(&update_check, &search_database)
+                .to_json()
+                .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(json["updates"][0]["name"], "SpeedrunTool");
+        assert_eq!(json["updates"][0]["available_version"], "3.21.0");
+        assert_eq!(json["updates"][0]["code_mod"], true);
+        assert_eq!(json["updates"][0]["category"], "Tool");
+        assert_eq!(json["pinned"], serde_json::json!([]));
+    }
+}
@@ -0,0 +1,223 @@
+use std::{collections::HashMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    download::ModDownloader,
+    error::{Context, Error},
+    installed_mods::{Dependency, LocalModInfo},
+};
+
+/// A curated list of mods, e.g. a starter pack of beginner maps
+///
+/// ```yaml
+/// Name: Beginner Maps Starter
+/// Description: Maps for players who just finished the base game
+/// Mods:
+///   - Name: SpringCollab2020
+///     Version: 1.7.3
+///   - Name: CommunalHelper
+/// ```
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Pack {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(
+        rename = "Description",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub description: Option<String>,
+    #[serde(rename = "Mods")]
+    pub mods: Vec<PackMod>,
+}
+
+/// Packs shipped with the tool, installable with `pack install --preset <name>`
+pub const PRESETS: [(&str, &str); 2] = [
+    (
+        "essential-helpers",
+        include_str!("../presets/essential-helpers.yaml"),
+    ),
+    (
+        "quality-of-life",
+        include_str!("../presets/quality-of-life.yaml"),
+    ),
+];
+
+/// A mod of a pack, optionally pinned to a version
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PackMod {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Version", default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+/// A mod of a pack which is not installed as the pack expects
+#[derive(Debug, PartialEq, Eq)]
+pub enum PackIssue<'a> {
+    Missing(&'a str),
+    VersionMismatch {
+        name: &'a str,
+        pinned: &'a str,
+        installed: &'a str,
+    },
+}
+
+impl Pack {
+    /// Reads a pack file
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        info!("Reading pack {}", path.display());
+        Self::parse(&fs::read(path).in_file(path)?).in_file(path)
+    }
+
+    /// Reads a pack file, or downloads it when `source` is an HTTP(S) URL
+    pub async fn load_from(source: &str, downloader: &ModDownloader) -> Result<Self, Error> {
+        if is_url(source) {
+            Self::parse(&downloader.fetch_pack(source).await?)
+        } else {
+            Self::load(Path::new(source))
+        }
+    }
+
+    /// Reads a built-in preset
+    pub fn preset(name: &str) -> Result<Self, Error> {
+        let (_, data) = PRESETS
+            .iter()
+            .find(|(preset, _)| *preset == name)
+            .ok_or_else(|| Error::UnknownPreset(name.to_string()))?;
+        Self::parse(data.as_bytes())
+    }
+
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        Ok(serde_yaml_ng::from_slice(data)?)
+    }
+
+    /// Creates a pack from installed mods, pinned to their installed versions if `pin` is set
+    pub fn from_installed(
+        name: &str,
+        description: Option<&str>,
+        installed_mods: &[&LocalModInfo],
+        pin: bool,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.map(String::from),
+            mods: installed_mods
+                .iter()
+                .map(|mod_info| PackMod {
+                    name: mod_info.manifest.name.clone(),
+                    version: pin.then(|| mod_info.manifest.version.clone()),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn to_yaml(&self) -> Result<String, Error> {
+        Ok(serde_yaml_ng::to_string(self)?)
+    }
+
+    /// The mods of the pack, as dependencies to resolve against the registry
+    pub fn as_dependencies(&self) -> Vec<Dependency> {
+        self.mods
+            .iter()
+            .map(|pack_mod| Dependency {
+                name: pack_mod.name.clone(),
+                version: pack_mod.version.clone(),
+            })
+            .collect()
+    }
+
+    /// Checks that every mod is installed, in the pinned version if any
+    ///
+    /// `installed` maps installed mod names to their versions.
+    pub fn verify<'a>(&'a self, installed: &HashMap<&'a str, &'a str>) -> Vec<PackIssue<'a>> {
+        self.mods
+            .iter()
+            .filter_map(|pack_mod| {
+                let name = pack_mod.name.as_str();
+                let Some(&installed_version) = installed.get(name) else {
+                    return Some(PackIssue::Missing(name));
+                };
+                match pack_mod.version.as_deref() {
+                    Some(pinned) if pinned != installed_version => {
+                        Some(PackIssue::VersionMismatch {
+                            name,
+                            pinned,
+                            installed: installed_version,
+                        })
+                    }
+                    _ => None,
+                }
+            })
+            .collect()
+    }
+}
+
+/// Whether a source is an HTTP(S) URL rather than a path
+pub fn is_url(source: &str) -> bool {
+    let lower = source.to_ascii_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://")
+}
+
+impl std::fmt::Display for PackIssue<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PackIssue::Missing(name) => write!(f, "{} is not installed", name),
+            PackIssue::VersionMismatch {
+                name,
+                pinned,
+                installed,
+            } => write!(
+                f,
+                "{} is installed in version {}, the pack pins {}",
+                name, installed, pinned
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_url() {
+        assert!(is_url("https://example.com/pack.yaml"));
+        assert!(is_url("HTTP://192.168.1.10:8080/pack.yaml"));
+        assert!(!is_url("packs/https.yaml"));
+    }
+
+    #[test]
+    fn test_presets_parse() {
+        for (name, _) in PRESETS {
+            assert!(!Pack::preset(name).unwrap().mods.is_empty());
+        }
+        assert!(matches!(Pack::preset("nope"), Err(Error::UnknownPreset(_))));
+    }
+
+    #[test]
+    fn test_verify_reports_missing_and_mismatched_mods() {
+        let pack = Pack::parse(
+            b"Name: Starter\nMods:\n  - Name: SpringCollab2020\n    Version: 1.7.3\n  - Name: CommunalHelper\n  - Name: MaxHelpingHand\n",
+        )
+        .unwrap();
+        let installed =
+            HashMap::from([("SpringCollab2020", "1.7.2"), ("CommunalHelper", "1.20.0")]);
+
+        let issues = pack.verify(&installed);
+
+        assert_eq!(
+            issues,
+            vec![
+                PackIssue::VersionMismatch {
+                    name: "SpringCollab2020",
+                    pinned: "1.7.3",
+                    installed: "1.7.2",
+                },
+                PackIssue::Missing("MaxHelpingHand"),
+            ]
+        );
+    }
+}
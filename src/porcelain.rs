@@ -0,0 +1,39 @@
+/// Stable line-oriented output of `--porcelain`, for scripts
+///
+/// Each line is a record: its type followed by tab-separated fields, as documented in the README.
+/// Once published, fields are only ever appended to a record and new record types may appear, so
+/// scripts must ignore the fields and records they do not know.
+pub fn record(kind: &str, fields: &[&str]) -> String {
+    let mut line = String::from(kind);
+    for field in fields {
+        line.push('\t');
+        line.push_str(&escape(field));
+    }
+    line
+}
+
+/// Escapes the separators of records in a field, backslashes first
+fn escape(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_escapes_separators() {
+        assert_eq!(
+            record("mod", &["SpeedrunTool", "3.25.0"]),
+            "mod\tSpeedrunTool\t3.25.0"
+        );
+        assert_eq!(
+            record("mod", &["Tab\there", "C:\\Mods\nx", ""]),
+            "mod\tTab\\there\tC:\\\\Mods\\nx\t"
+        );
+    }
+}
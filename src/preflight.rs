@@ -0,0 +1,182 @@
+use crate::{
+    constant::MOD_MANIFEST_FILES,
+    lint::{LintIssue, Severity},
+};
+
+/// Top-level folders Everest reads assets from, with their expected casing
+const ASSET_DIRECTORIES: [&str; 6] = [
+    "Maps",
+    "Graphics",
+    "Audio",
+    "Dialog",
+    "Effects",
+    "Tutorials",
+];
+
+/// Files left behind by archivers and file managers, which should not be shipped
+const JUNK_FILES: [&str; 3] = ["__MACOSX/", ".DS_Store", "Thumbs.db"];
+
+/// Checks the layout of a mod zip for the usual packaging mistakes
+///
+/// `files` are the paths of the files in the zip, with `/` separators.
+pub fn check_zip_structure(files: &[String]) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let mut report = |severity, message: String| {
+        issues.push(LintIssue {
+            severity,
+            line: None,
+            message,
+        })
+    };
+
+    if files.is_empty() {
+        report(Severity::Error, String::from("the zip is empty"));
+        return issues;
+    }
+
+    let nested_zips: Vec<&String> = files
+        .iter()
+        .filter(|file| file.to_ascii_lowercase().ends_with(".zip"))
+        .collect();
+    if nested_zips.len() == files.len() {
+        report(
+            Severity::Error,
+            format!(
+                "the zip only contains another zip (`{}`), upload the inner zip instead",
+                nested_zips[0]
+            ),
+        );
+        return issues;
+    }
+    for nested in nested_zips {
+        report(
+            Severity::Warning,
+            format!(
+                "`{}` is a zip inside the zip, Everest will not load it",
+                nested
+            ),
+        );
+    }
+
+    let is_manifest = |name: &str| {
+        MOD_MANIFEST_FILES
+            .iter()
+            .any(|manifest| name.eq_ignore_ascii_case(manifest))
+    };
+    if !files.iter().any(|file| is_manifest(file)) {
+        match files.iter().find_map(|file| {
+            let (folder, name) = file.rsplit_once('/')?;
+            is_manifest(name).then_some(folder)
+        }) {
+            Some(folder) => report(
+                Severity::Error,
+                format!(
+                    "everest.yaml is inside `{}/`, zip the contents of the folder rather than the folder itself",
+                    folder
+                ),
+            ),
+            None => report(
+                Severity::Error,
+                String::from("no everest.yaml at the root of the zip"),
+            ),
+        }
+    }
+
+    for file in files {
+        let top_level = file.split('/').next().unwrap_or_default();
+        if file.contains('/')
+            && let Some(expected) = ASSET_DIRECTORIES
+                .iter()
+                .find(|dir| dir.eq_ignore_ascii_case(top_level) && **dir != top_level)
+        {
+            report(
+                Severity::Error,
+                format!(
+                    "`{}` should be in `{}/`, paths are case-sensitive",
+                    file, expected
+                ),
+            );
+        }
+
+        if file.to_ascii_lowercase().ends_with(".bin") {
+            match file.split('/').collect::<Vec<_>>()[..] {
+                ["Maps", _] => report(
+                    Severity::Warning,
+                    format!(
+                        "`{}` is directly in Maps/, use Maps/<author>/<campaign>/ to avoid clashing with other mods",
+                        file
+                    ),
+                ),
+                ["Maps", ..] => {}
+                _ => report(
+                    Severity::Warning,
+                    format!("map `{}` is outside Maps/ and will not be loaded", file),
+                ),
+            }
+        }
+
+        if JUNK_FILES
+            .iter()
+            .any(|junk| file.starts_with(junk) || file.ends_with(junk))
+        {
+            report(
+                Severity::Warning,
+                format!("`{}` is a system file and should not be shipped", file),
+            );
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(files: &[&str]) -> Vec<(Severity, String)> {
+        let files: Vec<String> = files.iter().map(|file| file.to_string()).collect();
+        check_zip_structure(&files)
+            .into_iter()
+            .map(|issue| (issue.severity, issue.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_well_packaged_mod_has_no_issues() {
+        let issues = check(&[
+            "everest.yaml",
+            "Maps/Author/Campaign/1-Level.bin",
+            "Graphics/Atlases/Gameplay/Author/tile.png",
+            "Dialog/English.txt",
+        ]);
+
+        assert!(issues.is_empty(), "{:?}", issues);
+    }
+
+    #[test]
+    fn test_detects_double_zip_and_nested_folder() {
+        let double_zipped = check(&["MyMod.zip"]);
+        assert_eq!(double_zipped.len(), 1);
+        assert!(double_zipped[0].1.contains("only contains another zip"));
+
+        let nested = check(&["MyMod/everest.yaml", "MyMod/Maps/Author/1.bin"]);
+        assert_eq!(nested.len(), 2);
+        assert!(nested[0].1.contains("inside `MyMod/`"));
+        assert!(nested[1].1.contains("outside Maps/"));
+    }
+
+    #[test]
+    fn test_detects_wrong_case_and_junk() {
+        let issues = check(&[
+            "everest.yaml",
+            "maps/Author/1.bin",
+            "__MACOSX/._everest.yaml",
+        ]);
+
+        let severities: Vec<Severity> = issues.iter().map(|(severity, _)| *severity).collect();
+        assert_eq!(
+            severities,
+            vec![Severity::Error, Severity::Warning, Severity::Warning]
+        );
+    }
+}
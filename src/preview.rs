@@ -0,0 +1,202 @@
+use std::{env, io::Cursor};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use image::{ImageFormat, RgbaImage};
+use tracing::info;
+
+use crate::error::Error;
+
+/// Maximum size of a payload chunk in the kitty graphics protocol
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// Terminal graphics protocols able to display images inline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphicsProtocol {
+    Kitty,
+    ITerm2,
+    Sixel,
+}
+
+impl GraphicsProtocol {
+    /// Guesses the protocol supported by the current terminal from its environment variables
+    pub fn detect() -> Option<Self> {
+        let term = env::var("TERM").unwrap_or_default();
+        let term_program = env::var("TERM_PROGRAM").unwrap_or_default();
+        let protocol = detect_from(
+            &term,
+            &term_program,
+            env::var_os("KITTY_WINDOW_ID").is_some(),
+        );
+        info!("Terminal graphics protocol: {:?}", protocol);
+        protocol
+    }
+
+    /// Encodes an image as escape sequences displaying it inline
+    pub fn render(&self, image_data: &[u8]) -> Result<String, Error> {
+        match self {
+            // iTerm2 decodes the image itself, any common format is accepted
+            GraphicsProtocol::ITerm2 => Ok(format!(
+                "\x1b]1337;File=inline=1;size={}:{}\x07\n",
+                image_data.len(),
+                STANDARD.encode(image_data)
+            )),
+            GraphicsProtocol::Kitty => {
+                let image = image::load_from_memory(image_data)?;
+                let mut png = Vec::new();
+                image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+                Ok(kitty_sequence(&png))
+            }
+            GraphicsProtocol::Sixel => {
+                let image = image::load_from_memory(image_data)?.to_rgba8();
+                Ok(sixel_sequence(&image))
+            }
+        }
+    }
+}
+
+fn detect_from(term: &str, term_program: &str, in_kitty: bool) -> Option<GraphicsProtocol> {
+    if in_kitty || term == "xterm-kitty" || term == "xterm-ghostty" {
+        Some(GraphicsProtocol::Kitty)
+    } else if matches!(term_program, "iTerm.app" | "WezTerm") {
+        Some(GraphicsProtocol::ITerm2)
+    } else if term.contains("sixel") || matches!(term, "foot" | "foot-extra" | "mlterm") {
+        Some(GraphicsProtocol::Sixel)
+    } else {
+        None
+    }
+}
+
+/// Transmits a PNG image with the kitty graphics protocol, split in chunks
+fn kitty_sequence(png: &[u8]) -> String {
+    let payload = STANDARD.encode(png);
+    let chunks: Vec<&str> = payload
+        .as_bytes()
+        .chunks(KITTY_CHUNK_SIZE)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap_or_default())
+        .collect();
+    let mut sequence = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        if i == 0 {
+            sequence.push_str(&format!("\x1b_Gf=100,a=T,m={};{}\x1b\\", more, chunk));
+        } else {
+            sequence.push_str(&format!("\x1b_Gm={};{}\x1b\\", more, chunk));
+        }
+    }
+    sequence.push('\n');
+    sequence
+}
+
+/// Encodes an image as sixels, using a fixed palette of 6x6x6 colors
+fn sixel_sequence(image: &RgbaImage) -> String {
+    let (width, height) = image.dimensions();
+    let mut sequence = format!("\x1bPq\"1;1;{};{}", width, height);
+    for index in 0..216u32 {
+        let level = |component: u32| component * 100 / 5;
+        sequence.push_str(&format!(
+            "#{};2;{};{};{}",
+            index,
+            level(index / 36),
+            level(index / 6 % 6),
+            level(index % 6)
+        ));
+    }
+
+    let palette_index = |x: u32, y: u32| {
+        let pixel = image.get_pixel(x, y);
+        if pixel[3] < 128 {
+            return None;
+        }
+        let level = |component: u8| (u32::from(component) * 5 + 127) / 255;
+        Some(level(pixel[0]) * 36 + level(pixel[1]) * 6 + level(pixel[2]))
+    };
+
+    for band in (0..height).step_by(6) {
+        let rows = band..(band + 6).min(height);
+        let mut colors: Vec<u32> = rows
+            .clone()
+            .flat_map(|y| (0..width).filter_map(move |x| palette_index(x, y)))
+            .collect();
+        colors.sort_unstable();
+        colors.dedup();
+
+        for color in colors {
+            sequence.push_str(&format!("#{}", color));
+            let sixels: Vec<u8> = (0..width)
+                .map(|x| {
+                    rows.clone().fold(0u8, |bits, y| {
+                        if palette_index(x, y) == Some(color) {
+                            bits | 1 << (y - band)
+                        } else {
+                            bits
+                        }
+                    })
+                })
+                .collect();
+            push_run_length_encoded(&mut sequence, &sixels);
+            sequence.push('$');
+        }
+        sequence.push('-');
+    }
+    sequence.push_str("\x1b\\\n");
+    sequence
+}
+
+/// Appends sixel characters, using the repeat introducer for runs of the same value
+fn push_run_length_encoded(sequence: &mut String, sixels: &[u8]) {
+    let mut i = 0;
+    while i < sixels.len() {
+        let run = sixels[i..].iter().take_while(|&&s| s == sixels[i]).count();
+        let character = char::from(63 + sixels[i]);
+        if run > 3 {
+            sequence.push_str(&format!("!{}{}", run, character));
+        } else {
+            sequence.extend(std::iter::repeat_n(character, run));
+        }
+        i += run;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn test_detect_protocol() {
+        assert_eq!(
+            detect_from("xterm-kitty", "", false),
+            Some(GraphicsProtocol::Kitty)
+        );
+        assert_eq!(
+            detect_from("xterm-256color", "iTerm.app", false),
+            Some(GraphicsProtocol::ITerm2)
+        );
+        assert_eq!(
+            detect_from("foot", "", false),
+            Some(GraphicsProtocol::Sixel)
+        );
+        assert_eq!(detect_from("xterm-256color", "Apple_Terminal", false), None);
+    }
+
+    #[test]
+    fn test_kitty_payload_is_chunked() {
+        let sequence = kitty_sequence(&[0u8; 4000]);
+
+        assert!(sequence.starts_with("\x1b_Gf=100,a=T,m=1;"));
+        assert_eq!(sequence.matches("\x1b_G").count(), 2);
+        assert!(sequence.contains("\x1b_Gm=0;"));
+    }
+
+    #[test]
+    fn test_sixel_encoding() {
+        let mut image = RgbaImage::from_pixel(8, 2, Rgba([255, 0, 0, 255]));
+        image.put_pixel(0, 1, Rgba([0, 0, 0, 0]));
+
+        let sequence = sixel_sequence(&image);
+
+        assert!(sequence.starts_with("\x1bPq\"1;1;8;2#0;2;0;0;0"));
+        // Red is palette entry 5 * 36, the first column only has its top pixel set
+        assert!(sequence.ends_with("#180@!7B$-\x1b\\\n"));
+    }
+}
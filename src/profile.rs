@@ -0,0 +1,165 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    blacklist::Blacklist, constant::PROFILES_DIRECTORY, error::Error, fileutil,
+    installed_mods::LocalModInfo,
+};
+
+/// A named set of enabled mods, stored as `profiles/<name>.yaml` in the data directory
+///
+/// Loading a profile rewrites `blacklist.txt` so that exactly the archives of the profile are
+/// enabled; mods installed after the profile was saved are disabled.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Profile {
+    /// File names of the enabled archives
+    #[serde(rename = "Enabled")]
+    pub enabled: BTreeSet<String>,
+}
+
+/// Archives enabled and disabled by loading a profile
+#[derive(Debug, Default)]
+pub struct ProfileChanges {
+    pub enabled: Vec<String>,
+    pub disabled: Vec<String>,
+    /// Archives of the profile which are not installed anymore
+    pub missing: Vec<String>,
+}
+
+impl Profile {
+    /// Captures the archives currently enabled, i.e. not in the blacklist
+    pub fn capture(installed_mods: &[LocalModInfo], blacklist: &Blacklist) -> Self {
+        Self {
+            enabled: installed_mods
+                .iter()
+                .map(LocalModInfo::archive_file_name)
+                .filter(|file_name| !blacklist.contains(file_name))
+                .collect(),
+        }
+    }
+
+    pub fn load(data_dir: &Path, name: &str) -> Result<Self, Error> {
+        let path = profile_path(data_dir, name)?;
+        if !path.exists() {
+            return Err(Error::UnknownProfile(name.to_string()));
+        }
+        Ok(serde_yaml_ng::from_slice(&fs::read(&path)?)?)
+    }
+
+    pub fn save(&self, data_dir: &Path, name: &str) -> Result<(), Error> {
+        let path = profile_path(data_dir, name)?;
+        info!("Writing {}", path.display());
+        fs::create_dir_all(data_dir.join(PROFILES_DIRECTORY))?;
+        fs::write(path, serde_yaml_ng::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Names of the saved profiles, sorted
+    pub fn list(data_dir: &Path) -> Result<Vec<String>, Error> {
+        let dir = data_dir.join(PROFILES_DIRECTORY);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut names: Vec<_> = fs::read_dir(dir)?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                (path.extension()? == "yaml").then_some(())?;
+                Some(path.file_stem()?.to_string_lossy().into_owned())
+            })
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    /// Enables the archives of the profile and disables the other installed ones in the blacklist
+    ///
+    /// Blacklist entries of archives which are not installed are left as they are.
+    pub fn apply(
+        &self,
+        installed_mods: &[LocalModInfo],
+        blacklist: &mut Blacklist,
+    ) -> ProfileChanges {
+        let mut changes = ProfileChanges::default();
+        let mut installed = BTreeSet::new();
+        for file_name in installed_mods.iter().map(LocalModInfo::archive_file_name) {
+            if self.enabled.contains(&file_name) {
+                if blacklist.remove(&file_name) {
+                    changes.enabled.push(file_name.clone());
+                }
+            } else if blacklist.add(&file_name) {
+                changes.disabled.push(file_name.clone());
+            }
+            installed.insert(file_name);
+        }
+        changes.missing = self.enabled.difference(&installed).cloned().collect();
+        changes
+    }
+}
+
+/// Path of a profile file, rejecting names which are not plain file names
+fn profile_path(data_dir: &Path, name: &str) -> Result<PathBuf, Error> {
+    if !fileutil::is_plain_file_name(name) {
+        return Err(Error::InvalidProfileName(name.to_string()));
+    }
+    Ok(data_dir
+        .join(PROFILES_DIRECTORY)
+        .join(format!("{}.yaml", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{constant::BLACKLIST_FILE, installed_mods::ModManifest};
+    use tempfile::TempDir;
+
+    fn local_mod(file_name: &str) -> LocalModInfo {
+        let manifest = ModManifest::parse_mod_manifest_from_yaml(
+            format!("- Name: {}\n  Version: 1.0.0\n", file_name).as_bytes(),
+        )
+        .unwrap();
+        LocalModInfo::new(PathBuf::from(file_name), manifest)
+    }
+
+    #[test]
+    fn test_load_restores_the_enabled_set() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join(BLACKLIST_FILE),
+            "# Disabled\nSpeedrunTool.zip\nUninstalled.zip\n",
+        )
+        .unwrap();
+        let mut blacklist = Blacklist::load(dir.path()).unwrap();
+        let installed = vec![local_mod("CollabUtils2.zip"), local_mod("SpeedrunTool.zip")];
+        Profile::capture(&installed, &blacklist)
+            .save(dir.path(), "casual maps")
+            .unwrap();
+
+        let speedrun = Profile {
+            enabled: BTreeSet::from([String::from("SpeedrunTool.zip")]),
+        };
+        speedrun.apply(&installed, &mut blacklist);
+        let installed = vec![
+            local_mod("CollabUtils2.zip"),
+            local_mod("SpeedrunTool.zip"),
+            local_mod("NewMap.zip"),
+        ];
+        let changes = Profile::load(dir.path(), "casual maps")
+            .unwrap()
+            .apply(&installed, &mut blacklist);
+
+        assert_eq!(changes.enabled, vec!["CollabUtils2.zip"]);
+        assert_eq!(changes.disabled, vec!["SpeedrunTool.zip", "NewMap.zip"]);
+        blacklist.save().unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join(BLACKLIST_FILE)).unwrap(),
+            "# Disabled\nUninstalled.zip\nSpeedrunTool.zip\nNewMap.zip\n"
+        );
+        assert_eq!(Profile::list(dir.path()).unwrap(), vec!["casual maps"]);
+    }
+}
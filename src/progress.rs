@@ -0,0 +1,64 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
+
+/// Whether progress bars are hidden, set once from `--quiet`
+static HIDDEN: AtomicBool = AtomicBool::new(false);
+
+/// Hides every progress bar created from now on
+pub fn hide() {
+    HIDDEN.store(true, Ordering::Relaxed);
+}
+
+/// Where progress bars are drawn: stderr, unless hidden with `--quiet`
+///
+/// indicatif already skips drawing when stderr is not a terminal.
+pub fn draw_target() -> ProgressDrawTarget {
+    if HIDDEN.load(Ordering::Relaxed) {
+        ProgressDrawTarget::hidden()
+    } else {
+        ProgressDrawTarget::stderr()
+    }
+}
+
+/// Progress bar of the scanning of `len` archives, showing the current file name as message
+pub fn scan_bar(len: u64) -> ProgressBar {
+    ProgressBar::with_draw_target(Some(len), draw_target()).with_style(
+        ProgressStyle::default_bar()
+            .template("Scanning mods [{bar:30.cyan/blue}] {pos}/{len} {wide_msg}")
+            .unwrap()
+            .progress_chars("#>-"),
+    )
+}
+
+/// Progress bar of a download of `size` bytes labelled with `message`, a spinner if the size is unknown
+pub fn fetch_bar(size: Option<u64>, message: &'static str) -> ProgressBar {
+    let bar = match size {
+        Some(size) => ProgressBar::with_draw_target(Some(size), draw_target()).with_style(
+            ProgressStyle::default_bar()
+                .template("{msg} [{bar:30.cyan/blue}] {bytes}/{total_bytes} {binary_bytes_per_sec}")
+                .unwrap()
+                .progress_chars("#>-"),
+        ),
+        None => spinner(message).with_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner:.green} {msg} {bytes} {binary_bytes_per_sec}")
+                .unwrap(),
+        ),
+    };
+    bar.with_message(message)
+}
+
+/// Spinner labelled with `message`, turning on its own while the caller is busy
+pub fn spinner(message: &'static str) -> ProgressBar {
+    let bar = ProgressBar::with_draw_target(None, draw_target()).with_style(
+        ProgressStyle::default_spinner()
+            .template("{spinner:.green} {msg}")
+            .unwrap(),
+    );
+    bar.enable_steady_tick(Duration::from_millis(100));
+    bar.with_message(message)
+}
@@ -0,0 +1,79 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// Whether the user can answer prompts, i.e. stdin and stdout are attached to a terminal
+pub fn is_interactive() -> bool {
+    io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// Prints a prompt and reads a line of input, returns the trimmed answer
+///
+/// End of input is treated as an empty answer.
+pub fn ask(prompt: &str) -> io::Result<String> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
+
+/// Asks the user to pick one of `count` numbered options, returns the zero-based index
+///
+/// Returns `None` when the user skips the question with an empty answer.
+pub fn choose(prompt: &str, count: usize) -> io::Result<Option<usize>> {
+    loop {
+        let answer = ask(prompt)?;
+        if answer.is_empty() {
+            return Ok(None);
+        }
+        match answer.parse::<usize>() {
+            Ok(choice) if (1..=count).contains(&choice) => return Ok(Some(choice - 1)),
+            _ => println!("Please enter a number between 1 and {}", count),
+        }
+    }
+}
+
+/// Asks the user to pick any of `count` numbered options, separated by spaces or commas
+///
+/// Returns the zero-based indices in the order given, empty when the user skips the question.
+pub fn choose_many(prompt: &str, count: usize) -> io::Result<Vec<usize>> {
+    loop {
+        match parse_choices(&ask(prompt)?, count) {
+            Some(choices) => return Ok(choices),
+            None => println!("Please enter numbers between 1 and {}", count),
+        }
+    }
+}
+
+fn parse_choices(answer: &str, count: usize) -> Option<Vec<usize>> {
+    let mut choices = Vec::new();
+    for part in answer.split([' ', ',']).filter(|part| !part.is_empty()) {
+        match part.parse::<usize>() {
+            Ok(choice) if (1..=count).contains(&choice) => {
+                if !choices.contains(&(choice - 1)) {
+                    choices.push(choice - 1);
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(choices)
+}
+
+/// Asks a yes/no question, anything but `y` or `yes` is a no
+pub fn confirm(prompt: &str) -> io::Result<bool> {
+    let answer = ask(prompt)?;
+    Ok(matches!(answer.to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_choices() {
+        assert_eq!(parse_choices("3, 1 3", 3), Some(vec![2, 0]));
+        assert_eq!(parse_choices("", 3), Some(vec![]));
+        assert_eq!(parse_choices("4", 3), None);
+        assert_eq!(parse_choices("one", 3), None);
+    }
+}
@@ -0,0 +1,130 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use reqwest::{
+    Response, StatusCode,
+    header::{HeaderValue, RETRY_AFTER},
+};
+use tokio::time::{Instant, sleep_until};
+use tracing::info;
+
+use crate::constant::{DEFAULT_RETRY_AFTER, MAX_RETRY_AFTER, REQUEST_INTERVAL};
+
+/// Spaces out the requests of every clone of the downloader, and pauses all of them after a server
+/// answered `429 Too Many Requests`
+///
+/// GameBanana temporarily bans clients sending too many requests, which bulk operations such as
+/// `vendor` or `pack install` easily do.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    /// When the next request may start
+    next_request: Arc<Mutex<Instant>>,
+    interval: Duration,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self {
+            next_request: Arc::new(Mutex::new(Instant::now())),
+            interval: REQUEST_INTERVAL,
+        }
+    }
+
+    /// Waits for the turn of a new request
+    pub async fn wait(&self) {
+        let start = {
+            let mut next_request = self.next_request.lock().unwrap();
+            let start = (*next_request).max(Instant::now());
+            *next_request = start + self.interval;
+            start
+        };
+        sleep_until(start).await;
+    }
+
+    /// Holds back every request for `delay`
+    pub fn pause(&self, delay: Duration) {
+        let mut next_request = self.next_request.lock().unwrap();
+        *next_request = (*next_request).max(Instant::now() + delay);
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How long the server asks to wait before sending requests again, `None` if the response is not
+/// a rate limit or the wait is too long to be worth it
+pub fn retry_delay(response: &Response) -> Option<Duration> {
+    match response.status() {
+        StatusCode::TOO_MANY_REQUESTS => {}
+        // Also sent by some servers while they are overloaded, only waited for when asked to
+        StatusCode::SERVICE_UNAVAILABLE if response.headers().contains_key(RETRY_AFTER) => {}
+        _ => return None,
+    }
+    let delay = response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|value| parse_retry_after(value, Utc::now()))
+        .unwrap_or(DEFAULT_RETRY_AFTER);
+    info!(
+        "Rate limited by {}, retry after {:?}",
+        response.url(),
+        delay
+    );
+    (delay <= MAX_RETRY_AFTER).then_some(delay)
+}
+
+/// Parses a `Retry-After` header, either a number of seconds or an HTTP date
+fn parse_retry_after(value: &HeaderValue, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    // A date in the past means the request can be sent right away
+    Some(
+        (date.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = DateTime::parse_from_rfc2822("Thu, 15 Oct 2026 18:00:00 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(
+            parse_retry_after(&HeaderValue::from_static("120"), now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after(
+                &HeaderValue::from_static("Thu, 15 Oct 2026 18:01:30 GMT"),
+                now
+            ),
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(
+            parse_retry_after(
+                &HeaderValue::from_static("Thu, 15 Oct 2026 17:00:00 GMT"),
+                now
+            ),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            parse_retry_after(&HeaderValue::from_static("soon"), now),
+            None
+        );
+    }
+}
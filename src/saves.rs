@@ -0,0 +1,362 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use tracing::{info, warn};
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+use crate::{error::Error, fileutil, timeutil};
+
+/// Progress of a save file in a map which an update of the map may break
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct MapProgress {
+    /// Strawberries collected in any mode, map updates may move them and reset them
+    pub strawberries: u32,
+    /// Whether the player left in the middle of the map, resuming it after an update may put
+    /// them in a changed or missing room
+    pub in_session: bool,
+}
+
+/// A save file, e.g. `0.celeste`, reduced to the maps with progress
+#[derive(Debug)]
+pub struct SaveFile {
+    /// Name of the player, or the file name when it is missing
+    pub name: String,
+    /// Progress by map SID, e.g. `StrawberryJam2021/1-Beginner/asteriskblue`
+    pub maps: BTreeMap<String, MapProgress>,
+}
+
+/// Progress of a save file in a map of an archive
+#[derive(Debug)]
+pub struct MapInSave<'a> {
+    pub save: &'a str,
+    pub sid: &'a str,
+    pub progress: &'a MapProgress,
+}
+
+impl MapInSave<'_> {
+    pub fn describe(&self) -> String {
+        let mut details = Vec::new();
+        if self.progress.in_session {
+            details.push(String::from("session in progress"));
+        }
+        if self.progress.strawberries > 0 {
+            details.push(format!("{} strawberries", self.progress.strawberries));
+        }
+        format!("{} ({}: {})", self.sid, self.save, details.join(", "))
+    }
+}
+
+/// The save files of the game
+#[derive(Debug, Default)]
+pub struct SaveFiles {
+    saves: Vec<SaveFile>,
+}
+
+impl SaveFiles {
+    /// Reads the numbered save files of the game, unreadable ones are skipped with a warning
+    pub fn load(mods_directory: &Path) -> Self {
+        let mut saves = Vec::new();
+        for directory in fileutil::get_save_directories(mods_directory) {
+            info!("Reading save files in {}", directory.display());
+            for path in save_file_paths(&directory) {
+                match fs::read(&path) {
+                    Ok(data) => {
+                        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                        saves.push(SaveFile::parse(&String::from_utf8_lossy(&data), &file_name));
+                    }
+                    Err(e) => warn!("Failed to read the save file {}: {}", path.display(), e),
+                }
+            }
+        }
+        Self { saves }
+    }
+
+    /// Progress of every save file in the maps of an archive, i.e. its `Maps/<SID>.bin` entries
+    pub fn progress_in(&self, archive: &Path) -> Result<Vec<MapInSave<'_>>, Error> {
+        if self.saves.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sids = map_sids(archive)?;
+        Ok(self
+            .saves
+            .iter()
+            .flat_map(|save| {
+                save.maps
+                    .iter()
+                    .filter(|(sid, _)| sids.contains(sid))
+                    .map(|(sid, progress)| MapInSave {
+                        save: &save.name,
+                        sid,
+                        progress,
+                    })
+            })
+            .collect())
+    }
+}
+
+/// SIDs of the maps of an archive, i.e. its `Maps/<SID>.bin` entries
+pub fn map_sids(archive: &Path) -> Result<Vec<String>, Error> {
+    Ok(fileutil::list_zip_entries(archive)?
+        .into_iter()
+        .filter_map(|entry| {
+            entry
+                .strip_prefix("Maps/")?
+                .strip_suffix(".bin")
+                .map(String::from)
+        })
+        .collect())
+}
+
+impl SaveFile {
+    /// Extracts the progress in maps from the XML written by the game
+    ///
+    /// Everest adds the SID of maps to the `AreaStats` of every level set and to the `Area` of
+    /// the saved session. The document is machine-written, so scanning its tags is enough.
+    pub fn parse(xml: &str, file_name: &str) -> Self {
+        let mut maps: BTreeMap<String, MapProgress> = BTreeMap::new();
+        let mut area_sid: Option<String> = None;
+        let mut in_session = false;
+        let mut rest = xml;
+        while let Some(start) = rest.find('<') {
+            rest = &rest[start + 1..];
+            let Some(end) = rest.find('>') else {
+                break;
+            };
+            let tag = &rest[..end];
+            rest = &rest[end + 1..];
+            if tag.starts_with(['?', '!']) {
+                continue;
+            }
+            if let Some(name) = tag.strip_prefix('/') {
+                match name.trim() {
+                    "AreaStats" => area_sid = None,
+                    "CurrentSession_Safe" | "CurrentSession" => in_session = false,
+                    _ => {}
+                }
+                continue;
+            }
+            let self_closing = tag.ends_with('/');
+            let tag = tag.trim_end_matches('/');
+            let (name, attributes) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+            match name {
+                "CurrentSession_Safe" | "CurrentSession" => in_session = !self_closing,
+                "Area" if in_session => {
+                    if let Some(sid) = attribute(attributes, "SID") {
+                        maps.entry(sid).or_default().in_session = true;
+                    }
+                }
+                "AreaStats" if !self_closing => area_sid = attribute(attributes, "SID"),
+                "AreaModeStats" => {
+                    let strawberries = attribute(attributes, "TotalStrawberries")
+                        .and_then(|count| count.parse().ok())
+                        .unwrap_or(0);
+                    if let Some(sid) = &area_sid
+                        && strawberries > 0
+                    {
+                        maps.entry(sid.clone()).or_default().strawberries += strawberries;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let name = xml
+            .split_once("<Name>")
+            .and_then(|(_, after)| after.split_once("</Name>"))
+            .map(|(name, _)| unescape(name))
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| file_name.to_string());
+        Self { name, maps }
+    }
+}
+
+/// Zips the save directories into `backup_dir` as `Saves-<timestamp>.zip`, then deletes the
+/// oldest backups beyond `keep`, returns the path of the backup or `None` without save files
+///
+/// The first directory is stored under `Saves/` in the archive, any other under `Saves-2/`...
+pub fn backup(
+    save_directories: &[PathBuf],
+    backup_dir: &Path,
+    keep: u32,
+) -> Result<Option<PathBuf>, Error> {
+    if save_directories.is_empty() {
+        return Ok(None);
+    }
+    fs::create_dir_all(backup_dir)?;
+    let path = backup_dir.join(format!("Saves-{}.zip", timeutil::now()));
+    // A backup cut short must not rotate out a complete one
+    let partial = path.with_extension("zip.partial");
+    info!("Backing up the save files to {}", path.display());
+    let mut zip = ZipWriter::new(File::create(&partial)?);
+    for (index, directory) in save_directories.iter().enumerate() {
+        let prefix = match index {
+            0 => String::from("Saves"),
+            _ => format!("Saves-{}", index + 1),
+        };
+        add_directory(&mut zip, directory, directory, &prefix)?;
+    }
+    zip.finish()?;
+    fs::rename(&partial, &path)?;
+
+    let mut backups: Vec<PathBuf> = fs::read_dir(backup_dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|backup| {
+            backup
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("Saves-") && name.ends_with(".zip"))
+        })
+        .collect();
+    // Timestamps have the same number of digits for centuries, so names sort by age
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep.max(1) as usize);
+    for old in &backups[..excess] {
+        info!("Deleting the old backup {}", old.display());
+        if let Err(e) = fs::remove_file(old) {
+            warn!("Failed to delete the old backup {}: {}", old.display(), e);
+        }
+    }
+    Ok(Some(path))
+}
+
+fn add_directory(
+    zip: &mut ZipWriter<File>,
+    root: &Path,
+    directory: &Path,
+    prefix: &str,
+) -> Result<(), Error> {
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            add_directory(zip, root, &path, prefix)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            let name = format!(
+                "{}/{}",
+                prefix,
+                relative.to_string_lossy().replace('\\', "/")
+            );
+            zip.start_file(name, SimpleFileOptions::default())?;
+            io::copy(&mut File::open(&path)?, zip)?;
+        }
+    }
+    Ok(())
+}
+
+/// Numbered save files in a directory, e.g. `0.celeste`, leaving out `settings.celeste` and the
+/// files of mods
+fn save_file_paths(directory: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(".celeste"))
+                .is_some_and(|slot| !slot.is_empty() && slot.bytes().all(|b| b.is_ascii_digit()))
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Value of an attribute among the attributes of a tag, e.g. `ID="0" SID="Celeste/0-Intro"`
+fn attribute(attributes: &str, name: &str) -> Option<String> {
+    let mut rest = attributes;
+    while let Some((key, after)) = rest.split_once('=') {
+        let after = after.trim_start().strip_prefix('"')?;
+        let (value, after) = after.split_once('"')?;
+        if key.trim() == name {
+            return Some(unescape(value));
+        }
+        rest = after;
+    }
+    None
+}
+
+/// Replaces the predefined XML entities, `&amp;` last
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_keeps_the_newest() {
+        let saves = tempfile::TempDir::new().unwrap();
+        fs::write(saves.path().join("0.celeste"), "<SaveData />").unwrap();
+        let backup_dir = tempfile::TempDir::new().unwrap();
+        for old in ["Saves-1000000000.zip", "Saves-1000000001.zip"] {
+            fs::write(backup_dir.path().join(old), "").unwrap();
+        }
+
+        let path = backup(&[saves.path().to_path_buf()], backup_dir.path(), 2)
+            .unwrap()
+            .unwrap();
+
+        let mut names: Vec<_> = fs::read_dir(backup_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names[0], "Saves-1000000001.zip");
+        assert_eq!(names.len(), 2);
+        let entries = fileutil::list_zip_entries(&path).unwrap();
+        assert_eq!(entries, vec!["Saves/0.celeste"]);
+    }
+
+    #[test]
+    fn test_parse_save_file() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<SaveData xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+  <Name>Madeline &amp; Theo</Name>
+  <CurrentSession_Safe InArea="true">
+    <Area ID="10" Mode="Normal" SID="Author/Beginner/Lake" />
+    <OldStats TotalStrawberries="9" />
+  </CurrentSession_Safe>
+  <LevelSets>
+    <LevelSetStats Name="Author/Beginner">
+      <Areas>
+        <AreaStats ID="0" Cassette="false" SID="Author/Beginner/Lake">
+          <Modes>
+            <AreaModeStats TotalStrawberries="2" Completed="false" />
+            <AreaModeStats TotalStrawberries="1" Completed="false" />
+          </Modes>
+        </AreaStats>
+        <AreaStats ID="1" Cassette="false" SID="Author/Beginner/Untouched">
+          <Modes>
+            <AreaModeStats TotalStrawberries="0" Completed="false" />
+          </Modes>
+        </AreaStats>
+      </Areas>
+    </LevelSetStats>
+  </LevelSets>
+  <LastArea_Safe ID="1" Mode="Normal" SID="Author/Beginner/Untouched" />
+</SaveData>"#;
+
+        let save = SaveFile::parse(xml, "0.celeste");
+
+        assert_eq!(save.name, "Madeline & Theo");
+        assert_eq!(save.maps.len(), 1);
+        assert_eq!(
+            save.maps["Author/Beginner/Lake"],
+            MapProgress {
+                strawberries: 3,
+                in_session: true,
+            }
+        );
+    }
+}
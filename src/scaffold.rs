@@ -0,0 +1,100 @@
+use std::{fs, path::Path};
+
+use tracing::info;
+
+use crate::{
+    constant::{EVEREST_CORE_MIN_VERSION, MOD_MANIFEST_FILES},
+    error::Error,
+};
+
+/// What to generate for a new mod
+#[derive(Debug)]
+pub struct ScaffoldOptions<'a> {
+    pub name: &'a str,
+    pub version: &'a str,
+    /// Whether the mod ships code, adds a `DLL` field and a `Source` folder
+    pub with_code: bool,
+}
+
+/// Renders a starter `everest.yaml`
+pub fn render_manifest(options: &ScaffoldOptions) -> String {
+    let mut manifest = format!("- Name: {}\n  Version: {}\n", options.name, options.version);
+    if options.with_code {
+        manifest.push_str(&format!("  DLL: bin/{}.dll\n", options.name));
+    }
+    manifest.push_str(&format!(
+        "  Dependencies:\n    - Name: EverestCore\n      Version: {}\n",
+        EVEREST_CORE_MIN_VERSION
+    ));
+    manifest
+}
+
+/// Folders of the conventional mod layout, relative to the mod root
+pub fn layout_directories(options: &ScaffoldOptions) -> Vec<String> {
+    let mut directories = vec![
+        format!("Maps/{}", options.name),
+        format!("Graphics/Atlases/Gameplay/{}", options.name),
+        String::from("Dialog"),
+    ];
+    if options.with_code {
+        directories.push(String::from("Source"));
+    }
+    directories
+}
+
+/// Creates the manifest and folder layout of a new mod in `dir`
+///
+/// An existing manifest is only overwritten when `force` is set, existing files are never touched.
+pub fn init_mod(dir: &Path, options: &ScaffoldOptions, force: bool) -> Result<(), Error> {
+    if !force
+        && let Some(existing) = MOD_MANIFEST_FILES
+            .iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.exists())
+    {
+        return Err(Error::ManifestAlreadyExists(existing));
+    }
+
+    for directory in layout_directories(options) {
+        info!("Creating {}", directory);
+        fs::create_dir_all(dir.join(directory))?;
+    }
+    let dialog_path = dir.join("Dialog").join("English.txt");
+    if !dialog_path.exists() {
+        fs::write(
+            &dialog_path,
+            format!(
+                "# Dialog of {}, see the Everest wiki for the format\n",
+                options.name
+            ),
+        )?;
+    }
+    fs::write(dir.join(MOD_MANIFEST_FILES[0]), render_manifest(options))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::lint::{ManifestSource, lint_manifest};
+
+    #[test]
+    fn test_generated_manifest_passes_lint() {
+        let options = ScaffoldOptions {
+            name: "MyMod",
+            version: "0.1.0",
+            with_code: true,
+        };
+        let source = ManifestSource {
+            path: PathBuf::from("everest.yaml"),
+            content: render_manifest(&options),
+            files: Some(vec![String::from("bin/MyMod.dll")]),
+        };
+
+        let issues = lint_manifest(&source, None);
+
+        assert!(issues.is_empty(), "{:?}", issues);
+    }
+}
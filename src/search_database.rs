@@ -0,0 +1,223 @@
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use serde::Deserialize;
+use tracing::{info, warn};
+
+use crate::{
+    cache,
+    constant::{SEARCH_DATABASE_CACHE_FILE, SEARCH_DATABASE_CACHE_TTL},
+    download::ModDownloader,
+    error::Error,
+    installed_mods::LocalModInfo,
+    mod_registry::{ModRegistry, RemoteModInfo},
+};
+
+/// Each entry in `mod_search_database.yaml`, a GameBanana submission
+#[derive(Debug, Deserialize, Clone)]
+pub struct SearchDatabaseEntry {
+    #[serde(rename = "GameBananaType")]
+    pub gamebanana_type: String,
+    #[serde(rename = "GameBananaId")]
+    pub gamebanana_id: u32,
+    /// GameBanana member who submitted the mod
+    #[serde(rename = "Author", default)]
+    pub author: Option<String>,
+    #[serde(rename = "Views", default)]
+    pub views: u64,
+    #[serde(rename = "Downloads", default)]
+    pub downloads: u64,
+    #[serde(rename = "Likes", default)]
+    pub likes: u64,
+    /// GameBanana category, e.g. `Maps` or `Helpers`
+    #[serde(rename = "CategoryName", default)]
+    pub category_name: Option<String>,
+}
+
+/// Installed mods submitted by the same GameBanana member
+#[derive(Debug)]
+pub struct AuthorMods<'a> {
+    pub author: &'a str,
+    pub mods: Vec<&'a LocalModInfo>,
+}
+
+/// Search database published by the Everest update checker server, with GameBanana statistics
+#[derive(Debug, Default)]
+pub struct SearchDatabase {
+    entries: HashMap<(String, u32), SearchDatabaseEntry>,
+}
+
+impl SearchDatabase {
+    /// Loads the search database from the cache, fetching a fresh copy when it is outdated
+    pub async fn load(downloader: &ModDownloader, cache_dir: &Path) -> Result<Self, Error> {
+        let cache_path = cache_dir.join(SEARCH_DATABASE_CACHE_FILE);
+        let data = cache::fetch_cached(&cache_path, SEARCH_DATABASE_CACHE_TTL, || {
+            downloader.fetch_search_database()
+        })
+        .await?;
+        Self::parse(&data)
+    }
+
+    /// Loads the search database, statistics are simply not shown when it is unavailable
+    pub async fn load_or_default(downloader: &ModDownloader, cache_dir: &Path) -> Self {
+        Self::load(downloader, cache_dir).await.unwrap_or_else(|e| {
+            warn!("Could not load the mod search database: {}", e);
+            Self::default()
+        })
+    }
+
+    /// Parses the raw YAML data of the search database
+    pub fn parse(data: &[u8]) -> Result<Self, Error> {
+        info!("Parsing mod search database");
+        let entries: Vec<SearchDatabaseEntry> = serde_yaml_ng::from_slice(data)?;
+        Ok(Self {
+            entries: entries
+                .into_iter()
+                .map(|entry| ((entry.gamebanana_type.clone(), entry.gamebanana_id), entry))
+                .collect(),
+        })
+    }
+
+    /// Finds the GameBanana submission a registry entry belongs to
+    pub fn get(&self, mod_info: &RemoteModInfo) -> Option<&SearchDatabaseEntry> {
+        self.get_by_id(&mod_info.gamebanana_type, mod_info.gamebanana_id)
+    }
+
+    /// Finds a GameBanana submission by type (e.g. `Mod`) and id
+    pub fn get_by_id(
+        &self,
+        gamebanana_type: &str,
+        gamebanana_id: u32,
+    ) -> Option<&SearchDatabaseEntry> {
+        self.entries
+            .get(&(gamebanana_type.to_string(), gamebanana_id))
+    }
+
+    /// Author of the GameBanana submission a registry entry belongs to
+    pub fn author_of(&self, mod_info: &RemoteModInfo) -> Option<&str> {
+        self.get(mod_info)?.author.as_deref()
+    }
+
+    /// Groups installed mods by author, most represented authors first, and returns the mods whose
+    /// author is unknown because they are not in the registry or the database
+    pub fn group_by_author<'a>(
+        &'a self,
+        installed_mods: &'a [LocalModInfo],
+        registry: &ModRegistry,
+    ) -> (Vec<AuthorMods<'a>>, Vec<&'a LocalModInfo>) {
+        let mut by_author: BTreeMap<String, AuthorMods<'a>> = BTreeMap::new();
+        let mut unknown = Vec::new();
+        for local_mod in installed_mods {
+            let author = registry
+                .get_mod_info(&local_mod.manifest.name)
+                .and_then(|mod_info| self.author_of(mod_info));
+            match author {
+                // Names differing only by case are the same GameBanana member
+                Some(author) => by_author
+                    .entry(author.to_lowercase())
+                    .or_insert_with(|| AuthorMods {
+                        author,
+                        mods: Vec::new(),
+                    })
+                    .mods
+                    .push(local_mod),
+                None => unknown.push(local_mod),
+            }
+        }
+        let mut groups: Vec<_> = by_author.into_values().collect();
+        groups.sort_by_key(|group| Reverse(group.mods.len()));
+        (groups, unknown)
+    }
+
+    /// GameBanana category of a submission, e.g. `Maps`, or its type when it has none, e.g. `Tool`
+    ///
+    /// `None` for plain mods missing from the database, whose type tells nothing.
+    pub fn category_of<'a>(
+        &'a self,
+        gamebanana_type: &'a str,
+        gamebanana_id: u32,
+    ) -> Option<&'a str> {
+        match self
+            .get_by_id(gamebanana_type, gamebanana_id)
+            .and_then(|entry| entry.category_name.as_deref())
+        {
+            Some(category) => Some(category),
+            None if gamebanana_type == "Mod" => None,
+            None => Some(gamebanana_type),
+        }
+    }
+
+    /// Whether a submission has a GameBanana type or category, ignoring case and a plural `s`
+    ///
+    /// `map` and `maps` both match the `Maps` category, `tool` matches the `Tool` type.
+    pub fn is_in_category(
+        &self,
+        gamebanana_type: &str,
+        gamebanana_id: u32,
+        category: &str,
+    ) -> bool {
+        let matches = |name: &str| {
+            let name = name.trim_end_matches(['s', 'S']);
+            name.eq_ignore_ascii_case(category.trim_end_matches(['s', 'S']))
+        };
+        matches(gamebanana_type)
+            || self
+                .get_by_id(gamebanana_type, gamebanana_id)
+                .and_then(|entry| entry.category_name.as_deref())
+                .is_some_and(matches)
+    }
+}
+
+impl SearchDatabaseEntry {
+    /// Statistics formatted for display
+    pub fn stats_line(&self) -> String {
+        format!(
+            "{} downloads, {} views, {} likes",
+            self.downloads, self.views, self.likes
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_search_database() {
+        let data = br#"
+- Name: Strawberry Jam
+  GameBananaType: Mod
+  GameBananaId: 424541
+  Author: StrawberryJam2021
+  Views: 1000
+  Likes: 50
+  Downloads: 700
+  CategoryName: Maps
+  Screenshots: []
+- Name: A Tool
+  GameBananaType: Tool
+  GameBananaId: 424541
+"#;
+
+        let database = SearchDatabase::parse(data).unwrap();
+
+        let entry = &database.entries[&(String::from("Mod"), 424541)];
+        assert_eq!(entry.downloads, 700);
+        assert_eq!(entry.stats_line(), "700 downloads, 1000 views, 50 likes");
+        assert_eq!(database.entries[&(String::from("Tool"), 424541)].likes, 0);
+        assert!(database.is_in_category("Mod", 424541, "maps"));
+        assert!(database.is_in_category("Tool", 424541, "tools"));
+        assert!(!database.is_in_category("Mod", 424541, "helpers"));
+        assert_eq!(
+            database.get_by_id("Mod", 424541).unwrap().author.as_deref(),
+            Some("StrawberryJam2021")
+        );
+        assert_eq!(database.get_by_id("Tool", 424541).unwrap().author, None);
+        assert_eq!(database.category_of("Mod", 424541), Some("Maps"));
+        assert_eq!(database.category_of("Tool", 424541), Some("Tool"));
+        assert_eq!(database.category_of("Mod", 1), None);
+    }
+}
@@ -0,0 +1,180 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use reqwest::Url;
+use tokio::{
+    fs,
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{info, warn};
+
+use crate::{constant::VENDOR_MANIFEST_FILE, error::Error, vendor::VendorManifest};
+
+/// Minimal HTTP server hosting a vendor directory as a mod registry
+///
+/// Only `GET`/`HEAD` of `/everest_update.yaml` and of the vendored archives are supported.
+/// The registry is generated per request so that download URLs point at the host name
+/// the client used to reach the server.
+struct VendorServer {
+    vendor_dir: PathBuf,
+    manifest: VendorManifest,
+    /// URL-encoded request path (e.g. `/My%20Mod.zip`) to archive file name
+    archives: HashMap<String, String>,
+}
+
+/// Serves the vendor directory until the process is interrupted
+pub async fn serve(vendor_dir: &Path, addr: SocketAddr) -> Result<(), Error> {
+    let manifest = VendorManifest::load(vendor_dir)?;
+    if manifest.entries.is_empty() {
+        return Err(Error::EmptyVendorDirectory(vendor_dir.to_path_buf()));
+    }
+
+    let base = Url::parse("http://localhost/").expect("static URL is valid");
+    let archives = manifest
+        .entries
+        .values()
+        .filter_map(|entry| {
+            let url = base.join(&entry.download_url).ok()?;
+            Some((url.path().to_string(), entry.download_url.clone()))
+        })
+        .collect();
+    let server = Arc::new(VendorServer {
+        vendor_dir: vendor_dir.to_path_buf(),
+        manifest,
+        archives,
+    });
+
+    let listener = TcpListener::bind(addr).await?;
+    println!(
+        "Serving {} mods from {} on http://{}/{}",
+        server.manifest.entries.len(),
+        vendor_dir.display(),
+        listener.local_addr()?,
+        VENDOR_MANIFEST_FILE
+    );
+    println!("Press Ctrl+C to stop");
+
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let server = Arc::clone(&server);
+        tokio::spawn(async move {
+            if let Err(e) = server.handle_connection(stream).await {
+                warn!("Failed to handle request from {}: {}", peer, e);
+            }
+        });
+    }
+}
+
+impl VendorServer {
+    async fn handle_connection(&self, stream: TcpStream) -> Result<(), Error> {
+        let mut reader = BufReader::new(stream);
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line).await?;
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let path = parts.next().unwrap_or_default().to_string();
+
+        // Read the headers, only `Host` is of interest
+        let mut host = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':')
+                && name.eq_ignore_ascii_case("host")
+            {
+                host = Some(value.trim().to_string());
+            }
+        }
+        let mut stream = reader.into_inner();
+        info!("{} {}", method, path);
+
+        let head_only = match method.as_str() {
+            "GET" => false,
+            "HEAD" => true,
+            _ => {
+                let status = "405 Method Not Allowed";
+                return respond(&mut stream, status, "text/plain", b"", false).await;
+            }
+        };
+
+        if path == format!("/{}", VENDOR_MANIFEST_FILE) {
+            let host = host.unwrap_or_else(|| String::from("localhost"));
+            let body = self.registry_for_host(&host)?;
+            return respond(
+                &mut stream,
+                "200 OK",
+                "text/yaml",
+                body.as_bytes(),
+                head_only,
+            )
+            .await;
+        }
+
+        let Some(file_name) = self.archives.get(&path) else {
+            let status = "404 Not Found";
+            return respond(&mut stream, status, "text/plain", b"Not Found", head_only).await;
+        };
+        let archive_path = self.vendor_dir.join(file_name);
+        let mut file = fs::File::open(&archive_path).await?;
+        let size = file.metadata().await?.len();
+        let header = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/zip\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            size
+        );
+        stream.write_all(header.as_bytes()).await?;
+        if !head_only {
+            tokio::io::copy(&mut file, &mut stream).await?;
+        }
+        stream.shutdown().await?;
+        Ok(())
+    }
+
+    /// Renders the vendor manifest as a registry whose URLs point at this server
+    fn registry_for_host(&self, host: &str) -> Result<String, Error> {
+        let base = Url::parse(&format!("http://{}/", host))
+            .map_err(|_| Error::InvalidHostHeader(host.to_string()))?;
+        let entries: BTreeMap<_, _> = self
+            .manifest
+            .entries
+            .iter()
+            .map(|(name, entry)| {
+                let mut entry = entry.clone();
+                if let Ok(url) = base.join(&entry.download_url) {
+                    entry.download_url = url.to_string();
+                }
+                (name, entry)
+            })
+            .collect();
+        Ok(serde_yaml_ng::to_string(&entries)?)
+    }
+}
+
+/// Writes a complete response, the body is omitted for `HEAD` requests
+async fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+    head_only: bool,
+) -> Result<(), Error> {
+    let header = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    );
+    stream.write_all(header.as_bytes()).await?;
+    if !head_only {
+        stream.write_all(body).await?;
+    }
+    stream.shutdown().await?;
+    Ok(())
+}
@@ -0,0 +1,162 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use tracing::{info, warn};
+
+use crate::{
+    error::{Context, Error},
+    export::{self, CSV_HEADER},
+    installed_mods::LocalModInfo,
+    pack::Pack,
+};
+
+/// The enabled mods of a setup by name, with their version when the setup pins it
+#[derive(Debug, Default)]
+pub struct Setup {
+    pub mods: BTreeMap<String, Option<String>>,
+}
+
+/// Differences between two setups, sorted by mod name
+#[derive(Debug, Default)]
+pub struct SetupDiff {
+    /// Mods only in the second setup, with their version
+    pub added: Vec<(String, Option<String>)>,
+    /// Mods only in the first setup, with their version
+    pub removed: Vec<(String, Option<String>)>,
+    /// Mods in both setups in different versions: name, first version, second version
+    pub changed: Vec<(String, String, String)>,
+}
+
+impl Setup {
+    /// Reads an export, either a pack (`pack create`) or a CSV export (`list --csv`)
+    ///
+    /// Rows of the CSV export which are disabled are left out, like `pack create` leaves out
+    /// disabled mods, since the game does not load them.
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        info!("Reading the setup {}", path.display());
+        let data = fs::read(path).in_file(path)?;
+        let text = String::from_utf8_lossy(&data);
+        let header = text.lines().next().unwrap_or_default();
+        if export::parse_csv_row(header) == CSV_HEADER {
+            return Ok(Self::from_csv(&text, path));
+        }
+        let pack = Pack::parse(&data).in_file(path)?;
+        Ok(Self {
+            mods: pack
+                .mods
+                .into_iter()
+                .map(|pack_mod| (pack_mod.name, pack_mod.version))
+                .collect(),
+        })
+    }
+
+    /// The installed mods, which should only be the enabled ones
+    pub fn from_installed(installed_mods: &[&LocalModInfo]) -> Self {
+        Self {
+            mods: installed_mods
+                .iter()
+                .map(|mod_info| {
+                    (
+                        mod_info.manifest.name.clone(),
+                        Some(mod_info.manifest.version.clone()),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn from_csv(text: &str, path: &Path) -> Self {
+        let mut mods = BTreeMap::new();
+        for (line, row) in text.lines().enumerate().skip(1) {
+            if row.trim().is_empty() {
+                continue;
+            }
+            let fields = export::parse_csv_row(row);
+            let [_, name, version, _, _, enabled] = fields.as_slice() else {
+                warn!("Skipping line {} of {}: {}", line + 1, path.display(), row);
+                continue;
+            };
+            if enabled == "true" {
+                mods.insert(name.clone(), Some(version.clone()));
+            }
+        }
+        Self { mods }
+    }
+
+    /// Mods added, removed and changed from this setup to `other`
+    ///
+    /// A version is only compared when both setups have one, an unpinned mod matches any version.
+    pub fn diff(&self, other: &Setup) -> SetupDiff {
+        let mut diff = SetupDiff::default();
+        for (name, version) in &self.mods {
+            match other.mods.get(name) {
+                None => diff.removed.push((name.clone(), version.clone())),
+                Some(Some(other_version)) => {
+                    if let Some(version) = version
+                        && version != other_version
+                    {
+                        diff.changed
+                            .push((name.clone(), version.clone(), other_version.clone()));
+                    }
+                }
+                Some(None) => {}
+            }
+        }
+        for (name, version) in &other.mods {
+            if !self.mods.contains_key(name) {
+                diff.added.push((name.clone(), version.clone()));
+            }
+        }
+        diff
+    }
+}
+
+impl SetupDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_diff_csv_export_and_pack() {
+        let dir = TempDir::new().unwrap();
+        let csv = dir.path().join("mine.csv");
+        fs::write(
+            &csv,
+            "filename,name,version,size,xxhash,enabled\n\
+            SpeedrunTool.zip,SpeedrunTool,3.24.4,1024,0123456789abcdef,true\n\
+            \"Collab, Part 1.zip\",Collab,1.0.0,2048,fedcba9876543210,true\n\
+            Old.zip,OldMap,1.0.0,512,0011223344556677,false\n",
+        )
+        .unwrap();
+        let pack = dir.path().join("friend.yaml");
+        fs::write(
+            &pack,
+            "Name: Friend\nMods:\n  - Name: SpeedrunTool\n    Version: 3.25.0\n  \
+            - Name: Collab\n  - Name: CommunalHelper\n    Version: 1.20.0\n",
+        )
+        .unwrap();
+
+        let diff = Setup::load(&csv)
+            .unwrap()
+            .diff(&Setup::load(&pack).unwrap());
+
+        assert_eq!(
+            diff.added,
+            vec![(String::from("CommunalHelper"), Some(String::from("1.20.0")))]
+        );
+        assert!(diff.removed.is_empty());
+        assert_eq!(
+            diff.changed,
+            vec![(
+                String::from("SpeedrunTool"),
+                String::from("3.24.4"),
+                String::from("3.25.0")
+            )]
+        );
+    }
+}
@@ -0,0 +1,454 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    checksum,
+    constant::{BLACKLIST_FILE, FAVORITES_FILE, SNAPSHOTS_DIRECTORY, UPDATER_BLACKLIST_FILE},
+    error::{Context, Error},
+    fileutil,
+    game::GameVersions,
+    installed_index::{IndexEntry, InstalledIndex},
+    installed_mods::LocalModInfo,
+    timeutil,
+};
+
+/// Lists of archives in the mods directory captured with the archives
+const LISTS: [&str; 3] = [BLACKLIST_FILE, FAVORITES_FILE, UPDATER_BLACKLIST_FILE];
+
+/// Directory of the archives of every snapshot, in the snapshots directory
+const ARCHIVES_DIRECTORY: &str = "archives";
+
+/// The complete state of a mods directory, stored as `snapshots/<name>.yaml` in the data directory
+///
+/// The archives themselves are copied to `snapshots/archives/<xxHash>.zip`, so an archive kept
+/// by several snapshots is stored once.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Unix timestamp of the freeze
+    #[serde(rename = "CreatedAt")]
+    pub created_at: u64,
+    #[serde(rename = "Celeste", default, skip_serializing_if = "Option::is_none")]
+    pub celeste: Option<String>,
+    #[serde(rename = "Everest", default, skip_serializing_if = "Option::is_none")]
+    pub everest: Option<String>,
+    /// Installed archives by file name
+    #[serde(rename = "Archives")]
+    pub archives: BTreeMap<String, SnapshotArchive>,
+    /// Content of `blacklist.txt`, `favorites.txt` and `updaterblacklist.txt`, absent files are
+    /// left out
+    #[serde(rename = "Lists", default)]
+    pub lists: BTreeMap<String, String>,
+}
+
+/// An archive of a snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotArchive {
+    /// Mod name from the manifest, empty when the archive has none
+    #[serde(rename = "Name", default)]
+    pub name: String,
+    #[serde(rename = "Version", default)]
+    pub version: String,
+    #[serde(rename = "xxHash")]
+    pub xxhash: String,
+    #[serde(rename = "Size")]
+    pub size: u64,
+    /// What the installed index knew about the archive, restored with it
+    #[serde(rename = "Index", default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<IndexEntry>,
+}
+
+/// What thawing a snapshot changes in the mods directory
+#[derive(Debug, Default)]
+pub struct ThawPlan {
+    /// Archives missing or different from the snapshot, copied back from it
+    pub restored: Vec<String>,
+    /// Installed archives which are not in the snapshot
+    pub removed: Vec<String>,
+    /// Lists whose content differs from the snapshot
+    pub lists: Vec<String>,
+}
+
+impl ThawPlan {
+    pub fn is_empty(&self) -> bool {
+        self.restored.is_empty() && self.removed.is_empty() && self.lists.is_empty()
+    }
+}
+
+impl Snapshot {
+    /// Hashes the archives of a mods directory and reads its lists, without storing anything
+    pub fn capture(
+        mods_dir: &Path,
+        installed_mods: &[LocalModInfo],
+        installed_index: &InstalledIndex,
+        versions: &GameVersions,
+        jobs: u32,
+    ) -> Result<Self, Error> {
+        let archive_paths = fileutil::find_installed_mod_archives(mods_dir)?;
+        let files: Vec<(&Path, &[String])> = archive_paths
+            .iter()
+            .map(|path| (path.as_path(), &[][..]))
+            .collect();
+        let mut archives = BTreeMap::new();
+        for (path, digests) in archive_paths.iter().zip(checksum::hash_files(&files, jobs)) {
+            let manifest = installed_mods
+                .iter()
+                .find(|installed| &installed.archive_path == path)
+                .map(|installed| &installed.manifest);
+            archives.insert(
+                path.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into(),
+                SnapshotArchive {
+                    name: manifest.map(|m| m.name.clone()).unwrap_or_default(),
+                    version: manifest.map(|m| m.version.clone()).unwrap_or_default(),
+                    xxhash: digests?.xxh64,
+                    size: fs::metadata(path).in_file(path)?.len(),
+                    index: installed_index.get(path).cloned(),
+                },
+            );
+        }
+
+        let mut lists = BTreeMap::new();
+        for file_name in LISTS {
+            let path = mods_dir.join(file_name);
+            if path.exists() {
+                lists.insert(
+                    file_name.to_string(),
+                    fs::read_to_string(&path).in_file(&path)?,
+                );
+            }
+        }
+
+        Ok(Self {
+            created_at: timeutil::now(),
+            celeste: versions.celeste.clone(),
+            everest: versions.everest.clone(),
+            archives,
+            lists,
+        })
+    }
+
+    /// Copies the archives which are not stored yet from the mods directory, returns their count
+    pub fn store_archives(&self, data_dir: &Path, mods_dir: &Path) -> Result<usize, Error> {
+        let store = data_dir.join(SNAPSHOTS_DIRECTORY).join(ARCHIVES_DIRECTORY);
+        fs::create_dir_all(&store)?;
+        let mut stored = 0;
+        for (file_name, archive) in &self.archives {
+            let path = stored_path(data_dir, archive);
+            if path.exists() {
+                continue;
+            }
+            info!("Storing {} as {}", file_name, path.display());
+            // An interrupted copy must not pass for a stored archive
+            let partial = path.with_extension("zip.partial");
+            let source = mods_dir.join(file_name);
+            fs::copy(&source, &partial).in_file(&source)?;
+            fs::rename(&partial, &path)?;
+            stored += 1;
+        }
+        Ok(stored)
+    }
+
+    pub fn load(data_dir: &Path, name: &str) -> Result<Self, Error> {
+        let path = snapshot_path(data_dir, name)?;
+        if !path.exists() {
+            return Err(Error::UnknownSnapshot(name.to_string()));
+        }
+        Ok(serde_yaml_ng::from_slice(&fs::read(&path)?)?)
+    }
+
+    pub fn save(&self, data_dir: &Path, name: &str) -> Result<(), Error> {
+        let path = snapshot_path(data_dir, name)?;
+        info!("Writing {}", path.display());
+        fs::create_dir_all(data_dir.join(SNAPSHOTS_DIRECTORY))?;
+        fs::write(path, serde_yaml_ng::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Deletes a snapshot, then the stored archives which no other snapshot contains
+    ///
+    /// Returns the number and total size of the archives deleted.
+    pub fn delete(data_dir: &Path, name: &str) -> Result<(usize, u64), Error> {
+        let path = snapshot_path(data_dir, name)?;
+        if !path.exists() {
+            return Err(Error::UnknownSnapshot(name.to_string()));
+        }
+        info!("Deleting {}", path.display());
+        fileutil::remove_file(&path)?;
+        collect_archives(data_dir)
+    }
+
+    /// Saved snapshots sorted by name, unreadable ones are left out
+    pub fn list(data_dir: &Path) -> Result<Vec<(String, Self)>, Error> {
+        let dir = data_dir.join(SNAPSHOTS_DIRECTORY);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut snapshots: Vec<_> = fs::read_dir(dir)?
+            .filter_map(|entry| {
+                let path = entry.ok()?.path();
+                (path.extension()? == "yaml").then_some(())?;
+                let name = path.file_stem()?.to_string_lossy().into_owned();
+                let snapshot = serde_yaml_ng::from_slice(&fs::read(&path).ok()?).ok()?;
+                Some((name, snapshot))
+            })
+            .collect();
+        snapshots.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(snapshots)
+    }
+
+    /// Changes turning the `current` state into this snapshot
+    pub fn plan(&self, current: &Snapshot) -> ThawPlan {
+        ThawPlan {
+            restored: self
+                .archives
+                .iter()
+                .filter(|(file_name, archive)| {
+                    current
+                        .archives
+                        .get(*file_name)
+                        .is_none_or(|installed| installed.xxhash != archive.xxhash)
+                })
+                .map(|(file_name, _)| file_name.clone())
+                .collect(),
+            removed: current
+                .archives
+                .keys()
+                .filter(|file_name| !self.archives.contains_key(*file_name))
+                .cloned()
+                .collect(),
+            lists: LISTS
+                .iter()
+                .filter(|file_name| self.lists.get(**file_name) != current.lists.get(**file_name))
+                .map(|file_name| file_name.to_string())
+                .collect(),
+        }
+    }
+
+    /// Fails if an archive of the snapshot was deleted from the store, before anything is changed
+    pub fn check_archives(&self, data_dir: &Path) -> Result<(), Error> {
+        for archive in self.archives.values() {
+            let path = stored_path(data_dir, archive);
+            fs::metadata(&path).in_file(&path)?;
+        }
+        Ok(())
+    }
+
+    /// Applies a plan to the mods directory and restores the index entries of the archives
+    pub fn thaw(
+        &self,
+        plan: &ThawPlan,
+        data_dir: &Path,
+        mods_dir: &Path,
+        installed_index: &mut InstalledIndex,
+    ) -> Result<(), Error> {
+        // The index follows each archive as it changes, so that it stays right when a later one fails
+        for file_name in &plan.removed {
+            let path = mods_dir.join(file_name);
+            fileutil::remove_file(&path)?;
+            installed_index.remove(&path);
+        }
+        for file_name in &plan.restored {
+            let path = mods_dir.join(file_name);
+            let archive = &self.archives[file_name];
+            let stored = stored_path(data_dir, archive);
+            info!("Restoring {} from {}", path.display(), stored.display());
+            let partial = path.with_extension("zip.partial");
+            fs::copy(&stored, &partial).in_file(&stored)?;
+            fileutil::retry_locked(&path, || fs::rename(&partial, &path))?;
+            restore_index_entry(installed_index, &path, archive);
+        }
+        for (file_name, archive) in &self.archives {
+            restore_index_entry(installed_index, &mods_dir.join(file_name), archive);
+        }
+        for file_name in &plan.lists {
+            let path = mods_dir.join(file_name);
+            match self.lists.get(file_name) {
+                Some(content) => fs::write(&path, content).in_file(&path)?,
+                None => fileutil::remove_file(&path)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fails on names which cannot be used as snapshot names, before a snapshot is captured
+pub fn validate_name(name: &str) -> Result<(), Error> {
+    snapshot_path(Path::new(""), name).map(|_| ())
+}
+
+fn restore_index_entry(
+    installed_index: &mut InstalledIndex,
+    path: &Path,
+    archive: &SnapshotArchive,
+) {
+    match &archive.index {
+        Some(entry) => installed_index.insert(path, entry.clone()),
+        None => {
+            installed_index.remove(path);
+        }
+    }
+}
+
+/// Deletes the stored archives which no snapshot contains, returns their number and total size
+///
+/// Nothing is deleted while a snapshot cannot be read, as its archives are unknown.
+fn collect_archives(data_dir: &Path) -> Result<(usize, u64), Error> {
+    let dir = data_dir.join(SNAPSHOTS_DIRECTORY);
+    let store = dir.join(ARCHIVES_DIRECTORY);
+    if !store.exists() {
+        return Ok((0, 0));
+    }
+    let mut referenced = HashSet::new();
+    for entry in fs::read_dir(&dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|extension| extension != "yaml") {
+            continue;
+        }
+        let snapshot: Snapshot = match fs::read(&path)
+            .map_err(Error::from)
+            .and_then(|data| Ok(serde_yaml_ng::from_slice(&data)?))
+        {
+            Ok(snapshot) => snapshot,
+            Err(e) => {
+                warn!(
+                    "Keeping the stored archives, {} cannot be read: {}",
+                    path.display(),
+                    e
+                );
+                return Ok((0, 0));
+            }
+        };
+        referenced.extend(
+            snapshot
+                .archives
+                .values()
+                .map(|archive| stored_path(data_dir, archive)),
+        );
+    }
+
+    let (mut count, mut size) = (0, 0);
+    for entry in fs::read_dir(&store)? {
+        let path = entry?.path();
+        if referenced.contains(&path) {
+            continue;
+        }
+        let len = fs::metadata(&path).map_or(0, |metadata| metadata.len());
+        info!("Deleting the unreferenced archive {}", path.display());
+        fileutil::remove_file(&path)?;
+        count += 1;
+        size += len;
+    }
+    Ok((count, size))
+}
+
+/// Path of a stored archive, named after its hash
+fn stored_path(data_dir: &Path, archive: &SnapshotArchive) -> PathBuf {
+    data_dir
+        .join(SNAPSHOTS_DIRECTORY)
+        .join(ARCHIVES_DIRECTORY)
+        .join(format!("{}.zip", archive.xxhash))
+}
+
+/// Path of a snapshot file, rejecting names which are not plain file names
+fn snapshot_path(data_dir: &Path, name: &str) -> Result<PathBuf, Error> {
+    if !fileutil::is_plain_file_name(name) {
+        return Err(Error::InvalidSnapshotName(name.to_string()));
+    }
+    Ok(data_dir
+        .join(SNAPSHOTS_DIRECTORY)
+        .join(format!("{}.yaml", name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn capture(mods_dir: &Path) -> Snapshot {
+        Snapshot::capture(
+            mods_dir,
+            &[],
+            &InstalledIndex::default(),
+            &GameVersions::default(),
+            2,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_thaw_restores_the_frozen_state() {
+        let data_dir = TempDir::new().unwrap();
+        let mods_dir = TempDir::new().unwrap();
+        fs::write(mods_dir.path().join("Kept.zip"), "kept").unwrap();
+        fs::write(mods_dir.path().join("Updated.zip"), "1.0.0").unwrap();
+        fs::write(mods_dir.path().join(BLACKLIST_FILE), "Kept.zip\n").unwrap();
+        let frozen = capture(mods_dir.path());
+        frozen
+            .store_archives(data_dir.path(), mods_dir.path())
+            .unwrap();
+        frozen.save(data_dir.path(), "before").unwrap();
+
+        fs::write(mods_dir.path().join("Updated.zip"), "2.0.0").unwrap();
+        fs::write(mods_dir.path().join("New.zip"), "new").unwrap();
+        fs::remove_file(mods_dir.path().join(BLACKLIST_FILE)).unwrap();
+        fs::write(mods_dir.path().join(FAVORITES_FILE), "New.zip\n").unwrap();
+
+        let snapshot = Snapshot::load(data_dir.path(), "before").unwrap();
+        let plan = snapshot.plan(&capture(mods_dir.path()));
+        assert_eq!(plan.restored, vec!["Updated.zip"]);
+        assert_eq!(plan.removed, vec!["New.zip"]);
+        assert_eq!(plan.lists, vec![BLACKLIST_FILE, FAVORITES_FILE]);
+        snapshot
+            .thaw(
+                &plan,
+                data_dir.path(),
+                mods_dir.path(),
+                &mut InstalledIndex::default(),
+            )
+            .unwrap();
+
+        let read = |file_name| fs::read_to_string(mods_dir.path().join(file_name)).ok();
+        assert_eq!(read("Updated.zip").as_deref(), Some("1.0.0"));
+        assert_eq!(read("New.zip"), None);
+        assert_eq!(read(BLACKLIST_FILE).as_deref(), Some("Kept.zip\n"));
+        assert_eq!(read(FAVORITES_FILE), None);
+        assert!(snapshot.plan(&capture(mods_dir.path())).is_empty());
+    }
+
+    #[test]
+    fn test_delete_collects_the_archives_of_no_other_snapshot() {
+        let data_dir = TempDir::new().unwrap();
+        let mods_dir = TempDir::new().unwrap();
+        let freeze = |name| {
+            let snapshot = capture(mods_dir.path());
+            snapshot
+                .store_archives(data_dir.path(), mods_dir.path())
+                .unwrap();
+            snapshot.save(data_dir.path(), name).unwrap();
+        };
+        fs::write(mods_dir.path().join("Shared.zip"), "shared").unwrap();
+        fs::write(mods_dir.path().join("Old.zip"), "old").unwrap();
+        freeze("first");
+        fs::remove_file(mods_dir.path().join("Old.zip")).unwrap();
+        fs::write(mods_dir.path().join("New.zip"), "new").unwrap();
+        freeze("second");
+
+        assert_eq!(Snapshot::delete(data_dir.path(), "first").unwrap(), (1, 3));
+
+        let second = Snapshot::load(data_dir.path(), "second").unwrap();
+        second.check_archives(data_dir.path()).unwrap();
+        assert!(matches!(
+            Snapshot::delete(data_dir.path(), "first"),
+            Err(Error::UnknownSnapshot(_))
+        ));
+        assert_eq!(Snapshot::delete(data_dir.path(), "second").unwrap(), (2, 9));
+    }
+}
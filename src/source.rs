@@ -0,0 +1,139 @@
+use std::{collections::HashMap, sync::OnceLock};
+
+use futures_util::future::BoxFuture;
+
+use crate::{
+    checksum::Digests,
+    download::{BatchProgress, DownloadedArchive, ModDownloader},
+    error::Error,
+    mod_files_database::ModFilesDatabase,
+    mod_registry::{HashIndex, ModRegistry, RemoteModInfo},
+};
+
+/// A backend providing installable mods, e.g. the mod registry
+///
+/// Commands find, download and verify mods through this trait, so that other backends such as
+/// the GameBanana API, a local folder or a private registry can be added without changing them.
+pub trait ModSource: Send + Sync {
+    /// Where the mods come from, e.g. the URL of the registry
+    fn name(&self) -> &str;
+
+    /// Finds mods matching a query, best matches first
+    ///
+    /// Matches of the same kind are ordered by `popularity`, highest first.
+    fn search(
+        &self,
+        query: &str,
+        popularity: &dyn Fn(&RemoteModInfo) -> Option<(u64, u64)>,
+    ) -> Vec<&RemoteModInfo>;
+
+    /// Looks up a mod by its exact name
+    fn resolve(&self, name: &str) -> Option<&RemoteModInfo>;
+
+    /// Finds the mod a local archive is, whatever its file or mod name
+    fn identify(&self, digests: &Digests) -> Option<&RemoteModInfo>;
+
+    /// Mods downloaded from a GameBanana submission, sorted by name
+    fn find_by_gamebanana(&self, item_type: &str, item_id: u32) -> Vec<&RemoteModInfo>;
+
+    /// Downloads the archive of a mod, verified against its checksums
+    fn download<'s>(
+        &'s self,
+        mod_info: &'s RemoteModInfo,
+    ) -> BoxFuture<'s, Result<DownloadedArchive, Error>>;
+
+    /// Whether a local archive is the one provided for a mod
+    fn verify(&self, mod_info: &RemoteModInfo, digests: &Digests) -> bool {
+        mod_info.has_matching_hash(digests)
+    }
+
+    /// The same source, reporting its downloads to a batch progress
+    fn with_batch_progress(&self, batch_progress: &BatchProgress) -> Box<dyn ModSource + '_>;
+}
+
+/// Mods of an `everest_update.yaml` registry, downloaded over HTTP
+#[derive(Debug, Clone)]
+pub struct RegistrySource<'a> {
+    registry: &'a ModRegistry,
+    downloader: ModDownloader,
+    /// Names declared inside archives, mapped to the registry key of the archive
+    aliases: HashMap<String, String>,
+    /// Built on the first lookup by checksum
+    hash_index: OnceLock<HashIndex<'a>>,
+}
+
+impl<'a> RegistrySource<'a> {
+    pub fn new(registry: &'a ModRegistry, downloader: ModDownloader) -> Self {
+        Self {
+            registry,
+            downloader,
+            aliases: HashMap::new(),
+            hash_index: OnceLock::new(),
+        }
+    }
+
+    /// Also resolves the names which archives declare besides the one they are registered under
+    pub fn with_files_database(mut self, files_database: &ModFilesDatabase) -> Self {
+        self.aliases = files_database.aliases(self.registry);
+        self
+    }
+}
+
+impl ModSource for RegistrySource<'_> {
+    fn name(&self) -> &str {
+        self.downloader.registry_url()
+    }
+
+    fn search(
+        &self,
+        query: &str,
+        popularity: &dyn Fn(&RemoteModInfo) -> Option<(u64, u64)>,
+    ) -> Vec<&RemoteModInfo> {
+        self.registry.search(query, popularity)
+    }
+
+    fn resolve(&self, name: &str) -> Option<&RemoteModInfo> {
+        self.registry.get_mod_info(name).or_else(|| {
+            self.aliases
+                .get(name)
+                .and_then(|key| self.registry.get_mod_info(key))
+        })
+    }
+
+    fn identify(&self, digests: &Digests) -> Option<&RemoteModInfo> {
+        self.hash_index
+            .get_or_init(|| self.registry.hash_index())
+            .find(digests)
+    }
+
+    fn find_by_gamebanana(&self, item_type: &str, item_id: u32) -> Vec<&RemoteModInfo> {
+        self.registry.find_by_gamebanana(item_type, item_id)
+    }
+
+    fn download<'s>(
+        &'s self,
+        mod_info: &'s RemoteModInfo,
+    ) -> BoxFuture<'s, Result<DownloadedArchive, Error>> {
+        Box::pin(async move {
+            let mirror_url = mod_info.mirror_url();
+            self.downloader
+                .download_mod(
+                    &mod_info.download_url,
+                    mirror_url.as_deref(),
+                    &mod_info.name,
+                    &mod_info.checksums,
+                    mod_info.sha256.as_deref(),
+                )
+                .await
+        })
+    }
+
+    fn with_batch_progress(&self, batch_progress: &BatchProgress) -> Box<dyn ModSource + '_> {
+        Box::new(Self {
+            registry: self.registry,
+            downloader: self.downloader.with_batch_progress(batch_progress),
+            aliases: self.aliases.clone(),
+            hash_index: self.hash_index.clone(),
+        })
+    }
+}
@@ -0,0 +1,388 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use tracing::{info, warn};
+
+use crate::{constant::STAGING_DIRECTORY, error::Error, fileutil};
+
+/// An archive downloaded to the staging directory
+#[derive(Debug)]
+pub struct StagedArchive {
+    pub path: PathBuf,
+    /// Outdated archive of the mods directory to remove once the new one is in place
+    pub replaces: Option<PathBuf>,
+}
+
+/// Directory where the archives of a batch are downloaded before they are moved into the mods directory
+///
+/// It lives in the mods directory so that archives are moved rather than copied, and the tool
+/// does not scan subdirectories.
+#[derive(Debug)]
+pub struct Staging {
+    dir: PathBuf,
+    mods_dir: PathBuf,
+}
+
+/// A change made by a commit, to revert on failure
+enum Change {
+    Moved { from: PathBuf, to: PathBuf },
+    Created(PathBuf),
+}
+
+/// Moves a file, copying it when the destination is on another drive, e.g. an extra mods directory
+fn rename(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::rename(from, to) {
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            fs::copy(from, to)?;
+            fs::remove_file(from)
+        }
+        result => result,
+    }
+}
+
+/// File next to a subdirectory of set aside archives, with the mods directory they come from
+fn source_file(aside_subdir: &Path) -> PathBuf {
+    aside_subdir.with_extension("source")
+}
+
+/// Puts back the outdated archives an interrupted commit set aside, returns whether all could be
+///
+/// An archive whose place has been taken, e.g. by its update, is left where it is.
+fn restore_set_aside(aside_dir: &Path, mods_dir: &Path) -> Result<bool, Error> {
+    let mut restored = true;
+    let Ok(subdirs) = fs::read_dir(aside_dir) else {
+        return Ok(true);
+    };
+    for subdir in subdirs.filter_map(|entry| Some(entry.ok()?.path())) {
+        if !subdir.is_dir() {
+            continue;
+        }
+        let source_dir = fs::read_to_string(source_file(&subdir))
+            .map_or_else(|_| mods_dir.to_path_buf(), PathBuf::from);
+        for path in fs::read_dir(&subdir)?.filter_map(|entry| Some(entry.ok()?.path())) {
+            let original = source_dir.join(path.file_name().unwrap_or_default());
+            if original.exists() {
+                println!(
+                    "Warning: {} was set aside by an interrupted batch, but {} exists",
+                    path.display(),
+                    original.display()
+                );
+                restored = false;
+                continue;
+            }
+            rename(&path, &original)?;
+            println!("Restored {}", original.display());
+        }
+    }
+    Ok(restored)
+}
+
+/// Where the outdated target of a link is set aside during a commit
+fn outdated_path(target: &Path) -> PathBuf {
+    let mut path = target.as_os_str().to_os_string();
+    path.push(".outdated");
+    path.into()
+}
+
+impl Staging {
+    /// Creates an empty staging directory, removing leftovers of an interrupted batch
+    ///
+    /// Outdated archives the interrupted batch had set aside are put back first. When one cannot
+    /// be, the staging directory is kept and an error is returned, the user has to sort it out.
+    pub fn create(mods_dir: &Path) -> Result<Self, Error> {
+        let dir = mods_dir.join(STAGING_DIRECTORY);
+        if dir.exists() {
+            warn!(
+                "Removing leftovers of an interrupted batch in {}",
+                dir.display()
+            );
+            let aside_dir = dir.join("outdated");
+            if !restore_set_aside(&aside_dir, mods_dir)? {
+                return Err(Error::InterruptedBatch(aside_dir));
+            }
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            mods_dir: mods_dir.to_path_buf(),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Moves the staged archives into the mods directory and removes the outdated ones
+    ///
+    /// Outdated archives are first set aside in the staging directory, so that if any move fails
+    /// the mods directory is put back as it was. An outdated archive which is a symbolic link
+    /// keeps its link, the file it points to is replaced instead. Returns the new paths of the
+    /// archives, in order.
+    pub fn commit(self, archives: &[StagedArchive]) -> Result<Vec<PathBuf>, Error> {
+        let aside_dir = self.dir.join("outdated");
+        fs::create_dir_all(&aside_dir)?;
+        let mut changes = Vec::new();
+        match self.swap(archives, &aside_dir, &mut changes) {
+            Ok(paths) => {
+                // Targets of links are set aside next to them, not in the staging directory
+                for change in &changes {
+                    if let Change::Created(target) = change
+                        && let Err(e) = fileutil::remove_file(&outdated_path(target))
+                    {
+                        warn!("Failed to remove the outdated {}: {}", target.display(), e);
+                    }
+                }
+                self.discard();
+                Ok(paths)
+            }
+            Err(e) => {
+                for change in changes.iter().rev() {
+                    let (result, path) = match change {
+                        Change::Moved { from, to } => (rename(to, from), from),
+                        Change::Created(path) => (fs::remove_file(path), path),
+                    };
+                    if let Err(e) = result {
+                        eprintln!("Failed to restore {}: {}", path.display(), e);
+                    }
+                }
+                Err(e)
+            }
+        }
+    }
+
+    fn swap(
+        &self,
+        archives: &[StagedArchive],
+        aside_dir: &Path,
+        changes: &mut Vec<Change>,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let move_file = |changes: &mut Vec<Change>, from: &Path, to: PathBuf| {
+            info!("Moving {} to {}", from.display(), to.display());
+            fileutil::retry_locked(from, || rename(from, &to))?;
+            changes.push(Change::Moved {
+                from: from.to_path_buf(),
+                to: to.clone(),
+            });
+            Ok::<_, Error>(to)
+        };
+
+        // One subdirectory per mods directory, archives of the same name in two of them must not collide
+        let mut source_dirs = Vec::new();
+        let mut set_aside = |path: &Path| {
+            let source_dir = path.parent().unwrap_or(Path::new(""));
+            let index = match source_dirs.iter().position(|dir| dir == source_dir) {
+                Some(index) => index,
+                None => {
+                    source_dirs.push(source_dir.to_path_buf());
+                    source_dirs.len() - 1
+                }
+            };
+            let dir = aside_dir.join(index.to_string());
+            if !dir.exists() {
+                fs::create_dir_all(&dir)?;
+                fs::write(source_file(&dir), source_dir.to_string_lossy().as_bytes())?;
+            }
+            Ok::<_, Error>(dir.join(path.file_name().unwrap_or_default()))
+        };
+        for outdated in archives
+            .iter()
+            .filter_map(|archive| archive.replaces.as_deref())
+            .filter(|outdated| !outdated.is_symlink())
+        {
+            if outdated.exists() {
+                move_file(changes, outdated, set_aside(outdated)?)?;
+            }
+        }
+
+        let mut paths = Vec::with_capacity(archives.len());
+        for archive in archives {
+            if let Some(link) = archive.replaces.as_deref()
+                && link.is_symlink()
+            {
+                // Keep the new version on the drive the archive was linked from
+                let target = fs::canonicalize(link).map_err(|_| Error::BrokenLink(link.into()))?;
+                move_file(changes, &target, outdated_path(&target))?;
+                info!("Copying {} to {}", archive.path.display(), target.display());
+                fs::copy(&archive.path, &target)?;
+                changes.push(Change::Created(target));
+                paths.push(link.to_path_buf());
+                continue;
+            }
+            // Updates stay in the mods directory of the outdated archive, which may be an extra one
+            let destination = archive
+                .replaces
+                .as_deref()
+                .and_then(Path::parent)
+                .unwrap_or(&self.mods_dir)
+                .join(archive.path.file_name().unwrap_or_default());
+            // An unrelated file of the same name would be overwritten, keep it to restore it
+            if destination.exists() {
+                move_file(changes, &destination, set_aside(&destination)?)?;
+            }
+            paths.push(move_file(changes, &archive.path, destination)?);
+        }
+        Ok(paths)
+    }
+
+    /// Removes the staging directory, after moving corrupted downloads kept with `--keep-corrupt`
+    /// to the mods directory
+    pub fn discard(self) {
+        if let Ok(entries) = fs::read_dir(&self.dir) {
+            for path in entries.filter_map(|entry| Some(entry.ok()?.path())) {
+                if path.extension().is_some_and(|ext| ext == "corrupt") {
+                    let kept = self.mods_dir.join(path.file_name().unwrap_or_default());
+                    match fs::rename(&path, &kept) {
+                        Ok(()) => println!("Corrupted file moved to {}", kept.display()),
+                        Err(e) => warn!("Failed to move {}: {}", path.display(), e),
+                    }
+                }
+            }
+        }
+        if let Err(e) = fs::remove_dir_all(&self.dir) {
+            warn!("Failed to remove {}: {}", self.dir.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_commit_replaces_outdated_archives() {
+        let mods_dir = TempDir::new().unwrap();
+        let outdated = mods_dir.path().join("MyMod_v1.zip");
+        fs::write(&outdated, "1.0.0").unwrap();
+        let staging = Staging::create(mods_dir.path()).unwrap();
+        let staged = staging.dir().join("MyMod.zip");
+        fs::write(&staged, "1.1.0").unwrap();
+
+        let paths = staging
+            .commit(&[StagedArchive {
+                path: staged,
+                replaces: Some(outdated.clone()),
+            }])
+            .unwrap();
+
+        assert_eq!(paths, vec![mods_dir.path().join("MyMod.zip")]);
+        assert_eq!(fs::read_to_string(&paths[0]).unwrap(), "1.1.0");
+        assert!(!outdated.exists());
+        assert!(!mods_dir.path().join(STAGING_DIRECTORY).exists());
+    }
+
+    #[test]
+    fn test_failed_commit_restores_the_mods_directory() {
+        let mods_dir = TempDir::new().unwrap();
+        let first = mods_dir.path().join("First.zip");
+        let second = mods_dir.path().join("Second.zip");
+        fs::write(&first, "1.0.0").unwrap();
+        fs::write(&second, "1.0.0").unwrap();
+        let staging = Staging::create(mods_dir.path()).unwrap();
+        let staged = staging.dir().join("First.zip");
+        fs::write(&staged, "1.1.0").unwrap();
+
+        let result = staging.commit(&[
+            StagedArchive {
+                path: staged,
+                replaces: Some(first.clone()),
+            },
+            StagedArchive {
+                path: mods_dir.path().join(STAGING_DIRECTORY).join("Second.zip"),
+                replaces: Some(second.clone()),
+            },
+        ]);
+
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&first).unwrap(), "1.0.0");
+        assert_eq!(fs::read_to_string(&second).unwrap(), "1.0.0");
+    }
+
+    #[test]
+    fn test_failed_commit_restores_archives_of_the_same_name_in_two_mods_directories() {
+        let mods_dir = TempDir::new().unwrap();
+        let extra_dir = TempDir::new().unwrap();
+        let first = mods_dir.path().join("MyMod.zip");
+        let second = extra_dir.path().join("MyMod.zip");
+        fs::write(&first, "first").unwrap();
+        fs::write(&second, "second").unwrap();
+        let staging = Staging::create(mods_dir.path()).unwrap();
+        let staged = staging.dir().join("MyMod.zip");
+        fs::write(&staged, "1.1.0").unwrap();
+
+        let result = staging.commit(&[
+            StagedArchive {
+                path: staged,
+                replaces: Some(first.clone()),
+            },
+            StagedArchive {
+                path: mods_dir.path().join(STAGING_DIRECTORY).join("Missing.zip"),
+                replaces: Some(second.clone()),
+            },
+        ]);
+
+        assert!(result.is_err());
+        assert_eq!(fs::read_to_string(&first).unwrap(), "first");
+        assert_eq!(fs::read_to_string(&second).unwrap(), "second");
+    }
+
+    #[test]
+    fn test_create_restores_archives_set_aside_by_an_interrupted_batch() {
+        let mods_dir = TempDir::new().unwrap();
+        let extra_dir = TempDir::new().unwrap();
+        let aside_dir = mods_dir.path().join(STAGING_DIRECTORY).join("outdated");
+        fs::create_dir_all(aside_dir.join("0")).unwrap();
+        fs::create_dir_all(aside_dir.join("1")).unwrap();
+        fs::write(aside_dir.join("0/First.zip"), "first").unwrap();
+        fs::write(aside_dir.join("1/Second.zip"), "second").unwrap();
+        fs::write(
+            aside_dir.join("1.source"),
+            extra_dir.path().to_str().unwrap(),
+        )
+        .unwrap();
+
+        Staging::create(mods_dir.path()).unwrap();
+
+        let first = mods_dir.path().join("First.zip");
+        assert_eq!(fs::read_to_string(first).unwrap(), "first");
+        let second = extra_dir.path().join("Second.zip");
+        assert_eq!(fs::read_to_string(second).unwrap(), "second");
+
+        fs::create_dir_all(aside_dir.join("0")).unwrap();
+        fs::write(aside_dir.join("0/First.zip"), "outdated").unwrap();
+        assert!(matches!(
+            Staging::create(mods_dir.path()),
+            Err(Error::InterruptedBatch(_))
+        ));
+        assert!(aside_dir.join("0/First.zip").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_commit_replaces_the_target_of_a_linked_archive() {
+        let mods_dir = TempDir::new().unwrap();
+        let other_drive = TempDir::new().unwrap();
+        let target = other_drive.path().join("MyMod.zip");
+        fs::write(&target, "1.0.0").unwrap();
+        let link = mods_dir.path().join("MyMod.zip");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+        let staging = Staging::create(mods_dir.path()).unwrap();
+        let staged = staging.dir().join("123456.zip");
+        fs::write(&staged, "1.1.0").unwrap();
+
+        let paths = staging
+            .commit(&[StagedArchive {
+                path: staged,
+                replaces: Some(link.clone()),
+            }])
+            .unwrap();
+
+        assert_eq!(paths, vec![link.clone()]);
+        assert!(link.is_symlink());
+        assert_eq!(fs::read_to_string(&target).unwrap(), "1.1.0");
+        assert_eq!(fs::read_dir(other_drive.path()).unwrap().count(), 1);
+    }
+}
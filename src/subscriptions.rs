@@ -0,0 +1,237 @@
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{
+    constant::SUBSCRIPTIONS_FILE,
+    error::Error,
+    mod_registry::{ModRegistry, RemoteModInfo},
+};
+
+/// Registry entries followed without being installed, stored as `subscriptions.yaml` in the data directory
+///
+/// Each subscription remembers the entry as it was last seen, `None` while it is not in the registry,
+/// e.g. for a collab which is not released yet.
+#[derive(Debug)]
+pub struct Subscriptions {
+    path: PathBuf,
+    entries: BTreeMap<String, Option<SeenEntry>>,
+}
+
+/// Version and update time of a registry entry when it was last checked
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeenEntry {
+    #[serde(rename = "Version")]
+    pub version: String,
+    #[serde(rename = "LastUpdate")]
+    pub updated_at: u64,
+}
+
+/// A change of a subscribed entry since the previous check
+#[derive(Debug, PartialEq, Eq)]
+pub enum SubscriptionChange {
+    /// The entry appeared in the registry
+    Released {
+        name: String,
+        version: String,
+    },
+    Updated {
+        name: String,
+        from: String,
+        to: String,
+    },
+    /// A new archive was uploaded without changing the version
+    Reuploaded {
+        name: String,
+        version: String,
+    },
+    Removed {
+        name: String,
+    },
+}
+
+impl SeenEntry {
+    fn of(mod_info: &RemoteModInfo) -> Self {
+        Self {
+            version: mod_info.version.clone(),
+            updated_at: mod_info.updated_at,
+        }
+    }
+}
+
+impl Subscriptions {
+    pub fn load(data_dir: &Path) -> Result<Self, Error> {
+        let path = data_dir.join(SUBSCRIPTIONS_FILE);
+        let entries = if path.exists() {
+            serde_yaml_ng::from_slice(&fs::read(&path)?)?
+        } else {
+            BTreeMap::new()
+        };
+        Ok(Self { path, entries })
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        info!("Writing {}", self.path.display());
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_yaml_ng::to_string(&self.entries)?)?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Subscribed names, with the entry as last seen
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&SeenEntry>)> {
+        self.entries
+            .iter()
+            .map(|(name, seen)| (name.as_str(), seen.as_ref()))
+    }
+
+    /// Subscribes to a registry entry, returns `false` if already subscribed
+    pub fn add(&mut self, name: &str, registry: &ModRegistry) -> bool {
+        if self.entries.contains_key(name) {
+            return false;
+        }
+        let seen = registry.get_mod_info(name).map(SeenEntry::of);
+        self.entries.insert(name.to_string(), seen);
+        true
+    }
+
+    /// Returns `false` if not subscribed
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.entries.remove(name).is_some()
+    }
+
+    /// Compares the subscriptions with the registry and remembers the entries as seen now
+    pub fn check(&mut self, registry: &ModRegistry) -> Vec<SubscriptionChange> {
+        let mut changes = Vec::new();
+        for (name, seen) in &mut self.entries {
+            let current = registry.get_mod_info(name).map(SeenEntry::of);
+            let name = name.clone();
+            let change = match (seen.take(), &current) {
+                (None, None) => continue,
+                (Some(_), None) => SubscriptionChange::Removed { name },
+                (None, Some(current)) => SubscriptionChange::Released {
+                    name,
+                    version: current.version.clone(),
+                },
+                (Some(previous), Some(current)) if previous.version != current.version => {
+                    SubscriptionChange::Updated {
+                        name,
+                        from: previous.version,
+                        to: current.version.clone(),
+                    }
+                }
+                (Some(previous), Some(current)) if previous.updated_at != current.updated_at => {
+                    SubscriptionChange::Reuploaded {
+                        name,
+                        version: current.version.clone(),
+                    }
+                }
+                (Some(_), Some(_)) => {
+                    *seen = current;
+                    continue;
+                }
+            };
+            changes.push(change);
+            *seen = current;
+        }
+        changes
+    }
+}
+
+impl fmt::Display for SubscriptionChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SubscriptionChange::Released { name, version } => {
+                write!(f, "{} is now in the registry (version {})", name, version)
+            }
+            SubscriptionChange::Updated { name, from, to } => {
+                write!(f, "{} was updated ({} -> {})", name, from, to)
+            }
+            SubscriptionChange::Reuploaded { name, version } => {
+                write!(f, "{} was re-uploaded (version {})", name, version)
+            }
+            SubscriptionChange::Removed { name } => {
+                write!(f, "{} was removed from the registry", name)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn registry(yaml: &str) -> ModRegistry {
+        let mut registry: ModRegistry = serde_yaml_ng::from_str(yaml).unwrap();
+        for (name, mod_info) in &mut registry.entries {
+            mod_info.name = name.clone();
+        }
+        registry
+    }
+
+    fn entry(name: &str, version: &str, updated_at: u64) -> String {
+        format!(
+            "{}:\n  Version: {}\n  Size: 1\n  LastUpdate: {}\n  URL: ''\n  xxHash: []\n  \
+            GameBananaType: Mod\n  GameBananaId: 1\n",
+            name, version, updated_at
+        )
+    }
+
+    #[test]
+    fn test_check_reports_release_update_and_reupload() {
+        let dir = TempDir::new().unwrap();
+        let mut subscriptions = Subscriptions::load(dir.path()).unwrap();
+        let before = registry(&entry("SpeedrunTool", "3.20.0", 100));
+        assert!(subscriptions.add("SpeedrunTool", &before));
+        assert!(subscriptions.add("UpcomingCollab", &before));
+        assert!(subscriptions.add("OtherMod", &before));
+        subscriptions.save().unwrap();
+        let mut subscriptions = Subscriptions::load(dir.path()).unwrap();
+
+        let after = registry(&format!(
+            "{}{}",
+            entry("SpeedrunTool", "3.21.0", 200),
+            entry("UpcomingCollab", "1.0.0", 200)
+        ));
+        let changes = subscriptions.check(&after);
+
+        assert_eq!(
+            changes,
+            vec![
+                SubscriptionChange::Updated {
+                    name: String::from("SpeedrunTool"),
+                    from: String::from("3.20.0"),
+                    to: String::from("3.21.0"),
+                },
+                SubscriptionChange::Released {
+                    name: String::from("UpcomingCollab"),
+                    version: String::from("1.0.0"),
+                },
+            ]
+        );
+        assert!(subscriptions.check(&after).is_empty());
+        let reuploaded = registry(&format!(
+            "{}{}",
+            entry("SpeedrunTool", "3.21.0", 300),
+            entry("UpcomingCollab", "1.0.0", 200)
+        ));
+        assert_eq!(
+            subscriptions.check(&reuploaded),
+            vec![SubscriptionChange::Reuploaded {
+                name: String::from("SpeedrunTool"),
+                version: String::from("3.21.0"),
+            }]
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use std::fmt;
+
+use crate::{
+    download::TransferSummary,
+    error::{Context, Error},
+};
+
+/// What happened to one mod of a batch
+#[derive(Debug)]
+pub enum Outcome {
+    /// Installed or updated, with details such as the new version
+    Done(String),
+    Failed(Error),
+    /// Left untouched, with the reason
+    Skipped(String),
+}
+
+/// Outcome of every mod of a batch of installs or updates, printed at the end of the run
+///
+/// ```text
+/// Summary:
+///   StrawberryJam2021  updated  1.0.11 -> 1.0.12
+///   CollabUtils2       failed   Checksum verification failed for ...
+///   MaxHelpingHand     skipped  code mod, pass --include-code-mods to update it
+/// 1 updated, 1 failed, 1 skipped
+/// Downloaded 91.22 MiB in 3 minutes (512.00 KiB/s)
+/// ```
+#[derive(Debug)]
+pub struct BatchSummary {
+    /// Past participle of the operation, e.g. `updated`
+    verb: &'static str,
+    outcomes: Vec<(String, Outcome)>,
+    transfer: Option<TransferSummary>,
+}
+
+impl BatchSummary {
+    pub fn new(verb: &'static str) -> Self {
+        Self {
+            verb,
+            outcomes: Vec::new(),
+            transfer: None,
+        }
+    }
+
+    pub fn done(&mut self, name: &str, detail: impl Into<String>) {
+        self.outcomes
+            .push((name.to_string(), Outcome::Done(detail.into())));
+    }
+
+    pub fn failed(&mut self, name: &str, error: Error) {
+        self.outcomes
+            .push((name.to_string(), Outcome::Failed(error)));
+    }
+
+    pub fn skipped(&mut self, name: &str, reason: impl Into<String>) {
+        self.outcomes
+            .push((name.to_string(), Outcome::Skipped(reason.into())));
+    }
+
+    pub fn set_transfer(&mut self, transfer: TransferSummary) {
+        self.transfer = Some(transfer);
+    }
+
+    /// Number of mods in the batch, skipped ones included
+    pub fn len(&self) -> usize {
+        self.outcomes.len()
+    }
+
+    fn count(&self, predicate: impl Fn(&Outcome) -> bool) -> usize {
+        self.outcomes
+            .iter()
+            .filter(|(_, outcome)| predicate(outcome))
+            .count()
+    }
+
+    /// Fails with the first error if any mod of the batch failed
+    pub fn into_result(self) -> Result<(), Error> {
+        let failed = self.count(|outcome| matches!(outcome, Outcome::Failed(_)));
+        let total = self.len();
+        let first = self
+            .outcomes
+            .into_iter()
+            .find_map(|(name, outcome)| match outcome {
+                Outcome::Failed(error) => Some((name, error)),
+                _ => None,
+            });
+        let Some((name, error)) = first else {
+            return Ok(());
+        };
+        let result = Err(error).in_mod(&name);
+        if total == 1 {
+            return result;
+        }
+        result.map_err(|error| Error::BatchFailed {
+            failed,
+            total,
+            source: Box::new(error),
+        })
+    }
+}
+
+impl fmt::Display for BatchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .outcomes
+            .iter()
+            .map(|(name, _)| name.len())
+            .max()
+            .unwrap_or_default();
+        let result_width = self.verb.len().max("skipped".len());
+        writeln!(f, "Summary:")?;
+        for (name, outcome) in &self.outcomes {
+            let (result, detail) = match outcome {
+                Outcome::Done(detail) => (self.verb, detail.clone()),
+                Outcome::Failed(error) => ("failed", error.to_string()),
+                Outcome::Skipped(reason) => ("skipped", reason.clone()),
+            };
+            writeln!(
+                f,
+                "  {:<width$}  {:<result_width$}  {}",
+                name, result, detail
+            )?;
+        }
+        write!(
+            f,
+            "{} {}, {} failed, {} skipped",
+            self.count(|outcome| matches!(outcome, Outcome::Done(_))),
+            self.verb,
+            self.count(|outcome| matches!(outcome, Outcome::Failed(_))),
+            self.count(|outcome| matches!(outcome, Outcome::Skipped(_)))
+        )?;
+        if let Some(transfer) = &self.transfer {
+            write!(f, "\n{}", transfer)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_table() {
+        let mut summary = BatchSummary::new("updated");
+        summary.done("StrawberryJam2021", "1.0.11 -> 1.0.12");
+        summary.failed("CollabUtils2", Error::FileIsNotHashed);
+        summary.skipped("MaxHelpingHand", "pinned");
+
+        assert_eq!(
+            summary.to_string(),
+            "Summary:\n\
+            \x20 StrawberryJam2021  updated  1.0.11 -> 1.0.12\n\
+            \x20 CollabUtils2       failed   The file is not hashed. It seems developer's fault\n\
+            \x20 MaxHelpingHand     skipped  pinned\n\
+            1 updated, 1 failed, 1 skipped"
+        );
+    }
+
+    #[test]
+    fn test_failed_batch_keeps_the_code_of_the_first_failure() {
+        let mut summary = BatchSummary::new("installed");
+        summary.done("MaxHelpingHand", "1.33.3");
+        summary.failed("CollabUtils2", Error::EmptyVendorDirectory("vendor".into()));
+        summary.failed("SkinModHelper", Error::FileIsNotHashed);
+
+        let error = summary.into_result().unwrap_err();
+
+        assert_eq!(error.code(), "E405");
+        assert_eq!(
+            error.to_string(),
+            "2 of 3 mod(s) failed, first: CollabUtils2: No vendored mods found in 'vendor'"
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use crate::error::Error;
+
+/// An output template given with `--format`, e.g. `{name}\t{version}`, rendered once per item
+///
+/// Fields are written in braces, `{{` and `}}` stand for literal braces, and `\t`, `\n` and `\\`
+/// are unescaped since shells pass them through literally within single quotes.
+#[derive(Debug)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Field(&'static str),
+}
+
+impl Template {
+    /// Parses a template, refusing fields which are not among `fields`
+    pub fn parse(template: &str, fields: &[&'static str]) -> Result<Self, Error> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.next_if_eq(&'{').is_some() => text.push('{'),
+                '}' if chars.next_if_eq(&'}').is_some() => text.push('}'),
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => {
+                                return Err(Error::InvalidTemplate(format!(
+                                    "'{{{}' is not closed",
+                                    name
+                                )));
+                            }
+                        }
+                    }
+                    let Some(field) = fields.iter().find(|field| **field == name) else {
+                        return Err(Error::InvalidTemplate(format!(
+                            "unknown field '{{{}}}', available fields: {}",
+                            name,
+                            fields.join(", ")
+                        )));
+                    };
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Field(field));
+                }
+                '}' => {
+                    return Err(Error::InvalidTemplate(String::from(
+                        "unmatched '}', write '}}' for a literal brace",
+                    )));
+                }
+                '\\' => text.push(match chars.next_if(|c| matches!(c, 't' | 'n' | '\\')) {
+                    Some('t') => '\t',
+                    Some('n') => '\n',
+                    _ => '\\',
+                }),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Self { parts })
+    }
+
+    /// Renders the template, `value` is only called for the fields it contains
+    pub fn render(
+        &self,
+        mut value: impl FnMut(&str) -> Result<String, Error>,
+    ) -> Result<String, Error> {
+        let mut rendered = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => rendered.push_str(text),
+                Part::Field(field) => rendered.push_str(&value(field)?),
+            }
+        }
+        Ok(rendered)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_render_template() {
+        let fields = ["name", "version"];
+        let template = Template::parse(r"{name}\t{version} {{v}}\n", &fields).unwrap();
+
+        let rendered = template.render(|field| Ok(field.to_uppercase())).unwrap();
+
+        assert_eq!(rendered, "NAME\tVERSION {v}\n");
+        assert!(Template::parse("{nmae}", &fields).is_err());
+        assert!(Template::parse("{name", &fields).is_err());
+        assert!(Template::parse("name}", &fields).is_err());
+    }
+}
@@ -0,0 +1,42 @@
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+
+/// Current time as a Unix timestamp in seconds
+pub fn now() -> u64 {
+    Utc::now().timestamp().max(0) as u64
+}
+
+/// Formats a Unix timestamp in local time, e.g. `2025-03-14 18:05`
+pub fn format_timestamp(timestamp: u64) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .map(|time| {
+            time.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|| String::from("unknown"))
+}
+
+/// Formats a Unix timestamp as a local date, e.g. `2025-03-14`
+pub fn format_date(timestamp: u64) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .map(|time| time.with_timezone(&Local).format("%Y-%m-%d").to_string())
+        .unwrap_or_else(|| String::from("unknown"))
+}
+
+/// Formats a Unix timestamp in local time for file names, e.g. `2025-03-14-180500`
+pub fn format_file_stamp(timestamp: u64) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .map(|time| {
+            time.with_timezone(&Local)
+                .format("%Y-%m-%d-%H%M%S")
+                .to_string()
+        })
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+/// Formats a Unix timestamp as an RFC 3339 UTC time, e.g. `2025-03-14T17:05:00Z`, as used in feeds
+pub fn format_rfc3339(timestamp: u64) -> String {
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .unwrap_or_default()
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
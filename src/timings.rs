@@ -0,0 +1,139 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use tracing::{Subscriber, span};
+use tracing_subscriber::{Layer, filter::filter_fn, layer::Context, registry::LookupSpan};
+
+/// Time spent in the spans of one phase, e.g. every `hash` span
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhaseTiming {
+    pub count: u32,
+    /// Sum of the durations, concurrent spans (parallel downloads) are all counted
+    pub total: Duration,
+    pub longest: Duration,
+}
+
+/// Records how long the spans of this crate live, grouped by span name
+///
+/// Durations are measured from the creation to the closing of each span, so spans of
+/// async code do not need to be entered to be timed.
+#[derive(Debug, Clone, Default)]
+pub struct Timings {
+    /// Phases in the order they were first seen
+    phases: Arc<Mutex<Vec<(&'static str, PhaseTiming)>>>,
+}
+
+/// Start of a span, stored in its extensions
+struct SpanStart(Instant);
+
+impl Timings {
+    /// Wraps the recorder in a layer which only sees the spans of this crate
+    pub fn layer<S>(self) -> impl Layer<S>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        self.with_filter(filter_fn(|metadata| {
+            metadata.is_span() && metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+        }))
+    }
+
+    fn record(&self, phase: &'static str, elapsed: Duration) {
+        let mut phases = self.phases.lock().unwrap_or_else(|e| e.into_inner());
+        match phases.iter_mut().find(|(name, _)| *name == phase) {
+            Some((_, timing)) => {
+                timing.count += 1;
+                timing.total += elapsed;
+                timing.longest = timing.longest.max(elapsed);
+            }
+            None => phases.push((
+                phase,
+                PhaseTiming {
+                    count: 1,
+                    total: elapsed,
+                    longest: elapsed,
+                },
+            )),
+        }
+    }
+}
+
+impl<S> Layer<S> for Timings
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, _attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanStart(Instant::now()));
+        }
+    }
+
+    fn on_close(&self, id: span::Id, ctx: Context<'_, S>) {
+        if let Some(span) = ctx.span(&id)
+            && let Some(start) = span.extensions().get::<SpanStart>()
+        {
+            self.record(span.name(), start.0.elapsed());
+        }
+    }
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phases = self.phases.lock().unwrap_or_else(|e| e.into_inner());
+        writeln!(
+            f,
+            "{:<16} {:>6} {:>12} {:>12}",
+            "Phase", "Count", "Total", "Longest"
+        )?;
+        for (phase, timing) in phases.iter() {
+            writeln!(
+                f,
+                "{:<16} {:>6} {:>9.1} ms {:>9.1} ms",
+                phase,
+                timing.count,
+                timing.total.as_secs_f64() * 1000.0,
+                timing.longest.as_secs_f64() * 1000.0
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Prints the timing table to stderr when dropped, i.e. at the end of the command even if it failed
+pub struct TimingsReport(pub Timings);
+
+impl Drop for TimingsReport {
+    fn drop(&mut self) {
+        eprint!("\n{}", self.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing::info_span;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn test_spans_are_grouped_by_phase() {
+        let timings = Timings::default();
+        let subscriber = tracing_subscriber::registry().with(timings.clone().layer());
+
+        tracing::subscriber::with_default(subscriber, || {
+            for _ in 0..3 {
+                let _span = info_span!("hash").entered();
+            }
+            drop(info_span!("scan"));
+            drop(info_span!(target: "hyper", "connect"));
+        });
+
+        let phases = timings.phases.lock().unwrap();
+        let summary: Vec<_> = phases
+            .iter()
+            .map(|(phase, timing)| (*phase, timing.count))
+            .collect();
+        assert_eq!(summary, vec![("hash", 3), ("scan", 1)]);
+    }
+}
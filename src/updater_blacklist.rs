@@ -0,0 +1,77 @@
+use std::{
+    ops::{Deref, DerefMut},
+    path::Path,
+};
+
+use glob::{MatchOptions, Pattern};
+use tracing::warn;
+
+use crate::{archive_list::ArchiveList, constant::UPDATER_BLACKLIST_FILE, error::Error};
+
+/// Everest's `Mods/updaterblacklist.txt`, listing the archives the mod updater must not touch
+///
+/// Entries are usually plain file names, glob patterns such as `Collab*.zip` are accepted too.
+#[derive(Debug)]
+pub struct UpdaterBlacklist(ArchiveList);
+
+impl UpdaterBlacklist {
+    /// Loads the updater blacklist of a mods directory, an absent file excludes nothing
+    pub fn load(mods_dir: &Path) -> Result<Self, Error> {
+        ArchiveList::load(mods_dir.join(UPDATER_BLACKLIST_FILE)).map(Self)
+    }
+
+    /// Checks whether an archive file name is excluded from updates
+    pub fn is_excluded(&self, file_name: &str) -> bool {
+        let options = MatchOptions {
+            case_sensitive: false,
+            ..MatchOptions::new()
+        };
+        self.entries().any(|entry| match Pattern::new(entry) {
+            Ok(pattern) => pattern.matches_with(file_name, options),
+            Err(e) => {
+                warn!(
+                    "Invalid pattern '{}' in {}: {}",
+                    entry, UPDATER_BLACKLIST_FILE, e
+                );
+                entry == file_name
+            }
+        })
+    }
+}
+
+impl Deref for UpdaterBlacklist {
+    type Target = ArchiveList;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for UpdaterBlacklist {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_plain_names_and_globs_are_excluded() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join(UPDATER_BLACKLIST_FILE),
+            "# Pinned mods\nSpeedrunTool.zip\nCollab*.zip\n",
+        )
+        .unwrap();
+
+        let updater_blacklist = UpdaterBlacklist::load(dir.path()).unwrap();
+
+        assert!(updater_blacklist.is_excluded("SpeedrunTool.zip"));
+        assert!(updater_blacklist.is_excluded("collab2020.zip"));
+        assert!(!updater_blacklist.is_excluded("MaxHelpingHand.zip"));
+    }
+}
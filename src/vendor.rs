@@ -0,0 +1,106 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs,
+    path::Path,
+};
+
+use tracing::info;
+
+use crate::{
+    checksum,
+    constant::VENDOR_MANIFEST_FILE,
+    error::Error,
+    installed_mods::LocalModInfo,
+    mod_registry::{ModRegistry, RemoteModInfo},
+    source::ModSource,
+};
+
+/// Manifest of an offline mirror, written as `everest_update.yaml` in the vendor directory
+///
+/// It follows the schema of the remote mod registry, except that each `URL` holds the file name
+/// of the vendored archive relative to the vendor directory.
+#[derive(Debug, Default)]
+pub struct VendorManifest {
+    pub entries: BTreeMap<String, RemoteModInfo>,
+}
+
+impl VendorManifest {
+    /// Loads the manifest of a vendor directory, returns an empty manifest if there is none yet
+    pub fn load(vendor_dir: &Path) -> Result<Self, Error> {
+        let path = vendor_dir.join(VENDOR_MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read(&path)?;
+        let mut entries: BTreeMap<String, RemoteModInfo> = serde_yaml_ng::from_slice(&content)?;
+        for (name, mod_info) in entries.iter_mut() {
+            mod_info.name = name.clone();
+        }
+        Ok(Self { entries })
+    }
+
+    /// Writes the manifest to the vendor directory
+    pub fn save(&self, vendor_dir: &Path) -> Result<(), Error> {
+        let path = vendor_dir.join(VENDOR_MANIFEST_FILE);
+        info!("Writing vendor manifest to {}", path.display());
+        fs::write(path, serde_yaml_ng::to_string(&self.entries)?)?;
+        Ok(())
+    }
+
+    /// Checks whether the vendored archive of a mod matches the latest entry of the source
+    pub fn is_up_to_date(
+        &self,
+        vendor_dir: &Path,
+        remote: &RemoteModInfo,
+        source: &dyn ModSource,
+    ) -> bool {
+        self.entries
+            .get(&remote.name)
+            .and_then(|entry| {
+                checksum::hash_file(&vendor_dir.join(&entry.download_url), &remote.checksums).ok()
+            })
+            .is_some_and(|digests| source.verify(remote, &digests))
+    }
+
+    /// Records a vendored archive
+    pub fn insert(&mut self, remote: &RemoteModInfo, archive_path: &Path) {
+        let mut entry = remote.clone();
+        entry.download_url = archive_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        self.entries.insert(remote.name.clone(), entry);
+    }
+}
+
+/// Collects the registry entries to vendor for the installed mods
+///
+/// With `with_dependencies`, the dependencies declared by the installed mods are included too,
+/// even when they are not installed locally. Mods unknown to the registry are skipped.
+pub fn collect_vendor_targets<'a>(
+    installed_mods: &[LocalModInfo],
+    mod_registry: &'a ModRegistry,
+    with_dependencies: bool,
+) -> Vec<&'a RemoteModInfo> {
+    let mut names: Vec<&str> = installed_mods
+        .iter()
+        .map(|mod_info| mod_info.manifest.name.as_str())
+        .collect();
+    if with_dependencies {
+        names.extend(
+            installed_mods
+                .iter()
+                .flat_map(|mod_info| mod_info.manifest.dependencies.iter().flatten())
+                .map(|dependency| dependency.name.as_str()),
+        );
+    }
+
+    let mut seen = HashSet::new();
+    let mut targets: Vec<_> = names
+        .into_iter()
+        .filter(|name| seen.insert(*name))
+        .filter_map(|name| mod_registry.get_mod_info(name))
+        .collect();
+    targets.sort_by(|a, b| a.name.cmp(&b.name));
+    targets
+}
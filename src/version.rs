@@ -0,0 +1,175 @@
+use std::{cmp::Ordering, fmt};
+
+/// Mod version following the semantics Everest uses to check dependencies
+///
+/// Everest parses versions with `System.Version`, any suffix after `-` is ignored
+/// and missing components (build, revision) compare lower than present ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EverestVersion {
+    pub major: i64,
+    pub minor: i64,
+    pub build: i64,
+    pub revision: i64,
+}
+
+impl EverestVersion {
+    /// Parses a version string like `1.4.2` or `1.0.0-beta`, returns `None` if it is not numeric
+    pub fn parse(version: &str) -> Option<Self> {
+        let numeric = version
+            .trim()
+            .split(['-', ' ', '+'])
+            .next()
+            .unwrap_or_default();
+        let mut components = numeric.split('.').map(|part| part.parse::<i64>().ok());
+
+        let major = components.next()??;
+        let minor = components.next().unwrap_or(Some(0))?;
+        let build = components.next().unwrap_or(Some(-1))?;
+        let revision = components.next().unwrap_or(Some(-1))?;
+        if components.next().is_some() {
+            return None;
+        }
+        Some(Self {
+            major,
+            minor,
+            build,
+            revision,
+        })
+    }
+
+    /// Checks whether this (installed) version satisfies a dependency on `required`
+    ///
+    /// This mirrors `Everest.Loader.VersionSatisfiesDependency`: the major version must match,
+    /// and the installed version must not be lower. Versions `0.0.*` satisfy everything.
+    pub fn satisfies(&self, required: &EverestVersion) -> bool {
+        if self.major == 0 && self.minor == 0 {
+            return true;
+        }
+        self.major == required.major && self >= required
+    }
+}
+
+/// How the version of a mod changes when it is updated to another archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionChange {
+    /// Newer version, the first component differing is at this index
+    Upgrade(usize),
+    /// Older version, e.g. after the author pulled a broken release
+    Downgrade(usize),
+    /// Same version, only the archive and its hash changed
+    Reupload,
+    /// Versions which cannot be compared, e.g. `v2-beta`
+    Unknown,
+}
+
+impl VersionChange {
+    pub fn between(current: &str, available: &str) -> Self {
+        let (current, available) = (current.trim(), available.trim());
+        if current == available {
+            return Self::Reupload;
+        }
+        let (Some(current_version), Some(available_version)) = (
+            EverestVersion::parse(current),
+            EverestVersion::parse(available),
+        ) else {
+            return Self::Unknown;
+        };
+        let changed = current
+            .split('.')
+            .zip(available.split('.'))
+            .take_while(|(a, b)| a == b)
+            .count();
+        match available_version.cmp(&current_version) {
+            Ordering::Greater => Self::Upgrade(changed),
+            Ordering::Less => Self::Downgrade(changed),
+            Ordering::Equal => Self::Reupload,
+        }
+    }
+}
+
+impl PartialOrd for EverestVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for EverestVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.build, self.revision).cmp(&(
+            other.major,
+            other.minor,
+            other.build,
+            other.revision,
+        ))
+    }
+}
+
+impl fmt::Display for EverestVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)?;
+        if self.build >= 0 {
+            write!(f, ".{}", self.build)?;
+        }
+        if self.revision >= 0 {
+            write!(f, ".{}", self.revision)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(version: &str) -> EverestVersion {
+        EverestVersion::parse(version).unwrap()
+    }
+
+    #[test]
+    fn test_parse_versions() {
+        assert_eq!(v("1.4.2").to_string(), "1.4.2");
+        assert_eq!(v("1.0.0-beta").to_string(), "1.0.0");
+        assert_eq!(v("2.1").to_string(), "2.1");
+        assert_eq!(v("1").to_string(), "1.0");
+        assert!(EverestVersion::parse("v1.2.0").is_none());
+        assert!(EverestVersion::parse("").is_none());
+        assert!(EverestVersion::parse("1.2.3.4.5").is_none());
+    }
+
+    #[test]
+    fn test_satisfies_follows_everest_semantics() {
+        assert!(v("1.5.0").satisfies(&v("1.4.2")));
+        assert!(v("1.4.2").satisfies(&v("1.4.2")));
+        assert!(!v("1.4.1").satisfies(&v("1.4.2")));
+        // Breaking changes: major version must match
+        assert!(!v("2.0.0").satisfies(&v("1.4.2")));
+        // Missing build component is lower than any build
+        assert!(!v("1.4").satisfies(&v("1.4.0")));
+        // Development versions satisfy everything
+        assert!(v("0.0.1").satisfies(&v("3.2.1")));
+    }
+
+    #[test]
+    fn test_version_change() {
+        assert_eq!(
+            VersionChange::between("1.4.2", "1.5.0"),
+            VersionChange::Upgrade(1)
+        );
+        assert_eq!(
+            VersionChange::between("1.4.2", "1.4.10"),
+            VersionChange::Upgrade(2)
+        );
+        assert_eq!(
+            VersionChange::between("2.0.0", "1.9.9"),
+            VersionChange::Downgrade(0)
+        );
+        assert_eq!(
+            VersionChange::between("1.4.2", "1.4.2"),
+            VersionChange::Reupload
+        );
+        assert_eq!(
+            VersionChange::between("1.4.2", "v1.5-beta"),
+            VersionChange::Unknown
+        );
+    }
+}
@@ -0,0 +1,435 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use tracing::{info, warn};
+
+use crate::{
+    checksum,
+    constant::WATCH_DEBOUNCE,
+    dependency::check_installed_dependencies,
+    error::Error,
+    game::GameVersions,
+    identify::{Adoption, Identification, adopt},
+    installed_index::InstalledIndex,
+    installed_mods::{self, LocalModInfo},
+    journal::Journal,
+    mod_registry::HashIndex,
+};
+
+/// The installed mods, kept up to date from the changes to the archives of the mods directory
+pub struct ModsWatch<'a> {
+    mods_dir: PathBuf,
+    data_dir: PathBuf,
+    installed: BTreeMap<PathBuf, LocalModInfo>,
+    /// Registry entries by checksum, `None` without a copy of the registry
+    hash_index: Option<HashIndex<'a>>,
+    journal: Journal,
+    game: GameVersions,
+}
+
+impl<'a> ModsWatch<'a> {
+    pub fn new(
+        mods_dir: &Path,
+        data_dir: &Path,
+        installed_mods: Vec<LocalModInfo>,
+        hash_index: Option<HashIndex<'a>>,
+    ) -> Self {
+        Self {
+            mods_dir: mods_dir.to_path_buf(),
+            data_dir: data_dir.to_path_buf(),
+            installed: installed_mods
+                .into_iter()
+                .map(|mod_info| (mod_info.archive_path.clone(), mod_info))
+                .collect(),
+            hash_index,
+            journal: Journal::new(data_dir),
+            game: GameVersions::detect(mods_dir),
+        }
+    }
+
+    /// Takes changed archives into account, returns what to report about them
+    ///
+    /// Added archives found in the registry are recorded in the installed index like `identify
+    /// --adopt` does, and the dependencies they miss are reported. An archive removed and added
+    /// under another name in the same batch is a rename and keeps its index entry. The index is
+    /// read again for each batch, other commands such as `update` may have changed it meanwhile.
+    pub fn apply(&mut self, changed: &BTreeSet<PathBuf>) -> Result<Vec<String>, Error> {
+        let mut installed_index = InstalledIndex::load(&self.data_dir)?;
+        let mut report = Vec::new();
+        let mut removed = Vec::new();
+        let mut added = Vec::new();
+        for path in changed {
+            if path.extension().is_none_or(|ext| ext != "zip") {
+                continue;
+            }
+            if path.is_file() {
+                added.push(path);
+            } else if let Some(mod_info) = self.installed.remove(path) {
+                let entry = installed_index.remove(path);
+                removed.push((mod_info, entry));
+            }
+        }
+
+        let mut new_names = BTreeSet::new();
+        let mut index_changed = false;
+        for path in added {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            // Unreadable archives keep what was known about them, they are read again on their next change
+            let manifest = match installed_mods::read_manifest_in_time(path) {
+                Ok(Some(manifest)) => manifest,
+                Ok(None) => {
+                    report.push(format!(
+                        "{} has no everest.yaml, Everest will not load it",
+                        file_name
+                    ));
+                    continue;
+                }
+                Err(e) => {
+                    report.push(format!("Could not read {}: {}", file_name, e));
+                    continue;
+                }
+            };
+            let digests = match checksum::hash_file(path, &[]) {
+                Ok(digests) => digests,
+                Err(e) => {
+                    report.push(format!("Could not hash {}: {}", file_name, e));
+                    continue;
+                }
+            };
+            let mod_info = LocalModInfo::new(path.clone(), manifest);
+            let name = mod_info.manifest.name.clone();
+            let version = mod_info.manifest.version.clone();
+            let previous = self.installed.insert(path.clone(), mod_info);
+            // Recorded by the command which wrote the archive, e.g. `update`
+            let managed = installed_index
+                .get(path)
+                .is_some_and(|entry| entry.xxhash == digests.xxh64);
+
+            if let Some(previous) = previous {
+                if managed {
+                    continue;
+                }
+                index_changed |= installed_index.remove(path).is_some();
+                if previous.manifest.version != version {
+                    report.push(format!(
+                        "Replaced {} {} by version {}",
+                        name, previous.manifest.version, version
+                    ));
+                }
+            } else if let Some(position) = removed.iter().position(|(_, entry)| {
+                entry
+                    .as_ref()
+                    .is_some_and(|entry| entry.xxhash == digests.xxh64)
+            }) {
+                let (old, entry) = removed.remove(position);
+                installed_index.insert(path, entry.expect("found by its entry"));
+                index_changed = true;
+                report.push(format!(
+                    "Renamed {} to {}",
+                    old.archive_file_name(),
+                    file_name
+                ));
+                continue;
+            } else {
+                report.push(format!("Added {} {} ({})", name, version, file_name));
+            }
+            new_names.insert(name);
+            if managed {
+                continue;
+            }
+
+            let remote = self
+                .hash_index
+                .as_ref()
+                .and_then(|hash_index| hash_index.find(&digests));
+            let Some(remote) = remote else {
+                report.push(format!(
+                    "  {} is not in the registry, it will not be updated",
+                    file_name
+                ));
+                continue;
+            };
+            let identification = Identification {
+                path: path.clone(),
+                digests,
+                remote: Some(remote),
+            };
+            // Saves the index itself
+            match adopt(
+                &identification,
+                remote,
+                &self.mods_dir,
+                &mut installed_index,
+                &self.journal,
+            ) {
+                Ok(Adoption::Adopted(_)) => report.push(format!(
+                    "  Identified as {} {} in the registry, it will be updated",
+                    remote.name, remote.version
+                )),
+                Ok(Adoption::AlreadyManaged) => {}
+                Err(e) => report.push(format!("  Could not record {}: {}", file_name, e)),
+            }
+        }
+
+        let removed_names: BTreeSet<_> = removed
+            .iter()
+            .map(|(mod_info, _)| mod_info.manifest.name.clone())
+            .collect();
+        for (mod_info, entry) in &removed {
+            index_changed |= entry.is_some();
+            report.push(format!(
+                "Removed {} ({})",
+                mod_info.manifest.name,
+                mod_info.archive_file_name()
+            ));
+        }
+        if index_changed {
+            installed_index.save()?;
+        }
+
+        let installed_mods: Vec<_> = self.installed.values().cloned().collect();
+        let depcheck = check_installed_dependencies(&installed_mods, &self.game);
+        for (name, dependents) in &depcheck.missing {
+            let new_dependents: Vec<_> = dependents
+                .iter()
+                .filter(|dependent| new_names.contains(**dependent))
+                .copied()
+                .collect();
+            if !new_dependents.is_empty() {
+                report.push(format!(
+                    "  Missing dependency of {}: {}",
+                    new_dependents.join(", "),
+                    name
+                ));
+            } else if removed_names.contains(*name) {
+                report.push(format!(
+                    "  {} is still required by {}",
+                    name,
+                    dependents.join(", ")
+                ));
+            }
+        }
+        for (name, unsatisfied) in &depcheck.unsatisfied {
+            for (dependent, required_version) in &unsatisfied.required_by {
+                if new_names.contains(*dependent) {
+                    report.push(format!(
+                        "  {} requires {} {}, {} is installed",
+                        dependent, name, required_version, unsatisfied.installed_version
+                    ));
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+/// Watches the mods directory until the process is stopped, reporting each batch of changes
+///
+/// Copying an archive takes many events, so changes are only applied once the directory has been
+/// quiet for `WATCH_DEBOUNCE`. With a `check_interval`, `on_check` is called right away and then
+/// at that interval, between batches.
+pub fn watch(
+    mods_dir: &Path,
+    mods_watch: &mut ModsWatch,
+    mut on_report: impl FnMut(&[String]),
+    check_interval: Option<Duration>,
+    mut on_check: impl FnMut(),
+) -> Result<(), Error> {
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(mods_dir, RecursiveMode::NonRecursive)?;
+    info!("Watching {}", mods_dir.display());
+
+    let mut changed = BTreeSet::new();
+    let mut next_check = check_interval.map(|_| Instant::now());
+    loop {
+        let event = if changed.is_empty() {
+            let received = match next_check {
+                Some(next) => receiver.recv_timeout(next.saturating_duration_since(Instant::now())),
+                None => receiver
+                    .recv()
+                    .map_err(|_| mpsc::RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok(event) => event,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    on_check();
+                    next_check = check_interval.map(|interval| Instant::now() + interval);
+                    continue;
+                }
+                // The watcher was dropped
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        } else {
+            match receiver.recv_timeout(WATCH_DEBOUNCE) {
+                Ok(event) => event,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    // A failed batch is reported, later changes may still be taken into account
+                    let report = mods_watch.apply(&changed).unwrap_or_else(|e| {
+                        vec![format!("Could not take the changes into account: {}", e)]
+                    });
+                    changed.clear();
+                    if !report.is_empty() {
+                        on_report(&report);
+                    }
+                    continue;
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        };
+        match event {
+            Ok(event) => match event.kind {
+                EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_) => {
+                    changed.extend(event.paths);
+                }
+                _ => {}
+            },
+            Err(e) => warn!("Failed to watch {}: {}", mods_dir.display(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{installed_index::IndexEntry, mod_registry::ModRegistry};
+    use std::{fs, io::Write};
+    use tempfile::TempDir;
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    fn write_mod(path: &Path, manifest: &str) {
+        let mut zip = ZipWriter::new(fs::File::create(path).unwrap());
+        zip.start_file("everest.yaml", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(manifest.as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    fn index_entry(path: &Path, version: &str) -> IndexEntry {
+        IndexEntry {
+            name: String::from("Helper"),
+            version: version.to_string(),
+            xxhash: checksum::hash_file(path, &[]).unwrap().xxh64,
+            sha256: None,
+            installed_at: 0,
+            updated_at: None,
+            unverified: false,
+        }
+    }
+
+    #[test]
+    fn test_reports_added_and_removed_archives() {
+        let data_dir = TempDir::new().unwrap();
+        let mods_dir = TempDir::new().unwrap();
+        let mut mods_watch = ModsWatch::new(mods_dir.path(), data_dir.path(), Vec::new(), None);
+        let helper = mods_dir.path().join("Helper.zip");
+        let map = mods_dir.path().join("Map.zip");
+        write_mod(&helper, "- Name: Helper\n  Version: 1.0.0\n");
+        write_mod(
+            &map,
+            "- Name: Map\n  Version: 1.0.0\n  Dependencies:\n    - Name: Helper\n      Version: 1.2.0\n    - Name: Other\n      Version: 1.0.0\n",
+        );
+
+        let report = mods_watch
+            .apply(&BTreeSet::from([helper.clone(), map.clone()]))
+            .unwrap();
+        assert_eq!(
+            report,
+            vec![
+                "Added Helper 1.0.0 (Helper.zip)",
+                "  Helper.zip is not in the registry, it will not be updated",
+                "Added Map 1.0.0 (Map.zip)",
+                "  Map.zip is not in the registry, it will not be updated",
+                "  Missing dependency of Map: Other",
+                "  Map requires Helper 1.2.0, 1.0.0 is installed",
+            ]
+        );
+
+        // Still being copied, the archive is only replaced once it can be read
+        fs::write(&helper, "partial").unwrap();
+        let report = mods_watch.apply(&BTreeSet::from([helper.clone()])).unwrap();
+        assert!(report[0].starts_with("Could not read Helper.zip"));
+        write_mod(&helper, "- Name: Helper\n  Version: 1.2.0\n");
+        let report = mods_watch.apply(&BTreeSet::from([helper.clone()])).unwrap();
+        assert_eq!(report[0], "Replaced Helper 1.0.0 by version 1.2.0");
+
+        fs::remove_file(&helper).unwrap();
+        let report = mods_watch.apply(&BTreeSet::from([helper])).unwrap();
+        assert_eq!(
+            report,
+            vec![
+                "Removed Helper (Helper.zip)",
+                "  Helper is still required by Map",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keeps_the_index_entries_written_by_other_commands() {
+        let data_dir = TempDir::new().unwrap();
+        let mods_dir = TempDir::new().unwrap();
+        let helper = mods_dir.path().join("Helper.zip");
+        write_mod(&helper, "- Name: Helper\n  Version: 1.0.0\n");
+        let mut installed_index = InstalledIndex::load(data_dir.path()).unwrap();
+        installed_index.insert(&helper, index_entry(&helper, "1.0.0"));
+        installed_index.save().unwrap();
+        let installed_mods = installed_mods::list_installed_mods(mods_dir.path()).unwrap();
+        let mut mods_watch = ModsWatch::new(mods_dir.path(), data_dir.path(), installed_mods, None);
+
+        // `update` replaces the archive and records it while watching
+        write_mod(&helper, "- Name: Helper\n  Version: 1.1.0\n");
+        let mut installed_index = InstalledIndex::load(data_dir.path()).unwrap();
+        installed_index.insert(&helper, index_entry(&helper, "1.1.0"));
+        installed_index.save().unwrap();
+        let report = mods_watch.apply(&BTreeSet::from([helper.clone()])).unwrap();
+        assert!(report.is_empty());
+
+        let renamed = mods_dir.path().join("Helper_v1.1.0.zip");
+        fs::rename(&helper, &renamed).unwrap();
+        let report = mods_watch
+            .apply(&BTreeSet::from([helper.clone(), renamed.clone()]))
+            .unwrap();
+        assert_eq!(report, vec!["Renamed Helper.zip to Helper_v1.1.0.zip"]);
+        let installed_index = InstalledIndex::load(data_dir.path()).unwrap();
+        assert!(installed_index.get(&helper).is_none());
+        assert_eq!(installed_index.get(&renamed).unwrap().version, "1.1.0");
+    }
+
+    #[test]
+    fn test_records_archives_found_in_the_registry() {
+        let data_dir = TempDir::new().unwrap();
+        let mods_dir = TempDir::new().unwrap();
+        let helper = mods_dir.path().join("1298450.zip");
+        write_mod(&helper, "- Name: Helper\n  Version: 1.0.0\n");
+        let mut registry: ModRegistry = serde_yaml_ng::from_str(&format!(
+            "Helper:\n  Version: 1.0.0\n  Size: 1\n  LastUpdate: 0\n  URL: ''\n  \
+            xxHash: ['{}']\n  GameBananaType: Mod\n  GameBananaId: 1\n",
+            checksum::hash_file(&helper, &[]).unwrap().xxh64
+        ))
+        .unwrap();
+        registry.entries.get_mut("Helper").unwrap().name = String::from("Helper");
+        let mut mods_watch = ModsWatch::new(
+            mods_dir.path(),
+            data_dir.path(),
+            Vec::new(),
+            Some(registry.hash_index()),
+        );
+
+        let report = mods_watch.apply(&BTreeSet::from([helper.clone()])).unwrap();
+
+        assert_eq!(
+            report,
+            vec![
+                "Added Helper 1.0.0 (1298450.zip)",
+                "  Identified as Helper 1.0.0 in the registry, it will be updated",
+            ]
+        );
+        let installed_index = InstalledIndex::load(data_dir.path()).unwrap();
+        assert_eq!(installed_index.get(&helper).unwrap().name, "Helper");
+    }
+}
@@ -0,0 +1,286 @@
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tracing::info;
+
+use crate::{
+    constant::{APP_DIRECTORY_NAME, WEBHOOK_HISTORY_FILE},
+    download::ModDownloader,
+    error::Error,
+    installed_mods::AvailableUpdateInfo,
+};
+
+/// Longest description of a Discord embed, in characters
+const DISCORD_DESCRIPTION_LIMIT: usize = 4096;
+
+/// Shape of the JSON payload posted to the webhook
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    /// A message with an embed, for Discord webhooks (and compatible services)
+    Discord,
+    /// The event and the list of updates, for custom receivers
+    Json,
+}
+
+/// What the notification is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookEvent {
+    UpdatesFound,
+    UpdatesInstalled,
+}
+
+impl WebhookFormat {
+    /// The configured format, or Discord for Discord webhook URLs and generic JSON otherwise
+    pub fn for_url(url: &str, configured: Option<Self>) -> Self {
+        configured.unwrap_or_else(|| {
+            let is_discord = ["discord.com/api/webhooks/", "discordapp.com/api/webhooks/"]
+                .iter()
+                .any(|prefix| url.contains(prefix));
+            if is_discord {
+                Self::Discord
+            } else {
+                Self::Json
+            }
+        })
+    }
+}
+
+/// Updates already announced as found, stored as `webhook.yaml` in the data directory
+///
+/// Only the updates pending at the last announcement are kept, so that scheduled checks
+/// announce each update once while it stays pending.
+#[derive(Debug)]
+pub struct WebhookHistory {
+    path: PathBuf,
+    announced: BTreeSet<String>,
+}
+
+impl WebhookHistory {
+    pub fn load(data_dir: &Path) -> Result<Self, Error> {
+        let path = data_dir.join(WEBHOOK_HISTORY_FILE);
+        let announced = if path.exists() {
+            serde_yaml_ng::from_slice(&fs::read(&path)?)?
+        } else {
+            BTreeSet::new()
+        };
+        Ok(Self { path, announced })
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        info!("Writing {}", self.path.display());
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_yaml_ng::to_string(&self.announced)?)?;
+        Ok(())
+    }
+
+    /// The updates which were not announced yet
+    pub fn unannounced<'a>(
+        &self,
+        updates: &[&'a AvailableUpdateInfo],
+    ) -> Vec<&'a AvailableUpdateInfo> {
+        updates
+            .iter()
+            .filter(|update| !self.announced.contains(&id(update)))
+            .copied()
+            .collect()
+    }
+
+    /// Remembers the pending updates once announced, forgetting those which are no longer pending
+    pub fn record(&mut self, updates: &[&AvailableUpdateInfo]) {
+        self.announced = updates.iter().map(|update| id(update)).collect();
+    }
+}
+
+/// Identifies an update by mod and version, a newer version is announced again
+fn id(update: &AvailableUpdateInfo) -> String {
+    format!("{} {}", update.name, update.available_version)
+}
+
+/// Posts a notification about updates to the webhook, a failure does not fail the command
+///
+/// Returns whether the webhook was notified, or there was nothing to notify.
+pub async fn notify(
+    downloader: &ModDownloader,
+    url: &str,
+    format: Option<WebhookFormat>,
+    event: WebhookEvent,
+    updates: &[&AvailableUpdateInfo],
+) -> bool {
+    if updates.is_empty() {
+        return true;
+    }
+    let payload = payload(WebhookFormat::for_url(url, format), event, updates);
+    match downloader.post_json(url, &payload).await {
+        Ok(_) => true,
+        Err(e) => {
+            eprintln!("Could not notify the webhook: {}", e);
+            false
+        }
+    }
+}
+
+/// Announces the pending updates which were not announced by a previous run
+pub async fn notify_updates_found(
+    downloader: &ModDownloader,
+    url: &str,
+    format: Option<WebhookFormat>,
+    data_dir: &Path,
+    updates: &[&AvailableUpdateInfo],
+) -> Result<(), Error> {
+    let mut history = WebhookHistory::load(data_dir)?;
+    let unannounced = history.unannounced(updates);
+    if notify(
+        downloader,
+        url,
+        format,
+        WebhookEvent::UpdatesFound,
+        &unannounced,
+    )
+    .await
+    {
+        history.record(updates);
+        history.save()?;
+    }
+    Ok(())
+}
+
+/// Builds the payload notifying about updates
+pub fn payload(
+    format: WebhookFormat,
+    event: WebhookEvent,
+    updates: &[&AvailableUpdateInfo],
+) -> Value {
+    match format {
+        WebhookFormat::Json => json!({
+            "event": match event {
+                WebhookEvent::UpdatesFound => "updates_found",
+                WebhookEvent::UpdatesInstalled => "updates_installed",
+            },
+            "updates": updates
+                .iter()
+                .map(|update| json!({
+                    "name": update.name,
+                    "current_version": update.current_version,
+                    "available_version": update.available_version,
+                    "page": gamebanana_page(update),
+                }))
+                .collect::<Vec<_>>(),
+        }),
+        WebhookFormat::Discord => {
+            let (title, color) = match event {
+                WebhookEvent::UpdatesFound => (
+                    format!("{} mod update(s) available", updates.len()),
+                    0xf1c40f,
+                ),
+                WebhookEvent::UpdatesInstalled => {
+                    (format!("{} mod(s) updated", updates.len()), 0x2ecc71)
+                }
+            };
+            json!({
+                "username": APP_DIRECTORY_NAME,
+                "embeds": [{
+                    "title": title,
+                    "description": discord_description(updates),
+                    "color": color,
+                }],
+            })
+        }
+    }
+}
+
+/// One line per update, with as many updates as fit in an embed
+fn discord_description(updates: &[&AvailableUpdateInfo]) -> String {
+    let mut description = String::new();
+    for (i, update) in updates.iter().enumerate() {
+        let line = format!(
+            "- [{}]({}) {} → {}\n",
+            update.name,
+            gamebanana_page(update),
+            update.current_version,
+            update.available_version
+        );
+        // Leave room for the line telling how many updates are left out
+        if description.chars().count() + line.chars().count() > DISCORD_DESCRIPTION_LIMIT - 32 {
+            description.push_str(&format!("… and {} more", updates.len() - i));
+            break;
+        }
+        description.push_str(&line);
+    }
+    description
+}
+
+fn gamebanana_page(update: &AvailableUpdateInfo) -> String {
+    format!("https://gamebanana.com/mods/{}", update.gamebanana_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn update(name: &str) -> AvailableUpdateInfo {
+        AvailableUpdateInfo {
+            name: name.to_string(),
+            current_version: String::from("1.0.0"),
+            available_version: String::from("1.1.0"),
+            size: 0,
+            existing_path: PathBuf::from(format!("{}.zip", name)),
+            gamebanana_type: String::from("Mod"),
+            gamebanana_id: 42,
+            dll: None,
+        }
+    }
+
+    #[test]
+    fn test_history_announces_each_update_once() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let first = update("First");
+        let mut second = update("Second");
+        let mut history = WebhookHistory::load(dir.path()).unwrap();
+
+        assert_eq!(history.unannounced(&[&first]).len(), 1);
+        history.record(&[&first]);
+        history.save().unwrap();
+        let history = WebhookHistory::load(dir.path()).unwrap();
+
+        let unannounced = history.unannounced(&[&first, &second]);
+        assert_eq!(unannounced.len(), 1);
+        assert_eq!(unannounced[0].name, "Second");
+        second.available_version = String::from("1.2.0");
+        assert_eq!(history.unannounced(&[&second]).len(), 1);
+    }
+
+    #[test]
+    fn test_discord_payload_fits_in_an_embed() {
+        let updates: Vec<_> = (0..200).map(|i| update(&format!("Mod{}", i))).collect();
+        let updates: Vec<_> = updates.iter().collect();
+        let url = "https://discord.com/api/webhooks/1/token";
+
+        let payload = payload(
+            WebhookFormat::for_url(url, None),
+            WebhookEvent::UpdatesFound,
+            &updates,
+        );
+
+        let embed = &payload["embeds"][0];
+        assert_eq!(embed["title"], "200 mod update(s) available");
+        let description = embed["description"].as_str().unwrap();
+        assert!(
+            description.starts_with("- [Mod0](https://gamebanana.com/mods/42) 1.0.0 → 1.1.0\n")
+        );
+        assert!(description.chars().count() <= DISCORD_DESCRIPTION_LIMIT);
+        assert!(description.ends_with("more"));
+        assert_eq!(
+            WebhookFormat::for_url("https://example.com/hook", None),
+            WebhookFormat::Json
+        );
+    }
+}